@@ -1,5 +1,5 @@
 use cosmwasm_std::{
-    from_binary, log, to_binary, Api, Binary, CanonicalAddr, CosmosMsg, Env, Extern,
+    from_binary, log, plaintext_log, to_binary, Api, Binary, CanonicalAddr, CosmosMsg, Env, Extern,
     HandleResponse, HandleResult, HumanAddr, InitResponse, InitResult, Querier, QueryResult,
     StdError, StdResult, Storage,
 };
@@ -19,14 +19,16 @@ use crate::contract_info::ContractInfo;
 use crate::msg::{
     HandleAnswer, HandleMsg, InitMsg, PotionInfo, PotionNameIdx, QueryAnswer, QueryMsg, ViewerInfo,
 };
+use crate::notify::{channel_info, notify, CHANNELS, POTION_CHANNEL};
 use crate::rand::{extend_entropy, sha_256, Prng};
 use crate::server_msgs::{ServerQueryMsg, SkullTypeWrapper, TransmuteWrapper};
 use crate::snip721::{
     ImageInfoWrapper, NftInfoResponse, SendMsg, Snip721HandleMsg, Snip721QueryMsg,
 };
 use crate::state::{
-    State, StoredPotionInfo, ADMINS_KEY, MY_ADDRESS_KEY, PREFIX_POTION, PREFIX_POTION_IDX,
-    PREFIX_REVOKED_PERMITS, PREFIX_VIEW_KEY, PRNG_SEED_KEY, STATE_KEY,
+    State, StoredPotionInfo, ADMINS_KEY, MY_ADDRESS_KEY, NOTIFY_SECRET_KEY, PREFIX_POTION,
+    PREFIX_POTION_IDX, PREFIX_REVOKED_PERMITS, PREFIX_SERVER_KEYS, PREFIX_VIEW_KEY, PRNG_SEED_KEY,
+    STATE_KEY,
 };
 use crate::storage::{load, may_load, save};
 use crate::viewing_key::{ViewingKey, VIEWING_KEY_SIZE};
@@ -57,6 +59,11 @@ pub fn init<S: Storage, A: Api, Q: Querier>(
     let prng_seed: Vec<u8> = sha_256(base64::encode(msg.entropy.as_bytes()).as_bytes()).to_vec();
     let vk = ViewingKey::new(&env, &prng_seed, msg.entropy.as_ref());
     save(&mut deps.storage, PRNG_SEED_KEY, &prng_seed)?;
+    save(
+        &mut deps.storage,
+        NOTIFY_SECRET_KEY,
+        &sha_256(&[prng_seed.as_slice(), b"notifications"].concat()).to_vec(),
+    )?;
     let mut admins = vec![sender_raw];
     if let Some(addrs) = msg.admins {
         add_admins(&deps.api, &addrs, &mut admins)?;
@@ -141,6 +148,7 @@ pub fn handle<S: Storage, A: Api, Q: Querier>(
         HandleMsg::RevokePermit { permit_name } => {
             revoke_permit(&mut deps.storage, &env.message.sender, &permit_name)
         }
+        HandleMsg::ReceiveQueryKey { key } => try_receive_query_key(deps, &env.message.sender, key),
         HandleMsg::SetViewingKeyWithCollection {
             nft_contract,
             viewing_key,
@@ -327,7 +335,7 @@ fn try_batch_receive_nft<S: Storage, A: Api, Q: Querier>(
     let mut rng = Prng::new(&prng_seed, &rng_entropy);
     // find out if the skull is cyclops/jawless
     let type_msg = ServerQueryMsg::SkullType {
-        viewer: viewer.clone(),
+        viewer: server_viewer(deps, &image_resp.server_used.address, &viewer)?,
         image: image_resp.image_info.current.clone(),
     };
     let type_resp = type_msg
@@ -376,7 +384,7 @@ fn try_batch_receive_nft<S: Storage, A: Api, Q: Querier>(
     let new_layers = potion.variants.swap_remove(winner).layers;
     let cat_trans: Vec<String> = new_layers.iter().map(|l| l.category.clone()).collect();
     let xmut_msg = ServerQueryMsg::Transmute {
-        viewer,
+        viewer: server_viewer(deps, &svg.address, &viewer)?,
         current: image_resp.image_info.current.clone(),
         new_layers,
     };
@@ -406,9 +414,15 @@ fn try_batch_receive_nft<S: Storage, A: Api, Q: Querier>(
         ptn_contract.address,
     )?);
 
+    let from_raw = deps.api.canonical_address(&from)?;
+    let note = notify(&mut deps.storage, &from_raw, POTION_CHANNEL)?;
+
     Ok(HandleResponse {
         messages,
-        log: vec![log("transmuted categories", format!("{:?}", &cat_trans))],
+        log: vec![
+            log("transmuted categories", format!("{:?}", &cat_trans)),
+            plaintext_log(note, ""),
+        ],
         data: None,
     })
 }
@@ -760,6 +774,69 @@ fn try_set_key<S: Storage, A: Api, Q: Querier>(
     })
 }
 
+/// Returns HandleResult
+///
+/// saves a viewing key issued by the sender for this contract to use when querying the sender.
+/// Keys sent by anything other than one of the svg servers are ignored
+///
+/// # Arguments
+///
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `sender` - a reference to the message sender
+/// * `key` - the issued viewing key
+fn try_receive_query_key<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    sender: &HumanAddr,
+    key: String,
+) -> HandleResult {
+    let sender_raw = deps.api.canonical_address(sender)?;
+    let state: State = load(&deps.storage, STATE_KEY)?;
+    let status = if state.svg_contracts.iter().any(|s| s.address == sender_raw) {
+        let mut key_store = PrefixedStorage::new(PREFIX_SERVER_KEYS, &mut deps.storage);
+        save(&mut key_store, sender_raw.as_slice(), &key)?;
+        "success"
+    } else {
+        "ignored"
+    };
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![],
+        data: Some(to_binary(&HandleAnswer::ReceiveQueryKey {
+            status: status.to_string(),
+        })?),
+    })
+}
+
+/// Returns StdResult<ViewerInfo>
+///
+/// the viewer info to use when querying an svg server.  A viewing key issued by the server is
+/// preferred over this contract's own viewing key
+///
+/// # Arguments
+///
+/// * `deps` - a reference to Extern containing all the contract's external dependencies
+/// * `server` - a reference to the svg server's address
+/// * `viewer` - a reference to the viewer info with this contract's own viewing key
+fn server_viewer<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    server: &HumanAddr,
+    viewer: &ViewerInfo,
+) -> StdResult<ViewerInfo> {
+    let server_raw = deps.api.canonical_address(server)?;
+    let key_store = ReadonlyPrefixedStorage::new(PREFIX_SERVER_KEYS, &deps.storage);
+    Ok(
+        if let Some(viewing_key) = may_load(&key_store, server_raw.as_slice())? {
+            ViewerInfo {
+                address: viewer.address.clone(),
+                viewing_key,
+            }
+        } else {
+            viewer.clone()
+        },
+    )
+}
+
 /// Returns HandleResult
 ///
 /// revoke the ability to use a specified permit
@@ -809,10 +886,38 @@ pub fn query<S: Storage, A: Api, Q: Querier>(deps: &Extern<S, A, Q>, msg: QueryM
             name,
             index,
         } => query_potion(deps, viewer, permit, name, index),
+        QueryMsg::ListChannels {} => to_binary(&QueryAnswer::ListChannels {
+            channels: CHANNELS.iter().map(|c| c.to_string()).collect(),
+        }),
+        QueryMsg::ChannelInfo {
+            channels,
+            viewer,
+            permit,
+        } => query_channel_info(deps, channels, viewer, permit),
     };
     pad_query_result(response, BLOCK_SIZE)
 }
 
+/// Returns QueryResult displaying the querier's notification info for the specified channels
+///
+/// # Arguments
+///
+/// * `deps` - reference to Extern containing all the contract's external dependencies
+/// * `channels` - names of the channels
+/// * `viewer` - optional address and key making an authenticated query request
+/// * `permit` - optional permit with "owner" permission
+fn query_channel_info<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    channels: Vec<String>,
+    viewer: Option<ViewerInfo>,
+    permit: Option<Permit>,
+) -> QueryResult {
+    let (user_raw, _) = get_querier(deps, viewer, permit)?;
+    let channels = channel_info(&deps.storage, &user_raw, channels)?;
+
+    to_binary(&QueryAnswer::ChannelInfo { channels })
+}
+
 /// Returns QueryResult displaying either potion or svg server contracts
 ///
 /// # Arguments
@@ -1188,3 +1293,61 @@ fn add_svg_contrs<S: Storage, A: Api, Q: Querier>(
     }
     Ok(messages)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::contract_info::StoreContractInfo;
+    use cosmwasm_std::testing::{mock_dependencies, MockApi, MockQuerier, MockStorage};
+
+    #[test]
+    fn test_receive_query_key() {
+        let mut deps = mock_dependencies(20, &[]);
+        let server = HumanAddr("server".to_string());
+        let store_info =
+            |deps: &Extern<MockStorage, MockApi, MockQuerier>, address: &str| StoreContractInfo {
+                code_hash: format!("{}hash", address),
+                address: deps
+                    .api
+                    .canonical_address(&HumanAddr(address.to_string()))
+                    .unwrap(),
+            };
+        let state = State {
+            skulls: store_info(&deps, "skulls"),
+            potion_contracts: vec![store_info(&deps, "potion")],
+            svg_contracts: vec![store_info(&deps, "server")],
+            potion_cnt: 0,
+            v_key: "own".to_string(),
+            halt: false,
+        };
+        save(&mut deps.storage, STATE_KEY, &state).unwrap();
+        let own = ViewerInfo {
+            address: HumanAddr("alchemy".to_string()),
+            viewing_key: "own".to_string(),
+        };
+        let status = |resp: HandleResponse| match from_binary(&resp.data.unwrap()).unwrap() {
+            HandleAnswer::ReceiveQueryKey { status } => status,
+            _ => panic!("unexpected answer"),
+        };
+        let key_in_use = |deps: &Extern<MockStorage, MockApi, MockQuerier>| {
+            server_viewer(deps, &server, &own).unwrap().viewing_key
+        };
+
+        // keys from anyone but a known svg server are ignored
+        let attacker = HumanAddr("potion".to_string());
+        let resp = try_receive_query_key(&mut deps, &attacker, "forged".to_string()).unwrap();
+        assert_eq!(status(resp), "ignored");
+        assert_eq!(key_in_use(&deps), "own");
+
+        // the server's key replaces this contract's own key
+        let resp = try_receive_query_key(&mut deps, &server, "issued".to_string()).unwrap();
+        assert_eq!(status(resp), "success");
+        assert_eq!(key_in_use(&deps), "issued");
+
+        // a rotated key replaces the previous one
+        try_receive_query_key(&mut deps, &server, "rotated".to_string()).unwrap();
+        assert_eq!(key_in_use(&deps), "rotated");
+        try_receive_query_key(&mut deps, &attacker, "forged".to_string()).unwrap();
+        assert_eq!(key_in_use(&deps), "rotated");
+    }
+}
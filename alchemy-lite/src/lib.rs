@@ -2,6 +2,7 @@
 pub mod contract;
 mod contract_info;
 pub mod msg;
+mod notify;
 mod rand;
 mod server_msgs;
 mod snip721;
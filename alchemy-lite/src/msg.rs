@@ -82,6 +82,13 @@ pub enum HandleMsg {
         /// name of the permit that is no longer valid
         permit_name: String,
     },
+    /// receive a viewing key issued by an svg server that has this contract on its viewer
+    /// allowlist.  The key is only ever used when querying the server that sent it, and keys
+    /// sent by anything other than one of the svg servers are ignored
+    ReceiveQueryKey {
+        /// the viewing key
+        key: String,
+    },
     /// set a viewing key with an nft contract to facilitate in retrieval of an NFT from an unregistered collection
     SetViewingKeyWithCollection {
         /// the code hash and address of the nft contract
@@ -122,6 +129,11 @@ pub enum HandleAnswer {
     RevokePermit {
         status: String,
     },
+    /// response from receiving a server issued viewing key.  The status is "ignored" if the
+    /// sender is not one of the svg servers
+    ReceiveQueryKey {
+        status: String,
+    },
     RetrieveNft {
         status: String,
     },
@@ -206,6 +218,18 @@ pub enum QueryMsg {
         /// query will throw an error
         index: Option<u16>,
     },
+    /// lists the notification channels
+    ListChannels {},
+    /// displays the notification seed and counter of the querier for the specified channels
+    ChannelInfo {
+        /// names of the channels
+        channels: Vec<String>,
+        /// optional address and viewing key of a user
+        viewer: Option<ViewerInfo>,
+        /// optional permit used to verify user identity.  If both viewer and permit
+        /// are provided, the viewer will be ignored
+        permit: Option<Permit>,
+    },
 }
 
 /// responses to queries
@@ -234,6 +258,10 @@ pub enum QueryAnswer {
         halted: bool,
         potion: PotionInfo,
     },
+    /// lists the notification channels
+    ListChannels { channels: Vec<String> },
+    /// displays the notification info of the querier for the requested channels
+    ChannelInfo { channels: Vec<ChannelInfoData> },
 }
 
 /// the address and viewing key making an authenticated query request
@@ -280,6 +308,22 @@ pub struct PotionInfo {
     pub variants: Vec<VariantInfo>,
 }
 
+/// a user's notification info for a channel.  Notifications are emitted as plaintext logs
+/// keyed by `snip52:` followed by the base64 encoded notification id, where the id of the
+/// notification after `counter` previous ones is the HMAC-SHA256 of `{channel}:{counter}` keyed
+/// by the seed
+#[derive(Serialize, Deserialize, JsonSchema, Clone, PartialEq, Debug)]
+pub struct ChannelInfoData {
+    /// name of the channel
+    pub channel: String,
+    /// the user's notification seed
+    pub seed: Binary,
+    /// number of notifications the user has been sent on this channel
+    pub counter: u64,
+    /// id of the next notification on this channel
+    pub next_id: Binary,
+}
+
 /// potion name and index
 #[derive(Serialize, Deserialize, JsonSchema, Clone, PartialEq, Debug)]
 pub struct PotionNameIdx {
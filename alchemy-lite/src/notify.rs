@@ -0,0 +1,106 @@
+use cosmwasm_std::{Binary, CanonicalAddr, ReadonlyStorage, StdError, StdResult, Storage};
+use cosmwasm_storage::{PrefixedStorage, ReadonlyPrefixedStorage};
+use hmac::{Hmac, Mac, NewMac};
+use sha2::Sha256;
+
+use crate::msg::ChannelInfoData;
+use crate::state::{NOTIFY_SECRET_KEY, PREFIX_NOTIFY_COUNTERS};
+use crate::storage::{load, may_load, save};
+
+/// channel notified when a potion changes one of the user's skulls
+pub const POTION_CHANNEL: &str = "potion";
+/// all notification channels
+pub const CHANNELS: [&str; 1] = [POTION_CHANNEL];
+
+/// HMAC using SHA-256
+type HmacSha256 = Hmac<Sha256>;
+
+/// Returns [u8; 32] which is the HMAC-SHA256 of `msg` keyed by `key`
+fn hmac_sha256(key: &[u8], msg: &[u8]) -> [u8; 32] {
+    let mut mac = HmacSha256::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(msg);
+    mac.finalize().into_bytes().into()
+}
+
+/// Returns StdResult<[u8; 32]> which is the seed of a user's notification ids
+fn user_seed<S: ReadonlyStorage>(storage: &S, user: &CanonicalAddr) -> StdResult<[u8; 32]> {
+    let secret: Vec<u8> = load(storage, NOTIFY_SECRET_KEY)?;
+    Ok(hmac_sha256(&secret, user.as_slice()))
+}
+
+/// Returns [u8; 32] which is the id of the notification after `counter` previous ones
+fn notification_id(seed: &[u8], channel: &str, counter: u64) -> [u8; 32] {
+    hmac_sha256(seed, format!("{}:{}", channel, counter).as_bytes())
+}
+
+/// Returns StdResult<u64> which is the number of notifications a user was sent on a channel
+pub fn channel_counter<S: ReadonlyStorage>(
+    storage: &S,
+    user: &CanonicalAddr,
+    channel: &str,
+) -> StdResult<u64> {
+    let cnt_store =
+        ReadonlyPrefixedStorage::multilevel(&[PREFIX_NOTIFY_COUNTERS, channel.as_bytes()], storage);
+    Ok(may_load(&cnt_store, user.as_slice())?.unwrap_or(0))
+}
+
+/// Returns StdResult<Vec<ChannelInfoData>>
+///
+/// a user's notification info for the specified channels
+///
+/// # Arguments
+///
+/// * `storage` - a reference to this contract's storage
+/// * `user` - a reference to the user's address
+/// * `channels` - names of the channels
+pub fn channel_info<S: ReadonlyStorage>(
+    storage: &S,
+    user: &CanonicalAddr,
+    channels: Vec<String>,
+) -> StdResult<Vec<ChannelInfoData>> {
+    let seed = user_seed(storage, user)?;
+    channels
+        .into_iter()
+        .map(|channel| {
+            if !CHANNELS.contains(&channel.as_str()) {
+                return Err(StdError::generic_err(format!(
+                    "{} is not a notification channel",
+                    channel
+                )));
+            }
+            let counter = channel_counter(storage, user, &channel)?;
+            Ok(ChannelInfoData {
+                next_id: Binary::from(notification_id(&seed, &channel, counter).to_vec()),
+                seed: Binary::from(seed.to_vec()),
+                counter,
+                channel,
+            })
+        })
+        .collect()
+}
+
+/// Returns StdResult<String>
+///
+/// the key of a new notification for a user.  It should be emitted in plaintext with an empty
+/// value, because the notification only signals that the user should query the contract
+///
+/// # Arguments
+///
+/// * `storage` - a mutable reference to this contract's storage
+/// * `user` - a reference to the user's address
+/// * `channel` - name of the channel
+pub fn notify<S: Storage>(
+    storage: &mut S,
+    user: &CanonicalAddr,
+    channel: &str,
+) -> StdResult<String> {
+    let seed = user_seed(storage, user)?;
+    let counter = channel_counter(storage, user, channel)?;
+    let mut cnt_store =
+        PrefixedStorage::multilevel(&[PREFIX_NOTIFY_COUNTERS, channel.as_bytes()], storage);
+    save(&mut cnt_store, user.as_slice(), &(counter + 1))?;
+    Ok(format!(
+        "snip52:{}",
+        base64::encode(notification_id(&seed, channel, counter))
+    ))
+}
@@ -11,8 +11,14 @@ pub const ADMINS_KEY: &[u8] = b"admin";
 pub const STATE_KEY: &[u8] = b"state";
 /// storage key for prng seed
 pub const PRNG_SEED_KEY: &[u8] = b"prngseed";
+/// storage key for the secret notification seeds are derived from
+pub const NOTIFY_SECRET_KEY: &[u8] = b"ntfsec";
+/// prefix for storage of the notification counters of a channel
+pub const PREFIX_NOTIFY_COUNTERS: &[u8] = b"ntfcnt";
 /// prefix for storage of viewing keys
 pub const PREFIX_VIEW_KEY: &[u8] = b"viewkeys";
+/// prefix for storage of viewing keys issued to this contract by svg servers
+pub const PREFIX_SERVER_KEYS: &[u8] = b"srvkeys";
 /// prefix for the storage of revoked permits
 pub const PREFIX_REVOKED_PERMITS: &str = "revoke";
 /// prefix for storage that maps potion names to their indices
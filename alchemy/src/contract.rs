@@ -2,11 +2,11 @@ use base64::{engine::general_purpose, Engine as _};
 use rand_core::RngCore;
 
 use cosmwasm_std::{
-    entry_point, to_binary, Addr, Api, Binary, CanonicalAddr, CosmosMsg, Deps, DepsMut, Env,
-    MessageInfo, Response, StdError, StdResult, Storage, Uint128,
+    entry_point, from_binary, to_binary, Addr, Api, Binary, CanonicalAddr, CosmosMsg, Decimal,
+    Deps, DepsMut, Env, MessageInfo, Response, StdError, StdResult, Storage, Uint128,
 };
 use cosmwasm_storage::{PrefixedStorage, ReadonlyPrefixedStorage};
-use std::cmp::min;
+use std::cmp::{max, min};
 
 use secret_toolkit::{
     crypto::{sha_256, ContractPrng},
@@ -17,25 +17,54 @@ use secret_toolkit::{
 
 use crate::contract_info::{ContractInfo, StoreContractInfo};
 use crate::msg::{
-    AlchemyState, ChargeInfo, DisplayCrateState, EligibilityInfo, ExecuteAnswer, ExecuteMsg,
-    IngrSetWeight, IngredientQty, IngredientSet, InstantiateMsg, QueryAnswer, QueryMsg,
-    StakingState, StakingTable, StoredLayerId, VariantIdxName, ViewerInfo,
+    AdminAction, AdminLogEntry, AlchemyState, ChargeInfo, CrateContractInfo, DisplayCrateState,
+    EligibilityInfo, ExecuteAnswer, ExecuteMsg, ExpectedQty, HistoryAction, HistoryEvent,
+    IngrSetChance, IngrSetWeight, IngredientGrant, IngredientIcon, IngredientQty, IngredientSet,
+    IngredientTotal, InstantiateMsg, LeaderboardEntry, LeaderboardMetric, MaterialQty,
+    MaterialRate, OperatorInfo, OperatorScope, PityDrop, PityProgress, PitySet, PityTable,
+    QueryAnswer, QueryMsg, RewardStream, SeasonInfo, StakingBoost, StakingRules, StakingSeason,
+    StakingState, StakingTable, StoredLayerId, StreamInfo, TokenReward, UncrateMsg, VariantIdxName,
+    ViewerInfo,
 };
-use crate::server_msgs::{ServerQueryMsg, SkullTypePlusWrapper};
+use crate::notify::{channel_info, notify, CHANNELS, CHARGES_CHANNEL, INGREDIENTS_CHANNEL};
+use crate::server_msgs::{LayerIndicesWrapper, ServerQueryMsg, SkullTypePlusWrapper};
+use crate::snip20::Snip20HandleMsg;
 use crate::snip721::{
     BatchNftDossierWrapper, Burn, ImageInfo, ImageInfoWrapper, Metadata, Snip721HandleMsg,
     Snip721QueryMsg, Trait,
 };
 use crate::state::{
-    CrateState, SkullStakeInfo, StoredIngrSet, StoredSetWeight, ADMINS_KEY, ALCHEMY_STATE_KEY,
-    CRATES_KEY, CRATE_META_KEY, CRATE_STATE_KEY, INGREDIENTS_KEY, INGRED_SETS_KEY, MATERIALS_KEY,
-    MY_VIEWING_KEY, PREFIX_REVOKED_PERMITS, PREFIX_SKULL_STAKE, PREFIX_STAKING_TABLE,
-    PREFIX_USER_INGR_INVENTORY, PREFIX_USER_STAKE, SKULL_721_KEY, STAKING_STATE_KEY,
-    SVG_SERVER_KEY,
+    CrateState, SkullStakeInfo, StoredAction, StoredAdminEntry, StoredBoost, StoredCapDue,
+    StoredCrate, StoredCrateArt, StoredCrateContract, StoredEvent, StoredGrant, StoredIngrSet,
+    StoredOperator, StoredOptIn, StoredPity, StoredPityCount, StoredRank, StoredRankPage,
+    StoredSeason, StoredSeasonTable, StoredSetChance, StoredSetWeight, StoredStream, StoredTotals,
+    StoredUnpaid, ADMINS_KEY, ADMIN_LOG_CNT_KEY, ALCHEMY_STATE_KEY, CAP_HOURS_KEY, CRATES_KEY,
+    CRATE_ART_KEY, CRATE_META_KEY, CRATE_STATE_KEY, DEFAULT_CRATE_KEY, HISTORY_CNT_KEY,
+    HISTORY_TOTALS_KEY, HIST_CNT_KEY, INGREDIENTS_KEY, INGRED_SETS_KEY, MATERIALS_KEY,
+    MY_VIEWING_KEY, NOTIFY_SECRET_KEY, PREFIX_ADMIN_LOG, PREFIX_CAP_DUE, PREFIX_CAP_SCHEDULE,
+    PREFIX_HISTORY, PREFIX_LEADERBOARD, PREFIX_LEADER_ALIAS, PREFIX_LEADER_OPT,
+    PREFIX_LEADER_PAGES, PREFIX_LEADER_SCORES, PREFIX_LEGACY_CRATES, PREFIX_MINTED_CRATES,
+    PREFIX_PITY_TABLE, PREFIX_REVOKED_PERMITS, PREFIX_SERVER_KEYS, PREFIX_SKULL_STAKE,
+    PREFIX_STAKING_OPERATORS, PREFIX_STAKING_TABLE, PREFIX_TOKEN_HISTORY,
+    PREFIX_TOKEN_USER_HISTORY, PREFIX_UNPAID_TOKENS, PREFIX_USER_HISTORY,
+    PREFIX_USER_INGR_INVENTORY, PREFIX_USER_PITY, PREFIX_USER_STAKE, REWARD_STREAMS_KEY,
+    SEASON_ID_KEY, SKULL_721_KEY, STAKING_BOOSTS_KEY, STAKING_RULES_KEY, STAKING_SEASONS_KEY,
+    STAKING_STATE_KEY, SVG_SERVER_KEY,
 };
-use crate::storage::{load, may_load, save};
+use crate::storage::{load, may_load, remove, save};
 
 pub const BLOCK_SIZE: usize = 256;
+/// number of leaderboard metrics
+const METRIC_CNT: usize = 3;
+/// number of ranks a leaderboard page is split into halves of once it exceeds twice that size
+const RANK_PAGE_SIZE: usize = 50;
+/// number of seconds covered by each hour of the charge cap notification schedule
+const CAP_HOUR_SECONDS: u64 = 3600;
+/// maximum number of due charge cap notifications a staking transaction delivers
+const CAP_NOTIFY_LIMIT: usize = 10;
+
+/// quantities of each generated ingredient, and the material and set indices of pity wins
+type Generated = (Vec<u32>, Vec<(u8, u8)>);
 
 ////////////////////////////////////// Instantiate ///////////////////////////////////////
 /// Returns StdResult<Response>
@@ -62,6 +91,11 @@ pub fn instantiate(
             .as_bytes(),
     );
     ViewingKey::set_seed(deps.storage, &prng_seed);
+    save(
+        deps.storage,
+        NOTIFY_SECRET_KEY,
+        &sha_256(&[prng_seed.as_slice(), b"notifications"].concat()).to_vec(),
+    )?;
     let key = ViewingKey::create(
         deps.storage,
         &info,
@@ -101,22 +135,39 @@ pub fn instantiate(
         address: crate_addr,
         code_hash: msg.crate_contract.code_hash,
     };
-    let mut crates = vec![crate_raw];
+    if let Some(cnt) = msg.legacy_crates {
+        let mut lgcy_store = PrefixedStorage::new(deps.storage, PREFIX_LEGACY_CRATES);
+        save(&mut lgcy_store, crate_raw.address.as_slice(), &cnt)?;
+    }
+    let mut crates = vec![StoredCrateContract {
+        name: "default".to_string(),
+        contract: crate_raw,
+        mint: true,
+        uncrate: true,
+    }];
     save(deps.storage, CRATES_KEY, &crates)?;
+    save(deps.storage, DEFAULT_CRATE_KEY, &crates[0].name)?;
+    if let Some(mut rules) = msg.staking_rules {
+        validate_rules(&mut rules)?;
+        save(deps.storage, STAKING_RULES_KEY, &rules)?;
+    }
+    // the skull type info is retrieved from the svg server with GetSkullTypeInfo or
+    // SetStakingRules.  Staking and alchemy start halted, and SetHaltStatus will not enable them
+    // until the info has been retrieved, so these placeholder layers are never used
     let stk_st = StakingState {
         halt: true,
-        skull_idx: 2,
+        skull_idx: 0,
         cooldown: msg.charge_time,
     };
     save(deps.storage, STAKING_STATE_KEY, &stk_st)?;
     let alc_st = AlchemyState {
         halt: true,
         cyclops: StoredLayerId {
-            category: 5,
-            variant: 1,
+            category: 0,
+            variant: 0,
         },
         jawless: StoredLayerId {
-            category: 3,
+            category: 0,
             variant: 0,
         },
     };
@@ -139,7 +190,7 @@ pub fn instantiate(
             also_implements_batch_receive_nft: true,
         }
         .to_cosmos_msg(
-            crates.swap_remove(0).code_hash,
+            crates.swap_remove(0).contract.code_hash,
             msg.crate_contract.address,
             None,
         )?,
@@ -168,40 +219,103 @@ pub fn execute(deps: DepsMut, env: Env, info: MessageInfo, msg: ExecuteMsg) -> S
         ExecuteMsg::RemoveAdmins { admins } => {
             try_process_auth_list(deps, &info.sender, &admins, false)
         }
-        ExecuteMsg::GetSkullTypeInfo {} => try_get_skull_info(deps, &info.sender, env),
+        ExecuteMsg::GetSkullTypeInfo {} => try_get_skull_info(deps, &info.sender, &env),
         ExecuteMsg::AddIngredients { ingredients } => {
             try_add_ingredients(deps, &info.sender, ingredients)
         }
         ExecuteMsg::SetStakingTables { tables } => try_stake_tbl(deps, &info.sender, tables),
+        ExecuteMsg::SetStakingBoosts { boosts } => try_set_boosts(deps, &env, &info.sender, boosts),
+        ExecuteMsg::SetPityThresholds { tables } => try_set_pity(deps, &info.sender, tables),
+        ExecuteMsg::SetStakingSeasons { seasons } => {
+            try_set_seasons(deps, &env, &info.sender, seasons)
+        }
+        ExecuteMsg::SetStakingRules { rules } => try_set_rules(deps, &env, &info.sender, rules),
+        ExecuteMsg::SetRewardStream { stream } => try_set_stream(deps, &env, &info.sender, stream),
+        ExecuteMsg::RemoveRewardStream { token, recipient } => {
+            try_remove_stream(deps, &info.sender, &token, recipient)
+        }
+        ExecuteMsg::WithdrawRewardReserve {
+            token,
+            amount,
+            recipient,
+        } => try_withdraw_reserve(deps, &info.sender, &token, amount, recipient),
+        ExecuteMsg::Receive { from, amount, .. } => try_receive(deps, &info.sender, &from, amount),
         ExecuteMsg::DefineIngredientSets { sets } => try_set_ingred_set(deps, &info.sender, sets),
         ExecuteMsg::SetHaltStatus {
             staking,
             alchemy,
             crating,
         } => try_set_halt(deps, &info.sender, staking, alchemy, crating),
+        ExecuteMsg::SetCrateArt { background, icons } => {
+            try_set_crate_art(deps, &info.sender, background, icons)
+        }
         ExecuteMsg::SetCrateMetadata { public_metadata } => {
             try_set_crate_meta(deps, &info.sender, public_metadata)
         }
-        ExecuteMsg::CrateIngredients { ingredients } => {
-            try_mint_crate(deps, info.sender, ingredients)
+        ExecuteMsg::CrateIngredients {
+            ingredients,
+            crate_contract,
+        } => try_mint_crate(deps, &env, info.sender, ingredients, crate_contract),
+        ExecuteMsg::SetStake {
+            token_ids,
+            held_token_ids,
+            owner,
+        } => try_set_stake(deps, env, &info.sender, token_ids, held_token_ids, owner),
+        ExecuteMsg::ClaimStake { owner } => try_claim_stake(deps, env, &info.sender, owner),
+        ExecuteMsg::SetStakingOperator {
+            operator,
+            scope,
+            expiration,
+            recipient,
+        } => try_set_operator(
+            deps,
+            &env,
+            &info.sender,
+            &operator,
+            scope,
+            expiration,
+            recipient,
+        ),
+        ExecuteMsg::RevokeStakingOperator { operator } => {
+            try_revoke_operator(deps, &env, &info.sender, &operator)
         }
-        ExecuteMsg::SetStake { token_ids } => try_set_stake(deps, env, &info.sender, token_ids),
-        ExecuteMsg::ClaimStake {} => try_claim_stake(deps, env, &info.sender),
         ExecuteMsg::SetChargeTime { charge_time } => {
             try_set_charge_time(deps, &info.sender, charge_time)
         }
         ExecuteMsg::SetContractInfos {
             svg_server,
             skulls_contract,
-            crate_contract,
-        } => try_set_contracts(
+        } => try_set_contracts(deps, &info.sender, svg_server, skulls_contract),
+        ExecuteMsg::SetCrateContract {
+            name,
+            contract,
+            mint_enabled,
+            uncrate_enabled,
+            make_default,
+            legacy_crates,
+        } => try_set_crate_contract(
             deps,
+            env,
             &info.sender,
-            svg_server,
-            skulls_contract,
-            crate_contract,
-            env.contract.code_hash,
+            name,
+            contract,
+            mint_enabled,
+            uncrate_enabled,
+            make_default,
+            legacy_crates,
         ),
+        ExecuteMsg::RemoveCrateContract { name } => {
+            try_remove_crate_contract(deps, &info.sender, &name)
+        }
+        ExecuteMsg::SetLeaderboardOptIn { opt_in, alias } => {
+            try_set_leaderboard_opt_in(deps, &info.sender, opt_in, alias)
+        }
+        ExecuteMsg::GrantIngredients { grants, memo } => {
+            try_grant_ingredients(deps, &env, &info.sender, grants, memo, false)
+        }
+        ExecuteMsg::RevokeIngredients { revocations, memo } => {
+            try_grant_ingredients(deps, &env, &info.sender, revocations, memo, true)
+        }
         ExecuteMsg::BatchReceiveNft {
             from,
             token_ids,
@@ -212,6 +326,7 @@ pub fn execute(deps: DepsMut, env: Env, info: MessageInfo, msg: ExecuteMsg) -> S
             token_id,
             msg,
         } => try_batch_receive(deps, env, info.sender, &sender, vec![token_id], msg),
+        ExecuteMsg::ReceiveQueryKey { key } => try_receive_query_key(deps, &info.sender, key),
         ExecuteMsg::RevokePermit { permit_name } => {
             revoke_permit(deps.storage, &info.sender, &permit_name)
         }
@@ -226,14 +341,18 @@ pub fn execute(deps: DepsMut, env: Env, info: MessageInfo, msg: ExecuteMsg) -> S
 /// # Arguments
 ///
 /// * `deps` - a mutable reference to Extern containing all the contract's external dependencies
+/// * `env` - a reference to the Env of contract's environment
 /// * `sender` - the message sender
 /// * `crate_ingredients` - ingredients that should be crated
+/// * `crate_contract` - optional name of the crate contract registry entry to mint with
 fn try_mint_crate(
     deps: DepsMut,
+    env: &Env,
     sender: Addr,
     crate_ingredients: Vec<IngredientQty>,
+    crate_contract: Option<String>,
 ) -> StdResult<Response> {
-    let mut crt_state: CrateState = load(deps.storage, CRATE_STATE_KEY)?;
+    let crt_state: CrateState = load(deps.storage, CRATE_STATE_KEY)?;
     if crt_state.halt {
         return Err(StdError::generic_err("Crating has been halted"));
     }
@@ -267,16 +386,103 @@ fn try_mint_crate(
             )));
         }
     }
+    if for_crate.iter().all(|q| *q == 0) {
+        return Err(StdError::generic_err(
+            "You are trying to make an empty crate",
+        ));
+    }
     save(&mut inv_store, user_key, &raw_inv)?;
-    let mut public_metadata: Metadata = load(deps.storage, CRATE_META_KEY)?;
+    let messages = vec![mint_crate(
+        deps.storage,
+        deps.api,
+        sender.into_string(),
+        &ingredients,
+        &for_crate,
+        crate_contract.as_deref(),
+    )?];
+    add_scores(
+        deps.storage,
+        &user_raw,
+        env.block.time.seconds(),
+        &[0, 0, 1],
+    )?;
+    record_event(
+        deps.storage,
+        StoredEvent {
+            user: user_raw.clone(),
+            time: env.block.time.seconds(),
+            action: StoredAction::Crate {
+                ingredients: for_crate,
+            },
+        },
+        &[&user_raw],
+        &[],
+    )?;
+    // display what is left in the inventory
+    for (i, quantity) in raw_inv.into_iter().enumerate() {
+        updated_inventory.push(IngredientQty {
+            ingredient: ingredients[i].clone(),
+            quantity,
+        });
+    }
+
+    Ok(Response::new().add_messages(messages).set_data(to_binary(
+        &ExecuteAnswer::CrateIngredients { updated_inventory },
+    )?))
+}
+
+/// Returns StdResult<CosmosMsg>
+///
+/// create the message to mint a crate nft containing the specified ingredients, and record the
+/// crate's content hash so it can be verified when uncrated
+///
+/// # Arguments
+///
+/// * `storage` - a mutable reference to this contract's storage
+/// * `api` - a reference to the Api used to convert human and canonical addresses
+/// * `owner` - address of the crate's owner
+/// * `ingredients` - list of all ingredients
+/// * `for_crate` - quantities of each ingredient being crated
+/// * `crate_name` - optional name of the crate contract registry entry to mint with
+fn mint_crate(
+    storage: &mut dyn Storage,
+    api: &dyn Api,
+    owner: String,
+    ingredients: &[String],
+    for_crate: &[u32],
+    crate_name: Option<&str>,
+) -> StdResult<CosmosMsg> {
+    let mut raw_crates: Vec<StoredCrateContract> = load(storage, CRATES_KEY)?;
+    let name = if let Some(nm) = crate_name {
+        nm.to_string()
+    } else {
+        load::<String>(storage, DEFAULT_CRATE_KEY)?
+    };
+    let pos = raw_crates
+        .iter()
+        .position(|c| c.name == name)
+        .ok_or_else(|| {
+            StdError::generic_err(format!("{} is not a registered crate contract", name))
+        })?;
+    let raw_crate = raw_crates.swap_remove(pos);
+    if !raw_crate.mint {
+        return Err(StdError::generic_err(format!(
+            "Minting crates with {} has been disabled",
+            name
+        )));
+    }
+    let mut crt_state: CrateState = load(storage, CRATE_STATE_KEY)?;
+    let mut public_metadata: Metadata = load(storage, CRATE_META_KEY)?;
     let mut attrs: Vec<Trait> = Vec::new();
+    let mut total = 0u64;
     // create traits for the crated ingredients
-    for (i, qty) in for_crate.into_iter().enumerate() {
-        if qty > 0 {
+    for (i, qty) in for_crate.iter().enumerate() {
+        if *qty > 0 {
             attrs.push(Trait {
                 trait_type: ingredients[i].clone(),
                 value: qty.to_string(),
             });
+            total += *qty as u64;
         }
     }
     if attrs.is_empty() {
@@ -284,32 +490,114 @@ fn try_mint_crate(
             "You are trying to make an empty crate",
         ));
     }
+    let hash = content_hash(&attrs);
+    attrs.push(Trait {
+        trait_type: "Total Items".to_string(),
+        value: total.to_string(),
+    });
+    attrs.push(Trait {
+        trait_type: "Content Hash".to_string(),
+        value: hash.clone(),
+    });
+    attrs.push(Trait {
+        trait_type: "Crate Id".to_string(),
+        value: crt_state.cnt.to_string(),
+    });
     public_metadata.extension.name =
         Some(format!("Mystic Skulls Ingredient Crate #{}", crt_state.cnt));
+    let crate_id = crt_state.cnt;
     crt_state.cnt += 1;
-    save(deps.storage, CRATE_STATE_KEY, &crt_state)?;
+    save(storage, CRATE_STATE_KEY, &crt_state)?;
     public_metadata.extension.attributes = Some(attrs);
-    let mut raw_crates: Vec<StoreContractInfo> = load(deps.storage, CRATES_KEY)?;
-    let crate_contract = raw_crates
-        .pop()
-        .ok_or_else(|| StdError::generic_err("Crate contracts storage is corrupt"))
-        .and_then(|s| s.into_humanized(deps.api))?;
-    let messages = vec![Snip721HandleMsg::MintNft {
-        owner: sender.into_string(),
-        public_metadata,
+    let art: StoredCrateArt = may_load(storage, CRATE_ART_KEY)?.unwrap_or_default();
+    if let Some(image_data) = crate_image(&art, for_crate) {
+        public_metadata.extension.image = None;
+        public_metadata.extension.image_data = Some(image_data);
     }
-    .to_cosmos_msg(crate_contract.code_hash, crate_contract.address, None)?];
-    // display what is left in the inventory
-    for (i, quantity) in raw_inv.into_iter().enumerate() {
-        updated_inventory.push(IngredientQty {
-            ingredient: ingredients[i].clone(),
-            quantity,
-        });
+    let mut mint_store = PrefixedStorage::new(storage, PREFIX_MINTED_CRATES);
+    save(
+        &mut mint_store,
+        &crate_id.to_le_bytes(),
+        &StoredCrate {
+            contract: raw_crate.contract.address.clone(),
+            hash,
+        },
+    )?;
+    let crate_contract = raw_crate.contract.into_humanized(api)?;
+    Snip721HandleMsg::MintNft {
+        owner,
+        public_metadata,
     }
+    .to_cosmos_msg(crate_contract.code_hash, crate_contract.address, None)
+}
 
-    Ok(Response::new().add_messages(messages).set_data(to_binary(
-        &ExecuteAnswer::CrateIngredients { updated_inventory },
-    )?))
+/// Returns String
+///
+/// the hex encoded sha256 hash of a crate's contents.  The hashed string lists each crated
+/// ingredient as `name:quantity` in ingredient order, separated by `;`
+///
+/// # Arguments
+///
+/// * `attrs` - the ingredient traits of the crate
+fn content_hash(attrs: &[Trait]) -> String {
+    let contents = attrs
+        .iter()
+        .map(|t| format!("{}:{}", t.trait_type, t.value))
+        .collect::<Vec<String>>()
+        .join(";");
+    sha_256(contents.as_bytes())
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// Returns Option<String>
+///
+/// generate the svg image of a crate by drawing the icon of each crated ingredient in a
+/// square grid on top of the background.  Returns None if no crate art applies
+///
+/// # Arguments
+///
+/// * `art` - a reference to the crate art
+/// * `for_crate` - quantities of each ingredient being crated
+fn crate_image(art: &StoredCrateArt, for_crate: &[u32]) -> Option<String> {
+    let icons: Vec<&String> = for_crate
+        .iter()
+        .enumerate()
+        .filter_map(|(i, qty)| {
+            if *qty > 0 {
+                art.icons.get(i).and_then(|o| o.as_ref())
+            } else {
+                None
+            }
+        })
+        .collect();
+    if icons.is_empty() && art.background.is_none() {
+        return None;
+    }
+    // find the smallest square grid that fits all the icons
+    let mut cols = 1usize;
+    while cols * cols < icons.len() {
+        cols += 1;
+    }
+    let size = 24 * cols;
+    let mut image_data = format!(
+        r###"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 -0.5 {} {}" shape-rendering="crispEdges">"###,
+        size, size
+    );
+    if let Some(bg) = art.background.as_ref() {
+        image_data.push_str(&format!(r#"<g transform="scale({})">{}</g>"#, cols, bg));
+    }
+    for (i, icon) in icons.into_iter().enumerate() {
+        image_data.push_str(&format!(
+            r#"<g transform="translate({} {})">{}</g>"#,
+            24 * (i % cols),
+            24 * (i / cols),
+            icon
+        ));
+    }
+    image_data.push_str("</svg>");
+    Some(image_data)
 }
 
 /// Returns StdResult<Response>
@@ -321,36 +609,56 @@ fn try_mint_crate(
 /// * `deps` - a mutable reference to Extern containing all the contract's external dependencies
 /// * `env` - the Env of contract's environment
 /// * `sender` - a reference to the message sender
-fn try_claim_stake(deps: DepsMut, env: Env, sender: &Addr) -> StdResult<Response> {
+/// * `owner` - optional address of the skull owner if the sender is their operator
+fn try_claim_stake(
+    deps: DepsMut,
+    env: Env,
+    sender: &Addr,
+    owner: Option<String>,
+) -> StdResult<Response> {
     let stk_state: StakingState = load(deps.storage, STAKING_STATE_KEY)?;
     if stk_state.halt {
         return Err(StdError::generic_err("Staking has been halted"));
     }
+    let (owner, user_raw, rcpt_raw) = get_staker(deps.as_ref(), &env, sender, owner, false)?;
     let user_store = ReadonlyPrefixedStorage::new(deps.storage, PREFIX_USER_STAKE);
-    let user_raw = deps.api.addr_canonicalize(sender.as_str())?;
     let user_key = user_raw.as_slice();
     // get staking list and only keep the ones the user still owns
     let old_list = may_load::<Vec<String>>(&user_store, user_key)?
         .ok_or_else(|| StdError::generic_err("You have never started staking"))?;
-    if old_list.is_empty() {
+    let mut streams: Vec<StoredStream> =
+        may_load(deps.storage, REWARD_STREAMS_KEY)?.unwrap_or_default();
+    // start with the rewards previous claims could not pay
+    let mut owed = load_unpaid(deps.storage, user_key, &streams)?;
+    // unpaid rewards can be claimed even without charges
+    let has_unpaid = payable(&streams, &owed).iter().any(|p| *p > 0);
+    if old_list.is_empty() && !has_unpaid {
         return Err(StdError::generic_err("You are not staking any skulls"));
     }
     let (id_images, _) = verify_ownership(
         deps.as_ref(),
-        sender.as_str(),
+        owner.as_str(),
         old_list,
         env.contract.address.to_string(),
     )?;
-    if id_images.is_empty() {
+    if id_images.is_empty() && !has_unpaid {
         return Err(StdError::generic_err(
             "You no longer own any of the skulls you were staking",
         ));
     }
     let materials: Vec<String> = may_load(deps.storage, MATERIALS_KEY)?.unwrap_or_default();
-    let mut charges: Vec<u8> = vec![0; materials.len()];
-    let mut quantities: Vec<u8> = charges.clone();
+    let boosts: Vec<StoredBoost> = may_load(deps.storage, STAKING_BOOSTS_KEY)?.unwrap_or_default();
+    let seasons: Vec<StoredSeason> =
+        may_load(deps.storage, STAKING_SEASONS_KEY)?.unwrap_or_default();
+    let rules = load_rules(deps.storage)?;
+    // charges left under the user's charge cap
+    let mut remaining = rules.charge_cap;
+    let mut groups: Vec<ChargeGroup> = Vec::new();
+    let mut quantities: Vec<u8> = vec![0; materials.len()];
     let mut charge_infos: Vec<ChargeInfo> = Vec::new();
     let mut new_list: Vec<String> = Vec::new();
+    let mut stakes: Vec<u64> = Vec::new();
+    let mut claimed: Vec<String> = Vec::new();
     let now = env.block.time.seconds();
     let mut skull_store = PrefixedStorage::new(deps.storage, PREFIX_SKULL_STAKE);
     for id_img in id_images.into_iter() {
@@ -365,42 +673,152 @@ fn try_claim_stake(deps: DepsMut, env: Env, sender: &Addr) -> StdResult<Response
         if stk_inf.addr != user_raw {
             continue;
         }
-        let time_in_stake = now - stk_inf.stake;
-        // tally accrued charges
-        let charge_cnt = min(4, time_in_stake / stk_state.cooldown) as u8;
+        // tally accrued charges and the seasons they were earned in
+        let (mut earned, time_of_maturity) = earned_charges(
+            &seasons,
+            stk_state.cooldown,
+            stk_inf.stake,
+            now,
+            rules.charge_cap,
+        );
+        let skull_boosts = get_boosts(&boosts, &id_img.image.current);
         // if this skull has charge
-        if charge_cnt > 0 {
-            // tally skull materials
-            quantities[id_img.image.natural[stk_state.skull_idx as usize] as usize] += 1;
-            charges[id_img.image.natural[stk_state.skull_idx as usize] as usize] += charge_cnt;
-            let time_of_maturity = now - (time_in_stake % stk_state.cooldown);
+        if !earned.is_empty() {
+            let material = id_img.image.natural[stk_state.skull_idx as usize];
+            // charges over the user's cap are forfeited
+            if rules.cap_per_user {
+                earned.truncate(remaining as usize);
+                remaining -= earned.len() as u8;
+            }
+            add_owed(
+                &streams,
+                &mut owed,
+                material,
+                earned.len(),
+                charge_seconds(&seasons, stk_state.cooldown, stk_inf.stake, earned.len()),
+            );
+            if !earned.is_empty() {
+                // tally skull materials
+                quantities[material as usize] += 1;
+                for season in earned.into_iter() {
+                    add_charge_group(&mut groups, material, season, 1, skull_boosts.clone());
+                }
+                claimed.push(id_img.id.clone());
+            }
             stk_inf.stake = time_of_maturity;
             stk_inf.claim = time_of_maturity;
             save(&mut skull_store, id_key, &stk_inf)?;
         }
         new_list.push(id_img.id.clone());
+        stakes.push(stk_inf.stake);
         charge_infos.push(ChargeInfo {
             token_id: id_img.id,
             charge_start: stk_inf.stake,
             charges: 0,
+            boosts: boost_names(&boosts, &skull_boosts),
         });
     }
     let mut user_store = PrefixedStorage::new(deps.storage, PREFIX_USER_STAKE);
     save(&mut user_store, user_key, &new_list)?;
-    let rewards: Vec<IngredientQty> = if charges.iter().any(|i| *i > 0) {
-        process_charges(deps.storage, &env, &charges, &quantities, user_key)?
+    let (rewards, generated, pity_drops) = if !groups.is_empty() {
+        process_charges(
+            deps.storage,
+            &env,
+            &groups,
+            &quantities,
+            rcpt_raw.as_slice(),
+            user_key,
+        )?
+    } else if has_unpaid {
+        (Vec::new(), Vec::new(), Vec::new())
     } else {
         return Err(StdError::generic_err(
             "None of your staked skulls have charges",
         ));
     };
+    // only claims with charges are scored and recorded
+    if !groups.is_empty() {
+        let charges: u16 = groups.iter().map(|g| g.charges).sum();
+        add_scores(
+            deps.storage,
+            &user_raw,
+            now,
+            &[charges as u64, generated.iter().map(|q| *q as u64).sum(), 0],
+        )?;
+        let recipient = (rcpt_raw != user_raw).then(|| rcpt_raw.clone());
+        record_event(
+            deps.storage,
+            StoredEvent {
+                user: user_raw.clone(),
+                time: now,
+                action: StoredAction::Claim {
+                    token_ids: claimed.clone(),
+                    charges,
+                    rewards: generated,
+                    recipient,
+                },
+            },
+            &[&user_raw, &rcpt_raw],
+            &claimed,
+        )?;
+    }
+    // pay out any SNIP-20 rewards
+    let mut token_rewards: Vec<TokenReward> = Vec::new();
+    let mut messages: Vec<CosmosMsg> = Vec::new();
+    let paid = payable(&streams, &owed);
+    let unpaid = carry_unpaid(&streams, &owed, &paid);
+    let mut unpaid_store = PrefixedStorage::new(deps.storage, PREFIX_UNPAID_TOKENS);
+    if unpaid.is_empty() {
+        remove(&mut unpaid_store, user_key);
+    } else {
+        save(&mut unpaid_store, user_key, &unpaid)?;
+    }
+    if paid.iter().any(|p| *p > 0) {
+        let rcpt_hmn = deps.api.addr_humanize(&rcpt_raw)?.into_string();
+        for (stream, amt) in streams.iter_mut().zip(paid) {
+            if amt > 0 {
+                stream.reserve -= amt;
+                stream.distributed += amt;
+                let token = stream.token.get_humanized(deps.api)?;
+                messages.push(
+                    Snip20HandleMsg::Transfer {
+                        recipient: rcpt_hmn.clone(),
+                        amount: Uint128::new(amt),
+                        memo: None,
+                        padding: None,
+                    }
+                    .to_cosmos_msg(
+                        token.code_hash,
+                        token.address.clone(),
+                        None,
+                    )?,
+                );
+                token_rewards.push(TokenReward {
+                    token: token.address,
+                    amount: Uint128::new(amt),
+                });
+            }
+        }
+        save(deps.storage, REWARD_STREAMS_KEY, &streams)?;
+    }
 
-    Ok(
-        Response::new().set_data(to_binary(&ExecuteAnswer::StakeInfo {
-            charge_infos,
-            rewards,
-        })?),
-    )
+    let mut resp = Response::new().add_messages(messages);
+    if !rewards.is_empty() {
+        let note = notify(deps.storage, &rcpt_raw, INGREDIENTS_CHANNEL)?;
+        resp = resp.add_attribute_plaintext(note, "");
+    }
+    let due = cap_time(&seasons, stk_state.cooldown, &rules, &stakes);
+    schedule_cap_notification(deps.storage, &user_raw, due, now)?;
+    for note in due_cap_notifications(deps.storage, now)?.into_iter() {
+        resp = resp.add_attribute_plaintext(note, "");
+    }
+
+    Ok(resp.set_data(to_binary(&ExecuteAnswer::StakeInfo {
+        charge_infos,
+        rewards,
+        token_rewards,
+        pity_drops,
+    })?))
 }
 
 /// Returns StdResult<Response>
@@ -413,25 +831,35 @@ fn try_claim_stake(deps: DepsMut, env: Env, sender: &Addr) -> StdResult<Response
 /// * `env` - the Env of contract's environment
 /// * `sender` - a reference to the message sender
 /// * `token_ids` - list of skull ids to stake
+/// * `held_token_ids` - optional list of other owned skulls used to qualify for a staking tier
+/// * `owner` - optional address of the skull owner if the sender is their operator
 fn try_set_stake(
     deps: DepsMut,
     env: Env,
     sender: &Addr,
     token_ids: Vec<String>,
+    held_token_ids: Option<Vec<String>>,
+    owner: Option<String>,
 ) -> StdResult<Response> {
     let stk_state: StakingState = load(deps.storage, STAKING_STATE_KEY)?;
     if stk_state.halt {
         return Err(StdError::generic_err("Staking has been halted"));
     }
+    let (owner, user_raw, rcpt_raw) = get_staker(deps.as_ref(), &env, sender, owner, true)?;
+    let rules = load_rules(deps.storage)?;
     let skull_cnt = token_ids.len();
     // check if staking an appropriate number
-    if skull_cnt > 5 {
-        return Err(StdError::generic_err("You can only stake up to 5 skulls"));
+    let max_cnt = max_staked(&rules, None);
+    if skull_cnt > max_cnt as usize {
+        return Err(StdError::generic_err(format!(
+            "You can only stake up to {} skulls",
+            max_cnt
+        )));
     }
     // check if sender owns all the skulls they are trying to stake
     let (id_images, not_owned) = verify_ownership(
         deps.as_ref(),
-        sender.as_str(),
+        owner.as_str(),
         token_ids,
         env.contract.address.to_string(),
     )?;
@@ -448,21 +876,49 @@ fn try_set_stake(
         }
         return Err(StdError::generic_err(err_str));
     }
+    // if staking more than the base limit, verify enough skulls are owned to qualify for a tier
+    if id_images.len() > rules.max_staked as usize {
+        let held: Vec<String> = held_token_ids
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|h| !id_images.iter().any(|i| i.id == *h))
+            .collect();
+        let (held_images, _) = verify_ownership(
+            deps.as_ref(),
+            owner.as_str(),
+            held,
+            env.contract.address.to_string(),
+        )?;
+        let allowed = max_staked(&rules, Some(id_images.len() + held_images.len()));
+        if id_images.len() > allowed as usize {
+            return Err(StdError::generic_err(format!(
+                "You only own enough skulls to stake up to {}",
+                allowed
+            )));
+        }
+    }
     let user_store = ReadonlyPrefixedStorage::new(deps.storage, PREFIX_USER_STAKE);
-    let user_raw = deps.api.addr_canonicalize(sender.as_str())?;
     let user_key = user_raw.as_slice();
     let do_claim = may_load::<Vec<String>>(&user_store, user_key)?.is_none();
     // if they never started claiming, but sent an empty list
-    if do_claim && skull_cnt == 0 {
+    if do_claim && rules.first_stake_bonus > 0 && skull_cnt == 0 {
         return Err(StdError::generic_err(
             "Do not waste your First-Stake reward by initializing an empty staking inventory",
         ));
     }
     let materials: Vec<String> = may_load(deps.storage, MATERIALS_KEY)?.unwrap_or_default();
-    let mut charges: Vec<u8> = vec![0; materials.len()];
+    let boosts: Vec<StoredBoost> = may_load(deps.storage, STAKING_BOOSTS_KEY)?.unwrap_or_default();
+    let seasons: Vec<StoredSeason> =
+        may_load(deps.storage, STAKING_SEASONS_KEY)?.unwrap_or_default();
+    let mut groups: Vec<ChargeGroup> = Vec::new();
+    let mut quantities: Vec<u8> = vec![0; materials.len()];
     let mut charge_infos: Vec<ChargeInfo> = Vec::new();
     let mut stk_list: Vec<String> = Vec::new();
+    let mut stakes: Vec<u64> = Vec::new();
+    let mut bonus_list: Vec<String> = Vec::new();
+    let mut remaining = rules.charge_cap;
     let now = env.block.time.seconds();
+    let season = active_season(&seasons, now);
     let cutoff = now - stk_state.cooldown;
     let mut skull_store = PrefixedStorage::new(deps.storage, PREFIX_SKULL_STAKE);
     for id_img in id_images.into_iter() {
@@ -473,11 +929,21 @@ fn try_set_stake(
                 stake: now,
                 claim: 0,
             });
+        let skull_boosts = get_boosts(&boosts, &id_img.image.current);
         // generate resources if first time user has staked
         // don't allow a first stake reward to be given out for skulls that have been claimed within 1 cooldown
-        if do_claim && stk_inf.claim <= cutoff {
-            charges[id_img.image.natural[stk_state.skull_idx as usize] as usize] += 1;
+        if do_claim && rules.first_stake_bonus > 0 && stk_inf.claim <= cutoff {
+            let material = id_img.image.natural[stk_state.skull_idx as usize];
+            quantities[material as usize] += 1;
+            add_charge_group(
+                &mut groups,
+                material,
+                season,
+                rules.first_stake_bonus as u16,
+                skull_boosts.clone(),
+            );
             stk_inf.claim = now;
+            bonus_list.push(id_img.id.clone());
         }
         // if user has not been staking this skull
         if stk_inf.addr != user_raw {
@@ -486,26 +952,197 @@ fn try_set_stake(
         }
         save(&mut skull_store, id_key, &stk_inf)?;
         stk_list.push(id_img.id.clone());
+        stakes.push(stk_inf.stake);
+        let charges = capped_charges(
+            &rules,
+            &mut remaining,
+            earned_charges(
+                &seasons,
+                stk_state.cooldown,
+                stk_inf.stake,
+                now,
+                rules.charge_cap,
+            )
+            .0
+            .len(),
+        );
         charge_infos.push(ChargeInfo {
             token_id: id_img.id,
             charge_start: stk_inf.stake,
-            charges: min(4, (now - stk_inf.stake) / stk_state.cooldown) as u8,
+            charges,
+            boosts: boost_names(&boosts, &skull_boosts),
         });
     }
     let mut user_store = PrefixedStorage::new(deps.storage, PREFIX_USER_STAKE);
     save(&mut user_store, user_key, &stk_list)?;
-    let rewards: Vec<IngredientQty> = if charges.iter().any(|i| *i > 0) {
-        process_charges(deps.storage, &env, &charges, &charges, user_key)?
-    } else if do_claim {
+    record_event(
+        deps.storage,
+        StoredEvent {
+            user: user_raw.clone(),
+            time: now,
+            action: StoredAction::SetStake {
+                token_ids: stk_list.clone(),
+            },
+        },
+        &[&user_raw],
+        &stk_list,
+    )?;
+    let mut resp = Response::new();
+    let (rewards, pity_drops) = if !groups.is_empty() {
+        let (rewards, generated, pity_drops) = process_charges(
+            deps.storage,
+            &env,
+            &groups,
+            &quantities,
+            rcpt_raw.as_slice(),
+            user_key,
+        )?;
+        add_scores(
+            deps.storage,
+            &user_raw,
+            now,
+            &[0, generated.iter().map(|q| *q as u64).sum(), 0],
+        )?;
+        let recipient = (rcpt_raw != user_raw).then(|| rcpt_raw.clone());
+        record_event(
+            deps.storage,
+            StoredEvent {
+                user: user_raw.clone(),
+                time: now,
+                action: StoredAction::FirstStakeBonus {
+                    token_ids: bonus_list.clone(),
+                    rewards: generated,
+                    recipient,
+                },
+            },
+            &[&user_raw, &rcpt_raw],
+            &bonus_list,
+        )?;
+        let note = notify(deps.storage, &rcpt_raw, INGREDIENTS_CHANNEL)?;
+        resp = resp.add_attribute_plaintext(note, "");
+        (rewards, pity_drops)
+    } else if do_claim && rules.first_stake_bonus > 0 {
         return Err(StdError::generic_err("All skulls being staked have not cooled down long enough and are not eligible for First-Stake rewards and would waste this one time offer"));
     } else {
-        Vec::new()
+        (Vec::new(), Vec::new())
     };
+    let due = cap_time(&seasons, stk_state.cooldown, &rules, &stakes);
+    schedule_cap_notification(deps.storage, &user_raw, due, now)?;
+    for note in due_cap_notifications(deps.storage, now)?.into_iter() {
+        resp = resp.add_attribute_plaintext(note, "");
+    }
+
+    Ok(resp.set_data(to_binary(&ExecuteAnswer::StakeInfo {
+        charge_infos,
+        rewards,
+        token_rewards: Vec::new(),
+        pity_drops,
+    })?))
+}
+
+/// Returns StdResult<Response>
+///
+/// authorize an operator to claim and/or stake on the sender's behalf
+///
+/// # Arguments
+///
+/// * `deps` - a mutable reference to Extern containing all the contract's external dependencies
+/// * `env` - a reference to the Env of contract's environment
+/// * `sender` - a reference to the message sender
+/// * `operator` - address of the operator
+/// * `scope` - actions the operator is allowed to perform
+/// * `expiration` - optional time the authorization expires
+/// * `recipient` - optional address whose inventory should receive claimed rewards
+fn try_set_operator(
+    deps: DepsMut,
+    env: &Env,
+    sender: &Addr,
+    operator: &str,
+    scope: OperatorScope,
+    expiration: Option<u64>,
+    recipient: Option<String>,
+) -> StdResult<Response> {
+    let now = env.block.time.seconds();
+    if expiration.map_or(false, |e| e <= now) {
+        return Err(StdError::generic_err("Expiration time has already passed"));
+    }
+    let owner_raw = deps.api.addr_canonicalize(sender.as_str())?;
+    let op_raw = deps
+        .api
+        .addr_validate(operator)
+        .and_then(|a| deps.api.addr_canonicalize(a.as_str()))?;
+    if op_raw == owner_raw {
+        return Err(StdError::generic_err(
+            "You can not be your own staking operator",
+        ));
+    }
+    let recipient = recipient
+        .map(|r| {
+            deps.api
+                .addr_validate(&r)
+                .and_then(|a| deps.api.addr_canonicalize(a.as_str()))
+        })
+        .transpose()?;
+    let owner_key = owner_raw.as_slice();
+    let mut op_store = PrefixedStorage::new(deps.storage, PREFIX_STAKING_OPERATORS);
+    let mut operators: Vec<StoredOperator> = may_load(&op_store, owner_key)?.unwrap_or_default();
+    // remove expired authorizations and any previous one for this operator
+    operators.retain(|o| o.operator != op_raw && o.expiration.map_or(true, |e| now < e));
+    operators.push(StoredOperator {
+        operator: op_raw,
+        scope,
+        expiration,
+        recipient,
+    });
+    save(&mut op_store, owner_key, &operators)?;
+
+    Ok(
+        Response::new().set_data(to_binary(&ExecuteAnswer::StakingOperators {
+            operators: display_operators(deps.api, operators)?,
+        })?),
+    )
+}
+
+/// Returns StdResult<Response>
+///
+/// revoke an operator's authorization to claim and/or stake on the sender's behalf
+///
+/// # Arguments
+///
+/// * `deps` - a mutable reference to Extern containing all the contract's external dependencies
+/// * `env` - a reference to the Env of contract's environment
+/// * `sender` - a reference to the message sender
+/// * `operator` - address of the operator
+fn try_revoke_operator(
+    deps: DepsMut,
+    env: &Env,
+    sender: &Addr,
+    operator: &str,
+) -> StdResult<Response> {
+    let now = env.block.time.seconds();
+    let owner_raw = deps.api.addr_canonicalize(sender.as_str())?;
+    let op_raw = deps
+        .api
+        .addr_validate(operator)
+        .and_then(|a| deps.api.addr_canonicalize(a.as_str()))?;
+    let owner_key = owner_raw.as_slice();
+    let mut op_store = PrefixedStorage::new(deps.storage, PREFIX_STAKING_OPERATORS);
+    let mut operators: Vec<StoredOperator> = may_load(&op_store, owner_key)?.unwrap_or_default();
+    let old_len = operators.len();
+    operators.retain(|o| o.operator != op_raw);
+    if operators.len() == old_len {
+        return Err(StdError::generic_err(format!(
+            "{} is not one of your staking operators",
+            operator
+        )));
+    }
+    // also clean up expired authorizations
+    operators.retain(|o| o.expiration.map_or(true, |e| now < e));
+    save(&mut op_store, owner_key, &operators)?;
 
     Ok(
-        Response::new().set_data(to_binary(&ExecuteAnswer::StakeInfo {
-            charge_infos,
-            rewards,
+        Response::new().set_data(to_binary(&ExecuteAnswer::StakingOperators {
+            operators: display_operators(deps.api, operators)?,
         })?),
     )
 }
@@ -524,25 +1161,36 @@ fn try_set_stake(
 /// * `msg` - the base64 encoded msg containing the skull to apply the potion to (if applicable)
 fn try_batch_receive(
     deps: DepsMut,
-    _env: Env,
+    env: Env,
     sender: Addr,
     from: &str,
     token_ids: Vec<String>,
-    _msg: Option<Binary>,
+    msg: Option<Binary>,
 ) -> StdResult<Response> {
-    let mut raw_crates: Vec<StoreContractInfo> = load(deps.storage, CRATES_KEY)?;
+    let mut raw_crates: Vec<StoredCrateContract> = load(deps.storage, CRATES_KEY)?;
     let sender_raw = deps.api.addr_canonicalize(sender.as_str())?;
-    if let Some(pos) = raw_crates.iter().position(|c| c.address == sender_raw) {
+    if let Some(pos) = raw_crates
+        .iter()
+        .position(|c| c.contract.address == sender_raw)
+    {
         let crt_state: CrateState = load(deps.storage, CRATE_STATE_KEY)?;
         if crt_state.halt {
             return Err(StdError::generic_err("Uncrating has been halted"));
         }
+        if !raw_crates[pos].uncrate {
+            return Err(StdError::generic_err(format!(
+                "Uncrating {} crates has been disabled",
+                raw_crates[pos].name
+            )));
+        }
         uncrate(
             deps,
+            &env,
             sender.into_string(),
-            raw_crates.swap_remove(pos).code_hash,
+            raw_crates.swap_remove(pos),
             from,
             token_ids,
+            msg,
         )
     } else {
         Err(StdError::generic_err(
@@ -561,16 +1209,11 @@ fn try_batch_receive(
 /// * `sender` - a reference to the message sender
 /// * `new_svg_server` - optional code hash and address of the svg server
 /// * `new_skulls_contract` - optional code hash and address of the skulls contract
-/// * `new_crate_contract` - optional code hash and address of a crating contract (can either update the code
-///                     hash of an existing one or add a new one)
-/// * `code_hash` - code hash of this contract
 fn try_set_contracts(
     deps: DepsMut,
     sender: &Addr,
     new_svg_server: Option<ContractInfo>,
     new_skulls_contract: Option<ContractInfo>,
-    new_crate_contract: Option<ContractInfo>,
-    code_hash: String,
 ) -> StdResult<Response> {
     // only allow admins to do this
     check_admin_tx(deps.as_ref(), sender)?;
@@ -606,23 +1249,6 @@ fn try_set_contracts(
         load::<StoreContractInfo>(deps.storage, SKULL_721_KEY)
             .and_then(|s| s.into_humanized(deps.api))?
     };
-    let mut raw_crates: Vec<StoreContractInfo> = load(deps.storage, CRATES_KEY)?;
-    if let Some(crt) = new_crate_contract {
-        let raw = crt.get_store(deps.api)?;
-        if let Some(old) = raw_crates.iter_mut().find(|c| c.address == raw.address) {
-            old.code_hash = raw.code_hash;
-        } else {
-            raw_crates.push(raw);
-        }
-        save(deps.storage, CRATES_KEY, &raw_crates)?;
-        messages.push(
-            Snip721HandleMsg::RegisterReceiveNft {
-                code_hash,
-                also_implements_batch_receive_nft: true,
-            }
-            .to_cosmos_msg(crt.code_hash, crt.address, None)?,
-        );
-    }
 
     let mut resp = Response::new();
     if !messages.is_empty() {
@@ -631,49 +1257,427 @@ fn try_set_contracts(
     Ok(resp.set_data(to_binary(&ExecuteAnswer::SetContractInfos {
         svg_server,
         skulls_contract,
-        crate_contracts: raw_crates
-            .into_iter()
-            .map(|s| s.into_humanized(deps.api))
-            .collect::<StdResult<Vec<ContractInfo>>>()?,
+        crate_contracts: display_crates(deps.storage, deps.api)?,
     })?))
 }
 
 /// Returns StdResult<Response>
 ///
-/// set the staking charge time
+/// add or update an entry of the crate contract registry
 ///
 /// # Arguments
 ///
 /// * `deps` - a mutable reference to Extern containing all the contract's external dependencies
+/// * `env` - Env of contract's environment
 /// * `sender` - a reference to the message sender
-/// * `charge_time` - staking charge time in seconds
-fn try_set_charge_time(deps: DepsMut, sender: &Addr, charge_time: u64) -> StdResult<Response> {
+/// * `name` - name of the registry entry
+/// * `contract` - optional code hash and address of the crate contract
+/// * `mint_enabled` - optionally true if crates can be minted with this contract
+/// * `uncrate_enabled` - optionally true if this contract's crates can be uncrated
+/// * `make_default` - optionally true if this entry should become the default
+/// * `legacy_crates` - optional number of crates the contract minted before crates were recorded
+fn try_set_crate_contract(
+    deps: DepsMut,
+    env: Env,
+    sender: &Addr,
+    name: String,
+    contract: Option<ContractInfo>,
+    mint_enabled: Option<bool>,
+    uncrate_enabled: Option<bool>,
+    make_default: Option<bool>,
+    legacy_crates: Option<u64>,
+) -> StdResult<Response> {
     // only allow admins to do this
     check_admin_tx(deps.as_ref(), sender)?;
-
-    let mut stk_st: StakingState = load(deps.storage, STAKING_STATE_KEY)?;
-    if stk_st.cooldown != charge_time {
-        stk_st.cooldown = charge_time;
-        save(deps.storage, STAKING_STATE_KEY, &stk_st)?;
+    let mut raw_crates: Vec<StoredCrateContract> = load(deps.storage, CRATES_KEY)?;
+    let raw = contract
+        .as_ref()
+        .map(|c| c.get_store(deps.api))
+        .transpose()?;
+    if let Some(new) = raw.as_ref() {
+        if raw_crates
+            .iter()
+            .any(|c| c.contract.address == new.address && c.name != name)
+        {
+            return Err(StdError::generic_err(format!(
+                "{} is already registered under a different name",
+                contract
+                    .as_ref()
+                    .map(|c| c.address.as_str())
+                    .unwrap_or_default()
+            )));
+        }
+    }
+    if let Some(entry) = raw_crates.iter_mut().find(|c| c.name == name) {
+        if let Some(new) = raw {
+            // crates minted with the old address are verified against the entry, so only its
+            // code hash may change
+            if new.address != entry.contract.address {
+                return Err(StdError::generic_err(format!(
+                    "The address of crate contract entry {} can not be changed.  Add a new entry \
+                     instead",
+                    name
+                )));
+            }
+            entry.contract.code_hash = new.code_hash;
+        }
+        if let Some(mint) = mint_enabled {
+            entry.mint = mint;
+        }
+        if let Some(uncrate) = uncrate_enabled {
+            entry.uncrate = uncrate;
+        }
+    } else {
+        raw_crates.push(StoredCrateContract {
+            name: name.clone(),
+            contract: raw.ok_or_else(|| {
+                StdError::generic_err("A contract must be provided for a new crate contract entry")
+            })?,
+            mint: mint_enabled.unwrap_or(true),
+            uncrate: uncrate_enabled.unwrap_or(true),
+        });
+    }
+    let make_default = make_default.unwrap_or(false);
+    let default = if make_default {
+        name.clone()
+    } else {
+        load::<String>(deps.storage, DEFAULT_CRATE_KEY)?
+    };
+    // the default crate contract is used for minting
+    if !raw_crates.iter().any(|c| c.name == default && c.mint) {
+        return Err(StdError::generic_err(format!(
+            "The default crate contract {} must have minting enabled",
+            default
+        )));
+    }
+    if let Some(cnt) = legacy_crates {
+        let address = raw_crates
+            .iter()
+            .find(|c| c.name == name)
+            .map(|c| c.contract.address.clone())
+            .ok_or_else(|| StdError::generic_err("Crate contract entry storage is corrupt"))?;
+        let mut lgcy_store = PrefixedStorage::new(deps.storage, PREFIX_LEGACY_CRATES);
+        save(&mut lgcy_store, address.as_slice(), &cnt)?;
+    }
+    save(deps.storage, CRATES_KEY, &raw_crates)?;
+    if make_default {
+        save(deps.storage, DEFAULT_CRATE_KEY, &name)?;
+    }
+    let mut resp = Response::new();
+    // register with any new or updated contract
+    if let Some(crt) = contract {
+        resp = resp.add_message(
+            Snip721HandleMsg::RegisterReceiveNft {
+                code_hash: env.contract.code_hash,
+                also_implements_batch_receive_nft: true,
+            }
+            .to_cosmos_msg(crt.code_hash, crt.address, None)?,
+        );
     }
 
-    Ok(
-        Response::new().set_data(to_binary(&ExecuteAnswer::SetChargeTime {
-            charge_time: stk_st.cooldown,
-        })?),
-    )
+    Ok(resp.set_data(to_binary(&ExecuteAnswer::CrateContracts {
+        crate_contracts: display_crates(deps.storage, deps.api)?,
+    })?))
 }
 
 /// Returns StdResult<Response>
 ///
-/// set the halt status of staking, crating, and/or alchemy
+/// join or leave the public leaderboards
 ///
 /// # Arguments
 ///
 /// * `deps` - a mutable reference to Extern containing all the contract's external dependencies
 /// * `sender` - a reference to the message sender
-/// * `staking` - optionally set staking halt status
-/// * `alchemy` - optionally set alchemy halt status
+/// * `opt_in` - true if the sender should appear on the leaderboards
+/// * `alias` - optional alias displayed instead of the sender's address
+fn try_set_leaderboard_opt_in(
+    deps: DepsMut,
+    sender: &Addr,
+    opt_in: bool,
+    alias: Option<String>,
+) -> StdResult<Response> {
+    let user_raw = deps.api.addr_canonicalize(sender.as_str())?;
+    let user_key = user_raw.as_slice();
+    let mut opt_store = PrefixedStorage::new(deps.storage, PREFIX_LEADER_OPT);
+    let old: Option<StoredOptIn> = may_load(&opt_store, user_key)?;
+    let was_opted_in = old.is_some();
+    if opt_in {
+        save(
+            &mut opt_store,
+            user_key,
+            &StoredOptIn {
+                alias: alias.clone(),
+            },
+        )?;
+    } else {
+        remove(&mut opt_store, user_key);
+    }
+    // release any previous alias
+    if let Some(old_alias) = old.and_then(|o| o.alias) {
+        let mut alias_store = PrefixedStorage::new(deps.storage, PREFIX_LEADER_ALIAS);
+        remove(&mut alias_store, old_alias.to_lowercase().as_bytes());
+    }
+    let alias = if opt_in { alias } else { None };
+    if let Some(new_alias) = alias.as_ref() {
+        let len = new_alias.chars().count();
+        if len == 0 || len > 32 || new_alias.trim() != new_alias {
+            return Err(StdError::generic_err(
+                "Aliases must be 1 to 32 characters without leading or trailing spaces",
+            ));
+        }
+        let alias_key = new_alias.to_lowercase();
+        let mut alias_store = PrefixedStorage::new(deps.storage, PREFIX_LEADER_ALIAS);
+        if may_load::<CanonicalAddr>(&alias_store, alias_key.as_bytes())?.is_some() {
+            return Err(StdError::generic_err(format!(
+                "The alias {} is already in use",
+                new_alias
+            )));
+        }
+        save(&mut alias_store, alias_key.as_bytes(), &user_raw)?;
+    }
+    // add or remove the user from the all-time and seasonal leaderboards
+    if opt_in != was_opted_in {
+        // include the leaderboards of seasons that were removed from the schedule
+        let next_id: u16 = may_load(deps.storage, SEASON_ID_KEY)?.unwrap_or(0);
+        // the all-time leaderboards use an empty season key
+        let mut season_keys: Vec<Vec<u8>> = vec![Vec::new()];
+        season_keys.extend((0..next_id).map(|id| id.to_le_bytes().to_vec()));
+        for season_key in season_keys.iter().map(|k| k.as_slice()) {
+            let score_store = ReadonlyPrefixedStorage::multilevel(
+                deps.storage,
+                &[PREFIX_LEADER_SCORES, season_key],
+            );
+            if let Some(scores) = may_load::<Vec<u64>>(&score_store, user_key)? {
+                for (metric, score) in scores.into_iter().enumerate() {
+                    let (old, new) = if opt_in {
+                        (None, Some(score))
+                    } else {
+                        (Some(score), None)
+                    };
+                    rank_user(deps.storage, season_key, metric as u8, &user_raw, old, new)?;
+                }
+            }
+        }
+    }
+
+    Ok(
+        Response::new().set_data(to_binary(&ExecuteAnswer::SetLeaderboardOptIn {
+            opted_in: opt_in,
+            alias,
+        })?),
+    )
+}
+
+/// Returns StdResult<Response>
+///
+/// grant ingredients to or revoke ingredients from user inventories, and record the change in
+/// the admin audit log
+///
+/// # Arguments
+///
+/// * `deps` - a mutable reference to Extern containing all the contract's external dependencies
+/// * `env` - a reference to the Env of contract's environment
+/// * `sender` - a reference to the message sender
+/// * `grants` - list of addresses and the ingredients to grant or revoke
+/// * `memo` - reason for the change
+/// * `revoke` - true if the ingredients should be removed from the inventories
+fn try_grant_ingredients(
+    deps: DepsMut,
+    env: &Env,
+    sender: &Addr,
+    grants: Vec<IngredientGrant>,
+    memo: String,
+    revoke: bool,
+) -> StdResult<Response> {
+    // only allow admins to do this
+    check_admin_tx(deps.as_ref(), sender)?;
+    let admin = deps.api.addr_canonicalize(sender.as_str())?;
+    if memo.trim().is_empty() {
+        return Err(StdError::generic_err(
+            "A memo explaining the change is required",
+        ));
+    }
+    let ingredients: Vec<String> = may_load(deps.storage, INGREDIENTS_KEY)?.unwrap_or_default();
+    let ingr_cnt = ingredients.len();
+    let mut stored_grants: Vec<StoredGrant> = Vec::new();
+    let mut resp = Response::new();
+    for grant in grants.into_iter() {
+        let user = deps
+            .api
+            .addr_validate(&grant.address)
+            .and_then(|a| deps.api.addr_canonicalize(a.as_str()))?;
+        let mut quantities: Vec<u32> = vec![0; ingr_cnt];
+        for ing_qty in grant.ingredients.into_iter() {
+            let pos = ingredients
+                .iter()
+                .position(|i| *i == ing_qty.ingredient)
+                .ok_or_else(|| {
+                    StdError::generic_err(format!(
+                        "{} is not a known ingredient",
+                        ing_qty.ingredient
+                    ))
+                })?;
+            quantities[pos] = quantities[pos]
+                .checked_add(ing_qty.quantity)
+                .ok_or_else(|| {
+                    StdError::generic_err(format!(
+                        "The total {} granted to {} is too large",
+                        ing_qty.ingredient, grant.address
+                    ))
+                })?;
+        }
+        let mut inv_store = PrefixedStorage::new(deps.storage, PREFIX_USER_INGR_INVENTORY);
+        let mut raw_inv: Vec<u32> = may_load(&inv_store, user.as_slice())?.unwrap_or_default();
+        // just in case new ingredients get added, extend old inventories
+        raw_inv.resize(ingr_cnt, 0);
+        for (i, (inv, qty)) in raw_inv.iter_mut().zip(quantities.iter()).enumerate() {
+            if revoke {
+                if *inv < *qty {
+                    return Err(StdError::generic_err(format!(
+                        "{} does not have {} {}",
+                        grant.address, qty, ingredients[i]
+                    )));
+                }
+                *inv -= *qty;
+            } else {
+                *inv = inv.checked_add(*qty).ok_or_else(|| {
+                    StdError::generic_err(format!(
+                        "Granting {} {} would overflow the inventory of {}",
+                        qty, ingredients[i], grant.address
+                    ))
+                })?;
+            }
+        }
+        save(&mut inv_store, user.as_slice(), &raw_inv)?;
+        if !revoke {
+            let note = notify(deps.storage, &user, INGREDIENTS_CHANNEL)?;
+            resp = resp.add_attribute_plaintext(note, "");
+        }
+        stored_grants.push(StoredGrant { user, quantities });
+    }
+    let idx: u64 = may_load(deps.storage, ADMIN_LOG_CNT_KEY)?.unwrap_or(0);
+    save(deps.storage, ADMIN_LOG_CNT_KEY, &(idx + 1))?;
+    let mut log_store = PrefixedStorage::new(deps.storage, PREFIX_ADMIN_LOG);
+    save(
+        &mut log_store,
+        &idx.to_le_bytes(),
+        &StoredAdminEntry {
+            admin,
+            time: env.block.time.seconds(),
+            revoke,
+            memo,
+            grants: stored_grants,
+        },
+    )?;
+    let status = "success".to_string();
+    let answer = if revoke {
+        ExecuteAnswer::RevokeIngredients { status }
+    } else {
+        ExecuteAnswer::GrantIngredients { status }
+    };
+
+    Ok(resp.set_data(to_binary(&answer)?))
+}
+
+/// Returns StdResult<Response>
+///
+/// remove an entry from the crate contract registry
+///
+/// # Arguments
+///
+/// * `deps` - a mutable reference to Extern containing all the contract's external dependencies
+/// * `sender` - a reference to the message sender
+/// * `name` - name of the registry entry to remove
+fn try_remove_crate_contract(deps: DepsMut, sender: &Addr, name: &str) -> StdResult<Response> {
+    // only allow admins to do this
+    check_admin_tx(deps.as_ref(), sender)?;
+    let default: String = load(deps.storage, DEFAULT_CRATE_KEY)?;
+    if default == name {
+        return Err(StdError::generic_err(
+            "The default crate contract can not be removed",
+        ));
+    }
+    let mut raw_crates: Vec<StoredCrateContract> = load(deps.storage, CRATES_KEY)?;
+    let old_len = raw_crates.len();
+    raw_crates.retain(|c| c.name != name);
+    if raw_crates.len() == old_len {
+        return Err(StdError::generic_err(format!(
+            "{} is not a registered crate contract",
+            name
+        )));
+    }
+    save(deps.storage, CRATES_KEY, &raw_crates)?;
+
+    Ok(
+        Response::new().set_data(to_binary(&ExecuteAnswer::CrateContracts {
+            crate_contracts: display_crates(deps.storage, deps.api)?,
+        })?),
+    )
+}
+
+/// Returns StdResult<Vec<CrateContractInfo>>
+///
+/// create a readable list of the crate contract registry
+///
+/// # Arguments
+///
+/// * `storage` - a reference to this contract's storage
+/// * `api` - a reference to the Api used to convert human and canonical addresses
+fn display_crates(storage: &dyn Storage, api: &dyn Api) -> StdResult<Vec<CrateContractInfo>> {
+    let raw_crates: Vec<StoredCrateContract> = load(storage, CRATES_KEY)?;
+    let default: String = load(storage, DEFAULT_CRATE_KEY)?;
+    let lgcy_store = ReadonlyPrefixedStorage::new(storage, PREFIX_LEGACY_CRATES);
+    raw_crates
+        .into_iter()
+        .map(|c| {
+            Ok(CrateContractInfo {
+                legacy_crates: may_load(&lgcy_store, c.contract.address.as_slice())?.unwrap_or(0),
+                is_default: c.name == default,
+                name: c.name,
+                contract: c.contract.into_humanized(api)?,
+                mint_enabled: c.mint,
+                uncrate_enabled: c.uncrate,
+            })
+        })
+        .collect()
+}
+
+/// Returns StdResult<Response>
+///
+/// set the staking charge time
+///
+/// # Arguments
+///
+/// * `deps` - a mutable reference to Extern containing all the contract's external dependencies
+/// * `sender` - a reference to the message sender
+/// * `charge_time` - staking charge time in seconds
+fn try_set_charge_time(deps: DepsMut, sender: &Addr, charge_time: u64) -> StdResult<Response> {
+    // only allow admins to do this
+    check_admin_tx(deps.as_ref(), sender)?;
+
+    let mut stk_st: StakingState = load(deps.storage, STAKING_STATE_KEY)?;
+    if stk_st.cooldown != charge_time {
+        stk_st.cooldown = charge_time;
+        save(deps.storage, STAKING_STATE_KEY, &stk_st)?;
+    }
+
+    Ok(
+        Response::new().set_data(to_binary(&ExecuteAnswer::SetChargeTime {
+            charge_time: stk_st.cooldown,
+        })?),
+    )
+}
+
+/// Returns StdResult<Response>
+///
+/// set the halt status of staking, crating, and/or alchemy
+///
+/// # Arguments
+///
+/// * `deps` - a mutable reference to Extern containing all the contract's external dependencies
+/// * `sender` - a reference to the message sender
+/// * `staking` - optionally set staking halt status
+/// * `alchemy` - optionally set alchemy halt status
 /// * `crating` - optionally set crating halt status
 fn try_set_halt(
     deps: DepsMut,
@@ -695,23 +1699,7 @@ fn try_set_halt(
             stk_st.halt = stk;
             // if enabling staking
             if !stk_st.halt {
-                let materials: Vec<String> =
-                    may_load(deps.storage, MATERIALS_KEY)?.unwrap_or_default();
-                if materials.is_empty() {
-                    return Err(StdError::generic_err("Skull materials are undefined"));
-                }
-                // check if all materials have a staking table
-                let tbl_store = ReadonlyPrefixedStorage::new(deps.storage, PREFIX_STAKING_TABLE);
-                for (i, mat) in materials.into_iter().enumerate() {
-                    let i_sml = i as u8;
-                    if may_load::<Vec<StoredSetWeight>>(&tbl_store, &i_sml.to_le_bytes())?.is_none()
-                    {
-                        return Err(StdError::generic_err(format!(
-                            "{} staking table has not been defined",
-                            mat
-                        )));
-                    }
-                }
+                check_stakeable(deps.storage)?;
             }
             save(deps.storage, STAKING_STATE_KEY, &stk_st)?;
         }
@@ -720,6 +1708,16 @@ fn try_set_halt(
     if let Some(alc) = alchemy {
         if alc_st.halt != alc {
             alc_st.halt = alc;
+            // if enabling alchemy
+            if !alc_st.halt
+                && may_load::<Vec<String>>(deps.storage, MATERIALS_KEY)?
+                    .unwrap_or_default()
+                    .is_empty()
+            {
+                return Err(StdError::generic_err(
+                    "Skull type info has not been retrieved from the svg server",
+                ));
+            }
             save(deps.storage, ALCHEMY_STATE_KEY, &alc_st)?;
         }
     }
@@ -750,6 +1748,49 @@ fn try_set_halt(
     )
 }
 
+/// Returns StdResult<Response>
+///
+/// set the svg fragments used to generate crate images
+///
+/// # Arguments
+///
+/// * `deps` - a mutable reference to Extern containing all the contract's external dependencies
+/// * `sender` - a reference to the message sender
+/// * `background` - optional svg fragment drawn behind the ingredient icons
+/// * `icons` - list of ingredient icons to add or replace
+fn try_set_crate_art(
+    deps: DepsMut,
+    sender: &Addr,
+    background: Option<String>,
+    icons: Vec<IngredientIcon>,
+) -> StdResult<Response> {
+    // only allow admins to do this
+    check_admin_tx(deps.as_ref(), sender)?;
+    let ingredients: Vec<String> = may_load(deps.storage, INGREDIENTS_KEY)?.unwrap_or_default();
+    let mut art: StoredCrateArt = may_load(deps.storage, CRATE_ART_KEY)?.unwrap_or_default();
+    // just in case new ingredients got added
+    art.icons.resize(ingredients.len(), None);
+    if let Some(bg) = background {
+        art.background = (!bg.is_empty()).then_some(bg);
+    }
+    for icon in icons.into_iter() {
+        let pos = ingredients
+            .iter()
+            .position(|i| *i == icon.ingredient)
+            .ok_or_else(|| {
+                StdError::generic_err(format!("{} is not a known ingredient", icon.ingredient))
+            })?;
+        art.icons[pos] = (!icon.svg.is_empty()).then_some(icon.svg);
+    }
+    save(deps.storage, CRATE_ART_KEY, &art)?;
+
+    Ok(
+        Response::new().set_data(to_binary(&ExecuteAnswer::SetCrateArt {
+            status: "success".to_string(),
+        })?),
+    )
+}
+
 /// Returns StdResult<Response>
 ///
 /// set the base metadata for crate nfts
@@ -790,6 +1831,7 @@ fn try_stake_tbl(deps: DepsMut, sender: &Addr, tables: Vec<StakingTable>) -> Std
     let ingr_sets: Vec<StoredIngrSet> =
         may_load(deps.storage, INGRED_SETS_KEY)?.unwrap_or_default();
     let materials: Vec<String> = may_load(deps.storage, MATERIALS_KEY)?.unwrap_or_default();
+    let boosts: Vec<StoredBoost> = may_load(deps.storage, STAKING_BOOSTS_KEY)?.unwrap_or_default();
 
     for tbl in tables.into_iter() {
         let mut weights: Vec<StoredSetWeight> = Vec::new();
@@ -825,6 +1867,7 @@ fn try_stake_tbl(deps: DepsMut, sender: &Addr, tables: Vec<StakingTable>) -> Std
                 weight: st_wt.weight,
             });
         }
+        check_boosted_weights(&weights, &boosts, &tbl.material)?;
         let mut tbl_store = PrefixedStorage::new(deps.storage, PREFIX_STAKING_TABLE);
         save(&mut tbl_store, &mat_key, &weights)?;
     }
@@ -837,1077 +1880,3866 @@ fn try_stake_tbl(deps: DepsMut, sender: &Addr, tables: Vec<StakingTable>) -> Std
 
 /// Returns StdResult<Response>
 ///
-/// define ingredients sets for staking tables
+/// set the pity thresholds of the staking tables
 ///
 /// # Arguments
 ///
 /// * `deps` - a mutable reference to Extern containing all the contract's external dependencies
 /// * `sender` - a reference to the message sender
-/// * `sets` - list of ingredient sets
-fn try_set_ingred_set(
-    deps: DepsMut,
-    sender: &Addr,
-    sets: Vec<IngredientSet>,
-) -> StdResult<Response> {
+/// * `tables` - list of pity thresholds for specified materials
+fn try_set_pity(deps: DepsMut, sender: &Addr, tables: Vec<PityTable>) -> StdResult<Response> {
     // only allow admins to do this
     check_admin_tx(deps.as_ref(), sender)?;
-
-    let ingredients: Vec<String> = may_load(deps.storage, INGREDIENTS_KEY)?.unwrap_or_default();
-    let mut ingr_sets: Vec<StoredIngrSet> =
+    let ingr_sets: Vec<StoredIngrSet> =
         may_load(deps.storage, INGRED_SETS_KEY)?.unwrap_or_default();
-    for set in sets.into_iter() {
-        let mut list: Vec<u8> = Vec::new();
-        for member in set.members.iter() {
-            if let Some(pos) = ingredients.iter().position(|ing| ing == member) {
-                let pos8 = pos as u8;
-                if !list.contains(&pos8) {
-                    list.push(pos8);
-                }
-            } else {
+    let materials: Vec<String> = may_load(deps.storage, MATERIALS_KEY)?.unwrap_or_default();
+
+    for tbl in tables.into_iter() {
+        let mat = materials
+            .iter()
+            .position(|m| *m == tbl.material)
+            .ok_or_else(|| {
+                StdError::generic_err(format!("{} is not a known skull material", tbl.material))
+            })? as u8;
+        // thresholds can only guarantee sets the material's staking table can win
+        let tbl_store = ReadonlyPrefixedStorage::new(deps.storage, PREFIX_STAKING_TABLE);
+        let stk_tbl: Vec<StoredSetWeight> =
+            may_load(&tbl_store, &mat.to_le_bytes())?.ok_or_else(|| {
+                StdError::generic_err(format!(
+                    "{} staking table has not been defined",
+                    tbl.material
+                ))
+            })?;
+        let mut pity_sets: Vec<StoredPity> = Vec::new();
+        for pty in tbl.sets.iter() {
+            let set = find_ingr_set(&ingr_sets, &pty.ingredient_set)?;
+            if !stk_tbl.iter().any(|w| w.set == set && w.weight > 0) {
                 return Err(StdError::generic_err(format!(
-                    "{} is not a known ingredient",
-                    member
+                    "{} is not in the {} staking table",
+                    pty.ingredient_set, tbl.material
+                )));
+            }
+            if pty.threshold == 0 {
+                return Err(StdError::generic_err(
+                    "Pity thresholds must be greater than 0",
+                ));
+            }
+            if pity_sets.iter().any(|p| p.set == set) {
+                return Err(StdError::generic_err(format!(
+                    "{} has been duplicated in the pity thresholds",
+                    pty.ingredient_set
                 )));
             }
+            pity_sets.push(StoredPity {
+                set,
+                threshold: pty.threshold,
+            });
         }
-        if let Some(old_set) = ingr_sets.iter_mut().find(|s| s.name == set.name) {
-            old_set.list = list;
+        let mut pity_store = PrefixedStorage::new(deps.storage, PREFIX_PITY_TABLE);
+        if pity_sets.is_empty() {
+            remove(&mut pity_store, &mat.to_le_bytes());
         } else {
-            ingr_sets.push(StoredIngrSet {
-                name: set.name,
-                list,
-            });
+            save(&mut pity_store, &mat.to_le_bytes(), &pity_sets)?;
         }
     }
-    save(deps.storage, INGRED_SETS_KEY, &ingr_sets)?;
-
     Ok(
-        Response::new().set_data(to_binary(&ExecuteAnswer::DefineIngredientSets {
-            count: ingr_sets.len() as u8,
+        Response::new().set_data(to_binary(&ExecuteAnswer::SetPityThresholds {
+            status: "success".to_string(),
         })?),
     )
 }
 
 /// Returns StdResult<Response>
 ///
-/// add ingredient names
+/// set the staking boosts granted by skull traits
 ///
 /// # Arguments
 ///
 /// * `deps` - a mutable reference to Extern containing all the contract's external dependencies
+/// * `env` - a reference to the Env of contract's environment
 /// * `sender` - a reference to the message sender
-/// * `ingr_to_add` - list of ingredient names to add
-fn try_add_ingredients(
+/// * `boosts` - list of staking boosts
+fn try_set_boosts(
     deps: DepsMut,
+    env: &Env,
     sender: &Addr,
-    ingr_to_add: Vec<String>,
+    boosts: Vec<StakingBoost>,
 ) -> StdResult<Response> {
     // only allow admins to do this
     check_admin_tx(deps.as_ref(), sender)?;
-    let mut ingredients: Vec<String> = may_load(deps.storage, INGREDIENTS_KEY)?.unwrap_or_default();
-    for ingr in ingr_to_add.into_iter() {
-        if !ingredients.contains(&ingr) {
-            ingredients.push(ingr);
+    let ingr_sets: Vec<StoredIngrSet> =
+        may_load(deps.storage, INGRED_SETS_KEY)?.unwrap_or_default();
+    // get the layer indices from the svg server
+    let layers = if boosts.is_empty() {
+        Vec::new()
+    } else {
+        let svg_raw: StoreContractInfo = load(deps.storage, SVG_SERVER_KEY)?;
+        let viewer = server_viewer(
+            deps.storage,
+            &svg_raw.address,
+            env.contract.address.to_string(),
+        )?;
+        let svg_server = svg_raw.into_humanized(deps.api)?;
+        ServerQueryMsg::LayerIndices {
+            viewer,
+            layers: boosts.iter().map(|b| b.layer.clone()).collect(),
         }
+        .query::<_, LayerIndicesWrapper>(deps.querier, svg_server.code_hash, svg_server.address)?
+        .layer_indices
+        .layers
+    };
+    if layers.len() != boosts.len() {
+        return Err(StdError::generic_err(
+            "The svg server did not return a layer for every staking boost",
+        ));
     }
-    save(deps.storage, INGREDIENTS_KEY, &ingredients)?;
-    Ok(Response::new().set_data(to_binary(&ExecuteAnswer::AddIngredients { ingredients })?))
+    let mut stored: Vec<StoredBoost> = Vec::new();
+    for (boost, layer) in boosts.iter().zip(layers) {
+        if stored.iter().any(|b| b.name == boost.name) {
+            return Err(StdError::generic_err(format!(
+                "{} has been duplicated in the staking boosts",
+                boost.name
+            )));
+        }
+        let weights = boost
+            .ingredient_set_weights
+            .iter()
+            .map(|w| {
+                Ok(StoredSetWeight {
+                    set: find_ingr_set(&ingr_sets, &w.ingredient_set)?,
+                    weight: w.weight,
+                })
+            })
+            .collect::<StdResult<Vec<StoredSetWeight>>>()?;
+        let bonus = if let Some(bns) = boost.bonus_set.as_ref() {
+            if bns.chance > 10000 {
+                return Err(StdError::generic_err(
+                    "Bonus set chance can not be more than 10000",
+                ));
+            }
+            Some(StoredSetChance {
+                set: find_ingr_set(&ingr_sets, &bns.ingredient_set)?,
+                chance: bns.chance,
+            })
+        } else {
+            None
+        };
+        stored.push(StoredBoost {
+            name: boost.name.clone(),
+            layer,
+            layer_name: boost.layer.clone(),
+            extra_rolls: boost.extra_rolls,
+            weights,
+            bonus,
+        });
+    }
+    // make sure the boosts can all be applied to each staking table at once
+    let materials: Vec<String> = may_load(deps.storage, MATERIALS_KEY)?.unwrap_or_default();
+    let tbl_store = ReadonlyPrefixedStorage::new(deps.storage, PREFIX_STAKING_TABLE);
+    for (i, mat) in materials.iter().enumerate() {
+        let weights: Vec<StoredSetWeight> =
+            may_load(&tbl_store, &(i as u8).to_le_bytes())?.unwrap_or_default();
+        check_boosted_weights(&weights, &stored, mat)?;
+    }
+    let seasons: Vec<StoredSeason> =
+        may_load(deps.storage, STAKING_SEASONS_KEY)?.unwrap_or_default();
+    for ssn in seasons.iter() {
+        for tbl in ssn.tables.iter() {
+            let name = format!("{} {}", ssn.name, materials[tbl.material as usize]);
+            check_boosted_weights(&tbl.weights, &stored, &name)?;
+        }
+    }
+    save(deps.storage, STAKING_BOOSTS_KEY, &stored)?;
+
+    Ok(Response::new().set_data(to_binary(&ExecuteAnswer::SetStakingBoosts { boosts })?))
 }
 
 /// Returns StdResult<Response>
 ///
-/// get skull type and material info from the svg server
+/// set the schedule of staking seasons
 ///
 /// # Arguments
 ///
 /// * `deps` - a mutable reference to Extern containing all the contract's external dependencies
+/// * `env` - a reference to the Env of contract's environment
 /// * `sender` - a reference to the message sender
-/// * `env` - Env of contract's environment
-fn try_get_skull_info(deps: DepsMut, sender: &Addr, env: Env) -> StdResult<Response> {
+/// * `seasons` - list of staking seasons
+fn try_set_seasons(
+    deps: DepsMut,
+    env: &Env,
+    sender: &Addr,
+    mut seasons: Vec<StakingSeason>,
+) -> StdResult<Response> {
     // only allow admins to do this
     check_admin_tx(deps.as_ref(), sender)?;
-    let svg_server = load::<StoreContractInfo>(deps.storage, SVG_SERVER_KEY)
-        .and_then(|s| s.into_humanized(deps.api))?;
-    let viewing_key: String = load(deps.storage, MY_VIEWING_KEY)?;
-    let viewer = ViewerInfo {
-        address: env.contract.address.into_string(),
-        viewing_key,
-    };
-    let st_plus = ServerQueryMsg::SkullTypePlus { viewer }
-        .query::<_, SkullTypePlusWrapper>(deps.querier, svg_server.code_hash, svg_server.address)?
-        .skull_type_plus;
-    let mut stk_st: StakingState = load(deps.storage, STAKING_STATE_KEY)?;
-    stk_st.skull_idx = st_plus.skull_idx;
-    save(deps.storage, STAKING_STATE_KEY, &stk_st)?;
-    let mut alc_st: AlchemyState = load(deps.storage, ALCHEMY_STATE_KEY)?;
-    alc_st.cyclops = st_plus.cyclops;
-    alc_st.jawless = st_plus.jawless;
-    save(deps.storage, ALCHEMY_STATE_KEY, &alc_st)?;
-
-    let mut materials = vec![String::new(); st_plus.skull_variants.len()];
-    for idx_name in st_plus.skull_variants.into_iter() {
-        materials[idx_name.idx as usize] = idx_name.name;
+    let ingr_sets: Vec<StoredIngrSet> =
+        may_load(deps.storage, INGRED_SETS_KEY)?.unwrap_or_default();
+    let materials: Vec<String> = may_load(deps.storage, MATERIALS_KEY)?.unwrap_or_default();
+    let boosts: Vec<StoredBoost> = may_load(deps.storage, STAKING_BOOSTS_KEY)?.unwrap_or_default();
+    let old: Vec<StoredSeason> = may_load(deps.storage, STAKING_SEASONS_KEY)?.unwrap_or_default();
+    let mut next_id: u16 = may_load(deps.storage, SEASON_ID_KEY)?.unwrap_or(0);
+    let now = env.block.time.seconds();
+    seasons.sort_by_key(|s| s.start);
+    let mut stored: Vec<StoredSeason> = Vec::new();
+    for ssn in seasons.into_iter() {
+        let id = if let Some(id) = ssn.id {
+            if !old.iter().any(|s| s.id == id) {
+                return Err(StdError::generic_err(format!(
+                    "{} is not the id of a scheduled staking season",
+                    id
+                )));
+            }
+            if stored.iter().any(|s| s.id == id) {
+                return Err(StdError::generic_err(format!(
+                    "Staking season id {} has been duplicated",
+                    id
+                )));
+            }
+            id
+        } else {
+            let id = next_id;
+            next_id = next_id
+                .checked_add(1)
+                .ok_or_else(|| StdError::generic_err("Too many staking seasons"))?;
+            id
+        };
+        if ssn.start >= ssn.end {
+            return Err(StdError::generic_err(format!(
+                "{} must start before it ends",
+                ssn.name
+            )));
+        }
+        if let Some(prev) = stored.last() {
+            if prev.end > ssn.start {
+                return Err(StdError::generic_err(format!(
+                    "{} overlaps with {}",
+                    ssn.name, prev.name
+                )));
+            }
+        }
+        if stored.iter().any(|s| s.name == ssn.name) {
+            return Err(StdError::generic_err(format!(
+                "{} has been duplicated in the staking seasons",
+                ssn.name
+            )));
+        }
+        let charge_pct = ssn.charge_time_percent.unwrap_or(100);
+        if charge_pct == 0 {
+            return Err(StdError::generic_err(
+                "Charge time percent must be greater than 0",
+            ));
+        }
+        let mut tables: Vec<StoredSeasonTable> = Vec::new();
+        for tbl in ssn.tables.into_iter() {
+            let material = materials
+                .iter()
+                .position(|m| *m == tbl.material)
+                .ok_or_else(|| {
+                    StdError::generic_err(format!("{} is not a known skull material", tbl.material))
+                })? as u8;
+            if tables.iter().any(|t| t.material == material) {
+                return Err(StdError::generic_err(format!(
+                    "{} has multiple staking tables in {}",
+                    tbl.material, ssn.name
+                )));
+            }
+            let mut weights: Vec<StoredSetWeight> = Vec::new();
+            for st_wt in tbl.ingredient_set_weights.iter() {
+                let set = find_ingr_set(&ingr_sets, &st_wt.ingredient_set)?;
+                if weights.iter().any(|w| w.set == set) {
+                    return Err(StdError::generic_err(format!(
+                        "{} has been duplicated in the staking table",
+                        st_wt.ingredient_set
+                    )));
+                }
+                weights.push(StoredSetWeight {
+                    set,
+                    weight: st_wt.weight,
+                });
+            }
+            check_boosted_weights(&weights, &boosts, &format!("{} {}", ssn.name, tbl.material))?;
+            tables.push(StoredSeasonTable { material, weights });
+        }
+        let season = StoredSeason {
+            id,
+            name: ssn.name,
+            start: ssn.start,
+            end: ssn.end,
+            tables,
+            charge_pct,
+        };
+        // charges already earned depend on seasons that have started, so only let the season
+        // in progress be extended
+        if let Some(prev) = old.iter().find(|s| s.id == id && s.start <= now) {
+            if season.name != prev.name
+                || season.start != prev.start
+                || season.tables != prev.tables
+                || season.charge_pct != prev.charge_pct
+                || season.end < prev.end
+                || (prev.end <= now && season.end != prev.end)
+            {
+                return Err(StdError::generic_err(format!(
+                    "{} has already started and can only have its end extended while in progress",
+                    prev.name
+                )));
+            }
+        } else if season.start <= now {
+            return Err(StdError::generic_err(format!(
+                "{} must start in the future",
+                season.name
+            )));
+        }
+        stored.push(season);
     }
-    if materials.iter().any(|n| *n == String::new()) {
-        return Err(StdError::generic_err("Blank Name in skull material list"));
+    if let Some(gone) = old
+        .iter()
+        .find(|o| o.start <= now && !stored.iter().any(|s| s.id == o.id))
+    {
+        return Err(StdError::generic_err(format!(
+            "{} has already started and can not be removed",
+            gone.name
+        )));
     }
-    save(deps.storage, MATERIALS_KEY, &materials)?;
+    save(deps.storage, STAKING_SEASONS_KEY, &stored)?;
+    save(deps.storage, SEASON_ID_KEY, &next_id)?;
 
-    Ok(Response::default())
+    Ok(
+        Response::new().set_data(to_binary(&ExecuteAnswer::SetStakingSeasons {
+            count: stored.len() as u16,
+        })?),
+    )
 }
 
 /// Returns StdResult<Response>
 ///
-/// creates a viewing key
+/// add or update a SNIP-20 staking reward stream
 ///
 /// # Arguments
 ///
-/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `deps` - a mutable reference to Extern containing all the contract's external dependencies
 /// * `env` - a reference to the Env of contract's environment
-/// * `info` - calling message information MessageInfo
-/// * `entropy` - string slice of the input String to be used as entropy in randomization
-fn try_create_key(
+/// * `sender` - a reference to the message sender
+/// * `stream` - the reward stream
+fn try_set_stream(
     deps: DepsMut,
     env: &Env,
-    info: &MessageInfo,
-    entropy: &str,
+    sender: &Addr,
+    stream: RewardStream,
 ) -> StdResult<Response> {
-    let key = ViewingKey::create(
-        deps.storage,
-        info,
-        env,
-        info.sender.as_str(),
-        entropy.as_ref(),
-    );
-    Ok(Response::new().set_data(to_binary(&ExecuteAnswer::ViewingKey { key })?))
+    // only allow admins to do this
+    check_admin_tx(deps.as_ref(), sender)?;
+    let materials: Vec<String> = may_load(deps.storage, MATERIALS_KEY)?.unwrap_or_default();
+    let token = stream.token.get_store(deps.api)?;
+    let mut rates: Vec<u128> = vec![0; materials.len()];
+    for mat_rate in stream.rates.iter() {
+        let pos = materials
+            .iter()
+            .position(|m| *m == mat_rate.material)
+            .ok_or_else(|| {
+                StdError::generic_err(format!(
+                    "{} is not a known skull material",
+                    mat_rate.material
+                ))
+            })?;
+        rates[pos] = mat_rate.rate.u128();
+    }
+    let cap = stream.cap.u128();
+    let mut streams: Vec<StoredStream> =
+        may_load(deps.storage, REWARD_STREAMS_KEY)?.unwrap_or_default();
+    let mut messages: Vec<CosmosMsg> = Vec::new();
+    let pos = if let Some(pos) = streams
+        .iter()
+        .position(|s| s.token.address == token.address)
+    {
+        let existing = &mut streams[pos];
+        if cap < existing.distributed {
+            return Err(StdError::generic_err(format!(
+                "The cap can not be less than the {} tokens already distributed",
+                existing.distributed
+            )));
+        }
+        existing.token.code_hash = token.code_hash;
+        existing.per_second = stream.per_second;
+        existing.rates = rates;
+        existing.cap = cap;
+        pos
+    } else {
+        // register to receive the funding tokens
+        messages.push(
+            Snip20HandleMsg::RegisterReceive {
+                code_hash: env.contract.code_hash.clone(),
+                padding: None,
+            }
+            .to_cosmos_msg(token.code_hash.clone(), stream.token.address, None)?,
+        );
+        streams.push(StoredStream {
+            token,
+            per_second: stream.per_second,
+            rates,
+            cap,
+            reserve: 0,
+            distributed: 0,
+        });
+        streams.len() - 1
+    };
+    save(deps.storage, REWARD_STREAMS_KEY, &streams)?;
+
+    Ok(Response::new().add_messages(messages).set_data(to_binary(
+        &ExecuteAnswer::SetRewardStream {
+            stream: display_stream(deps.api, &materials, &streams[pos])?,
+        },
+    )?))
 }
 
 /// Returns StdResult<Response>
 ///
-/// sets the viewing key to the input String
+/// handles receiving SNIP-20 tokens to fund a reward stream
 ///
 /// # Arguments
 ///
-/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
-/// * `sender` - a reference to the message sender
-/// * `key` - String to be used as the viewing key
-fn try_set_key(deps: DepsMut, sender: &Addr, key: String) -> StdResult<Response> {
-    ViewingKey::set(deps.storage, sender.as_str(), &key);
+/// * `deps` - a mutable reference to Extern containing all the contract's external dependencies
+/// * `sender` - a reference to the message sender (the token contract)
+/// * `from` - the address that owned the tokens
+/// * `amount` - amount of tokens received
+fn try_receive(deps: DepsMut, sender: &Addr, from: &str, amount: Uint128) -> StdResult<Response> {
+    let from_raw = deps
+        .api
+        .addr_validate(from)
+        .and_then(|a| deps.api.addr_canonicalize(a.as_str()))?;
+    // only admins can fund reward streams
+    check_admin(deps.storage, &from_raw)?;
+    let token_raw = deps.api.addr_canonicalize(sender.as_str())?;
+    let mut streams: Vec<StoredStream> =
+        may_load(deps.storage, REWARD_STREAMS_KEY)?.unwrap_or_default();
+    let stream = streams
+        .iter_mut()
+        .find(|s| s.token.address == token_raw)
+        .ok_or_else(|| StdError::generic_err(format!("{} is not a reward stream token", sender)))?;
+    stream.reserve = stream
+        .reserve
+        .checked_add(amount.u128())
+        .ok_or_else(|| StdError::generic_err("Reward stream reserve would overflow"))?;
+    let reserve = Uint128::new(stream.reserve);
+    save(deps.storage, REWARD_STREAMS_KEY, &streams)?;
 
-    Ok(Response::new().set_data(to_binary(&ExecuteAnswer::ViewingKey { key })?))
+    Ok(Response::new().set_data(to_binary(&ExecuteAnswer::FundRewardStream { reserve })?))
 }
 
 /// Returns StdResult<Response>
 ///
-/// revoke the ability to use a specified permit
+/// remove a SNIP-20 reward stream and withdraw its remaining reserve
 ///
 /// # Arguments
 ///
-/// * `storage` - mutable reference to the contract's storage
-/// * `sender` - a reference to the message sender address
-/// * `permit_name` - string slice of the name of the permit to revoke
-fn revoke_permit(
-    storage: &mut dyn Storage,
+/// * `deps` - a mutable reference to Extern containing all the contract's external dependencies
+/// * `sender` - a reference to the message sender
+/// * `token` - address of the stream's token
+/// * `recipient` - optional address to receive the reserve
+fn try_remove_stream(
+    deps: DepsMut,
     sender: &Addr,
-    permit_name: &str,
+    token: &str,
+    recipient: Option<String>,
 ) -> StdResult<Response> {
-    RevokedPermits::revoke_permit(
-        storage,
-        PREFIX_REVOKED_PERMITS,
-        sender.as_str(),
-        permit_name,
-    );
+    // only allow admins to do this
+    check_admin_tx(deps.as_ref(), sender)?;
+    let mut streams: Vec<StoredStream> =
+        may_load(deps.storage, REWARD_STREAMS_KEY)?.unwrap_or_default();
+    let pos = stream_pos(deps.api, &streams, token)?;
+    let stream = streams.remove(pos);
+    save(deps.storage, REWARD_STREAMS_KEY, &streams)?;
+    let mut messages: Vec<CosmosMsg> = Vec::new();
+    if stream.reserve > 0 {
+        messages.push(reserve_transfer(
+            deps.api,
+            &stream,
+            stream.reserve,
+            recipient.unwrap_or_else(|| sender.to_string()),
+        )?);
+    }
 
-    Ok(
-        Response::new().set_data(to_binary(&ExecuteAnswer::RevokePermit {
-            status: "success".to_string(),
-        })?),
-    )
+    Ok(Response::new().add_messages(messages).set_data(to_binary(
+        &ExecuteAnswer::RemoveRewardStream {
+            withdrawn: Uint128::new(stream.reserve),
+        },
+    )?))
 }
 
-/////////////////////////////////////// Query /////////////////////////////////////
-/// Returns StdResult<Binary>
-///
-/// # Arguments
+/// Returns StdResult<Response>
 ///
-/// * `deps` - reference to Extern containing all the contract's external dependencies
-/// * `env` - Env of contract's environment
-/// * `msg` - QueryMsg passed in with the query call
-#[entry_point]
-pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
-    let response = match msg {
-        QueryMsg::Admins { viewer, permit } => {
-            query_admins(deps, viewer, permit, &env.contract.address)
-        }
-        QueryMsg::HaltStatuses {} => query_halt(deps.storage),
-        QueryMsg::Contracts {} => query_contracts(deps),
-        QueryMsg::MyStaking { viewer, permit } => query_my_stake(deps, env, viewer, permit),
-        QueryMsg::MyIngredients { viewer, permit } => {
-            query_my_inv(deps, viewer, permit, &env.contract.address)
-        }
-        QueryMsg::UserEligibleForBonus { viewer, permit } => {
-            query_user_bonus(deps, viewer, permit, &env.contract.address)
-        }
-        QueryMsg::TokensEligibleForBonus {
-            viewer,
-            permit,
-            token_ids,
-        } => query_token_bonus(deps, env, viewer, permit, token_ids),
-        QueryMsg::Materials { viewer, permit } => {
-            query_mater(deps, viewer, permit, &env.contract.address)
-        }
-        QueryMsg::Ingredients {} => query_ingr(deps.storage),
-        QueryMsg::IngredientSets {
-            viewer,
-            permit,
-            page,
-            page_size,
-        } => query_ingr_sets(deps, viewer, permit, page, page_size, &env.contract.address),
-        QueryMsg::StakingTable {
-            viewer,
-            permit,
-            by_name,
-            by_index,
-        } => query_stk_tbl(
-            deps,
-            viewer,
-            permit,
-            by_name,
-            by_index,
-            &env.contract.address,
-        ),
-        QueryMsg::States { viewer, permit } => {
-            query_state(deps, viewer, permit, &env.contract.address)
-        }
-    };
-    pad_query_result(response, BLOCK_SIZE)
-}
-
-/// Returns StdResult<Binary> which displays staking and alchemy halt statuses
+/// withdraw tokens from a SNIP-20 reward stream's reserve
 ///
 /// # Arguments
 ///
-/// * `storage` - a reference to this contract's storage
-fn query_halt(storage: &dyn Storage) -> StdResult<Binary> {
-    let stk_st: StakingState = load(storage, STAKING_STATE_KEY)?;
-    let alc_st: AlchemyState = load(storage, ALCHEMY_STATE_KEY)?;
-    let crt_st: CrateState = load(storage, CRATE_STATE_KEY)?;
+/// * `deps` - a mutable reference to Extern containing all the contract's external dependencies
+/// * `sender` - a reference to the message sender
+/// * `token` - address of the stream's token
+/// * `amount` - amount of tokens to withdraw
+/// * `recipient` - optional address to receive the tokens
+fn try_withdraw_reserve(
+    deps: DepsMut,
+    sender: &Addr,
+    token: &str,
+    amount: Uint128,
+    recipient: Option<String>,
+) -> StdResult<Response> {
+    // only allow admins to do this
+    check_admin_tx(deps.as_ref(), sender)?;
+    let mut streams: Vec<StoredStream> =
+        may_load(deps.storage, REWARD_STREAMS_KEY)?.unwrap_or_default();
+    let pos = stream_pos(deps.api, &streams, token)?;
+    let stream = &mut streams[pos];
+    let amt = amount.u128();
+    if amt == 0 {
+        return Err(StdError::generic_err("Can not withdraw 0 tokens"));
+    }
+    stream.reserve = stream.reserve.checked_sub(amt).ok_or_else(|| {
+        StdError::generic_err(format!(
+            "The reserve only has {} tokens to withdraw",
+            stream.reserve
+        ))
+    })?;
+    let reserve = Uint128::new(stream.reserve);
+    let message = reserve_transfer(
+        deps.api,
+        stream,
+        amt,
+        recipient.unwrap_or_else(|| sender.to_string()),
+    )?;
+    save(deps.storage, REWARD_STREAMS_KEY, &streams)?;
 
-    to_binary(&QueryAnswer::HaltStatuses {
-        staking_is_halted: stk_st.halt,
-        alchemy_is_halted: alc_st.halt,
-        crating_is_halted: crt_st.halt,
-    })
+    Ok(Response::new().add_message(message).set_data(to_binary(
+        &ExecuteAnswer::WithdrawRewardReserve { reserve },
+    )?))
 }
 
-/// Returns StdResult<Binary> which displays the code hashes and addresses
-/// of used contract
-///
-/// # Arguments
+/// Returns StdResult<Response>
 ///
-/// * `deps` - reference to Extern containing all the contract's external dependencies
-fn query_contracts(deps: Deps) -> StdResult<Binary> {
-    let svg_server = load::<StoreContractInfo>(deps.storage, SVG_SERVER_KEY)
-        .and_then(|s| s.into_humanized(deps.api))?;
-    let skulls_contract = load::<StoreContractInfo>(deps.storage, SKULL_721_KEY)
-        .and_then(|s| s.into_humanized(deps.api))?;
-    let crate_contracts =
-        load::<Vec<StoreContractInfo>>(deps.storage, CRATES_KEY).and_then(|v| {
-            v.into_iter()
-                .map(|s| s.into_humanized(deps.api))
-                .collect::<StdResult<Vec<ContractInfo>>>()
-        })?;
-
-    to_binary(&QueryAnswer::Contracts {
-        svg_server,
-        skulls_contract,
-        crate_contracts,
-    })
-}
-
-/// Returns StdResult<Binary> displaying the staking table for a specified skull material
+/// define ingredients sets for staking tables
 ///
 /// # Arguments
 ///
-/// * `deps` - reference to Extern containing all the contract's external dependencies
-/// * `viewer` - optional address and key making an authenticated query request
-/// * `permit` - optional permit with "owner" permission
-/// * `by_name` - optional material string to display
-/// * `by_index` - optional material index to display
-/// * `my_addr` - a reference to this contract's address
-fn query_stk_tbl(
-    deps: Deps,
-    viewer: Option<ViewerInfo>,
-    permit: Option<Permit>,
-    by_name: Option<String>,
-    by_index: Option<u8>,
-    my_addr: &Addr,
-) -> StdResult<Binary> {
-    // only allow admins to do this
-    check_admin_query(deps, viewer, permit, my_addr)?;
-    let mut materials: Vec<String> = may_load(deps.storage, MATERIALS_KEY)?.unwrap_or_default();
-    let idx = if let Some(nm) = by_name {
-        materials
-            .iter()
-            .position(|m| *m == nm)
-            .ok_or_else(|| StdError::generic_err(format!("Unknown material: {}", nm)))?
-            as u8
-    } else {
-        by_index.ok_or_else(|| StdError::generic_err("Must provide either a name or index"))?
-    };
-    let tbl_store = ReadonlyPrefixedStorage::new(deps.storage, PREFIX_STAKING_TABLE);
-    let tbl = may_load::<Vec<StoredSetWeight>>(&tbl_store, &idx.to_le_bytes())
-        .and_then(|s| s.ok_or_else(|| StdError::generic_err("Invalid SetWeight index")))?;
-    let ingr_sets: Vec<StoredIngrSet> =
+/// * `deps` - a mutable reference to Extern containing all the contract's external dependencies
+/// * `sender` - a reference to the message sender
+/// * `sets` - list of ingredient sets
+fn try_set_ingred_set(
+    deps: DepsMut,
+    sender: &Addr,
+    sets: Vec<IngredientSet>,
+) -> StdResult<Response> {
+    // only allow admins to do this
+    check_admin_tx(deps.as_ref(), sender)?;
+
+    let ingredients: Vec<String> = may_load(deps.storage, INGREDIENTS_KEY)?.unwrap_or_default();
+    let mut ingr_sets: Vec<StoredIngrSet> =
         may_load(deps.storage, INGRED_SETS_KEY)?.unwrap_or_default();
+    for set in sets.into_iter() {
+        let mut list: Vec<u8> = Vec::new();
+        for member in set.members.iter() {
+            if let Some(pos) = ingredients.iter().position(|ing| ing == member) {
+                let pos8 = pos as u8;
+                if !list.contains(&pos8) {
+                    list.push(pos8);
+                }
+            } else {
+                return Err(StdError::generic_err(format!(
+                    "{} is not a known ingredient",
+                    member
+                )));
+            }
+        }
+        if let Some(old_set) = ingr_sets.iter_mut().find(|s| s.name == set.name) {
+            old_set.list = list;
+        } else {
+            ingr_sets.push(StoredIngrSet {
+                name: set.name,
+                list,
+            });
+        }
+    }
+    save(deps.storage, INGRED_SETS_KEY, &ingr_sets)?;
 
-    to_binary(&QueryAnswer::StakingTable {
-        staking_table: StakingTable {
-            material: materials.swap_remove(idx as usize),
-            ingredient_set_weights: tbl
-                .iter()
-                .map(|s| IngrSetWeight {
-                    ingredient_set: ingr_sets[s.set as usize].name.clone(),
-                    weight: s.weight,
-                })
-                .collect::<Vec<IngrSetWeight>>(),
-        },
-    })
+    Ok(
+        Response::new().set_data(to_binary(&ExecuteAnswer::DefineIngredientSets {
+            count: ingr_sets.len() as u8,
+        })?),
+    )
 }
 
-/// Returns StdResult<Binary> displaying the ingredient sets
+/// Returns StdResult<Response>
+///
+/// add ingredient names
 ///
 /// # Arguments
 ///
-/// * `deps` - reference to Extern containing all the contract's external dependencies
-/// * `viewer` - optional address and key making an authenticated query request
-/// * `permit` - optional permit with "owner" permission
-/// * `page` - optional page to display
-/// * `page_size` - optional number of sets to display
-/// * `my_addr` - a reference to this contract's address
-fn query_ingr_sets(
-    deps: Deps,
-    viewer: Option<ViewerInfo>,
-    permit: Option<Permit>,
-    page: Option<u16>,
-    page_size: Option<u16>,
-    my_addr: &Addr,
-) -> StdResult<Binary> {
+/// * `deps` - a mutable reference to Extern containing all the contract's external dependencies
+/// * `sender` - a reference to the message sender
+/// * `ingr_to_add` - list of ingredient names to add
+fn try_add_ingredients(
+    deps: DepsMut,
+    sender: &Addr,
+    ingr_to_add: Vec<String>,
+) -> StdResult<Response> {
     // only allow admins to do this
-    check_admin_query(deps, viewer, permit, my_addr)?;
-    let ingr_sets: Vec<StoredIngrSet> =
-        may_load(deps.storage, INGRED_SETS_KEY)?.unwrap_or_default();
-    let ingredients: Vec<String> = may_load(deps.storage, INGREDIENTS_KEY)?.unwrap_or_default();
-
-    let page = page.unwrap_or(0);
-    let limit = page_size.unwrap_or(30);
-    let skip = (page * limit) as usize;
-
-    to_binary(&QueryAnswer::IngredientSets {
-        ingredient_sets: ingr_sets
-            .into_iter()
-            .skip(skip)
-            .take(limit as usize)
-            .map(|s| IngredientSet {
-                name: s.name,
-                members: s
-                    .list
-                    .iter()
-                    .map(|u| ingredients[*u as usize].clone())
-                    .collect::<Vec<String>>(),
-            })
-            .collect::<Vec<IngredientSet>>(),
-    })
+    check_admin_tx(deps.as_ref(), sender)?;
+    let mut ingredients: Vec<String> = may_load(deps.storage, INGREDIENTS_KEY)?.unwrap_or_default();
+    for ingr in ingr_to_add.into_iter() {
+        if !ingredients.contains(&ingr) {
+            ingredients.push(ingr);
+        }
+    }
+    save(deps.storage, INGREDIENTS_KEY, &ingredients)?;
+    Ok(Response::new().set_data(to_binary(&ExecuteAnswer::AddIngredients { ingredients })?))
 }
 
-/// Returns StdResult<Binary> displaying the user's inventory of ingredients
+/// Returns StdResult<Response>
+///
+/// get skull type and material info from the svg server
 ///
 /// # Arguments
 ///
-/// * `deps` - reference to Extern containing all the contract's external dependencies
-/// * `viewer` - optional address and key making an authenticated query request
-/// * `permit` - optional permit with "owner" permission
-/// * `my_addr` - a reference to this contract's address
-fn query_my_inv(
-    deps: Deps,
-    viewer: Option<ViewerInfo>,
-    permit: Option<Permit>,
-    my_addr: &Addr,
-) -> StdResult<Binary> {
-    let (user_raw, _) = get_querier(deps, viewer, permit, my_addr)?;
-
-    // retrieve the user's ingredient inventory
-    let inventory = display_inventory(deps.storage, user_raw.as_slice())?;
+/// * `deps` - a mutable reference to Extern containing all the contract's external dependencies
+/// * `sender` - a reference to the message sender
+/// * `env` - a reference to the Env of contract's environment
+fn try_get_skull_info(deps: DepsMut, sender: &Addr, env: &Env) -> StdResult<Response> {
+    // only allow admins to do this
+    check_admin_tx(deps.as_ref(), sender)?;
+    sync_skull_info(deps, env)?;
 
-    to_binary(&QueryAnswer::MyIngredients { inventory })
+    Ok(Response::default())
 }
 
-/// Returns StdResult<Binary> displaying whether the user is eligible for the first time staking bonus
+/// Returns StdResult<Response>
+///
+/// set the staking rules after refreshing the skull type info from the svg server
 ///
 /// # Arguments
 ///
-/// * `deps` - reference to Extern containing all the contract's external dependencies
-/// * `viewer` - optional address and key making an authenticated query request
-/// * `permit` - optional permit with "owner" permission
-/// * `my_addr` - a reference to this contract's address
-fn query_user_bonus(
-    deps: Deps,
-    viewer: Option<ViewerInfo>,
-    permit: Option<Permit>,
-    my_addr: &Addr,
-) -> StdResult<Binary> {
-    let (user_raw, _) = get_querier(deps, viewer, permit, my_addr)?;
-    let user_store = ReadonlyPrefixedStorage::new(deps.storage, PREFIX_USER_STAKE);
+/// * `deps` - a mutable reference to Extern containing all the contract's external dependencies
+/// * `env` - a reference to the Env of contract's environment
+/// * `sender` - a reference to the message sender
+/// * `rules` - the new staking rules
+fn try_set_rules(
+    mut deps: DepsMut,
+    env: &Env,
+    sender: &Addr,
+    mut rules: StakingRules,
+) -> StdResult<Response> {
+    // only allow admins to do this
+    check_admin_tx(deps.as_ref(), sender)?;
+    validate_rules(&mut rules)?;
+    sync_skull_info(deps.branch(), env)?;
+    // every material must be stakeable before rules can depend on it
+    check_stakeable(deps.storage)?;
+    save(deps.storage, STAKING_RULES_KEY, &rules)?;
 
-    to_binary(&QueryAnswer::UserEligibleForBonus {
-        is_eligible: may_load::<Vec<String>>(&user_store, user_raw.as_slice())?.is_none(),
-    })
+    Ok(Response::new().set_data(to_binary(&ExecuteAnswer::SetStakingRules { rules })?))
 }
 
-/// Returns StdResult<Binary> displaying first staking bonus eligibility for the user and
-/// specified tokens
+/// Returns StdResult<()>
+///
+/// verifies the skull type info has been retrieved from the svg server, so the skull category
+/// index is known, and that every skull material has a staking table
 ///
 /// # Arguments
 ///
-/// * `deps` - reference to Extern containing all the contract's external dependencies
-/// * `env` - Env of contract's environment
-/// * `viewer` - optional address and key making an authenticated query request
-/// * `permit` - optional permit with "owner" permission
-/// * `token_ids` - list of tokens to check
-fn query_token_bonus(
-    deps: Deps,
-    env: Env,
-    viewer: Option<ViewerInfo>,
-    permit: Option<Permit>,
-    token_ids: Vec<String>,
-) -> StdResult<Binary> {
-    let (user_raw, user_hmn) = get_querier(deps, viewer, permit, &env.contract.address)?;
-    let stk_state: StakingState = load(deps.storage, STAKING_STATE_KEY)?;
-    let user_store = ReadonlyPrefixedStorage::new(deps.storage, PREFIX_USER_STAKE);
-    let user_is_eligible = may_load::<Vec<String>>(&user_store, user_raw.as_slice())?.is_none();
-    let mut token_eligibility: Vec<EligibilityInfo> = Vec::new();
-    if user_is_eligible {
-        let skull_store = ReadonlyPrefixedStorage::new(deps.storage, PREFIX_SKULL_STAKE);
-        let (_, not_owned) = verify_ownership(
-            deps,
-            &user_hmn,
-            token_ids.clone(),
-            env.contract.address.into_string(),
-        )?;
-        let now = env.block.time.seconds();
-        let cutoff = now - stk_state.cooldown;
-        for token_id in token_ids.into_iter() {
-            let (is_eligible, claimed_at) = if not_owned.contains(&token_id) {
-                (None, None)
-            } else {
-                let stk_inf = may_load::<SkullStakeInfo>(&skull_store, token_id.as_bytes())?
-                    .unwrap_or(SkullStakeInfo {
-                        addr: CanonicalAddr::from(Binary::default()),
-                        stake: 0,
-                        claim: 0,
-                    });
-                let is_elg = stk_inf.claim <= cutoff;
-                let claim = if is_elg { None } else { Some(stk_inf.claim) };
-                (Some(is_elg), claim)
-            };
-            token_eligibility.push(EligibilityInfo {
-                token_id,
-                is_eligible,
-                claimed_at,
-            });
+/// * `storage` - a reference to this contract's storage
+fn check_stakeable(storage: &dyn Storage) -> StdResult<()> {
+    // materials are only saved when the rest of the skull type info is retrieved
+    let materials: Vec<String> = may_load(storage, MATERIALS_KEY)?.unwrap_or_default();
+    if materials.is_empty() {
+        return Err(StdError::generic_err(
+            "Skull type info has not been retrieved from the svg server",
+        ));
+    }
+    let tbl_store = ReadonlyPrefixedStorage::new(storage, PREFIX_STAKING_TABLE);
+    for (i, mat) in materials.iter().enumerate() {
+        if may_load::<Vec<StoredSetWeight>>(&tbl_store, &(i as u8).to_le_bytes())?.is_none() {
+            return Err(StdError::generic_err(format!(
+                "{} staking table has not been defined",
+                mat
+            )));
         }
     }
-
-    to_binary(&QueryAnswer::TokensEligibleForBonus {
-        user_is_eligible,
-        token_eligibility,
-    })
+    Ok(())
 }
 
-/// Returns StdResult<Binary> displaying the user's staking skulls and charges as well as
-/// their inventory of ingredients
+/// Returns StdResult<()>
+///
+/// retrieve the skull category index, cyclops and jawless layers, and skull materials from the
+/// svg server
 ///
 /// # Arguments
 ///
-/// * `deps` - reference to Extern containing all the contract's external dependencies
-/// * `env` - Env of contract's environment
-/// * `viewer` - optional address and key making an authenticated query request
-/// * `permit` - optional permit with "owner" permission
-fn query_my_stake(
-    deps: Deps,
-    env: Env,
-    viewer: Option<ViewerInfo>,
-    permit: Option<Permit>,
-) -> StdResult<Binary> {
-    let (user_raw, user_hmn) = get_querier(deps, viewer, permit, &env.contract.address)?;
-    let stk_state: StakingState = load(deps.storage, STAKING_STATE_KEY)?;
-    let user_store = ReadonlyPrefixedStorage::new(deps.storage, PREFIX_USER_STAKE);
-    let user_key = user_raw.as_slice();
-    // get staking list
-    let may_stk_list = may_load::<Vec<String>>(&user_store, user_key)?;
-    let first_stake_bonus_available = may_stk_list.is_none();
-    let stk_list = may_stk_list.unwrap_or_default();
-    // only show skulls the user still owns
-    let id_images = if stk_state.halt {
-        Vec::new()
-    } else {
-        let (idi, _) = verify_ownership(
-            deps,
-            &user_hmn,
-            stk_list,
-            env.contract.address.into_string(),
-        )?;
-        idi
-    };
-    let mut charge_infos: Vec<ChargeInfo> = Vec::new();
-    let now = env.block.time.seconds();
-    let skull_store = ReadonlyPrefixedStorage::new(deps.storage, PREFIX_SKULL_STAKE);
-    for id_img in id_images.into_iter() {
-        // get staking info of each skull
-        let id_key = id_img.id.as_bytes();
-        let stk_inf = may_load::<SkullStakeInfo>(&skull_store, id_key)?.unwrap_or(SkullStakeInfo {
-            addr: CanonicalAddr::from(Binary::default()),
-            stake: 0,
-            claim: 0,
-        });
-        // can't claim skulls that are staking with a different user now
-        if stk_inf.addr != user_raw {
-            continue;
-        }
-        let time_in_stake = now - stk_inf.stake;
-        // calc accrued charges
-        let charges = min(4, time_in_stake / stk_state.cooldown) as u8;
-        charge_infos.push(ChargeInfo {
-            token_id: id_img.id,
-            charge_start: stk_inf.stake,
-            charges,
-        });
+/// * `deps` - a mutable reference to Extern containing all the contract's external dependencies
+/// * `env` - a reference to the Env of contract's environment
+fn sync_skull_info(deps: DepsMut, env: &Env) -> StdResult<()> {
+    let svg_raw: StoreContractInfo = load(deps.storage, SVG_SERVER_KEY)?;
+    let viewer = server_viewer(
+        deps.storage,
+        &svg_raw.address,
+        env.contract.address.to_string(),
+    )?;
+    let svg_server = svg_raw.into_humanized(deps.api)?;
+    let st_plus = ServerQueryMsg::SkullTypePlus { viewer }
+        .query::<_, SkullTypePlusWrapper>(deps.querier, svg_server.code_hash, svg_server.address)?
+        .skull_type_plus;
+    let mut stk_st: StakingState = load(deps.storage, STAKING_STATE_KEY)?;
+    stk_st.skull_idx = st_plus.skull_idx;
+    save(deps.storage, STAKING_STATE_KEY, &stk_st)?;
+    let mut alc_st: AlchemyState = load(deps.storage, ALCHEMY_STATE_KEY)?;
+    alc_st.cyclops = st_plus.cyclops;
+    alc_st.jawless = st_plus.jawless;
+    save(deps.storage, ALCHEMY_STATE_KEY, &alc_st)?;
+
+    let mut materials = vec![String::new(); st_plus.skull_variants.len()];
+    for idx_name in st_plus.skull_variants.into_iter() {
+        materials[idx_name.idx as usize] = idx_name.name;
     }
-    // retrieve the user's ingredient inventory
-    let inventory = display_inventory(deps.storage, user_key)?;
+    if materials.iter().any(|n| *n == String::new()) {
+        return Err(StdError::generic_err("Blank Name in skull material list"));
+    }
+    save(deps.storage, MATERIALS_KEY, &materials)?;
 
-    to_binary(&QueryAnswer::MyStaking {
-        first_stake_bonus_available,
-        charge_infos,
-        inventory,
-        staking_is_halted: stk_state.halt,
-    })
+    Ok(())
 }
 
-/// Returns StdResult<Binary> displaying the list of ingredients
+/// Returns StdResult<Response>
+///
+/// creates a viewing key
 ///
 /// # Arguments
 ///
-/// * `storage` - a reference to the storage this item is in
-fn query_ingr(storage: &dyn Storage) -> StdResult<Binary> {
-    let ingredients: Vec<String> = may_load(storage, INGREDIENTS_KEY)?.unwrap_or_default();
-
-    to_binary(&QueryAnswer::Ingredients { ingredients })
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `env` - a reference to the Env of contract's environment
+/// * `info` - calling message information MessageInfo
+/// * `entropy` - string slice of the input String to be used as entropy in randomization
+fn try_create_key(
+    deps: DepsMut,
+    env: &Env,
+    info: &MessageInfo,
+    entropy: &str,
+) -> StdResult<Response> {
+    let key = ViewingKey::create(
+        deps.storage,
+        info,
+        env,
+        info.sender.as_str(),
+        entropy.as_ref(),
+    );
+    Ok(Response::new().set_data(to_binary(&ExecuteAnswer::ViewingKey { key })?))
 }
 
-/// Returns StdResult<Binary> displaying the skull materials and their indices
+/// Returns StdResult<Response>
+///
+/// sets the viewing key to the input String
 ///
 /// # Arguments
 ///
-/// * `deps` - reference to Extern containing all the contract's external dependencies
-/// * `viewer` - optional address and key making an authenticated query request
-/// * `permit` - optional permit with "owner" permission
-/// * `my_addr` - a reference to this contract's address
-fn query_mater(
-    deps: Deps,
-    viewer: Option<ViewerInfo>,
-    permit: Option<Permit>,
-    my_addr: &Addr,
-) -> StdResult<Binary> {
-    // only allow admins to do this
-    check_admin_query(deps, viewer, permit, my_addr)?;
-    let materials: Vec<String> = may_load(deps.storage, MATERIALS_KEY)?.unwrap_or_default();
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `sender` - a reference to the message sender
+/// * `key` - String to be used as the viewing key
+fn try_set_key(deps: DepsMut, sender: &Addr, key: String) -> StdResult<Response> {
+    ViewingKey::set(deps.storage, sender.as_str(), &key);
 
-    to_binary(&QueryAnswer::Materials {
-        materials: materials
-            .into_iter()
-            .enumerate()
-            .map(|(i, m)| VariantIdxName {
-                idx: i as u8,
-                name: m,
-            })
-            .collect::<Vec<VariantIdxName>>(),
-    })
+    Ok(Response::new().set_data(to_binary(&ExecuteAnswer::ViewingKey { key })?))
 }
 
-/// Returns StdResult<Binary> displaying the staking and alchemy states
+/// Returns StdResult<Response>
+///
+/// saves a viewing key issued by the sender for this contract to use when querying the sender.
+/// Keys sent by anything other than the svg server are ignored
 ///
 /// # Arguments
 ///
-/// * `deps` - reference to Extern containing all the contract's external dependencies
-/// * `viewer` - optional address and key making an authenticated query request
-/// * `permit` - optional permit with "owner" permission
-/// * `my_addr` - a reference to this contract's address
-fn query_state(
-    deps: Deps,
-    viewer: Option<ViewerInfo>,
-    permit: Option<Permit>,
-    my_addr: &Addr,
-) -> StdResult<Binary> {
-    // only allow admins to do this
-    check_admin_query(deps, viewer, permit, my_addr)?;
-    let staking_state: StakingState = load(deps.storage, STAKING_STATE_KEY)?;
-    let alchemy_state: AlchemyState = load(deps.storage, ALCHEMY_STATE_KEY)?;
-    let crt_st: CrateState = load(deps.storage, CRATE_STATE_KEY)?;
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `sender` - a reference to the message sender
+/// * `key` - the issued viewing key
+fn try_receive_query_key(deps: DepsMut, sender: &Addr, key: String) -> StdResult<Response> {
+    let sender_raw = deps.api.addr_canonicalize(sender.as_str())?;
+    let svg_raw: StoreContractInfo = load(deps.storage, SVG_SERVER_KEY)?;
+    let status = if svg_raw.address == sender_raw {
+        let mut key_store = PrefixedStorage::new(deps.storage, PREFIX_SERVER_KEYS);
+        save(&mut key_store, sender_raw.as_slice(), &key)?;
+        "success"
+    } else {
+        "ignored"
+    };
 
-    to_binary(&QueryAnswer::States {
-        staking_state,
-        alchemy_state,
-        crating_state: DisplayCrateState {
-            halt: crt_st.halt,
-            cnt: Uint128::new(crt_st.cnt),
-        },
-    })
+    Ok(
+        Response::new().set_data(to_binary(&ExecuteAnswer::ReceiveQueryKey {
+            status: status.to_string(),
+        })?),
+    )
 }
 
-/// Returns StdResult<Binary> displaying the admin addresses
+/// Returns StdResult<ViewerInfo>
+///
+/// the viewer info to use when querying a server.  A viewing key issued by the server is
+/// preferred over this contract's own viewing key
 ///
 /// # Arguments
 ///
-/// * `deps` - reference to Extern containing all the contract's external dependencies
-/// * `viewer` - optional address and key making an authenticated query request
-/// * `permit` - optional permit with "owner" permission
-/// * `my_addr` - a reference to this contract's address
-fn query_admins(
-    deps: Deps,
-    viewer: Option<ViewerInfo>,
-    permit: Option<Permit>,
-    my_addr: &Addr,
-) -> StdResult<Binary> {
-    // only allow admins to do this
-    let admins = check_admin_query(deps, viewer, permit, my_addr)?;
-    to_binary(&QueryAnswer::Admins {
-        admins: admins
-            .iter()
-            .map(|a| deps.api.addr_humanize(a))
-            .collect::<StdResult<Vec<Addr>>>()?,
+/// * `storage` - a reference to this contract's storage
+/// * `server` - a reference to the server's address
+/// * `my_addr` - this contract's address
+fn server_viewer(
+    storage: &dyn Storage,
+    server: &CanonicalAddr,
+    my_addr: String,
+) -> StdResult<ViewerInfo> {
+    let key_store = ReadonlyPrefixedStorage::new(storage, PREFIX_SERVER_KEYS);
+    let viewing_key = if let Some(key) = may_load(&key_store, server.as_slice())? {
+        key
+    } else {
+        load(storage, MY_VIEWING_KEY)?
+    };
+    Ok(ViewerInfo {
+        address: my_addr,
+        viewing_key,
     })
 }
 
-/// Returns StdResult<(CanonicalAddr, String)> from determining the querying address
-/// either from a Permit or a ViewerInfo
+/// Returns StdResult<Response>
+///
+/// revoke the ability to use a specified permit
 ///
 /// # Arguments
 ///
-/// * `deps` - a reference to Extern containing all the contract's external dependencies
-/// * `viewer` - optional address and key making an authenticated query request
-/// * `permit` - optional permit with "owner" permission
-/// * `my_addr` - a reference to this contract's address
-fn get_querier(
-    deps: Deps,
-    viewer: Option<ViewerInfo>,
-    permit: Option<Permit>,
-    my_addr: &Addr,
-) -> StdResult<(CanonicalAddr, String)> {
-    if let Some(pmt) = permit {
-        // Validate permit content
-        let querier = validate(
-            deps,
-            PREFIX_REVOKED_PERMITS,
-            &pmt,
-            my_addr.to_string(),
-            Some("secret"),
-        )?;
-        let raw = deps
-            .api
-            .addr_validate(&querier)
-            .and_then(|a| deps.api.addr_canonicalize(a.as_str()))?;
-        if !pmt.check_permission(&secret_toolkit::permit::TokenPermissions::Owner) {
-            return Err(StdError::generic_err(format!(
-                "Owner permission is required for queries, got permissions {:?}",
-                pmt.params.permissions
-            )));
-        }
-        return Ok((raw, querier));
-    }
-    if let Some(vwr) = viewer {
-        let hmn = deps.api.addr_validate(&vwr.address)?;
-        let raw = deps.api.addr_canonicalize(hmn.as_str())?;
-        ViewingKey::check(deps.storage, hmn.as_str(), &vwr.viewing_key).map_err(|_| {
-            StdError::generic_err("Wrong viewing key for this address or viewing key not set")
-        })?;
-        return Ok((raw, vwr.address));
-    }
-    Err(StdError::generic_err(
-        "A permit or viewing key must be provided",
-    ))
+/// * `storage` - mutable reference to the contract's storage
+/// * `sender` - a reference to the message sender address
+/// * `permit_name` - string slice of the name of the permit to revoke
+fn revoke_permit(
+    storage: &mut dyn Storage,
+    sender: &Addr,
+    permit_name: &str,
+) -> StdResult<Response> {
+    RevokedPermits::revoke_permit(
+        storage,
+        PREFIX_REVOKED_PERMITS,
+        sender.as_str(),
+        permit_name,
+    );
+
+    Ok(
+        Response::new().set_data(to_binary(&ExecuteAnswer::RevokePermit {
+            status: "success".to_string(),
+        })?),
+    )
 }
 
-/// Returns StdResult<Vec<CanonicalAddr>> which is the admin list and checks if the querier is an admin
+/////////////////////////////////////// Query /////////////////////////////////////
+/// Returns StdResult<Binary>
 ///
 /// # Arguments
 ///
-/// * `deps` - a reference to Extern containing all the contract's external dependencies
-/// * `viewer` - optional address and key making an authenticated query request
-/// * `permit` - optional permit with "owner" permission
-/// * `my_addr` - a reference to this contract's address
-fn check_admin_query(
-    deps: Deps,
-    viewer: Option<ViewerInfo>,
-    permit: Option<Permit>,
-    my_addr: &Addr,
-) -> StdResult<Vec<CanonicalAddr>> {
-    let (address, _) = get_querier(deps, viewer, permit, my_addr)?;
-    check_admin(deps.storage, &address)
+/// * `deps` - reference to Extern containing all the contract's external dependencies
+/// * `env` - Env of contract's environment
+/// * `msg` - QueryMsg passed in with the query call
+#[entry_point]
+pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
+    let response = match msg {
+        QueryMsg::Admins { viewer, permit } => {
+            query_admins(deps, viewer, permit, &env.contract.address)
+        }
+        QueryMsg::HaltStatuses {} => query_halt(deps.storage),
+        QueryMsg::Contracts {} => query_contracts(deps),
+        QueryMsg::MyStaking { viewer, permit } => query_my_stake(deps, env, viewer, permit),
+        QueryMsg::StakingOperators { viewer, permit } => {
+            query_operators(deps, &env, viewer, permit)
+        }
+        QueryMsg::MyIngredients { viewer, permit } => {
+            query_my_inv(deps, viewer, permit, &env.contract.address)
+        }
+        QueryMsg::UserEligibleForBonus { viewer, permit } => {
+            query_user_bonus(deps, viewer, permit, &env.contract.address)
+        }
+        QueryMsg::MyHistory {
+            viewer,
+            permit,
+            page,
+            page_size,
+        } => query_my_history(deps, viewer, permit, page, page_size, &env.contract.address),
+        QueryMsg::TokenHistory {
+            viewer,
+            permit,
+            token_id,
+            page,
+            page_size,
+        } => query_token_history(
+            deps,
+            viewer,
+            permit,
+            token_id,
+            page,
+            page_size,
+            env.contract.address,
+        ),
+        QueryMsg::AdminLog {
+            viewer,
+            permit,
+            page,
+            page_size,
+        } => query_admin_log(deps, viewer, permit, &env.contract.address, page, page_size),
+        QueryMsg::HistoryTotals { viewer, permit } => {
+            query_hist_totals(deps, viewer, permit, &env.contract.address)
+        }
+        QueryMsg::TokensEligibleForBonus {
+            viewer,
+            permit,
+            token_ids,
+        } => query_token_bonus(deps, env, viewer, permit, token_ids),
+        QueryMsg::Materials { viewer, permit } => {
+            query_mater(deps, viewer, permit, &env.contract.address)
+        }
+        QueryMsg::Ingredients {} => query_ingr(deps.storage),
+        QueryMsg::IngredientSets {
+            viewer,
+            permit,
+            page,
+            page_size,
+        } => query_ingr_sets(deps, viewer, permit, page, page_size, &env.contract.address),
+        QueryMsg::StakingTable {
+            viewer,
+            permit,
+            by_name,
+            by_index,
+            season,
+        } => query_stk_tbl(
+            deps,
+            viewer,
+            permit,
+            by_name,
+            by_index,
+            season,
+            &env.contract.address,
+        ),
+        QueryMsg::StakingSeasons {} => query_seasons(deps, &env),
+        QueryMsg::StakingRules {} => to_binary(&QueryAnswer::StakingRules {
+            rules: load_rules(deps.storage)?,
+        }),
+        QueryMsg::RewardStreams {} => query_streams(deps),
+        QueryMsg::CrateArt {} => query_crate_art(deps),
+        QueryMsg::ListChannels {} => to_binary(&QueryAnswer::ListChannels {
+            channels: CHANNELS.iter().map(|c| c.to_string()).collect(),
+        }),
+        QueryMsg::ChannelInfo {
+            channels,
+            viewer,
+            permit,
+        } => query_channel_info(deps, channels, viewer, permit, &env.contract.address),
+        QueryMsg::Leaderboard {
+            metric,
+            season_id,
+            page,
+            page_size,
+        } => query_leaderboard(deps, metric, season_id, page, page_size),
+        QueryMsg::States { viewer, permit } => {
+            query_state(deps, viewer, permit, &env.contract.address)
+        }
+        QueryMsg::PityThresholds { viewer, permit } => {
+            query_pity(deps, viewer, permit, &env.contract.address)
+        }
+        QueryMsg::StakingBoosts { viewer, permit } => {
+            query_boosts(deps, viewer, permit, &env.contract.address)
+        }
+        QueryMsg::ExpectedRewards {
+            viewer,
+            permit,
+            token_ids,
+            materials,
+            charges,
+        } => query_expected(deps, env, viewer, permit, token_ids, materials, charges),
+    };
+    pad_query_result(response, BLOCK_SIZE)
 }
 
-/// Returns StdResult<Vec<CanonicalAddr>> which is the admin list and checks if the message
-/// sender is an admin
+/// Returns StdResult<Binary> which displays staking and alchemy halt statuses
 ///
 /// # Arguments
 ///
-/// * `deps` - a reference to Extern containing all the contract's external dependencies
-/// * `sender` - a reference to the message sender
-fn check_admin_tx(deps: Deps, sender: &Addr) -> StdResult<Vec<CanonicalAddr>> {
-    let sender_raw = deps.api.addr_canonicalize(sender.as_str())?;
-    check_admin(deps.storage, &sender_raw)
+/// * `storage` - a reference to this contract's storage
+fn query_halt(storage: &dyn Storage) -> StdResult<Binary> {
+    let stk_st: StakingState = load(storage, STAKING_STATE_KEY)?;
+    let alc_st: AlchemyState = load(storage, ALCHEMY_STATE_KEY)?;
+    let crt_st: CrateState = load(storage, CRATE_STATE_KEY)?;
+
+    to_binary(&QueryAnswer::HaltStatuses {
+        staking_is_halted: stk_st.halt,
+        alchemy_is_halted: alc_st.halt,
+        crating_is_halted: crt_st.halt,
+    })
 }
 
-/// Returns StdResult<Vec<CanonicalAddr>> which is the admin list and checks if the address
-/// is an admin
+/// Returns StdResult<Binary> which displays the code hashes and addresses
+/// of used contract
 ///
 /// # Arguments
 ///
-/// * `storage` - a reference to this contract's storage
-/// * `address` - a reference to the address in question
-fn check_admin(storage: &dyn Storage, address: &CanonicalAddr) -> StdResult<Vec<CanonicalAddr>> {
-    let admins: Vec<CanonicalAddr> = load(storage, ADMINS_KEY)?;
-    if !admins.contains(address) {
-        return Err(StdError::generic_err("Not an admin"));
-    }
-    Ok(admins)
+/// * `deps` - reference to Extern containing all the contract's external dependencies
+fn query_contracts(deps: Deps) -> StdResult<Binary> {
+    let svg_server = load::<StoreContractInfo>(deps.storage, SVG_SERVER_KEY)
+        .and_then(|s| s.into_humanized(deps.api))?;
+    let skulls_contract = load::<StoreContractInfo>(deps.storage, SKULL_721_KEY)
+        .and_then(|s| s.into_humanized(deps.api))?;
+    let crate_contracts = display_crates(deps.storage, deps.api)?;
+
+    to_binary(&QueryAnswer::Contracts {
+        svg_server,
+        skulls_contract,
+        crate_contracts,
+    })
 }
 
-/// Returns StdResult<Response>
-///
-/// updates the admin list
+/// Returns StdResult<Binary> displaying the staking table for a specified skull material
 ///
 /// # Arguments
 ///
-/// * `deps` - a mutable reference to Extern containing all the contract's external dependencies
-/// * `sender` - a reference to the message sender
-/// * `update_list` - list of addresses to use for update
-/// * `is_add` - true if the update is for adding to the list
-fn try_process_auth_list(
-    deps: DepsMut,
-    sender: &Addr,
-    update_list: &[String],
-    is_add: bool,
-) -> StdResult<Response> {
+/// * `deps` - reference to Extern containing all the contract's external dependencies
+/// * `viewer` - optional address and key making an authenticated query request
+/// * `permit` - optional permit with "owner" permission
+/// * `by_name` - optional material string to display
+/// * `by_index` - optional material index to display
+/// * `season` - optional name of the staking season whose table should be displayed
+/// * `my_addr` - a reference to this contract's address
+fn query_stk_tbl(
+    deps: Deps,
+    viewer: Option<ViewerInfo>,
+    permit: Option<Permit>,
+    by_name: Option<String>,
+    by_index: Option<u8>,
+    season: Option<String>,
+    my_addr: &Addr,
+) -> StdResult<Binary> {
     // only allow admins to do this
-    let mut admins = check_admin_tx(deps.as_ref(), sender)?;
-
-    // update the authorization list if needed
-    let save_it = if is_add {
-        add_addrs_to_auth(deps.api, &mut admins, update_list)?
+    check_admin_query(deps, viewer, permit, my_addr)?;
+    let mut materials: Vec<String> = may_load(deps.storage, MATERIALS_KEY)?.unwrap_or_default();
+    let idx = if let Some(nm) = by_name {
+        materials
+            .iter()
+            .position(|m| *m == nm)
+            .ok_or_else(|| StdError::generic_err(format!("Unknown material: {}", nm)))?
+            as u8
     } else {
-        remove_addrs_from_auth(deps.api, &mut admins, update_list)?
+        by_index.ok_or_else(|| StdError::generic_err("Must provide either a name or index"))?
     };
-    // save list if it changed
-    if save_it {
-        save(deps.storage, ADMINS_KEY, &admins)?;
-    }
-    Ok(
-        Response::new().set_data(to_binary(&ExecuteAnswer::AdminsList {
-            admins: admins
+    let tbl = if let Some(ssn_name) = season {
+        let mut seasons: Vec<StoredSeason> =
+            may_load(deps.storage, STAKING_SEASONS_KEY)?.unwrap_or_default();
+        let ssn = seasons
+            .iter()
+            .position(|s| s.name == ssn_name)
+            .map(|p| seasons.swap_remove(p))
+            .ok_or_else(|| {
+                StdError::generic_err(format!("Unknown staking season: {}", ssn_name))
+            })?;
+        ssn.tables
+            .into_iter()
+            .find(|t| t.material == idx)
+            .map(|t| t.weights)
+            .ok_or_else(|| {
+                StdError::generic_err(format!(
+                    "{} does not change this material's staking table",
+                    ssn_name
+                ))
+            })?
+    } else {
+        let tbl_store = ReadonlyPrefixedStorage::new(deps.storage, PREFIX_STAKING_TABLE);
+        may_load::<Vec<StoredSetWeight>>(&tbl_store, &idx.to_le_bytes())
+            .and_then(|s| s.ok_or_else(|| StdError::generic_err("Invalid SetWeight index")))?
+    };
+    let ingr_sets: Vec<StoredIngrSet> =
+        may_load(deps.storage, INGRED_SETS_KEY)?.unwrap_or_default();
+
+    to_binary(&QueryAnswer::StakingTable {
+        staking_table: StakingTable {
+            material: materials.swap_remove(idx as usize),
+            ingredient_set_weights: tbl
                 .iter()
-                .map(|a| deps.api.addr_humanize(a))
-                .collect::<StdResult<Vec<Addr>>>()?,
-        })?),
-    )
+                .map(|s| IngrSetWeight {
+                    ingredient_set: ingr_sets[s.set as usize].name.clone(),
+                    weight: s.weight,
+                })
+                .collect::<Vec<IngrSetWeight>>(),
+        },
+    })
 }
 
-/// Returns StdResult<bool>
-///
-/// adds to an authorization list of addresses and returns true if the list changed
+/// Returns StdResult<Binary> displaying the expected quantities of ingredients that would be
+/// rewarded for claiming charges from the specified skulls
 ///
 /// # Arguments
 ///
-/// * `api` - a reference to the Api used to convert human and canonical addresses
-/// * `addresses` - current mutable list of addresses
-/// * `addrs_to_add` - list of addresses to add
-fn add_addrs_to_auth(
-    api: &dyn Api,
-    addresses: &mut Vec<CanonicalAddr>,
-    addrs_to_add: &[String],
-) -> StdResult<bool> {
-    let mut save_it = false;
-    for addr in addrs_to_add.iter() {
-        let raw = api
-            .addr_validate(addr)
-            .and_then(|a| api.addr_canonicalize(a.as_str()))?;
-        if !addresses.contains(&raw) {
-            addresses.push(raw);
-            save_it = true;
+/// * `deps` - reference to Extern containing all the contract's external dependencies
+/// * `env` - Env of contract's environment
+/// * `viewer` - optional address and key making an authenticated query request
+/// * `permit` - optional permit with "owner" permission
+/// * `token_ids` - optional list of the user's skulls to evaluate
+/// * `materials` - optional list of skull materials and quantities to evaluate (admin only)
+/// * `charges` - number of charges each skull would be claiming
+fn query_expected(
+    deps: Deps,
+    env: Env,
+    viewer: Option<ViewerInfo>,
+    permit: Option<Permit>,
+    token_ids: Option<Vec<String>>,
+    materials: Option<Vec<MaterialQty>>,
+    charges: u8,
+) -> StdResult<Binary> {
+    let (user_raw, user_hmn) = get_querier(deps, viewer, permit, &env.contract.address)?;
+    let stk_state: StakingState = load(deps.storage, STAKING_STATE_KEY)?;
+    let mater_names: Vec<String> = may_load(deps.storage, MATERIALS_KEY)?.unwrap_or_default();
+    let mut quantities: Vec<u8> = vec![0; mater_names.len()];
+    let mut groups: Vec<ChargeGroup> = Vec::new();
+    let rules = load_rules(deps.storage)?;
+    let max_cnt = max_staked(&rules, None) as usize;
+    let charges = min(rules.charge_cap, charges);
+    let seasons: Vec<StoredSeason> =
+        may_load(deps.storage, STAKING_SEASONS_KEY)?.unwrap_or_default();
+    let season = active_season(&seasons, env.block.time.seconds());
+    let mut skull_cnt = 0usize;
+    if let Some(mats) = materials {
+        // only admins can evaluate hypothetical skulls
+        check_admin(deps.storage, &user_raw)?;
+        for mat_qty in mats.into_iter() {
+            let pos = mater_names
+                .iter()
+                .position(|m| *m == mat_qty.material)
+                .ok_or_else(|| {
+                    StdError::generic_err(format!(
+                        "{} is not a known skull material",
+                        mat_qty.material
+                    ))
+                })?;
+            skull_cnt += mat_qty.quantity as usize;
+            if skull_cnt > max_cnt {
+                return Err(StdError::generic_err(format!(
+                    "You can only stake up to {} skulls",
+                    max_cnt
+                )));
+            }
+            quantities[pos] += mat_qty.quantity;
+            if charges > 0 && mat_qty.quantity > 0 {
+                add_charge_group(
+                    &mut groups,
+                    pos as u8,
+                    season,
+                    charges as u16 * mat_qty.quantity as u16,
+                    Vec::new(),
+                );
+            }
         }
     }
-    Ok(save_it)
+    if let Some(ids) = token_ids {
+        let (id_images, not_owned) =
+            verify_ownership(deps, &user_hmn, ids, env.contract.address.into_string())?;
+        if !not_owned.is_empty() {
+            return Err(StdError::generic_err(format!(
+                "You do not own skull(s): {}",
+                not_owned.join(", ")
+            )));
+        }
+        skull_cnt += id_images.len();
+        if skull_cnt > max_cnt {
+            return Err(StdError::generic_err(format!(
+                "You can only stake up to {} skulls",
+                max_cnt
+            )));
+        }
+        let boosts: Vec<StoredBoost> =
+            may_load(deps.storage, STAKING_BOOSTS_KEY)?.unwrap_or_default();
+        for id_img in id_images.into_iter() {
+            let material = id_img.image.natural[stk_state.skull_idx as usize];
+            quantities[material as usize] += 1;
+            if charges > 0 {
+                let skull_boosts = get_boosts(&boosts, &id_img.image.current);
+                add_charge_group(&mut groups, material, season, charges as u16, skull_boosts);
+            }
+        }
+    }
+    let ingredients: Vec<String> = may_load(deps.storage, INGREDIENTS_KEY)?.unwrap_or_default();
+    let expected = expected_resources(deps.storage, &groups, &quantities, ingredients.len())?;
+
+    to_binary(&QueryAnswer::ExpectedRewards {
+        charges,
+        expected_rewards: expected
+            .into_iter()
+            .zip(ingredients)
+            .filter(|(qty, _)| !qty.is_zero())
+            .map(|(quantity, ingredient)| ExpectedQty {
+                ingredient,
+                quantity,
+            })
+            .collect::<Vec<ExpectedQty>>(),
+    })
 }
 
-/// Returns StdResult<bool>
-///
-/// removes from an authorization list of addresses and returns true if the list changed
+/// Returns StdResult<Binary> displaying the current and upcoming staking seasons
 ///
 /// # Arguments
 ///
-/// * `api` - a reference to the Api used to convert human and canonical addresses
-/// * `addresses` - current mutable list of addresses
-/// * `addrs_to_remove` - list of addresses to remove
-fn remove_addrs_from_auth(
-    api: &dyn Api,
-    addresses: &mut Vec<CanonicalAddr>,
-    addrs_to_remove: &[String],
-) -> StdResult<bool> {
-    let old_len = addresses.len();
-    let rem_list = addrs_to_remove
-        .iter()
-        .map(|a| {
-            api.addr_validate(a)
-                .and_then(|a| api.addr_canonicalize(a.as_str()))
-        })
-        .collect::<StdResult<Vec<CanonicalAddr>>>()?;
-    addresses.retain(|a| !rem_list.contains(a));
-    // only save if the list changed
-    Ok(old_len != addresses.len())
-}
+/// * `deps` - reference to Extern containing all the contract's external dependencies
+/// * `env` - a reference to the Env of contract's environment
+fn query_seasons(deps: Deps, env: &Env) -> StdResult<Binary> {
+    let stk_state: StakingState = load(deps.storage, STAKING_STATE_KEY)?;
+    let seasons: Vec<StoredSeason> =
+        may_load(deps.storage, STAKING_SEASONS_KEY)?.unwrap_or_default();
+    let materials: Vec<String> = may_load(deps.storage, MATERIALS_KEY)?.unwrap_or_default();
+    let now = env.block.time.seconds();
+    let mut current: Option<SeasonInfo> = None;
+    let mut upcoming: Vec<SeasonInfo> = Vec::new();
+    for ssn in seasons.into_iter().filter(|s| now < s.end) {
+        let info = SeasonInfo {
+            id: ssn.id,
+            name: ssn.name,
+            start: ssn.start,
+            end: ssn.end,
+            charge_time: max(1, stk_state.cooldown * ssn.charge_pct as u64 / 100),
+            materials: ssn
+                .tables
+                .iter()
+                .map(|t| materials[t.material as usize].clone())
+                .collect(),
+        };
+        if info.start <= now {
+            current = Some(info);
+        } else {
+            upcoming.push(info);
+        }
+    }
 
-// a skull's token id and the ImageInfo retrieved for it
-pub struct IdImage {
-    pub id: String,
-    pub image: ImageInfo,
+    to_binary(&QueryAnswer::StakingSeasons { current, upcoming })
 }
 
-/// Returns StdResult<(Vec<IdImage>, Vec<String>)>
+/// Returns StdResult<Binary> displaying the SNIP-20 reward streams
 ///
-/// Verifies ownership of a list of skull token ids and returns the list of token ids and image infos for
-/// skulls that have been verified to be owned by the specified address, and the list of token ids of the
-/// skulls that do not belong to the address
+/// # Arguments
+///
+/// * `deps` - reference to Extern containing all the contract's external dependencies
+fn query_streams(deps: Deps) -> StdResult<Binary> {
+    let materials: Vec<String> = may_load(deps.storage, MATERIALS_KEY)?.unwrap_or_default();
+    let streams: Vec<StoredStream> =
+        may_load(deps.storage, REWARD_STREAMS_KEY)?.unwrap_or_default();
+
+    to_binary(&QueryAnswer::RewardStreams {
+        streams: streams
+            .iter()
+            .map(|s| display_stream(deps.api, &materials, s))
+            .collect::<StdResult<Vec<StreamInfo>>>()?,
+    })
+}
+
+/// Returns StdResult<Binary> displaying the pity thresholds of all staking tables
 ///
 /// # Arguments
 ///
-/// * `deps` - a reference to Extern containing all the contract's external dependencies
-/// * `owner` - a reference to the owner address for verification
-/// * `skulls` - list of token ids to check
-/// * `my_addr` - this contract's address
-fn verify_ownership(
+/// * `deps` - reference to Extern containing all the contract's external dependencies
+/// * `viewer` - optional address and key making an authenticated query request
+/// * `permit` - optional permit with "owner" permission
+/// * `my_addr` - a reference to this contract's address
+fn query_pity(
     deps: Deps,
-    owner: &str,
-    skulls: Vec<String>,
-    my_addr: String,
-) -> StdResult<(Vec<IdImage>, Vec<String>)> {
-    let mut owned: Vec<IdImage> = Vec::new();
-    let mut not_owned: Vec<String> = Vec::new();
-    let viewing_key: String = load(deps.storage, MY_VIEWING_KEY)?;
-    let viewer = ViewerInfo {
-        address: my_addr,
-        viewing_key,
-    };
-    let skull_contract = load::<StoreContractInfo>(deps.storage, SKULL_721_KEY)
-        .and_then(|s| s.into_humanized(deps.api))?;
-
-    for id in skulls.into_iter() {
-        // see if this is a duplicate in the list
-        if owned.iter().any(|i| i.id == id) {
-            continue;
-        }
-        if not_owned.contains(&id) {
-            continue;
-        }
-        // get the image info
-        let img_inf_resp = Snip721QueryMsg::ImageInfo {
-            token_id: id.clone(),
-            viewer: viewer.clone(),
-        }
-        .query::<_, ImageInfoWrapper>(
-            deps.querier,
-            skull_contract.code_hash.clone(),
-            skull_contract.address.clone(),
-        )?
-        .image_info;
-        // if not the current owner
-        if img_inf_resp.owner != *owner {
-            not_owned.push(id);
-        } else {
-            owned.push(IdImage {
-                id,
-                image: img_inf_resp.image_info,
+    viewer: Option<ViewerInfo>,
+    permit: Option<Permit>,
+    my_addr: &Addr,
+) -> StdResult<Binary> {
+    // only allow admins to do this
+    check_admin_query(deps, viewer, permit, my_addr)?;
+    let materials: Vec<String> = may_load(deps.storage, MATERIALS_KEY)?.unwrap_or_default();
+    let ingr_sets: Vec<StoredIngrSet> =
+        may_load(deps.storage, INGRED_SETS_KEY)?.unwrap_or_default();
+    let pity_store = ReadonlyPrefixedStorage::new(deps.storage, PREFIX_PITY_TABLE);
+    let mut tables: Vec<PityTable> = Vec::new();
+    for (i, material) in materials.into_iter().enumerate() {
+        let pity_sets: Vec<StoredPity> =
+            may_load(&pity_store, &(i as u8).to_le_bytes())?.unwrap_or_default();
+        if !pity_sets.is_empty() {
+            tables.push(PityTable {
+                material,
+                sets: pity_sets
+                    .into_iter()
+                    .map(|p| PitySet {
+                        ingredient_set: ingr_sets[p.set as usize].name.clone(),
+                        threshold: p.threshold,
+                    })
+                    .collect(),
             });
         }
     }
-    Ok((owned, not_owned))
+
+    to_binary(&QueryAnswer::PityThresholds { tables })
 }
 
-/// Returns StdResult<Vec<u32>>
+/// Returns StdResult<Binary> displaying the querier's notification info for the specified
+/// channels
+///
+/// # Arguments
 ///
-/// Take a list of charges per material type, and randomly draw resources according to the weighted staking table
+/// * `deps` - reference to Extern containing all the contract's external dependencies
+/// * `channels` - names of the channels
+/// * `viewer` - optional address and key making an authenticated query request
+/// * `permit` - optional permit with "owner" permission
+/// * `my_addr` - a reference to this contract's address
+fn query_channel_info(
+    deps: Deps,
+    channels: Vec<String>,
+    viewer: Option<ViewerInfo>,
+    permit: Option<Permit>,
+    my_addr: &Addr,
+) -> StdResult<Binary> {
+    let (user_raw, _) = get_querier(deps, viewer, permit, my_addr)?;
+    let channels = channel_info(deps.storage, &user_raw, channels)?;
+
+    to_binary(&QueryAnswer::ChannelInfo { channels })
+}
+
+/// Returns StdResult<Binary> displaying a page of a leaderboard
 ///
 /// # Arguments
 ///
-/// * `storage` - a reference to this contract's storage
-/// * `env` - a reference to the Env of contract's environment
-/// * `charges` - number of charges per material type
-/// * `quantities` - number of skulls per material type
-/// * `ingr_cnt` - number of different ingredients
-fn gen_resources(
-    storage: &dyn Storage,
-    env: &Env,
-    charges: &[u8],
-    quantities: &[u8],
-    ingr_cnt: usize,
-) -> StdResult<Vec<u32>> {
-    let mut generated: Vec<u32> = vec![0; ingr_cnt];
-    let mut rng = ContractPrng::from_env(env);
-    let type_cnt = quantities.iter().filter(|&q| *q > 0).count() as u64;
-    let variety_lim = (2 * type_cnt) + 1;
-    let ingr_sets: Vec<StoredIngrSet> = may_load(storage, INGRED_SETS_KEY)?.unwrap_or_default();
-    let mut wins_per_set: Vec<u16> = vec![0; ingr_sets.len()];
-    // go through each material type and the number of charges for each
-    for (i, charge) in charges.iter().enumerate() {
-        // process each charge for this material type
-        for _ in 0u8..*charge {
-            // randomly determine number of resources generated for this charge
-            let rdm_mat = rng.next_u64();
-            let rdm_var = rng.next_u64();
-            let rolls: u8 =
-                1 + (rdm_mat % (quantities[i] as u64 + 1u64)) as u8 + (rdm_var % variety_lim) as u8;
-            let tbl_store = ReadonlyPrefixedStorage::new(storage, PREFIX_STAKING_TABLE);
-            let i_sml = i as u8;
-            let stk_tbl: Vec<StoredSetWeight> = load(&tbl_store, &i_sml.to_le_bytes())?;
-            let just_weights: Vec<u16> = stk_tbl.iter().map(|t| t.weight).collect();
-            let total_weight: u16 = just_weights.iter().sum();
-            // randomly pick the winning ingredient set for each resource
-            for _ in 0u8..rolls {
-                let rdm = rng.next_u64();
-                let winning_num: u16 = (rdm % total_weight as u64) as u16;
-                let mut tally = 0u16;
-                let mut winner = 0usize;
-                for set_weight in stk_tbl.iter() {
-                    // if the sum didn't panic on overflow, it can't happen here
-                    tally += set_weight.weight;
-                    if tally > winning_num {
-                        winner = set_weight.set as usize;
-                        break;
-                    }
-                }
-                // increment wins for the winning ingredient set
-                wins_per_set[winner] += 1;
-            }
+/// * `deps` - reference to Extern containing all the contract's external dependencies
+/// * `metric` - the ranked activity
+/// * `season_id` - optional id of a staking season
+/// * `page` - optional page to display
+/// * `page_size` - optional number of entries to display
+fn query_leaderboard(
+    deps: Deps,
+    metric: LeaderboardMetric,
+    season_id: Option<u16>,
+    page: Option<u32>,
+    page_size: Option<u32>,
+) -> StdResult<Binary> {
+    let season_key = if let Some(id) = season_id {
+        // leaderboards outlive their seasons' removal from the schedule
+        let next_id: u16 = may_load(deps.storage, SEASON_ID_KEY)?.unwrap_or(0);
+        if id >= next_id {
+            return Err(StdError::generic_err(format!(
+                "{} is not a staking season id",
+                id
+            )));
         }
-    }
-    // randomly pick ingredients from each winning set of ingredients
-    for (idx, resource_cnt) in wins_per_set.iter().enumerate() {
-        // if this set had been picked
-        if *resource_cnt > 0 {
-            // number of ingredients to pick from
-            let ingr_cnt = ingr_sets[idx].list.len() as u64;
-            for _ in 0u16..*resource_cnt {
-                let win = if ingr_cnt == 1 {
-                    // no need to waste resources getting a rdm number if there is only one possible
-                    0usize
-                } else {
-                    // more than one ingredient in this set
-                    let rdm_ing = rng.next_u64();
-                    (rdm_ing % ingr_cnt) as usize
-                };
-                generated[ingr_sets[idx].list[win] as usize] += 1;
+        id.to_le_bytes().to_vec()
+    } else {
+        Vec::new()
+    };
+    let dir_store =
+        ReadonlyPrefixedStorage::multilevel(deps.storage, &[PREFIX_LEADERBOARD, &season_key]);
+    let dir: Vec<StoredRankPage> = may_load(&dir_store, &[metric as u8])?.unwrap_or_default();
+    let page_store =
+        ReadonlyPrefixedStorage::multilevel(deps.storage, &[PREFIX_LEADER_PAGES, &season_key]);
+    let opt_store = ReadonlyPrefixedStorage::new(deps.storage, PREFIX_LEADER_OPT);
+    let limit = page_size.unwrap_or(30) as usize;
+    let skip = (page.unwrap_or(0) as usize).saturating_mul(limit);
+    let mut entries: Vec<LeaderboardEntry> = Vec::new();
+    // rank index of the first player of the current page
+    let mut start = 0usize;
+    // only load the pages that hold the requested ranks
+    for summary in dir.iter() {
+        let end = start + summary.count as usize;
+        if entries.len() >= limit {
+            break;
+        } else if end > skip {
+            let ranks: Vec<StoredRank> =
+                may_load(&page_store, &rank_page_key(metric as u8, summary.id))?
+                    .unwrap_or_default();
+            let wanted = limit - entries.len();
+            for (i, r) in ranks
+                .into_iter()
+                .enumerate()
+                .skip(skip.saturating_sub(start))
+                .take(wanted)
+            {
+                let alias =
+                    may_load::<StoredOptIn>(&opt_store, r.user.as_slice())?.and_then(|o| o.alias);
+                entries.push(LeaderboardEntry {
+                    rank: (start + i) as u32 + 1,
+                    player: match alias {
+                        Some(a) => a,
+                        None => deps.api.addr_humanize(&r.user)?.into_string(),
+                    },
+                    score: r.score,
+                });
             }
         }
+        start = end;
     }
-    Ok(generated)
+
+    to_binary(&QueryAnswer::Leaderboard {
+        count: dir.iter().map(|p| p.count).sum(),
+        entries,
+    })
 }
 
-/// Returns StdResult<Vec<IngredientQty>>
-///
-/// generate resources for the charges and update user ingredients inventory
+/// Returns StdResult<Binary> displaying the svg fragments used to generate crate images
 ///
 /// # Arguments
 ///
-/// * `storage` - a mutable reference to this contract's storage
-/// * `env` - a reference to the Env of contract's environment
-/// * `charges` - number of charges per material type
-/// * `quantities` - number of skulls per material type
-/// * `user_key` - user address storage key
-fn process_charges(
-    storage: &mut dyn Storage,
-    env: &Env,
-    charges: &[u8],
-    quantities: &[u8],
-    user_key: &[u8],
-) -> StdResult<Vec<IngredientQty>> {
-    let mut rewards: Vec<IngredientQty> = Vec::new();
-    let ingredients: Vec<String> = may_load(storage, INGREDIENTS_KEY)?.unwrap_or_default();
-    let ingr_cnt = ingredients.len();
-    // generate the ingredients
-    let generated = gen_resources(storage, env, charges, quantities, ingr_cnt)?;
-    let mut inv_store = PrefixedStorage::new(storage, PREFIX_USER_INGR_INVENTORY);
-    let mut inventory: Vec<u32> = may_load(&inv_store, user_key)?.unwrap_or_default();
-    // just in case new ingredients get added, extend old inventories
-    inventory.resize(ingr_cnt, 0);
-    // add the newly generated resources
-    for (inv, new) in inventory.iter_mut().zip(&generated) {
-        *inv += *new;
-    }
-    save(&mut inv_store, user_key, &inventory)?;
-    // create the list of generated resources for the output
-    for (i, quantity) in generated.into_iter().enumerate() {
-        if quantity > 0 {
-            rewards.push(IngredientQty {
-                ingredient: ingredients[i].clone(),
-                quantity,
-            });
-        }
-    }
-    Ok(rewards)
+/// * `deps` - reference to Extern containing all the contract's external dependencies
+fn query_crate_art(deps: Deps) -> StdResult<Binary> {
+    let ingredients: Vec<String> = may_load(deps.storage, INGREDIENTS_KEY)?.unwrap_or_default();
+    let art: StoredCrateArt = may_load(deps.storage, CRATE_ART_KEY)?.unwrap_or_default();
+
+    to_binary(&QueryAnswer::CrateArt {
+        background: art.background,
+        icons: art
+            .icons
+            .into_iter()
+            .zip(ingredients)
+            .filter_map(|(svg, ingredient)| svg.map(|svg| IngredientIcon { ingredient, svg }))
+            .collect(),
+    })
 }
 
-/// Returns StdResult<Vec<IngredientQty>>
-///
-/// create a readable list of a user's ingredient inventory
+/// Returns StdResult<Binary> displaying the staking boosts
 ///
 /// # Arguments
 ///
-/// * `storage` - a reference to this contract's storage
-/// * `user_key` - user address storage key
-fn display_inventory(storage: &dyn Storage, user_key: &[u8]) -> StdResult<Vec<IngredientQty>> {
-    let mut inventory: Vec<IngredientQty> = Vec::new();
-    let ingredients: Vec<String> = may_load(storage, INGREDIENTS_KEY)?.unwrap_or_default();
-    let ingr_cnt = ingredients.len();
-    let inv_store = ReadonlyPrefixedStorage::new(storage, PREFIX_USER_INGR_INVENTORY);
-    let mut raw_inv: Vec<u32> = may_load(&inv_store, user_key)?.unwrap_or_default();
-    // just in case new ingredients get added, extend old inventories
-    raw_inv.resize(ingr_cnt, 0);
-    // create the readable list of ingredients
-    for (i, quantity) in raw_inv.into_iter().enumerate() {
-        inventory.push(IngredientQty {
-            ingredient: ingredients[i].clone(),
-            quantity,
-        });
-    }
-    Ok(inventory)
+/// * `deps` - reference to Extern containing all the contract's external dependencies
+/// * `viewer` - optional address and key making an authenticated query request
+/// * `permit` - optional permit with "owner" permission
+/// * `my_addr` - a reference to this contract's address
+fn query_boosts(
+    deps: Deps,
+    viewer: Option<ViewerInfo>,
+    permit: Option<Permit>,
+    my_addr: &Addr,
+) -> StdResult<Binary> {
+    // only allow admins to do this
+    check_admin_query(deps, viewer, permit, my_addr)?;
+    let boosts: Vec<StoredBoost> = may_load(deps.storage, STAKING_BOOSTS_KEY)?.unwrap_or_default();
+    let ingr_sets: Vec<StoredIngrSet> =
+        may_load(deps.storage, INGRED_SETS_KEY)?.unwrap_or_default();
+
+    to_binary(&QueryAnswer::StakingBoosts {
+        boosts: boosts
+            .into_iter()
+            .map(|b| StakingBoost {
+                name: b.name,
+                layer: b.layer_name,
+                extra_rolls: b.extra_rolls,
+                ingredient_set_weights: b
+                    .weights
+                    .iter()
+                    .map(|w| IngrSetWeight {
+                        ingredient_set: ingr_sets[w.set as usize].name.clone(),
+                        weight: w.weight,
+                    })
+                    .collect::<Vec<IngrSetWeight>>(),
+                bonus_set: b.bonus.map(|c| IngrSetChance {
+                    ingredient_set: ingr_sets[c.set as usize].name.clone(),
+                    chance: c.chance,
+                }),
+            })
+            .collect::<Vec<StakingBoost>>(),
+    })
 }
 
-/// Returns StdResult<Response>
-///
-/// uncrate crate nfts
+/// Returns StdResult<Binary> displaying the ingredient sets
 ///
 /// # Arguments
 ///
-/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
-/// * `crate_addr` - the message sender's address
-/// * `crate_hash` - the message sender's code hash
+/// * `deps` - reference to Extern containing all the contract's external dependencies
+/// * `viewer` - optional address and key making an authenticated query request
+/// * `permit` - optional permit with "owner" permission
+/// * `page` - optional page to display
+/// * `page_size` - optional number of sets to display
+/// * `my_addr` - a reference to this contract's address
+fn query_ingr_sets(
+    deps: Deps,
+    viewer: Option<ViewerInfo>,
+    permit: Option<Permit>,
+    page: Option<u16>,
+    page_size: Option<u16>,
+    my_addr: &Addr,
+) -> StdResult<Binary> {
+    // only allow admins to do this
+    check_admin_query(deps, viewer, permit, my_addr)?;
+    let ingr_sets: Vec<StoredIngrSet> =
+        may_load(deps.storage, INGRED_SETS_KEY)?.unwrap_or_default();
+    let ingredients: Vec<String> = may_load(deps.storage, INGREDIENTS_KEY)?.unwrap_or_default();
+
+    let page = page.unwrap_or(0);
+    let limit = page_size.unwrap_or(30);
+    let skip = (page * limit) as usize;
+
+    to_binary(&QueryAnswer::IngredientSets {
+        ingredient_sets: ingr_sets
+            .into_iter()
+            .skip(skip)
+            .take(limit as usize)
+            .map(|s| IngredientSet {
+                name: s.name,
+                members: s
+                    .list
+                    .iter()
+                    .map(|u| ingredients[*u as usize].clone())
+                    .collect::<Vec<String>>(),
+            })
+            .collect::<Vec<IngredientSet>>(),
+    })
+}
+
+/// Returns StdResult<Binary> displaying the user's inventory of ingredients
+///
+/// # Arguments
+///
+/// * `deps` - reference to Extern containing all the contract's external dependencies
+/// * `viewer` - optional address and key making an authenticated query request
+/// * `permit` - optional permit with "owner" permission
+/// * `my_addr` - a reference to this contract's address
+fn query_my_inv(
+    deps: Deps,
+    viewer: Option<ViewerInfo>,
+    permit: Option<Permit>,
+    my_addr: &Addr,
+) -> StdResult<Binary> {
+    let (user_raw, _) = get_querier(deps, viewer, permit, my_addr)?;
+
+    // retrieve the user's ingredient inventory
+    let inventory = display_inventory(deps.storage, user_raw.as_slice())?;
+
+    to_binary(&QueryAnswer::MyIngredients { inventory })
+}
+
+/// Returns StdResult<Binary> displaying whether the user is eligible for the first time staking bonus
+///
+/// # Arguments
+///
+/// * `deps` - reference to Extern containing all the contract's external dependencies
+/// * `viewer` - optional address and key making an authenticated query request
+/// * `permit` - optional permit with "owner" permission
+/// * `my_addr` - a reference to this contract's address
+fn query_user_bonus(
+    deps: Deps,
+    viewer: Option<ViewerInfo>,
+    permit: Option<Permit>,
+    my_addr: &Addr,
+) -> StdResult<Binary> {
+    let (user_raw, _) = get_querier(deps, viewer, permit, my_addr)?;
+    let user_store = ReadonlyPrefixedStorage::new(deps.storage, PREFIX_USER_STAKE);
+
+    to_binary(&QueryAnswer::UserEligibleForBonus {
+        is_eligible: load_rules(deps.storage)?.first_stake_bonus > 0
+            && may_load::<Vec<String>>(&user_store, user_raw.as_slice())?.is_none(),
+    })
+}
+
+/// Returns StdResult<Binary> displaying first staking bonus eligibility for the user and
+/// specified tokens
+///
+/// # Arguments
+///
+/// * `deps` - reference to Extern containing all the contract's external dependencies
+/// * `env` - Env of contract's environment
+/// * `viewer` - optional address and key making an authenticated query request
+/// * `permit` - optional permit with "owner" permission
+/// * `token_ids` - list of tokens to check
+fn query_token_bonus(
+    deps: Deps,
+    env: Env,
+    viewer: Option<ViewerInfo>,
+    permit: Option<Permit>,
+    token_ids: Vec<String>,
+) -> StdResult<Binary> {
+    let (user_raw, user_hmn) = get_querier(deps, viewer, permit, &env.contract.address)?;
+    let stk_state: StakingState = load(deps.storage, STAKING_STATE_KEY)?;
+    let user_store = ReadonlyPrefixedStorage::new(deps.storage, PREFIX_USER_STAKE);
+    let user_is_eligible = load_rules(deps.storage)?.first_stake_bonus > 0
+        && may_load::<Vec<String>>(&user_store, user_raw.as_slice())?.is_none();
+    let mut token_eligibility: Vec<EligibilityInfo> = Vec::new();
+    if user_is_eligible {
+        let skull_store = ReadonlyPrefixedStorage::new(deps.storage, PREFIX_SKULL_STAKE);
+        let (_, not_owned) = verify_ownership(
+            deps,
+            &user_hmn,
+            token_ids.clone(),
+            env.contract.address.into_string(),
+        )?;
+        let now = env.block.time.seconds();
+        let cutoff = now - stk_state.cooldown;
+        for token_id in token_ids.into_iter() {
+            let (is_eligible, claimed_at) = if not_owned.contains(&token_id) {
+                (None, None)
+            } else {
+                let stk_inf = may_load::<SkullStakeInfo>(&skull_store, token_id.as_bytes())?
+                    .unwrap_or(SkullStakeInfo {
+                        addr: CanonicalAddr::from(Binary::default()),
+                        stake: 0,
+                        claim: 0,
+                    });
+                let is_elg = stk_inf.claim <= cutoff;
+                let claim = if is_elg { None } else { Some(stk_inf.claim) };
+                (Some(is_elg), claim)
+            };
+            token_eligibility.push(EligibilityInfo {
+                token_id,
+                is_eligible,
+                claimed_at,
+            });
+        }
+    }
+
+    to_binary(&QueryAnswer::TokensEligibleForBonus {
+        user_is_eligible,
+        token_eligibility,
+    })
+}
+
+/// Returns StdResult<Binary> displaying the user's staking skulls and charges as well as
+/// their inventory of ingredients
+///
+/// # Arguments
+///
+/// * `deps` - reference to Extern containing all the contract's external dependencies
+/// * `env` - Env of contract's environment
+/// * `viewer` - optional address and key making an authenticated query request
+/// * `permit` - optional permit with "owner" permission
+fn query_my_stake(
+    deps: Deps,
+    env: Env,
+    viewer: Option<ViewerInfo>,
+    permit: Option<Permit>,
+) -> StdResult<Binary> {
+    let (user_raw, user_hmn) = get_querier(deps, viewer, permit, &env.contract.address)?;
+    let stk_state: StakingState = load(deps.storage, STAKING_STATE_KEY)?;
+    let user_store = ReadonlyPrefixedStorage::new(deps.storage, PREFIX_USER_STAKE);
+    let user_key = user_raw.as_slice();
+    // get staking list
+    let may_stk_list = may_load::<Vec<String>>(&user_store, user_key)?;
+    let first_stake_bonus_available = may_stk_list.is_none();
+    let stk_list = may_stk_list.unwrap_or_default();
+    // only show skulls the user still owns
+    let id_images = if stk_state.halt {
+        Vec::new()
+    } else {
+        let (idi, _) = verify_ownership(
+            deps,
+            &user_hmn,
+            stk_list,
+            env.contract.address.into_string(),
+        )?;
+        idi
+    };
+    let mut charge_infos: Vec<ChargeInfo> = Vec::new();
+    let now = env.block.time.seconds();
+    let boosts: Vec<StoredBoost> = may_load(deps.storage, STAKING_BOOSTS_KEY)?.unwrap_or_default();
+    let seasons: Vec<StoredSeason> =
+        may_load(deps.storage, STAKING_SEASONS_KEY)?.unwrap_or_default();
+    let rules = load_rules(deps.storage)?;
+    let mut remaining = rules.charge_cap;
+    let streams: Vec<StoredStream> =
+        may_load(deps.storage, REWARD_STREAMS_KEY)?.unwrap_or_default();
+    let mut owed = load_unpaid(deps.storage, user_key, &streams)?;
+    let skull_store = ReadonlyPrefixedStorage::new(deps.storage, PREFIX_SKULL_STAKE);
+    for id_img in id_images.into_iter() {
+        // get staking info of each skull
+        let id_key = id_img.id.as_bytes();
+        let stk_inf = may_load::<SkullStakeInfo>(&skull_store, id_key)?.unwrap_or(SkullStakeInfo {
+            addr: CanonicalAddr::from(Binary::default()),
+            stake: 0,
+            claim: 0,
+        });
+        // can't claim skulls that are staking with a different user now
+        if stk_inf.addr != user_raw {
+            continue;
+        }
+        // calc accrued charges
+        let (earned, _) = earned_charges(
+            &seasons,
+            stk_state.cooldown,
+            stk_inf.stake,
+            now,
+            rules.charge_cap,
+        );
+        let charges = capped_charges(&rules, &mut remaining, earned.len());
+        // calc pending token rewards
+        add_owed(
+            &streams,
+            &mut owed,
+            id_img.image.natural[stk_state.skull_idx as usize],
+            charges as usize,
+            charge_seconds(
+                &seasons,
+                stk_state.cooldown,
+                stk_inf.stake,
+                charges as usize,
+            ),
+        );
+        charge_infos.push(ChargeInfo {
+            token_id: id_img.id,
+            charge_start: stk_inf.stake,
+            charges,
+            boosts: boost_names(&boosts, &get_boosts(&boosts, &id_img.image.current)),
+        });
+    }
+    // retrieve the user's ingredient inventory
+    let inventory = display_inventory(deps.storage, user_key)?;
+    let mut pending_token_rewards: Vec<TokenReward> = Vec::new();
+    for (stream, amt) in streams.iter().zip(payable(&streams, &owed)) {
+        if amt > 0 {
+            pending_token_rewards.push(TokenReward {
+                token: deps.api.addr_humanize(&stream.token.address)?.into_string(),
+                amount: Uint128::new(amt),
+            });
+        }
+    }
+
+    let pity_progress = display_pity(deps.storage, user_key)?;
+
+    to_binary(&QueryAnswer::MyStaking {
+        first_stake_bonus_available,
+        charge_infos,
+        inventory,
+        pending_token_rewards,
+        pity_progress,
+        staking_is_halted: stk_state.halt,
+    })
+}
+
+/// Returns StdResult<Binary> displaying the operators authorized to claim and/or stake on the
+/// user's behalf
+///
+/// # Arguments
+///
+/// * `deps` - a reference to Extern containing all the contract's external dependencies
+/// * `env` - a reference to the Env of contract's environment
+/// * `viewer` - optional address and key making an authenticated query request
+/// * `permit` - optional permit with "owner" permission
+fn query_operators(
+    deps: Deps,
+    env: &Env,
+    viewer: Option<ViewerInfo>,
+    permit: Option<Permit>,
+) -> StdResult<Binary> {
+    let (user_raw, _) = get_querier(deps, viewer, permit, &env.contract.address)?;
+    let now = env.block.time.seconds();
+    let op_store = ReadonlyPrefixedStorage::new(deps.storage, PREFIX_STAKING_OPERATORS);
+    let mut operators: Vec<StoredOperator> =
+        may_load(&op_store, user_raw.as_slice())?.unwrap_or_default();
+    // don't display expired authorizations
+    operators.retain(|o| o.expiration.map_or(true, |e| now < e));
+
+    to_binary(&QueryAnswer::StakingOperators {
+        operators: display_operators(deps.api, operators)?,
+    })
+}
+
+/// Returns StdResult<Binary> displaying the user's history
+///
+/// # Arguments
+///
+/// * `deps` - a reference to Extern containing all the contract's external dependencies
+/// * `viewer` - optional address and key making an authenticated query request
+/// * `permit` - optional permit with "owner" permission
+/// * `page` - optional page to display
+/// * `page_size` - optional number of events to display
+/// * `my_addr` - a reference to this contract's address
+fn query_my_history(
+    deps: Deps,
+    viewer: Option<ViewerInfo>,
+    permit: Option<Permit>,
+    page: Option<u32>,
+    page_size: Option<u32>,
+    my_addr: &Addr,
+) -> StdResult<Binary> {
+    let (user_raw, _) = get_querier(deps, viewer, permit, my_addr)?;
+    display_history(
+        deps,
+        PREFIX_USER_HISTORY,
+        user_raw.as_slice(),
+        Some(&user_raw),
+        page,
+        page_size,
+    )
+}
+
+/// Returns StdResult<Binary> displaying a skull's history
+///
+/// # Arguments
+///
+/// * `deps` - a reference to Extern containing all the contract's external dependencies
+/// * `viewer` - optional address and key making an authenticated query request
+/// * `permit` - optional permit with "owner" permission
+/// * `token_id` - the skull's token id
+/// * `page` - optional page to display
+/// * `page_size` - optional number of events to display
+/// * `my_addr` - this contract's address
+fn query_token_history(
+    deps: Deps,
+    viewer: Option<ViewerInfo>,
+    permit: Option<Permit>,
+    token_id: String,
+    page: Option<u32>,
+    page_size: Option<u32>,
+    my_addr: Addr,
+) -> StdResult<Binary> {
+    let (user_raw, user_hmn) = get_querier(deps, viewer, permit, &my_addr)?;
+    let is_admin = check_admin(deps.storage, &user_raw).is_ok();
+    // non-admins must own the skull
+    if !is_admin {
+        let (_, not_owned) = verify_ownership(
+            deps,
+            &user_hmn,
+            vec![token_id.clone()],
+            my_addr.into_string(),
+        )?;
+        if !not_owned.is_empty() {
+            return Err(StdError::generic_err(format!(
+                "You do not own skull {}",
+                token_id
+            )));
+        }
+    }
+    // only show owners the events they performed or were rewarded by
+    if is_admin {
+        display_history(
+            deps,
+            PREFIX_TOKEN_HISTORY,
+            token_id.as_bytes(),
+            None,
+            page,
+            page_size,
+        )
+    } else {
+        display_history(
+            deps,
+            PREFIX_TOKEN_USER_HISTORY,
+            &token_user_key(&user_raw, &token_id),
+            Some(&user_raw),
+            page,
+            page_size,
+        )
+    }
+}
+
+/// Returns StdResult<Binary> displaying a page of the admin audit log
+///
+/// # Arguments
+///
+/// * `deps` - a reference to Extern containing all the contract's external dependencies
+/// * `viewer` - optional address and key making an authenticated query request
+/// * `permit` - optional permit with "owner" permission
+/// * `my_addr` - a reference to this contract's address
+/// * `page` - optional page to display
+/// * `page_size` - optional number of entries to display
+fn query_admin_log(
+    deps: Deps,
+    viewer: Option<ViewerInfo>,
+    permit: Option<Permit>,
+    my_addr: &Addr,
+    page: Option<u32>,
+    page_size: Option<u32>,
+) -> StdResult<Binary> {
+    // only allow admins to do this
+    check_admin_query(deps, viewer, permit, my_addr)?;
+    let ingredients: Vec<String> = may_load(deps.storage, INGREDIENTS_KEY)?.unwrap_or_default();
+    let count: u64 = may_load(deps.storage, ADMIN_LOG_CNT_KEY)?.unwrap_or(0);
+    let log_store = ReadonlyPrefixedStorage::new(deps.storage, PREFIX_ADMIN_LOG);
+    let limit = page_size.unwrap_or(30) as u64;
+    let skip = (page.unwrap_or(0) as u64).saturating_mul(limit);
+    let mut entries: Vec<AdminLogEntry> = Vec::new();
+    // display the most recent first
+    for idx in (0..count.saturating_sub(skip)).rev().take(limit as usize) {
+        let entry: StoredAdminEntry = load(&log_store, &idx.to_le_bytes())?;
+        entries.push(AdminLogEntry {
+            admin: deps.api.addr_humanize(&entry.admin)?,
+            time: entry.time,
+            action: if entry.revoke {
+                AdminAction::Revoke
+            } else {
+                AdminAction::Grant
+            },
+            memo: entry.memo,
+            grants: entry
+                .grants
+                .into_iter()
+                .map(|g| {
+                    Ok(IngredientGrant {
+                        address: deps.api.addr_humanize(&g.user)?.into_string(),
+                        ingredients: display_quantities(&ingredients, g.quantities),
+                    })
+                })
+                .collect::<StdResult<Vec<IngredientGrant>>>()?,
+        });
+    }
+
+    to_binary(&QueryAnswer::AdminLog { count, entries })
+}
+
+/// Returns StdResult<Binary> displaying the aggregate totals of all recorded history
+///
+/// # Arguments
+///
+/// * `deps` - a reference to Extern containing all the contract's external dependencies
+/// * `viewer` - optional address and key making an authenticated query request
+/// * `permit` - optional permit with "owner" permission
+/// * `my_addr` - a reference to this contract's address
+fn query_hist_totals(
+    deps: Deps,
+    viewer: Option<ViewerInfo>,
+    permit: Option<Permit>,
+    my_addr: &Addr,
+) -> StdResult<Binary> {
+    // only allow admins to do this
+    check_admin_query(deps, viewer, permit, my_addr)?;
+    let ingredients: Vec<String> = may_load(deps.storage, INGREDIENTS_KEY)?.unwrap_or_default();
+    let totals: StoredTotals = may_load(deps.storage, HISTORY_TOTALS_KEY)?.unwrap_or_default();
+    let display = |qtys: Vec<u64>| -> Vec<IngredientTotal> {
+        qtys.into_iter()
+            .enumerate()
+            .filter(|(_, q)| *q > 0)
+            .map(|(i, quantity)| IngredientTotal {
+                ingredient: ingredients[i].clone(),
+                quantity,
+            })
+            .collect()
+    };
+
+    to_binary(&QueryAnswer::HistoryTotals {
+        stake_changes: totals.stake_changes,
+        claims: totals.claims,
+        charges_claimed: totals.charges,
+        first_stake_bonuses: totals.first_stakes,
+        crates: totals.crates,
+        uncrates: totals.uncrates,
+        rewarded: display(totals.rewarded),
+        crated: display(totals.crated),
+        uncrated: display(totals.uncrated),
+    })
+}
+
+/// Returns StdResult<Binary> displaying the list of ingredients
+///
+/// # Arguments
+///
+/// * `storage` - a reference to the storage this item is in
+fn query_ingr(storage: &dyn Storage) -> StdResult<Binary> {
+    let ingredients: Vec<String> = may_load(storage, INGREDIENTS_KEY)?.unwrap_or_default();
+
+    to_binary(&QueryAnswer::Ingredients { ingredients })
+}
+
+/// Returns StdResult<Binary> displaying the skull materials and their indices
+///
+/// # Arguments
+///
+/// * `deps` - reference to Extern containing all the contract's external dependencies
+/// * `viewer` - optional address and key making an authenticated query request
+/// * `permit` - optional permit with "owner" permission
+/// * `my_addr` - a reference to this contract's address
+fn query_mater(
+    deps: Deps,
+    viewer: Option<ViewerInfo>,
+    permit: Option<Permit>,
+    my_addr: &Addr,
+) -> StdResult<Binary> {
+    // only allow admins to do this
+    check_admin_query(deps, viewer, permit, my_addr)?;
+    let materials: Vec<String> = may_load(deps.storage, MATERIALS_KEY)?.unwrap_or_default();
+
+    to_binary(&QueryAnswer::Materials {
+        materials: materials
+            .into_iter()
+            .enumerate()
+            .map(|(i, m)| VariantIdxName {
+                idx: i as u16,
+                name: m,
+            })
+            .collect::<Vec<VariantIdxName>>(),
+    })
+}
+
+/// Returns StdResult<Binary> displaying the staking and alchemy states
+///
+/// # Arguments
+///
+/// * `deps` - reference to Extern containing all the contract's external dependencies
+/// * `viewer` - optional address and key making an authenticated query request
+/// * `permit` - optional permit with "owner" permission
+/// * `my_addr` - a reference to this contract's address
+fn query_state(
+    deps: Deps,
+    viewer: Option<ViewerInfo>,
+    permit: Option<Permit>,
+    my_addr: &Addr,
+) -> StdResult<Binary> {
+    // only allow admins to do this
+    check_admin_query(deps, viewer, permit, my_addr)?;
+    let staking_state: StakingState = load(deps.storage, STAKING_STATE_KEY)?;
+    let alchemy_state: AlchemyState = load(deps.storage, ALCHEMY_STATE_KEY)?;
+    let crt_st: CrateState = load(deps.storage, CRATE_STATE_KEY)?;
+
+    to_binary(&QueryAnswer::States {
+        staking_state,
+        alchemy_state,
+        crating_state: DisplayCrateState {
+            halt: crt_st.halt,
+            cnt: Uint128::new(crt_st.cnt),
+        },
+    })
+}
+
+/// Returns StdResult<Binary> displaying the admin addresses
+///
+/// # Arguments
+///
+/// * `deps` - reference to Extern containing all the contract's external dependencies
+/// * `viewer` - optional address and key making an authenticated query request
+/// * `permit` - optional permit with "owner" permission
+/// * `my_addr` - a reference to this contract's address
+fn query_admins(
+    deps: Deps,
+    viewer: Option<ViewerInfo>,
+    permit: Option<Permit>,
+    my_addr: &Addr,
+) -> StdResult<Binary> {
+    // only allow admins to do this
+    let admins = check_admin_query(deps, viewer, permit, my_addr)?;
+    to_binary(&QueryAnswer::Admins {
+        admins: admins
+            .iter()
+            .map(|a| deps.api.addr_humanize(a))
+            .collect::<StdResult<Vec<Addr>>>()?,
+    })
+}
+
+/// Returns StdResult<(CanonicalAddr, String)> from determining the querying address
+/// either from a Permit or a ViewerInfo
+///
+/// # Arguments
+///
+/// * `deps` - a reference to Extern containing all the contract's external dependencies
+/// * `viewer` - optional address and key making an authenticated query request
+/// * `permit` - optional permit with "owner" permission
+/// * `my_addr` - a reference to this contract's address
+fn get_querier(
+    deps: Deps,
+    viewer: Option<ViewerInfo>,
+    permit: Option<Permit>,
+    my_addr: &Addr,
+) -> StdResult<(CanonicalAddr, String)> {
+    if let Some(pmt) = permit {
+        // Validate permit content
+        let querier = validate(
+            deps,
+            PREFIX_REVOKED_PERMITS,
+            &pmt,
+            my_addr.to_string(),
+            Some("secret"),
+        )?;
+        let raw = deps
+            .api
+            .addr_validate(&querier)
+            .and_then(|a| deps.api.addr_canonicalize(a.as_str()))?;
+        if !pmt.check_permission(&secret_toolkit::permit::TokenPermissions::Owner) {
+            return Err(StdError::generic_err(format!(
+                "Owner permission is required for queries, got permissions {:?}",
+                pmt.params.permissions
+            )));
+        }
+        return Ok((raw, querier));
+    }
+    if let Some(vwr) = viewer {
+        let hmn = deps.api.addr_validate(&vwr.address)?;
+        let raw = deps.api.addr_canonicalize(hmn.as_str())?;
+        ViewingKey::check(deps.storage, hmn.as_str(), &vwr.viewing_key).map_err(|_| {
+            StdError::generic_err("Wrong viewing key for this address or viewing key not set")
+        })?;
+        return Ok((raw, vwr.address));
+    }
+    Err(StdError::generic_err(
+        "A permit or viewing key must be provided",
+    ))
+}
+
+/// Returns StdResult<(Addr, CanonicalAddr, CanonicalAddr)>
+///
+/// determines the owner whose skulls are being staked/claimed and the address whose inventory
+/// receives the rewards, verifying the sender is an authorized operator when acting on behalf
+/// of a different owner
+///
+/// # Arguments
+///
+/// * `deps` - a reference to Extern containing all the contract's external dependencies
+/// * `env` - a reference to the Env of contract's environment
+/// * `sender` - a reference to the message sender
+/// * `owner` - optional address of the skull owner
+/// * `staking` - true if setting the staking list instead of just claiming
+fn get_staker(
+    deps: Deps,
+    env: &Env,
+    sender: &Addr,
+    owner: Option<String>,
+    staking: bool,
+) -> StdResult<(Addr, CanonicalAddr, CanonicalAddr)> {
+    let sender_raw = deps.api.addr_canonicalize(sender.as_str())?;
+    if let Some(own) = owner {
+        let owner_hmn = deps.api.addr_validate(&own)?;
+        let owner_raw = deps.api.addr_canonicalize(owner_hmn.as_str())?;
+        if owner_raw != sender_raw {
+            let now = env.block.time.seconds();
+            let op_store = ReadonlyPrefixedStorage::new(deps.storage, PREFIX_STAKING_OPERATORS);
+            let operators: Vec<StoredOperator> =
+                may_load(&op_store, owner_raw.as_slice())?.unwrap_or_default();
+            let op = operators
+                .into_iter()
+                .find(|o| o.operator == sender_raw && o.expiration.map_or(true, |e| now < e))
+                .ok_or_else(|| {
+                    StdError::generic_err(format!(
+                        "You are not a staking operator for {}",
+                        owner_hmn
+                    ))
+                })?;
+            if staking && op.scope == OperatorScope::Claim {
+                return Err(StdError::generic_err(format!(
+                    "You are only authorized to claim for {}",
+                    owner_hmn
+                )));
+            }
+            let rcpt_raw = op.recipient.unwrap_or_else(|| owner_raw.clone());
+            return Ok((owner_hmn, owner_raw, rcpt_raw));
+        }
+    }
+    Ok((sender.clone(), sender_raw.clone(), sender_raw))
+}
+
+/// Returns StdResult<Vec<OperatorInfo>>
+///
+/// create a readable list of staking operators
+///
+/// # Arguments
+///
+/// * `api` - a reference to the Api used to convert human and canonical addresses
+/// * `operators` - list of StoredOperators
+fn display_operators(
+    api: &dyn Api,
+    operators: Vec<StoredOperator>,
+) -> StdResult<Vec<OperatorInfo>> {
+    operators
+        .into_iter()
+        .map(|o| {
+            Ok(OperatorInfo {
+                operator: api.addr_humanize(&o.operator)?,
+                scope: o.scope,
+                expiration: o.expiration,
+                recipient: o.recipient.map(|r| api.addr_humanize(&r)).transpose()?,
+            })
+        })
+        .collect::<StdResult<Vec<OperatorInfo>>>()
+}
+
+/// Returns StdResult<Vec<CanonicalAddr>> which is the admin list and checks if the querier is an admin
+///
+/// # Arguments
+///
+/// * `deps` - a reference to Extern containing all the contract's external dependencies
+/// * `viewer` - optional address and key making an authenticated query request
+/// * `permit` - optional permit with "owner" permission
+/// * `my_addr` - a reference to this contract's address
+fn check_admin_query(
+    deps: Deps,
+    viewer: Option<ViewerInfo>,
+    permit: Option<Permit>,
+    my_addr: &Addr,
+) -> StdResult<Vec<CanonicalAddr>> {
+    let (address, _) = get_querier(deps, viewer, permit, my_addr)?;
+    check_admin(deps.storage, &address)
+}
+
+/// Returns StdResult<Vec<CanonicalAddr>> which is the admin list and checks if the message
+/// sender is an admin
+///
+/// # Arguments
+///
+/// * `deps` - a reference to Extern containing all the contract's external dependencies
+/// * `sender` - a reference to the message sender
+fn check_admin_tx(deps: Deps, sender: &Addr) -> StdResult<Vec<CanonicalAddr>> {
+    let sender_raw = deps.api.addr_canonicalize(sender.as_str())?;
+    check_admin(deps.storage, &sender_raw)
+}
+
+/// Returns StdResult<Vec<CanonicalAddr>> which is the admin list and checks if the address
+/// is an admin
+///
+/// # Arguments
+///
+/// * `storage` - a reference to this contract's storage
+/// * `address` - a reference to the address in question
+fn check_admin(storage: &dyn Storage, address: &CanonicalAddr) -> StdResult<Vec<CanonicalAddr>> {
+    let admins: Vec<CanonicalAddr> = load(storage, ADMINS_KEY)?;
+    if !admins.contains(address) {
+        return Err(StdError::generic_err("Not an admin"));
+    }
+    Ok(admins)
+}
+
+/// Returns StdResult<Response>
+///
+/// updates the admin list
+///
+/// # Arguments
+///
+/// * `deps` - a mutable reference to Extern containing all the contract's external dependencies
+/// * `sender` - a reference to the message sender
+/// * `update_list` - list of addresses to use for update
+/// * `is_add` - true if the update is for adding to the list
+fn try_process_auth_list(
+    deps: DepsMut,
+    sender: &Addr,
+    update_list: &[String],
+    is_add: bool,
+) -> StdResult<Response> {
+    // only allow admins to do this
+    let mut admins = check_admin_tx(deps.as_ref(), sender)?;
+
+    // update the authorization list if needed
+    let save_it = if is_add {
+        add_addrs_to_auth(deps.api, &mut admins, update_list)?
+    } else {
+        remove_addrs_from_auth(deps.api, &mut admins, update_list)?
+    };
+    // save list if it changed
+    if save_it {
+        save(deps.storage, ADMINS_KEY, &admins)?;
+    }
+    Ok(
+        Response::new().set_data(to_binary(&ExecuteAnswer::AdminsList {
+            admins: admins
+                .iter()
+                .map(|a| deps.api.addr_humanize(a))
+                .collect::<StdResult<Vec<Addr>>>()?,
+        })?),
+    )
+}
+
+/// Returns StdResult<bool>
+///
+/// adds to an authorization list of addresses and returns true if the list changed
+///
+/// # Arguments
+///
+/// * `api` - a reference to the Api used to convert human and canonical addresses
+/// * `addresses` - current mutable list of addresses
+/// * `addrs_to_add` - list of addresses to add
+fn add_addrs_to_auth(
+    api: &dyn Api,
+    addresses: &mut Vec<CanonicalAddr>,
+    addrs_to_add: &[String],
+) -> StdResult<bool> {
+    let mut save_it = false;
+    for addr in addrs_to_add.iter() {
+        let raw = api
+            .addr_validate(addr)
+            .and_then(|a| api.addr_canonicalize(a.as_str()))?;
+        if !addresses.contains(&raw) {
+            addresses.push(raw);
+            save_it = true;
+        }
+    }
+    Ok(save_it)
+}
+
+/// Returns StdResult<bool>
+///
+/// removes from an authorization list of addresses and returns true if the list changed
+///
+/// # Arguments
+///
+/// * `api` - a reference to the Api used to convert human and canonical addresses
+/// * `addresses` - current mutable list of addresses
+/// * `addrs_to_remove` - list of addresses to remove
+fn remove_addrs_from_auth(
+    api: &dyn Api,
+    addresses: &mut Vec<CanonicalAddr>,
+    addrs_to_remove: &[String],
+) -> StdResult<bool> {
+    let old_len = addresses.len();
+    let rem_list = addrs_to_remove
+        .iter()
+        .map(|a| {
+            api.addr_validate(a)
+                .and_then(|a| api.addr_canonicalize(a.as_str()))
+        })
+        .collect::<StdResult<Vec<CanonicalAddr>>>()?;
+    addresses.retain(|a| !rem_list.contains(a));
+    // only save if the list changed
+    Ok(old_len != addresses.len())
+}
+
+/// Returns StdResult<u8>
+///
+/// finds the index of a named ingredient set
+///
+/// # Arguments
+///
+/// * `ingr_sets` - list of all ingredient sets
+/// * `name` - name of the ingredient set
+fn find_ingr_set(ingr_sets: &[StoredIngrSet], name: &str) -> StdResult<u8> {
+    ingr_sets
+        .iter()
+        .position(|s| s.name == name)
+        .map(|p| p as u8)
+        .ok_or_else(|| StdError::generic_err(format!("{} is not a known IngredientSet", name)))
+}
+
+// a skull's token id and the ImageInfo retrieved for it
+pub struct IdImage {
+    pub id: String,
+    pub image: ImageInfo,
+}
+
+/// Returns StdResult<(Vec<IdImage>, Vec<String>)>
+///
+/// Verifies ownership of a list of skull token ids and returns the list of token ids and image infos for
+/// skulls that have been verified to be owned by the specified address, and the list of token ids of the
+/// skulls that do not belong to the address
+///
+/// # Arguments
+///
+/// * `deps` - a reference to Extern containing all the contract's external dependencies
+/// * `owner` - a reference to the owner address for verification
+/// * `skulls` - list of token ids to check
+/// * `my_addr` - this contract's address
+fn verify_ownership(
+    deps: Deps,
+    owner: &str,
+    skulls: Vec<String>,
+    my_addr: String,
+) -> StdResult<(Vec<IdImage>, Vec<String>)> {
+    let mut owned: Vec<IdImage> = Vec::new();
+    let mut not_owned: Vec<String> = Vec::new();
+    let viewing_key: String = load(deps.storage, MY_VIEWING_KEY)?;
+    let viewer = ViewerInfo {
+        address: my_addr,
+        viewing_key,
+    };
+    let skull_contract = load::<StoreContractInfo>(deps.storage, SKULL_721_KEY)
+        .and_then(|s| s.into_humanized(deps.api))?;
+
+    for id in skulls.into_iter() {
+        // see if this is a duplicate in the list
+        if owned.iter().any(|i| i.id == id) {
+            continue;
+        }
+        if not_owned.contains(&id) {
+            continue;
+        }
+        // get the image info
+        let img_inf_resp = Snip721QueryMsg::ImageInfo {
+            token_id: id.clone(),
+            viewer: viewer.clone(),
+        }
+        .query::<_, ImageInfoWrapper>(
+            deps.querier,
+            skull_contract.code_hash.clone(),
+            skull_contract.address.clone(),
+        )?
+        .image_info;
+        // if not the current owner
+        if img_inf_resp.owner != *owner {
+            not_owned.push(id);
+        } else {
+            owned.push(IdImage {
+                id,
+                image: img_inf_resp.image_info,
+            });
+        }
+    }
+    Ok((owned, not_owned))
+}
+
+/// charges from skulls of the same material, earned in the same staking season, that receive the
+/// same staking boosts
+pub struct ChargeGroup {
+    /// skull material index
+    pub material: u8,
+    /// index of the staking season the charges were earned in
+    pub season: Option<u16>,
+    /// number of charges
+    pub charges: u16,
+    /// indices of the staking boosts
+    pub boosts: Vec<u8>,
+}
+
+/// Adds charges to the appropriate ChargeGroup, keeping the groups ordered by material
+///
+/// # Arguments
+///
+/// * `groups` - a mutable reference to the list of ChargeGroups
+/// * `material` - skull material index
+/// * `season` - optional index of the staking season the charges were earned in
+/// * `charges` - number of charges to add
+/// * `boosts` - indices of the staking boosts for these charges
+fn add_charge_group(
+    groups: &mut Vec<ChargeGroup>,
+    material: u8,
+    season: Option<u16>,
+    charges: u16,
+    boosts: Vec<u8>,
+) {
+    match groups
+        .binary_search_by(|g| (g.material, g.season, &g.boosts).cmp(&(material, season, &boosts)))
+    {
+        Ok(pos) => groups[pos].charges += charges,
+        Err(pos) => groups.insert(
+            pos,
+            ChargeGroup {
+                material,
+                season,
+                charges,
+                boosts,
+            },
+        ),
+    }
+}
+
+/// Returns Vec<u8>
+///
+/// list of the indices of the staking boosts granted by a skull's current image
+///
+/// # Arguments
+///
+/// * `boosts` - list of all staking boosts
+/// * `image` - the skull's current image indices
+fn get_boosts(boosts: &[StoredBoost], image: &[u8]) -> Vec<u8> {
+    boosts
+        .iter()
+        .enumerate()
+        .filter(|(_, b)| {
+            image
+                .get(b.layer.category as usize)
+                .map_or(false, |v| *v as u16 == b.layer.variant)
+        })
+        .map(|(i, _)| i as u8)
+        .collect()
+}
+
+/// Returns Vec<String>
+///
+/// list of the names of the specified staking boosts
+///
+/// # Arguments
+///
+/// * `boosts` - list of all staking boosts
+/// * `idxs` - indices of the boosts to name
+fn boost_names(boosts: &[StoredBoost], idxs: &[u8]) -> Vec<String> {
+    idxs.iter()
+        .map(|i| boosts[*i as usize].name.clone())
+        .collect()
+}
+
+/// adds the tokens each reward stream owes for a skull's claim
+///
+/// # Arguments
+///
+/// * `streams` - list of all reward streams
+/// * `owed` - a mutable reference to the amounts owed by each stream
+/// * `material` - the skull's material index
+/// * `charges` - number of charges being claimed
+/// * `seconds` - number of seconds of matured staking time being claimed
+fn add_owed(
+    streams: &[StoredStream],
+    owed: &mut [u128],
+    material: u8,
+    charges: usize,
+    seconds: u64,
+) {
+    for (stream, amt) in streams.iter().zip(owed.iter_mut()) {
+        let rate = stream.rates.get(material as usize).copied().unwrap_or(0);
+        let units = if stream.per_second {
+            seconds as u128
+        } else {
+            charges as u128
+        };
+        *amt = amt.saturating_add(rate.saturating_mul(units));
+    }
+}
+
+/// Returns StdResult<usize>
+///
+/// the position of a token's reward stream
+///
+/// # Arguments
+///
+/// * `api` - a reference to the Api used to convert human and canonical addresses
+/// * `streams` - list of all reward streams
+/// * `token` - address of the stream's token
+fn stream_pos(api: &dyn Api, streams: &[StoredStream], token: &str) -> StdResult<usize> {
+    let token_raw = api
+        .addr_validate(token)
+        .and_then(|a| api.addr_canonicalize(a.as_str()))?;
+    streams
+        .iter()
+        .position(|s| s.token.address == token_raw)
+        .ok_or_else(|| StdError::generic_err(format!("{} is not a reward stream token", token)))
+}
+
+/// Returns StdResult<CosmosMsg>
+///
+/// creates the message transferring tokens out of a reward stream's reserve
+///
+/// # Arguments
+///
+/// * `api` - a reference to the Api used to convert human and canonical addresses
+/// * `stream` - a reference to the StoredStream
+/// * `amount` - amount of tokens to transfer
+/// * `recipient` - address to receive the tokens
+fn reserve_transfer(
+    api: &dyn Api,
+    stream: &StoredStream,
+    amount: u128,
+    recipient: String,
+) -> StdResult<CosmosMsg> {
+    let recipient = api.addr_validate(&recipient)?.into_string();
+    let token = stream.token.get_humanized(api)?;
+    Snip20HandleMsg::Transfer {
+        recipient,
+        amount: Uint128::new(amount),
+        memo: None,
+        padding: None,
+    }
+    .to_cosmos_msg(token.code_hash, token.address, None)
+}
+
+/// Returns StdResult<Vec<u128>>
+///
+/// retrieves the rewards each reward stream still owes a user from previous claims
+///
+/// # Arguments
+///
+/// * `storage` - a reference to this contract's storage
+/// * `user_key` - user address storage key
+/// * `streams` - list of all reward streams
+fn load_unpaid(
+    storage: &dyn Storage,
+    user_key: &[u8],
+    streams: &[StoredStream],
+) -> StdResult<Vec<u128>> {
+    let unpaid_store = ReadonlyPrefixedStorage::new(storage, PREFIX_UNPAID_TOKENS);
+    let unpaid: Vec<StoredUnpaid> = may_load(&unpaid_store, user_key)?.unwrap_or_default();
+    // rewards of removed streams are forfeited
+    Ok(streams
+        .iter()
+        .map(|s| {
+            unpaid
+                .iter()
+                .find(|u| u.token == s.token.address)
+                .map_or(0, |u| u.amount)
+        })
+        .collect())
+}
+
+/// Returns Vec<u128>
+///
+/// limits the amounts owed by each reward stream to its reserve and remaining cap
+///
+/// # Arguments
+///
+/// * `streams` - list of all reward streams
+/// * `owed` - amounts owed by each stream
+fn payable(streams: &[StoredStream], owed: &[u128]) -> Vec<u128> {
+    streams
+        .iter()
+        .zip(owed.iter())
+        .map(|(s, o)| min(*o, min(s.reserve, s.cap.saturating_sub(s.distributed))))
+        .collect()
+}
+
+/// Returns Vec<StoredUnpaid>
+///
+/// the rewards to carry forward because the reserves could not cover them, up to each stream's
+/// remaining cap
+///
+/// # Arguments
+///
+/// * `streams` - list of all reward streams
+/// * `owed` - amounts owed by each stream
+/// * `paid` - amounts being paid by each stream
+fn carry_unpaid(streams: &[StoredStream], owed: &[u128], paid: &[u128]) -> Vec<StoredUnpaid> {
+    streams
+        .iter()
+        .zip(owed.iter().zip(paid.iter()))
+        .filter_map(|(s, (o, p))| {
+            let amount = min(*o, s.cap.saturating_sub(s.distributed)) - p;
+            (amount > 0).then(|| StoredUnpaid {
+                token: s.token.address.clone(),
+                amount,
+            })
+        })
+        .collect()
+}
+
+/// Returns StdResult<StreamInfo>
+///
+/// create a displayable reward stream
+///
+/// # Arguments
+///
+/// * `api` - a reference to the Api used to convert human and canonical addresses
+/// * `materials` - list of skull material names
+/// * `stream` - a reference to the StoredStream
+fn display_stream(
+    api: &dyn Api,
+    materials: &[String],
+    stream: &StoredStream,
+) -> StdResult<StreamInfo> {
+    Ok(StreamInfo {
+        token: stream.token.get_humanized(api)?,
+        per_second: stream.per_second,
+        rates: stream
+            .rates
+            .iter()
+            .enumerate()
+            .filter(|(_, r)| **r > 0)
+            .map(|(i, r)| MaterialRate {
+                material: materials[i].clone(),
+                rate: Uint128::new(*r),
+            })
+            .collect(),
+        cap: Uint128::new(stream.cap),
+        reserve: Uint128::new(stream.reserve),
+        distributed: Uint128::new(stream.distributed),
+    })
+}
+
+/// Returns StdResult<StakingRules>
+///
+/// retrieves the staking rules, falling back to the original game rules if they were never set
+///
+/// # Arguments
+///
+/// * `storage` - a reference to this contract's storage
+fn load_rules(storage: &dyn Storage) -> StdResult<StakingRules> {
+    Ok(
+        may_load(storage, STAKING_RULES_KEY)?.unwrap_or(StakingRules {
+            max_staked: 5,
+            tiers: Vec::new(),
+            charge_cap: 4,
+            cap_per_user: false,
+            first_stake_bonus: 1,
+        }),
+    )
+}
+
+/// Returns StdResult<()>
+///
+/// verifies the staking rules are valid and sorts the tiers
+///
+/// # Arguments
+///
+/// * `rules` - a mutable reference to the StakingRules
+fn validate_rules(rules: &mut StakingRules) -> StdResult<()> {
+    if rules.max_staked == 0 {
+        return Err(StdError::generic_err(
+            "The maximum number of staked skulls must be greater than 0",
+        ));
+    }
+    if rules.charge_cap == 0 {
+        return Err(StdError::generic_err(
+            "The charge cap must be greater than 0",
+        ));
+    }
+    if rules.first_stake_bonus > rules.charge_cap {
+        return Err(StdError::generic_err(
+            "The first-stake bonus can not exceed the charge cap",
+        ));
+    }
+    rules.tiers.sort_by_key(|t| t.min_owned);
+    let mut prev_max = rules.max_staked;
+    let mut prev_owned: Option<u16> = None;
+    for tier in rules.tiers.iter() {
+        if prev_owned == Some(tier.min_owned) {
+            return Err(StdError::generic_err(format!(
+                "Multiple staking tiers require owning {} skulls",
+                tier.min_owned
+            )));
+        }
+        if tier.max_staked < prev_max {
+            return Err(StdError::generic_err(
+                "Staking tiers can not lower the maximum number of staked skulls",
+            ));
+        }
+        prev_max = tier.max_staked;
+        prev_owned = Some(tier.min_owned);
+    }
+    Ok(())
+}
+
+/// Returns u8
+///
+/// the maximum number of skulls that can be staked by a user owning the specified number of
+/// skulls, or the largest maximum of any tier if the number owned is not specified
+///
+/// # Arguments
+///
+/// * `rules` - a reference to the StakingRules
+/// * `owned` - optional number of skulls owned
+fn max_staked(rules: &StakingRules, owned: Option<usize>) -> u8 {
+    rules
+        .tiers
+        .iter()
+        .filter(|t| owned.map_or(true, |o| o >= t.min_owned as usize))
+        .map(|t| t.max_staked)
+        .fold(rules.max_staked, max)
+}
+
+/// Returns u8
+///
+/// the number of a skull's accrued charges that can be claimed, reducing the charges remaining
+/// under the user's charge cap if it applies
+///
+/// # Arguments
+///
+/// * `rules` - a reference to the StakingRules
+/// * `remaining` - a mutable reference to the number of charges remaining under the user's cap
+/// * `earned` - number of charges the skull has accrued
+fn capped_charges(rules: &StakingRules, remaining: &mut u8, earned: usize) -> u8 {
+    let mut charges = earned as u8;
+    if rules.cap_per_user {
+        charges = min(charges, *remaining);
+        *remaining -= charges;
+    }
+    charges
+}
+
+/// Returns Option<u16>
+///
+/// the index of the staking season in progress at the specified time
+///
+/// # Arguments
+///
+/// * `seasons` - list of all staking seasons
+/// * `time` - time in seconds
+fn active_season(seasons: &[StoredSeason], time: u64) -> Option<u16> {
+    seasons
+        .iter()
+        .position(|s| s.start <= time && time < s.end)
+        .map(|p| p as u16)
+}
+
+/// Returns (Vec<Option<u16>>, u64)
+///
+/// lists the staking season (if any) that each accrued charge was earned in (limited by the
+/// charge cap), and returns the time the most recently completed charge matured.  The season
+/// in progress when a charge started charging determines how long it takes, and the season in
+/// progress during its final second determines which table it uses
+///
+/// # Arguments
+///
+/// * `seasons` - list of all staking seasons sorted by start time
+/// * `cooldown` - number of seconds to earn a charge outside of a season
+/// * `stake` - time the skull started charging
+/// * `now` - current time
+/// * `cap` - maximum number of charges
+fn earned_charges(
+    seasons: &[StoredSeason],
+    cooldown: u64,
+    stake: u64,
+    now: u64,
+    cap: u8,
+) -> (Vec<Option<u16>>, u64) {
+    let mut earned: Vec<Option<u16>> = Vec::new();
+    let mut time = stake;
+    loop {
+        let (charge_time, seg_end) = charge_segment(seasons, cooldown, time);
+        // stop if the current charge has not matured
+        if time + charge_time > now {
+            break;
+        }
+        // count the matured charges that started before the charge time could change
+        let started = (seg_end - time - 1) / charge_time + 1;
+        let cnt = min(started, (now - time) / charge_time);
+        for k in 0..min(cnt, (cap as usize - earned.len()) as u64) {
+            let matured = time + (k + 1) * charge_time;
+            earned.push(active_season(seasons, matured - 1));
+        }
+        time += cnt * charge_time;
+    }
+    (earned, time)
+}
+
+/// Returns (u64, u64)
+///
+/// the charge time of a charge starting at the specified time, and the time the charge time
+/// could next change
+///
+/// # Arguments
+///
+/// * `seasons` - list of all staking seasons sorted by start time
+/// * `cooldown` - number of seconds to earn a charge outside of a season
+/// * `time` - time the charge starts
+fn charge_segment(seasons: &[StoredSeason], cooldown: u64, time: u64) -> (u64, u64) {
+    if let Some(idx) = active_season(seasons, time) {
+        let ssn = &seasons[idx as usize];
+        (max(1, cooldown * ssn.charge_pct as u64 / 100), ssn.end)
+    } else {
+        let next_start = seasons
+            .iter()
+            .find(|s| s.start > time)
+            .map_or(u64::MAX, |s| s.start);
+        (cooldown, next_start)
+    }
+}
+
+/// Returns u64
+///
+/// the number of seconds it took to earn the first charges of a skull, so that per-second
+/// rewards are only paid for the charging time of the charges that were not forfeited
+///
+/// # Arguments
+///
+/// * `seasons` - list of all staking seasons sorted by start time
+/// * `cooldown` - number of seconds to earn a charge outside of a season
+/// * `stake` - time the skull started charging
+/// * `charges` - number of charges being paid
+fn charge_seconds(seasons: &[StoredSeason], cooldown: u64, stake: u64, charges: usize) -> u64 {
+    let mut time = stake;
+    let mut left = charges as u64;
+    while left > 0 {
+        let (charge_time, seg_end) = charge_segment(seasons, cooldown, time);
+        let cnt = min(left, (seg_end - time - 1) / charge_time + 1);
+        time += cnt * charge_time;
+        left -= cnt;
+    }
+    time - stake
+}
+
+/// Returns Option<u64>
+///
+/// the time a user's staked skulls reach their charge cap, or None if nothing is staked
+///
+/// # Arguments
+///
+/// * `seasons` - list of all staking seasons sorted by start time
+/// * `cooldown` - number of seconds to earn a charge outside of a season
+/// * `rules` - a reference to the staking rules
+/// * `stakes` - times each staked skull started charging
+fn cap_time(
+    seasons: &[StoredSeason],
+    cooldown: u64,
+    rules: &StakingRules,
+    stakes: &[u64],
+) -> Option<u64> {
+    let cap = rules.charge_cap as usize;
+    if cap == 0 {
+        return None;
+    }
+    if rules.cap_per_user {
+        // the cap is reached when the skulls' combined charges first total the cap
+        let mut matured: Vec<u64> = stakes
+            .iter()
+            .flat_map(|s| (1..=cap).map(move |k| s + charge_seconds(seasons, cooldown, *s, k)))
+            .collect();
+        matured.sort_unstable();
+        matured.get(cap - 1).copied()
+    } else {
+        // charges start being forfeited once any skull is full
+        stakes
+            .iter()
+            .map(|s| s + charge_seconds(seasons, cooldown, *s, cap))
+            .min()
+    }
+}
+
+/// Returns StdResult<()>
+///
+/// replace a user's scheduled charge cap notification
+///
+/// # Arguments
+///
+/// * `storage` - a mutable reference to this contract's storage
+/// * `user` - a reference to the user's address
+/// * `due` - optional time the user's charges reach their cap
+/// * `now` - current time in seconds
+fn schedule_cap_notification(
+    storage: &mut dyn Storage,
+    user: &CanonicalAddr,
+    due: Option<u64>,
+    now: u64,
+) -> StdResult<()> {
+    let user_key = user.as_slice();
+    let due_store = ReadonlyPrefixedStorage::new(storage, PREFIX_CAP_DUE);
+    let old: Option<u64> = may_load(&due_store, user_key)?;
+    // users who are already capped know it, because they just staked or claimed
+    let due = due.filter(|d| *d > now);
+    if old == due {
+        return Ok(());
+    }
+    let mut hours: Vec<u64> = may_load(storage, CAP_HOURS_KEY)?.unwrap_or_default();
+    if let Some(time) = old {
+        let hour = time / CAP_HOUR_SECONDS;
+        let mut sched_store = PrefixedStorage::new(storage, PREFIX_CAP_SCHEDULE);
+        let mut sched: Vec<StoredCapDue> =
+            may_load(&sched_store, &hour.to_le_bytes())?.unwrap_or_default();
+        sched.retain(|s| s.user != *user);
+        if sched.is_empty() {
+            remove(&mut sched_store, &hour.to_le_bytes());
+            hours.retain(|h| *h != hour);
+        } else {
+            save(&mut sched_store, &hour.to_le_bytes(), &sched)?;
+        }
+    }
+    let mut due_store = PrefixedStorage::new(storage, PREFIX_CAP_DUE);
+    if let Some(time) = due {
+        save(&mut due_store, user_key, &time)?;
+        let hour = time / CAP_HOUR_SECONDS;
+        let mut sched_store = PrefixedStorage::new(storage, PREFIX_CAP_SCHEDULE);
+        let mut sched: Vec<StoredCapDue> =
+            may_load(&sched_store, &hour.to_le_bytes())?.unwrap_or_default();
+        // keep each hour sorted so the due notifications are always at the front
+        let pos = sched.partition_point(|s| s.time <= time);
+        sched.insert(
+            pos,
+            StoredCapDue {
+                user: user.clone(),
+                time,
+            },
+        );
+        save(&mut sched_store, &hour.to_le_bytes(), &sched)?;
+        if let Err(pos) = hours.binary_search(&hour) {
+            hours.insert(pos, hour);
+        }
+    } else {
+        remove(&mut due_store, user_key);
+    }
+    save(storage, CAP_HOURS_KEY, &hours)
+}
+
+/// Returns StdResult<Vec<String>>
+///
+/// the attribute keys of the charge cap notifications that have come due, up to
+/// CAP_NOTIFY_LIMIT.  Contracts only execute inside transactions, so due notifications are
+/// delivered by the next staking transaction of any user
+///
+/// # Arguments
+///
+/// * `storage` - a mutable reference to this contract's storage
+/// * `now` - current time in seconds
+fn due_cap_notifications(storage: &mut dyn Storage, now: u64) -> StdResult<Vec<String>> {
+    let mut notes: Vec<String> = Vec::new();
+    let mut hours: Vec<u64> = may_load(storage, CAP_HOURS_KEY)?.unwrap_or_default();
+    let cnt = hours.len();
+    while let Some(hour) = hours
+        .first()
+        .copied()
+        .filter(|h| *h <= now / CAP_HOUR_SECONDS && notes.len() < CAP_NOTIFY_LIMIT)
+    {
+        let sched_store = ReadonlyPrefixedStorage::new(storage, PREFIX_CAP_SCHEDULE);
+        let mut sched: Vec<StoredCapDue> =
+            may_load(&sched_store, &hour.to_le_bytes())?.unwrap_or_default();
+        let due = min(
+            sched.partition_point(|s| s.time <= now),
+            CAP_NOTIFY_LIMIT - notes.len(),
+        );
+        for cap_due in sched.drain(..due) {
+            let mut due_store = PrefixedStorage::new(storage, PREFIX_CAP_DUE);
+            remove(&mut due_store, cap_due.user.as_slice());
+            notes.push(notify(storage, &cap_due.user, CHARGES_CHANNEL)?);
+        }
+        let mut sched_store = PrefixedStorage::new(storage, PREFIX_CAP_SCHEDULE);
+        if sched.is_empty() {
+            remove(&mut sched_store, &hour.to_le_bytes());
+            hours.remove(0);
+        } else {
+            save(&mut sched_store, &hour.to_le_bytes(), &sched)?;
+            // the rest of this hour is not due yet, or the limit was reached
+            break;
+        }
+    }
+    if hours.len() != cnt {
+        save(storage, CAP_HOURS_KEY, &hours)?;
+    }
+    Ok(notes)
+}
+
+/// a staking table after applying staking boosts
+pub struct BoostedTable {
+    /// ingredient sets and their weights
+    pub weights: Vec<StoredSetWeight>,
+    /// additional rolls per charge
+    pub extra_rolls: u8,
+    /// chances for additional ingredients per charge
+    pub bonuses: Vec<StoredSetChance>,
+}
+
+/// Returns StdResult<()>
+///
+/// verifies that applying every staking boost to a staking table at once can not overflow an
+/// ingredient set weight
+///
+/// # Arguments
+///
+/// * `weights` - the staking table's ingredient set weights
+/// * `boosts` - list of all staking boosts
+/// * `table` - name of the staking table used in the error message
+fn check_boosted_weights(
+    weights: &[StoredSetWeight],
+    boosts: &[StoredBoost],
+    table: &str,
+) -> StdResult<()> {
+    let mut boosted = weights.to_vec();
+    for add in boosts.iter().flat_map(|b| b.weights.iter()) {
+        if let Some(existing) = boosted.iter_mut().find(|w| w.set == add.set) {
+            existing.weight = existing.weight.checked_add(add.weight).ok_or_else(|| {
+                StdError::generic_err(format!(
+                    "Staking boosts would overflow an ingredient set weight of the {} staking table",
+                    table
+                ))
+            })?;
+        } else {
+            boosted.push(add.clone());
+        }
+    }
+    Ok(())
+}
+
+/// Returns StdResult<BoostedTable>
+///
+/// retrieves a material's staking table for the season the charges were earned in, and applies
+/// the specified staking boosts
+///
+/// # Arguments
+///
+/// * `storage` - a reference to this contract's storage
+/// * `group` - a reference to the ChargeGroup whose table should be retrieved
+/// * `boosts` - list of all staking boosts
+/// * `seasons` - list of all staking seasons
+fn boosted_table(
+    storage: &dyn Storage,
+    group: &ChargeGroup,
+    boosts: &[StoredBoost],
+    seasons: &[StoredSeason],
+) -> StdResult<BoostedTable> {
+    // use the season's table if it overrides this material
+    let season_tbl = group.season.and_then(|s| {
+        seasons[s as usize]
+            .tables
+            .iter()
+            .find(|t| t.material == group.material)
+    });
+    let mut weights: Vec<StoredSetWeight> = if let Some(tbl) = season_tbl {
+        tbl.weights.clone()
+    } else {
+        let tbl_store = ReadonlyPrefixedStorage::new(storage, PREFIX_STAKING_TABLE);
+        load(&tbl_store, &group.material.to_le_bytes())?
+    };
+    let mut extra_rolls = 0u8;
+    let mut bonuses: Vec<StoredSetChance> = Vec::new();
+    for boost in group.boosts.iter().map(|i| &boosts[*i as usize]) {
+        extra_rolls = extra_rolls.saturating_add(boost.extra_rolls);
+        for add in boost.weights.iter() {
+            if let Some(existing) = weights.iter_mut().find(|w| w.set == add.set) {
+                existing.weight = existing
+                    .weight
+                    .checked_add(add.weight)
+                    .ok_or_else(|| StdError::generic_err("Boosted staking weight overflow"))?;
+            } else {
+                weights.push(add.clone());
+            }
+        }
+        if let Some(bonus) = boost.bonus.as_ref() {
+            bonuses.push(bonus.clone());
+        }
+    }
+    Ok(BoostedTable {
+        weights,
+        extra_rolls,
+        bonuses,
+    })
+}
+
+/// Returns StdResult<(Vec<u32>, Vec<(u8, u8)>)>
+///
+/// Take a list of charge groups, and randomly draw resources according to the weighted staking
+/// table after applying any staking boosts.  Rolls are guaranteed to win any ingredient set
+/// whose pity threshold has been reached, and the material and set indices of those wins are
+/// also returned
+///
+/// # Arguments
+///
+/// * `storage` - a reference to this contract's storage
+/// * `env` - a reference to the Env of contract's environment
+/// * `groups` - charges grouped by material type and staking boosts
+/// * `quantities` - number of skulls per material type
+/// * `ingr_cnt` - number of different ingredients
+/// * `pity` - a mutable reference to the user's pity counters
+fn gen_resources(
+    storage: &dyn Storage,
+    env: &Env,
+    groups: &[ChargeGroup],
+    quantities: &[u8],
+    ingr_cnt: usize,
+    pity: &mut Vec<StoredPityCount>,
+) -> StdResult<Generated> {
+    let mut generated: Vec<u32> = vec![0; ingr_cnt];
+    let mut rng = ContractPrng::from_env(env);
+    let type_cnt = quantities.iter().filter(|&q| *q > 0).count() as u64;
+    let variety_lim = (2 * type_cnt) + 1;
+    let ingr_sets: Vec<StoredIngrSet> = may_load(storage, INGRED_SETS_KEY)?.unwrap_or_default();
+    let boosts: Vec<StoredBoost> = may_load(storage, STAKING_BOOSTS_KEY)?.unwrap_or_default();
+    let seasons: Vec<StoredSeason> = may_load(storage, STAKING_SEASONS_KEY)?.unwrap_or_default();
+    let mut wins_per_set: Vec<u32> = vec![0; ingr_sets.len()];
+    let mut guaranteed: Vec<(u8, u8)> = Vec::new();
+    let pity_store = ReadonlyPrefixedStorage::new(storage, PREFIX_PITY_TABLE);
+    // go through each group of charges
+    for group in groups.iter() {
+        let stk_tbl = boosted_table(storage, group, &boosts, &seasons)?;
+        // boosted weights can sum past u16::MAX
+        let total_weight: u64 = stk_tbl.weights.iter().map(|t| t.weight as u64).sum();
+        if total_weight == 0 {
+            return Err(StdError::generic_err(
+                "Staking table has no ingredient set weights",
+            ));
+        }
+        let pity_sets: Vec<StoredPity> =
+            may_load(&pity_store, &group.material.to_le_bytes())?.unwrap_or_default();
+        // process each charge for this group
+        for _ in 0u16..group.charges {
+            // randomly determine number of resources generated for this charge
+            let rdm_mat = rng.next_u64();
+            let rdm_var = rng.next_u64();
+            // computed in u32 so large staking limits can not overflow the roll count
+            let rolls: u32 = 1u32
+                .saturating_add(
+                    (rdm_mat % (quantities[group.material as usize] as u64 + 1u64)) as u32,
+                )
+                .saturating_add((rdm_var % variety_lim) as u32)
+                .saturating_add(stk_tbl.extra_rolls as u32);
+            // sets whose pity thresholds have been reached are guaranteed
+            let mut forced: Vec<u8> = Vec::new();
+            for pty in pity_sets.iter() {
+                let pos = pity_pos(pity, group.material, pty.set);
+                if pity[pos].misses >= pty.threshold {
+                    forced.push(pty.set);
+                }
+            }
+            let mut charge_wins: Vec<usize> = Vec::new();
+            // randomly pick the winning ingredient set for each resource
+            for roll in 0u32..rolls {
+                let rdm = rng.next_u64();
+                let winner = if let Some(set) = forced.get(roll as usize) {
+                    guaranteed.push((group.material, *set));
+                    *set as usize
+                } else {
+                    let winning_num = rdm % total_weight;
+                    let mut tally = 0u64;
+                    let mut winner = 0usize;
+                    for set_weight in stk_tbl.weights.iter() {
+                        tally += set_weight.weight as u64;
+                        if tally > winning_num {
+                            winner = set_weight.set as usize;
+                            break;
+                        }
+                    }
+                    winner
+                };
+                // increment wins for the winning ingredient set
+                add_win(&mut wins_per_set, winner)?;
+                charge_wins.push(winner);
+            }
+            // roll for any bonus sets granted by staking boosts
+            for bonus in stk_tbl.bonuses.iter() {
+                let rdm = rng.next_u64();
+                if rdm % 10000 < bonus.chance as u64 {
+                    add_win(&mut wins_per_set, bonus.set as usize)?;
+                    charge_wins.push(bonus.set as usize);
+                }
+            }
+            // update the pity counters
+            for pty in pity_sets.iter() {
+                let pos = pity_pos(pity, group.material, pty.set);
+                if charge_wins.contains(&(pty.set as usize)) {
+                    pity[pos].misses = 0;
+                } else {
+                    pity[pos].misses = pity[pos].misses.saturating_add(1);
+                }
+            }
+        }
+    }
+    // randomly pick ingredients from each winning set of ingredients
+    for (idx, resource_cnt) in wins_per_set.iter().enumerate() {
+        // if this set had been picked
+        if *resource_cnt > 0 {
+            // number of ingredients to pick from
+            let ingr_cnt = ingr_sets[idx].list.len() as u64;
+            for _ in 0u32..*resource_cnt {
+                let win = if ingr_cnt == 1 {
+                    // no need to waste resources getting a rdm number if there is only one possible
+                    0usize
+                } else {
+                    // more than one ingredient in this set
+                    let rdm_ing = rng.next_u64();
+                    (rdm_ing % ingr_cnt) as usize
+                };
+                generated[ingr_sets[idx].list[win] as usize] += 1;
+            }
+        }
+    }
+    Ok((generated, guaranteed))
+}
+
+/// Returns StdResult<()>
+///
+/// adds a win to an ingredient set's tally
+///
+/// # Arguments
+///
+/// * `wins_per_set` - a mutable reference to the number of wins of each ingredient set
+/// * `set` - index of the winning ingredient set
+fn add_win(wins_per_set: &mut [u32], set: usize) -> StdResult<()> {
+    wins_per_set[set] = wins_per_set[set]
+        .checked_add(1)
+        .ok_or_else(|| StdError::generic_err("Ingredient set win count overflow"))?;
+    Ok(())
+}
+
+/// Returns usize
+///
+/// the position of a user's pity counter for the specified material and set, adding a new
+/// counter if necessary
+///
+/// # Arguments
+///
+/// * `pity` - a mutable reference to the user's pity counters
+/// * `material` - skull material index
+/// * `set` - ingredient set index
+fn pity_pos(pity: &mut Vec<StoredPityCount>, material: u8, set: u8) -> usize {
+    pity.iter()
+        .position(|p| p.material == material && p.set == set)
+        .unwrap_or_else(|| {
+            pity.push(StoredPityCount {
+                material,
+                set,
+                misses: 0,
+            });
+            pity.len() - 1
+        })
+}
+
+/// Returns StdResult<Vec<PityProgress>>
+///
+/// create a readable list of a user's progress towards guaranteed ingredient set wins
+///
+/// # Arguments
+///
+/// * `storage` - a reference to this contract's storage
+/// * `user_key` - user address storage key
+fn display_pity(storage: &dyn Storage, user_key: &[u8]) -> StdResult<Vec<PityProgress>> {
+    let materials: Vec<String> = may_load(storage, MATERIALS_KEY)?.unwrap_or_default();
+    let ingr_sets: Vec<StoredIngrSet> = may_load(storage, INGRED_SETS_KEY)?.unwrap_or_default();
+    let user_store = ReadonlyPrefixedStorage::new(storage, PREFIX_USER_PITY);
+    let counts: Vec<StoredPityCount> = may_load(&user_store, user_key)?.unwrap_or_default();
+    let pity_store = ReadonlyPrefixedStorage::new(storage, PREFIX_PITY_TABLE);
+    let mut progress: Vec<PityProgress> = Vec::new();
+    for (i, material) in materials.iter().enumerate() {
+        let mat = i as u8;
+        let pity_sets: Vec<StoredPity> =
+            may_load(&pity_store, &mat.to_le_bytes())?.unwrap_or_default();
+        for pty in pity_sets.into_iter() {
+            progress.push(PityProgress {
+                material: material.clone(),
+                ingredient_set: ingr_sets[pty.set as usize].name.clone(),
+                charges_without_win: counts
+                    .iter()
+                    .find(|c| c.material == mat && c.set == pty.set)
+                    .map(|c| c.misses)
+                    .unwrap_or(0),
+                threshold: pty.threshold,
+            });
+        }
+    }
+    Ok(progress)
+}
+
+/// Returns StdResult<Vec<Decimal>>
+///
+/// Take a list of charge groups, and calculate the expected number of each ingredient
+/// that gen_resources would reward for them (ignoring the negligible modulo bias of the rng)
+///
+/// # Arguments
+///
+/// * `storage` - a reference to this contract's storage
+/// * `groups` - charges grouped by material type and staking boosts
+/// * `quantities` - number of skulls per material type
+/// * `ingr_cnt` - number of different ingredients
+fn expected_resources(
+    storage: &dyn Storage,
+    groups: &[ChargeGroup],
+    quantities: &[u8],
+    ingr_cnt: usize,
+) -> StdResult<Vec<Decimal>> {
+    let mut expected: Vec<Decimal> = vec![Decimal::zero(); ingr_cnt];
+    let type_cnt = quantities.iter().filter(|&q| *q > 0).count() as u64;
+    let ingr_sets: Vec<StoredIngrSet> = may_load(storage, INGRED_SETS_KEY)?.unwrap_or_default();
+    let boosts: Vec<StoredBoost> = may_load(storage, STAKING_BOOSTS_KEY)?.unwrap_or_default();
+    let seasons: Vec<StoredSeason> = may_load(storage, STAKING_SEASONS_KEY)?.unwrap_or_default();
+    // go through each group of charges
+    for group in groups.iter() {
+        let stk_tbl = boosted_table(storage, group, &boosts, &seasons)?;
+        let charges = group.charges as u64;
+        let total_weight: u64 = stk_tbl.weights.iter().map(|t| t.weight as u64).sum();
+        if total_weight > 0 {
+            // each charge rolls 1 + U[0, quantity] + U[0, 2 * type_cnt] + extra times, so twice
+            // the expected number of rolls is 2 + quantity + (2 * type_cnt) + (2 * extra)
+            let dbl_rolls = 2
+                + quantities[group.material as usize] as u64
+                + (2 * type_cnt)
+                + (2 * stk_tbl.extra_rolls as u64);
+            for set_weight in stk_tbl.weights.iter() {
+                let list = &ingr_sets[set_weight.set as usize].list;
+                if list.is_empty() {
+                    continue;
+                }
+                // each ingredient in a winning set is equally likely to be picked
+                let share = Decimal::from_ratio(
+                    charges * dbl_rolls * set_weight.weight as u64,
+                    2 * total_weight * list.len() as u64,
+                );
+                for ingr in list.iter() {
+                    expected[*ingr as usize] += share;
+                }
+            }
+        }
+        // add the chances of winning bonus sets
+        for bonus in stk_tbl.bonuses.iter() {
+            let list = &ingr_sets[bonus.set as usize].list;
+            if list.is_empty() {
+                continue;
+            }
+            let share =
+                Decimal::from_ratio(charges * bonus.chance as u64, 10000 * list.len() as u64);
+            for ingr in list.iter() {
+                expected[*ingr as usize] += share;
+            }
+        }
+    }
+    Ok(expected)
+}
+
+/// Returns StdResult<(Vec<IngredientQty>, Vec<u32>, Vec<PityDrop>)>
+///
+/// generate resources for the charges and update user ingredients inventory.  Returns the
+/// readable list of rewards, the quantities generated of each ingredient, and the ingredient
+/// set wins that were guaranteed by pity thresholds
+///
+/// # Arguments
+///
+/// * `storage` - a mutable reference to this contract's storage
+/// * `env` - a reference to the Env of contract's environment
+/// * `groups` - charges grouped by material type and staking boosts
+/// * `quantities` - number of skulls per material type
+/// * `user_key` - storage key of the address receiving the ingredients
+/// * `pity_key` - storage key of the staker whose pity counters apply
+fn process_charges(
+    storage: &mut dyn Storage,
+    env: &Env,
+    groups: &[ChargeGroup],
+    quantities: &[u8],
+    user_key: &[u8],
+    pity_key: &[u8],
+) -> StdResult<(Vec<IngredientQty>, Vec<u32>, Vec<PityDrop>)> {
+    let mut rewards: Vec<IngredientQty> = Vec::new();
+    let ingredients: Vec<String> = may_load(storage, INGREDIENTS_KEY)?.unwrap_or_default();
+    let ingr_cnt = ingredients.len();
+    let pity_store = ReadonlyPrefixedStorage::new(storage, PREFIX_USER_PITY);
+    let mut pity: Vec<StoredPityCount> = may_load(&pity_store, pity_key)?.unwrap_or_default();
+    // generate the ingredients
+    let (generated, guaranteed) =
+        gen_resources(storage, env, groups, quantities, ingr_cnt, &mut pity)?;
+    if !pity.is_empty() {
+        let mut pity_store = PrefixedStorage::new(storage, PREFIX_USER_PITY);
+        save(&mut pity_store, pity_key, &pity)?;
+    }
+    let pity_drops = if guaranteed.is_empty() {
+        Vec::new()
+    } else {
+        let materials: Vec<String> = may_load(storage, MATERIALS_KEY)?.unwrap_or_default();
+        let ingr_sets: Vec<StoredIngrSet> = may_load(storage, INGRED_SETS_KEY)?.unwrap_or_default();
+        guaranteed
+            .into_iter()
+            .map(|(mat, set)| PityDrop {
+                material: materials[mat as usize].clone(),
+                ingredient_set: ingr_sets[set as usize].name.clone(),
+            })
+            .collect()
+    };
+    let mut inv_store = PrefixedStorage::new(storage, PREFIX_USER_INGR_INVENTORY);
+    let mut inventory: Vec<u32> = may_load(&inv_store, user_key)?.unwrap_or_default();
+    // just in case new ingredients get added, extend old inventories
+    inventory.resize(ingr_cnt, 0);
+    // add the newly generated resources
+    for (inv, new) in inventory.iter_mut().zip(&generated) {
+        *inv += *new;
+    }
+    save(&mut inv_store, user_key, &inventory)?;
+    // create the list of generated resources for the output
+    for (i, quantity) in generated.iter().enumerate() {
+        if *quantity > 0 {
+            rewards.push(IngredientQty {
+                ingredient: ingredients[i].clone(),
+                quantity: *quantity,
+            });
+        }
+    }
+    Ok((rewards, generated, pity_drops))
+}
+
+/// Returns StdResult<()>
+///
+/// add to a user's all-time leaderboard scores and their scores for the season in progress,
+/// and update their leaderboard ranks if they have opted in
+///
+/// # Arguments
+///
+/// * `storage` - a mutable reference to this contract's storage
+/// * `user` - a reference to the user's address
+/// * `now` - current time in seconds
+/// * `gains` - score increases indexed by leaderboard metric
+fn add_scores(
+    storage: &mut dyn Storage,
+    user: &CanonicalAddr,
+    now: u64,
+    gains: &[u64; METRIC_CNT],
+) -> StdResult<()> {
+    let user_key = user.as_slice();
+    let seasons: Vec<StoredSeason> = may_load(storage, STAKING_SEASONS_KEY)?.unwrap_or_default();
+    // the all-time leaderboards use an empty season key
+    let mut season_keys: Vec<Vec<u8>> = vec![Vec::new()];
+    if let Some(ssn) = active_season(&seasons, now) {
+        season_keys.push(seasons[ssn as usize].id.to_le_bytes().to_vec());
+    }
+    let opt_store = ReadonlyPrefixedStorage::new(storage, PREFIX_LEADER_OPT);
+    let opted_in = may_load::<StoredOptIn>(&opt_store, user_key)?.is_some();
+    for season_key in season_keys.iter().map(|k| k.as_slice()) {
+        let mut score_store =
+            PrefixedStorage::multilevel(storage, &[PREFIX_LEADER_SCORES, season_key]);
+        let mut scores: Vec<u64> = may_load(&score_store, user_key)?.unwrap_or_default();
+        scores.resize(METRIC_CNT, 0);
+        let old = scores.clone();
+        for (score, gain) in scores.iter_mut().zip(gains.iter()) {
+            *score += *gain;
+        }
+        save(&mut score_store, user_key, &scores)?;
+        if opted_in {
+            for (metric, gain) in gains.iter().enumerate() {
+                if *gain > 0 {
+                    rank_user(
+                        storage,
+                        season_key,
+                        metric as u8,
+                        user,
+                        Some(old[metric]),
+                        Some(scores[metric]),
+                    )?;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Returns StdResult<()>
+///
+/// move a user on a leaderboard from the position of their old score to that of their new one.
+/// Every opted-in player with a score is ranked.  The ranks are split into sorted pages, so an
+/// update only loads the page directory and the pages holding the old and new positions
+///
+/// # Arguments
+///
+/// * `storage` - a mutable reference to this contract's storage
+/// * `season_key` - season id storage key (empty for the all-time leaderboards)
+/// * `metric` - index of the leaderboard metric
+/// * `user` - a reference to the user's address
+/// * `old` - the score the user is ranked with, or None if they are not on the leaderboard
+/// * `new` - the user's new score, or None if they should be removed
+fn rank_user(
+    storage: &mut dyn Storage,
+    season_key: &[u8],
+    metric: u8,
+    user: &CanonicalAddr,
+    old: Option<u64>,
+    new: Option<u64>,
+) -> StdResult<()> {
+    // players without a score are not ranked
+    let old = old.filter(|s| *s > 0);
+    let new = new.filter(|s| *s > 0);
+    if old == new {
+        return Ok(());
+    }
+    let dir_store = ReadonlyPrefixedStorage::multilevel(storage, &[PREFIX_LEADERBOARD, season_key]);
+    let mut dir: Vec<StoredRankPage> = may_load(&dir_store, &[metric])?.unwrap_or_default();
+    let mut page_store = PrefixedStorage::multilevel(storage, &[PREFIX_LEADER_PAGES, season_key]);
+    if let Some(score) = old {
+        // only pages whose score range includes the old score can hold the user
+        let mut i = dir.partition_point(|p| p.low > score);
+        while i < dir.len() && dir[i].high >= score {
+            let key = rank_page_key(metric, dir[i].id);
+            let mut ranks: Vec<StoredRank> = may_load(&page_store, &key)?.unwrap_or_default();
+            if let Some(pos) = ranks.iter().position(|r| r.user == *user) {
+                ranks.remove(pos);
+                if ranks.is_empty() {
+                    remove(&mut page_store, &key);
+                    dir.remove(i);
+                } else {
+                    save(&mut page_store, &key, &ranks)?;
+                    dir[i] = rank_page_summary(dir[i].id, &ranks);
+                }
+                break;
+            }
+            i += 1;
+        }
+    }
+    if let Some(score) = new {
+        let rank = StoredRank {
+            user: user.clone(),
+            score,
+        };
+        // players reaching a score first rank above those who tie them later, so insert into
+        // the first page with a lower score, or the last page if there is none
+        if let Some(i) = dir
+            .iter()
+            .position(|p| p.low < score)
+            .or_else(|| dir.len().checked_sub(1))
+        {
+            let key = rank_page_key(metric, dir[i].id);
+            let mut ranks: Vec<StoredRank> = may_load(&page_store, &key)?.unwrap_or_default();
+            let pos = ranks.partition_point(|r| r.score >= score);
+            ranks.insert(pos, rank);
+            if ranks.len() > 2 * RANK_PAGE_SIZE {
+                let tail = ranks.split_off(RANK_PAGE_SIZE);
+                let id = dir.iter().map(|p| p.id).max().map_or(0, |m| m + 1);
+                save(&mut page_store, &rank_page_key(metric, id), &tail)?;
+                dir.insert(i + 1, rank_page_summary(id, &tail));
+            }
+            save(&mut page_store, &key, &ranks)?;
+            dir[i] = rank_page_summary(dir[i].id, &ranks);
+        } else {
+            let ranks = vec![rank];
+            save(&mut page_store, &rank_page_key(metric, 0), &ranks)?;
+            dir.push(rank_page_summary(0, &ranks));
+        }
+    }
+    let mut dir_store = PrefixedStorage::multilevel(storage, &[PREFIX_LEADERBOARD, season_key]);
+    save(&mut dir_store, &[metric], &dir)
+}
+
+/// Returns Vec<u8>
+///
+/// storage key of a leaderboard page
+///
+/// # Arguments
+///
+/// * `metric` - index of the leaderboard metric
+/// * `id` - storage id of the page
+fn rank_page_key(metric: u8, id: u32) -> Vec<u8> {
+    [[metric].as_slice(), &id.to_le_bytes()].concat()
+}
+
+/// Returns StoredRankPage
+///
+/// summarize a non-empty leaderboard page
+///
+/// # Arguments
+///
+/// * `id` - storage id of the page
+/// * `ranks` - the sorted ranks of the page
+fn rank_page_summary(id: u32, ranks: &[StoredRank]) -> StoredRankPage {
+    StoredRankPage {
+        id,
+        count: ranks.len() as u32,
+        high: ranks.first().map_or(0, |r| r.score),
+        low: ranks.last().map_or(0, |r| r.score),
+    }
+}
+
+/// Returns StdResult<()>
+///
+/// record an event and add it to the histories of the specified users and skulls
+///
+/// # Arguments
+///
+/// * `storage` - a mutable reference to this contract's storage
+/// * `event` - the event to record
+/// * `users` - addresses whose histories should include this event
+/// * `token_ids` - skulls whose histories should include this event
+fn record_event(
+    storage: &mut dyn Storage,
+    event: StoredEvent,
+    users: &[&CanonicalAddr],
+    token_ids: &[String],
+) -> StdResult<()> {
+    let idx: u64 = may_load(storage, HISTORY_CNT_KEY)?.unwrap_or(0);
+    save(storage, HISTORY_CNT_KEY, &(idx + 1))?;
+    // update the aggregate totals
+    let mut totals: StoredTotals = may_load(storage, HISTORY_TOTALS_KEY)?.unwrap_or_default();
+    match &event.action {
+        StoredAction::SetStake { .. } => totals.stake_changes += 1,
+        StoredAction::Claim {
+            charges, rewards, ..
+        } => {
+            totals.claims += 1;
+            totals.charges += *charges as u64;
+            add_totals(&mut totals.rewarded, rewards);
+        }
+        StoredAction::FirstStakeBonus { rewards, .. } => {
+            totals.first_stakes += 1;
+            add_totals(&mut totals.rewarded, rewards);
+        }
+        StoredAction::Crate { ingredients } => {
+            totals.crates += 1;
+            add_totals(&mut totals.crated, ingredients);
+        }
+        StoredAction::Uncrate { ingredients, .. } => {
+            totals.uncrates += 1;
+            add_totals(&mut totals.uncrated, ingredients);
+        }
+    }
+    save(storage, HISTORY_TOTALS_KEY, &totals)?;
+    // the users who performed the event or were rewarded by it
+    let mut involved: Vec<CanonicalAddr> = vec![event.user.clone()];
+    if let StoredAction::Claim {
+        recipient: Some(rcpt),
+        ..
+    }
+    | StoredAction::FirstStakeBonus {
+        recipient: Some(rcpt),
+        ..
+    } = &event.action
+    {
+        if *rcpt != event.user {
+            involved.push(rcpt.clone());
+        }
+    }
+    let mut hist_store = PrefixedStorage::new(storage, PREFIX_HISTORY);
+    save(&mut hist_store, &idx.to_le_bytes(), &event)?;
+    // add the event to the user histories (skipping duplicates)
+    let mut done: Vec<&CanonicalAddr> = Vec::new();
+    for user in users {
+        if !done.contains(user) {
+            append_history(storage, PREFIX_USER_HISTORY, user.as_slice(), idx)?;
+            done.push(*user);
+        }
+    }
+    for id in token_ids.iter() {
+        append_history(storage, PREFIX_TOKEN_HISTORY, id.as_bytes(), idx)?;
+        for user in involved.iter() {
+            append_history(
+                storage,
+                PREFIX_TOKEN_USER_HISTORY,
+                &token_user_key(user, id),
+                idx,
+            )?;
+        }
+    }
+    Ok(())
+}
+
+/// Returns Vec<u8>
+///
+/// the storage key of the part of a skull's history that involves a user
+///
+/// # Arguments
+///
+/// * `user` - a reference to the user's address
+/// * `token_id` - the skull's token id
+fn token_user_key(user: &CanonicalAddr, token_id: &str) -> Vec<u8> {
+    [user.as_slice(), token_id.as_bytes()].concat()
+}
+
+/// add quantities indexed by ingredient to a list of totals
+///
+/// # Arguments
+///
+/// * `totals` - a mutable reference to the list of totals
+/// * `quantities` - quantities to add
+fn add_totals(totals: &mut Vec<u64>, quantities: &[u32]) {
+    if totals.len() < quantities.len() {
+        totals.resize(quantities.len(), 0);
+    }
+    for (tot, qty) in totals.iter_mut().zip(quantities) {
+        *tot += *qty as u64;
+    }
+}
+
+/// Returns StdResult<()>
+///
+/// append an event index to a user's or skull's history
+///
+/// # Arguments
+///
+/// * `storage` - a mutable reference to this contract's storage
+/// * `prefix` - storage prefix of the history
+/// * `key` - user address or token id storage key
+/// * `idx` - index of the event
+fn append_history(storage: &mut dyn Storage, prefix: &[u8], key: &[u8], idx: u64) -> StdResult<()> {
+    let mut idx_store = PrefixedStorage::multilevel(storage, &[prefix, key]);
+    let cnt: u32 = may_load(&idx_store, HIST_CNT_KEY)?.unwrap_or(0);
+    save(&mut idx_store, &cnt.to_le_bytes(), &idx)?;
+    save(&mut idx_store, HIST_CNT_KEY, &(cnt + 1))
+}
+
+/// Returns Vec<IngredientQty>
+///
+/// create a readable list of non-zero ingredient quantities
+///
+/// # Arguments
+///
+/// * `ingredients` - list of all ingredient names
+/// * `quantities` - quantities indexed by ingredient
+fn display_quantities(ingredients: &[String], quantities: Vec<u32>) -> Vec<IngredientQty> {
+    quantities
+        .into_iter()
+        .enumerate()
+        .filter(|(_, q)| *q > 0)
+        .map(|(i, quantity)| IngredientQty {
+            ingredient: ingredients[i].clone(),
+            quantity,
+        })
+        .collect()
+}
+
+/// Returns StdResult<Binary> displaying a page of a user's or skull's history
+///
+/// # Arguments
+///
+/// * `deps` - reference to Extern containing all the contract's external dependencies
+/// * `prefix` - storage prefix of the history
+/// * `key` - user address or token id storage key
+/// * `querier` - address of the querier, or None if the querier is an admin
+/// * `page` - optional page to display
+/// * `page_size` - optional number of events to display
+fn display_history(
+    deps: Deps,
+    prefix: &[u8],
+    key: &[u8],
+    querier: Option<&CanonicalAddr>,
+    page: Option<u32>,
+    page_size: Option<u32>,
+) -> StdResult<Binary> {
+    let ingredients: Vec<String> = may_load(deps.storage, INGREDIENTS_KEY)?.unwrap_or_default();
+    let idx_store = ReadonlyPrefixedStorage::multilevel(deps.storage, &[prefix, key]);
+    let hist_store = ReadonlyPrefixedStorage::new(deps.storage, PREFIX_HISTORY);
+    let count: u32 = may_load(&idx_store, HIST_CNT_KEY)?.unwrap_or(0);
+    let page = page.unwrap_or(0);
+    let limit = page_size.unwrap_or(30);
+    let skip = page.saturating_mul(limit);
+    let humanize = |raw: Option<CanonicalAddr>| -> StdResult<Option<Addr>> {
+        raw.map(|r| deps.api.addr_humanize(&r)).transpose()
+    };
+    let mut events: Vec<HistoryEvent> = Vec::new();
+    // display the most recent first
+    for pos in (0..count.saturating_sub(skip)).rev().take(limit as usize) {
+        let idx: u64 = load(&idx_store, &pos.to_le_bytes())?;
+        let event: StoredEvent = load(&hist_store, &idx.to_le_bytes())?;
+        // only reveal who performed the event to admins, or to a different reward recipient
+        let show_user = match querier {
+            None => true,
+            Some(q) => {
+                event.user != *q
+                    && match &event.action {
+                        StoredAction::Claim { recipient, .. }
+                        | StoredAction::FirstStakeBonus { recipient, .. } => {
+                            recipient.as_ref() == Some(q)
+                        }
+                        _ => false,
+                    }
+            }
+        };
+        let user = humanize(show_user.then_some(event.user))?;
+        let action = match event.action {
+            StoredAction::SetStake { token_ids } => HistoryAction::SetStake { token_ids },
+            StoredAction::Claim {
+                token_ids,
+                charges,
+                rewards,
+                recipient,
+            } => HistoryAction::Claim {
+                token_ids,
+                charges,
+                rewards: display_quantities(&ingredients, rewards),
+                recipient: humanize(recipient)?,
+            },
+            StoredAction::FirstStakeBonus {
+                token_ids,
+                rewards,
+                recipient,
+            } => HistoryAction::FirstStakeBonus {
+                token_ids,
+                rewards: display_quantities(&ingredients, rewards),
+                recipient: humanize(recipient)?,
+            },
+            StoredAction::Crate { ingredients: qtys } => HistoryAction::Crate {
+                ingredients: display_quantities(&ingredients, qtys),
+            },
+            StoredAction::Uncrate {
+                token_ids,
+                ingredients: qtys,
+            } => HistoryAction::Uncrate {
+                token_ids,
+                ingredients: display_quantities(&ingredients, qtys),
+            },
+        };
+        events.push(HistoryEvent {
+            time: event.time,
+            user,
+            action,
+        });
+    }
+
+    to_binary(&QueryAnswer::History { count, events })
+}
+
+/// Returns StdResult<Vec<IngredientQty>>
+///
+/// create a readable list of a user's ingredient inventory
+///
+/// # Arguments
+///
+/// * `storage` - a reference to this contract's storage
+/// * `user_key` - user address storage key
+fn display_inventory(storage: &dyn Storage, user_key: &[u8]) -> StdResult<Vec<IngredientQty>> {
+    let mut inventory: Vec<IngredientQty> = Vec::new();
+    let ingredients: Vec<String> = may_load(storage, INGREDIENTS_KEY)?.unwrap_or_default();
+    let ingr_cnt = ingredients.len();
+    let inv_store = ReadonlyPrefixedStorage::new(storage, PREFIX_USER_INGR_INVENTORY);
+    let mut raw_inv: Vec<u32> = may_load(&inv_store, user_key)?.unwrap_or_default();
+    // just in case new ingredients get added, extend old inventories
+    raw_inv.resize(ingr_cnt, 0);
+    // create the readable list of ingredients
+    for (i, quantity) in raw_inv.into_iter().enumerate() {
+        inventory.push(IngredientQty {
+            ingredient: ingredients[i].clone(),
+            quantity,
+        });
+    }
+    Ok(inventory)
+}
+
+/// Returns StdResult<Response>
+///
+/// uncrate crate nfts after verifying they were minted by this contract.  Legacy crates, minted
+/// before crates were recorded, only have ingredient traits and are only accepted if their
+/// token id is below the legacy crate count recorded for the crate contract.  If an UncrateMsg
+/// is provided, only the specified quantities are added to the inventory, and the remaining
+/// ingredients are re-crated into a new crate nft
+///
+/// # Arguments
+///
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `env` - a reference to the Env of contract's environment
+/// * `crate_addr` - the message sender's address
+/// * `crate_contract` - the message sender's crate contract registry entry
 /// * `from` - a reference to the address that owned the crate NFTs
 /// * `token_ids` - list of tokens sent
+/// * `msg` - optional base64 encoded UncrateMsg
 fn uncrate(
     deps: DepsMut,
+    env: &Env,
     crate_addr: String,
-    crate_hash: String,
+    crate_contract: StoredCrateContract,
     from: &str,
     token_ids: Vec<String>,
+    msg: Option<Binary>,
 ) -> StdResult<Response> {
     if token_ids.is_empty() {
         return Err(StdError::generic_err("No crate NFTs were sent"));
@@ -1920,45 +5752,1834 @@ fn uncrate(
     // get list of ingredients
     let ingredients: Vec<String> = may_load(deps.storage, INGREDIENTS_KEY)?.unwrap_or_default();
     let ingr_cnt = ingredients.len();
-    // get user's inventory
-    let mut inv_store = PrefixedStorage::new(deps.storage, PREFIX_USER_INGR_INVENTORY);
-    let mut raw_inv: Vec<u32> = may_load(&inv_store, user_key)?.unwrap_or_default();
-    // just in case new ingredients get added, extend old inventories
-    raw_inv.resize(ingr_cnt, 0);
     // get the public metadata of all nfts sent
     let dossiers = Snip721QueryMsg::BatchNftDossier {
         token_ids: token_ids.clone(),
     }
-    .query::<_, BatchNftDossierWrapper>(deps.querier, crate_hash.clone(), crate_addr.clone())?
+    .query::<_, BatchNftDossierWrapper>(
+        deps.querier,
+        crate_contract.contract.code_hash.clone(),
+        crate_addr.clone(),
+    )?
     .batch_nft_dossier
     .nft_dossiers;
     // burn all the crates sent
-    let burns = vec![Burn { token_ids }];
-    let mut resp = Response::new().add_message(
-        Snip721HandleMsg::BatchBurnNft { burns }.to_cosmos_msg(crate_hash, crate_addr, None)?,
-    );
-    let mut added: Vec<u32> = vec![0; ingr_cnt];
-    // add the crate ingredients to the user inventory
+    let burns = vec![Burn {
+        token_ids: token_ids.clone(),
+    }];
+    let mut resp =
+        Response::new().add_message(Snip721HandleMsg::BatchBurnNft { burns }.to_cosmos_msg(
+            crate_contract.contract.code_hash.clone(),
+            crate_addr,
+            None,
+        )?);
+    let lgcy_store = ReadonlyPrefixedStorage::new(deps.storage, PREFIX_LEGACY_CRATES);
+    let legacy_cnt: u64 =
+        may_load(&lgcy_store, crate_contract.contract.address.as_slice())?.unwrap_or(0);
+    let mut contents: Vec<u32> = vec![0; ingr_cnt];
+    // tally and verify the contents of the crates
     for dossier in dossiers.into_iter() {
         let attrs = dossier
             .public_metadata
             .extension
             .attributes
             .ok_or_else(|| StdError::generic_err("Crate NFT is missing traits"))?;
+        let mut crate_id: Option<u128> = None;
+        let mut hash: Option<String> = None;
+        let mut ingr_attrs: Vec<Trait> = Vec::new();
         for attr in attrs.into_iter() {
-            if let Some(pos) = ingredients.iter().position(|i| *i == attr.trait_type) {
-                let qty = attr.value.parse::<u32>().map_err(|e| {
-                    StdError::generic_err(format!("Ingredient quantity parse error: {}", e))
+            match attr.trait_type.as_str() {
+                "Crate Id" => {
+                    crate_id = Some(attr.value.parse::<u128>().map_err(|e| {
+                        StdError::generic_err(format!("Crate Id parse error: {}", e))
+                    })?);
+                }
+                "Content Hash" => hash = Some(attr.value),
+                "Total Items" => {}
+                _ => {
+                    let pos = ingredients
+                        .iter()
+                        .position(|i| *i == attr.trait_type)
+                        .ok_or_else(|| {
+                            StdError::generic_err(format!(
+                                "Crate {} contains {}, which is not a known ingredient",
+                                dossier.token_id, attr.trait_type
+                            ))
+                        })?;
+                    let qty = attr.value.parse::<u32>().map_err(|e| {
+                        StdError::generic_err(format!("Ingredient quantity parse error: {}", e))
+                    })?;
+                    contents[pos] = contents[pos].checked_add(qty).ok_or_else(|| {
+                        StdError::generic_err(format!(
+                            "The total {} in the crates sent is too large",
+                            attr.trait_type
+                        ))
+                    })?;
+                    ingr_attrs.push(attr);
+                }
+            }
+        }
+        let not_minted = || {
+            StdError::generic_err(format!(
+                "Crate {} was not minted by this contract",
+                dossier.token_id
+            ))
+        };
+        // legacy crates have no record to verify, so their ingredient traits are used as is if
+        // the crate contract minted them before crates were recorded
+        if crate_id.is_none() && hash.is_none() {
+            if dossier
+                .token_id
+                .parse::<u64>()
+                .map_or(true, |id| id >= legacy_cnt)
+            {
+                return Err(not_minted());
+            }
+            if ingr_attrs.is_empty() {
+                return Err(StdError::generic_err(format!(
+                    "Crate {} does not contain any ingredients",
+                    dossier.token_id
+                )));
+            }
+            continue;
+        }
+        // verify the crate was minted by this contract with these contents
+        let id_key = crate_id.ok_or_else(not_minted)?.to_le_bytes();
+        let mut mint_store = PrefixedStorage::new(deps.storage, PREFIX_MINTED_CRATES);
+        let minted: StoredCrate = may_load(&mint_store, &id_key)?.ok_or_else(not_minted)?;
+        let computed = content_hash(&ingr_attrs);
+        if minted.contract != crate_contract.contract.address
+            || minted.hash != computed
+            || hash.as_ref() != Some(&computed)
+        {
+            return Err(not_minted());
+        }
+        remove(&mut mint_store, &id_key);
+    }
+    // determine how much to uncrate
+    let added = if let Some(bin) = msg {
+        let unc_msg: UncrateMsg = from_binary(&bin)?;
+        let mut wanted: Vec<u32> = vec![0; ingr_cnt];
+        for ing_qty in unc_msg.ingredients.into_iter() {
+            let pos = ingredients
+                .iter()
+                .position(|i| *i == ing_qty.ingredient)
+                .ok_or_else(|| {
+                    StdError::generic_err(format!(
+                        "{} is not a known ingredient",
+                        ing_qty.ingredient
+                    ))
                 })?;
-                raw_inv[pos] += qty;
-                added[pos] += qty;
+            wanted[pos] = wanted[pos].checked_add(ing_qty.quantity).ok_or_else(|| {
+                StdError::generic_err(format!(
+                    "The total {} requested is too large",
+                    ing_qty.ingredient
+                ))
+            })?;
+            if wanted[pos] > contents[pos] {
+                return Err(StdError::generic_err(format!(
+                    "The crates sent only contain {} {}",
+                    contents[pos], ing_qty.ingredient
+                )));
             }
         }
+        wanted
+    } else {
+        contents.clone()
+    };
+    // re-crate anything not being uncrated, using the same crate contract if it still mints
+    let remainder: Vec<u32> = contents
+        .iter()
+        .zip(added.iter())
+        .map(|(c, a)| c - a)
+        .collect();
+    if remainder.iter().any(|r| *r > 0) {
+        resp = resp.add_message(mint_crate(
+            deps.storage,
+            deps.api,
+            from.to_string(),
+            &ingredients,
+            &remainder,
+            crate_contract.mint.then_some(crate_contract.name.as_str()),
+        )?);
     }
     // create logs for each inredient added
     for (i, qty) in added.iter().enumerate() {
         resp = resp.add_attribute(ingredients[i].clone(), qty.to_string());
     }
+    // add the uncrated ingredients to the user inventory
+    let mut inv_store = PrefixedStorage::new(deps.storage, PREFIX_USER_INGR_INVENTORY);
+    let mut raw_inv: Vec<u32> = may_load(&inv_store, user_key)?.unwrap_or_default();
+    // just in case new ingredients get added, extend old inventories
+    raw_inv.resize(ingr_cnt, 0);
+    for (i, (inv, add)) in raw_inv.iter_mut().zip(added.iter()).enumerate() {
+        *inv = inv.checked_add(*add).ok_or_else(|| {
+            StdError::generic_err(format!(
+                "Uncrating {} {} would overflow your inventory",
+                add, ingredients[i]
+            ))
+        })?;
+    }
     save(&mut inv_store, user_key, &raw_inv)?;
+    if added.iter().any(|a| *a > 0) {
+        let note = notify(deps.storage, &user_raw, INGREDIENTS_CHANNEL)?;
+        resp = resp.add_attribute_plaintext(note, "");
+    }
+    record_event(
+        deps.storage,
+        StoredEvent {
+            user: user_raw.clone(),
+            time: env.block.time.seconds(),
+            action: StoredAction::Uncrate {
+                token_ids,
+                ingredients: added,
+            },
+        },
+        &[&user_raw],
+        &[],
+    )?;
     Ok(resp)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::msg::{LayerId, StakingTier};
+    use crate::notify::channel_counter;
+    use cosmwasm_std::testing::{mock_dependencies, mock_env, MockApi, MockQuerier, MockStorage};
+    use cosmwasm_std::{ContractResult, OwnedDeps, SystemResult, Timestamp, WasmQuery};
+
+    #[test]
+    fn test_expected_resources() {
+        let mut storage = MockStorage::new();
+        let sets = vec![
+            StoredIngrSet {
+                name: "Common".to_string(),
+                list: vec![0, 1],
+            },
+            StoredIngrSet {
+                name: "Rare".to_string(),
+                list: vec![2],
+            },
+        ];
+        save(&mut storage, INGRED_SETS_KEY, &sets).unwrap();
+        let table = vec![
+            StoredSetWeight { set: 0, weight: 1 },
+            StoredSetWeight { set: 1, weight: 3 },
+        ];
+        let mut tbl_store = PrefixedStorage::new(&mut storage, PREFIX_STAKING_TABLE);
+        save(&mut tbl_store, &0u8.to_le_bytes(), &table).unwrap();
+        let groups = vec![ChargeGroup {
+            material: 0,
+            season: None,
+            charges: 2,
+            boosts: Vec::new(),
+        }];
+        // 2 charges each rolling 2.5 times on average, a quarter of them winning the common set
+        let expected = expected_resources(&storage, &groups, &[1], 3).unwrap();
+        assert_eq!(
+            expected,
+            vec![
+                Decimal::from_ratio(5u64, 8u64),
+                Decimal::from_ratio(5u64, 8u64),
+                Decimal::from_ratio(15u64, 4u64),
+            ]
+        );
+        // a skull material with no staking table can not be evaluated
+        let groups = vec![ChargeGroup {
+            material: 1,
+            season: None,
+            charges: 1,
+            boosts: Vec::new(),
+        }];
+        assert!(expected_resources(&storage, &groups, &[0, 1], 3).is_err());
+    }
+
+    #[test]
+    fn test_boosted_weights() {
+        let boost =
+            |name: &str, extra_rolls: u8, weights: Vec<(u8, u16)>, bonus: Option<u8>| StoredBoost {
+                name: name.to_string(),
+                layer: StoredLayerId {
+                    category: 1,
+                    variant: 1,
+                },
+                layer_name: LayerId {
+                    category: "Eyes".to_string(),
+                    variant: name.to_string(),
+                },
+                extra_rolls,
+                weights: weights
+                    .into_iter()
+                    .map(|(set, weight)| StoredSetWeight { set, weight })
+                    .collect(),
+                bonus: bonus.map(|set| StoredSetChance { set, chance: 500 }),
+            };
+        let pairs = |weights: &[StoredSetWeight]| {
+            weights
+                .iter()
+                .map(|w| (w.set, w.weight))
+                .collect::<Vec<(u8, u16)>>()
+        };
+        // every boost is applied at once when checking for overflow
+        let table = vec![StoredSetWeight {
+            set: 0,
+            weight: 65000,
+        }];
+        let boosts = vec![boost("Red", 0, vec![(0, 300)], None)];
+        assert!(check_boosted_weights(&table, &boosts, "Gold").is_ok());
+        let boosts = vec![
+            boost("Red", 0, vec![(0, 300)], None),
+            boost("Blue", 0, vec![(0, 300)], None),
+        ];
+        let err = check_boosted_weights(&table, &boosts, "Gold").unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("overflow an ingredient set weight of the Gold staking table"));
+        // sets only added by boosts can overflow too
+        let boosts = vec![
+            boost("Red", 0, vec![(1, 65535)], None),
+            boost("Blue", 0, vec![(1, 1)], None),
+        ];
+        assert!(check_boosted_weights(&table, &boosts, "Gold").is_err());
+
+        // staking tables are rejected if the existing boosts could overflow them
+        let mut deps = mock_dependencies();
+        let admin = Addr::unchecked("admin");
+        let admin_raw = deps.api.addr_canonicalize("admin").unwrap();
+        save(&mut deps.storage, ADMINS_KEY, &vec![admin_raw]).unwrap();
+        let materials = vec!["Gold".to_string(), "Bone".to_string()];
+        save(&mut deps.storage, MATERIALS_KEY, &materials).unwrap();
+        let sets = vec![
+            StoredIngrSet {
+                name: "Common".to_string(),
+                list: vec![0, 1],
+            },
+            StoredIngrSet {
+                name: "Rare".to_string(),
+                list: vec![2],
+            },
+        ];
+        save(&mut deps.storage, INGRED_SETS_KEY, &sets).unwrap();
+        let boosts = vec![
+            boost("Red", 1, vec![(0, 300)], None),
+            boost("Blue", 2, vec![(1, 10)], Some(1)),
+            boost("Green", 0, vec![(0, 5), (1, 5)], Some(0)),
+        ];
+        save(&mut deps.storage, STAKING_BOOSTS_KEY, &boosts).unwrap();
+        let stk_tbl = |weight: u16| StakingTable {
+            material: "Gold".to_string(),
+            ingredient_set_weights: vec![IngrSetWeight {
+                ingredient_set: "Common".to_string(),
+                weight,
+            }],
+        };
+        let err = try_stake_tbl(deps.as_mut(), &admin, vec![stk_tbl(65300)]).unwrap_err();
+        assert!(err.to_string().contains("Gold staking table"));
+        try_stake_tbl(deps.as_mut(), &admin, vec![stk_tbl(60)]).unwrap();
+
+        // a charge group only gets its own boosts, which add to existing sets and append new ones
+        let group = ChargeGroup {
+            material: 0,
+            season: None,
+            charges: 1,
+            boosts: vec![0, 1],
+        };
+        let boosted = boosted_table(&deps.storage, &group, &boosts, &[]).unwrap();
+        assert_eq!(pairs(&boosted.weights), vec![(0, 360), (1, 10)]);
+        assert_eq!(boosted.extra_rolls, 3);
+        let bonus_sets = boosted.bonuses.iter().map(|b| b.set).collect::<Vec<u8>>();
+        assert_eq!(bonus_sets, vec![1]);
+        // a season that overrides the material's table is boosted instead
+        let seasons = vec![StoredSeason {
+            id: 0,
+            name: "Event".to_string(),
+            start: 0,
+            end: 100,
+            tables: vec![StoredSeasonTable {
+                material: 0,
+                weights: vec![StoredSetWeight { set: 1, weight: 7 }],
+            }],
+            charge_pct: 100,
+        }];
+        let group = ChargeGroup {
+            material: 0,
+            season: Some(0),
+            charges: 1,
+            boosts: vec![2],
+        };
+        let boosted = boosted_table(&deps.storage, &group, &boosts, &seasons).unwrap();
+        assert_eq!(pairs(&boosted.weights), vec![(1, 12), (0, 5)]);
+        assert_eq!(boosted.extra_rolls, 0);
+        assert_eq!(boosted.bonuses.len(), 1);
+    }
+
+    #[test]
+    fn test_earned_charges() {
+        let seasons = vec![StoredSeason {
+            id: 0,
+            name: "Event".to_string(),
+            start: 100,
+            end: 200,
+            tables: Vec::new(),
+            charge_pct: 50,
+        }];
+        // charges take as long as the season in progress when they start, and use the table
+        // of the season in progress during their final second
+        let (earned, last) = earned_charges(&seasons, 60, 0, 300, 10);
+        assert_eq!(earned, vec![None, Some(0), Some(0), Some(0), None, None]);
+        assert_eq!(last, 270);
+        // charges past the cap are forfeited but still advance the charging time
+        let (earned, last) = earned_charges(&seasons, 60, 0, 300, 2);
+        assert_eq!(earned, vec![None, Some(0)]);
+        assert_eq!(last, 270);
+        // a charge that matures exactly when a season starts was not earned in it
+        let (earned, _) = earned_charges(&seasons, 50, 0, 100, 10);
+        assert_eq!(earned, vec![None, None]);
+        assert_eq!(charge_seconds(&seasons, 60, 0, 4), 180);
+    }
+
+    #[test]
+    fn test_staking_rules() {
+        let tier = |min_owned: u16, max_staked: u8| StakingTier {
+            min_owned,
+            max_staked,
+        };
+        let rules = |max_staked: u8, tiers: Vec<StakingTier>| StakingRules {
+            max_staked,
+            tiers,
+            charge_cap: 4,
+            cap_per_user: false,
+            first_stake_bonus: 1,
+        };
+        let rejects = |mut rules: StakingRules, msg: &str| {
+            let err = validate_rules(&mut rules).unwrap_err();
+            assert!(err.to_string().contains(msg), "{}", err);
+        };
+        rejects(rules(0, Vec::new()), "must be greater than 0");
+        let mut no_cap = rules(5, Vec::new());
+        no_cap.charge_cap = 0;
+        rejects(no_cap, "charge cap must be greater than 0");
+        let mut big_bonus = rules(5, Vec::new());
+        big_bonus.first_stake_bonus = 5;
+        rejects(big_bonus, "can not exceed the charge cap");
+        rejects(
+            rules(5, vec![tier(10, 8), tier(10, 9)]),
+            "Multiple staking tiers require owning 10 skulls",
+        );
+        rejects(rules(5, vec![tier(10, 4)]), "can not lower the maximum");
+        rejects(
+            rules(5, vec![tier(20, 7), tier(10, 8)]),
+            "can not lower the maximum",
+        );
+        // tiers are sorted by the number of skulls that must be owned
+        let mut tiered = rules(5, vec![tier(20, 10), tier(10, 8)]);
+        validate_rules(&mut tiered).unwrap();
+        assert_eq!(tiered.tiers, vec![tier(10, 8), tier(20, 10)]);
+        assert_eq!(max_staked(&tiered, None), 10);
+        assert_eq!(max_staked(&tiered, Some(9)), 5);
+        assert_eq!(max_staked(&tiered, Some(10)), 8);
+        assert_eq!(max_staked(&tiered, Some(25)), 10);
+
+        // a per-skull cap leaves each skull's charges alone, but a per-user cap forfeits
+        // charges once the user's total reaches it
+        let mut capped = rules(5, Vec::new());
+        let mut remaining = capped.charge_cap;
+        assert_eq!(capped_charges(&capped, &mut remaining, 3), 3);
+        assert_eq!(capped_charges(&capped, &mut remaining, 3), 3);
+        assert_eq!(remaining, 4);
+        capped.cap_per_user = true;
+        let mut remaining = capped.charge_cap;
+        assert_eq!(capped_charges(&capped, &mut remaining, 3), 3);
+        assert_eq!(capped_charges(&capped, &mut remaining, 3), 1);
+        assert_eq!(capped_charges(&capped, &mut remaining, 2), 0);
+        assert_eq!(remaining, 0);
+    }
+
+    #[test]
+    fn test_stake_tiers() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        let skulls_raw = deps.api.addr_canonicalize("skulls").unwrap();
+        let skull_contract = StoreContractInfo {
+            code_hash: "skullhash".to_string(),
+            address: skulls_raw,
+        };
+        save(&mut deps.storage, SKULL_721_KEY, &skull_contract).unwrap();
+        save(&mut deps.storage, MY_VIEWING_KEY, &"key".to_string()).unwrap();
+        save(&mut deps.storage, NOTIFY_SECRET_KEY, &vec![7u8; 32]).unwrap();
+        let stk_state = StakingState {
+            halt: false,
+            skull_idx: 0,
+            cooldown: 3600,
+        };
+        save(&mut deps.storage, STAKING_STATE_KEY, &stk_state).unwrap();
+        let rules = StakingRules {
+            max_staked: 2,
+            tiers: vec![StakingTier {
+                min_owned: 4,
+                max_staked: 3,
+            }],
+            charge_cap: 4,
+            cap_per_user: false,
+            first_stake_bonus: 0,
+        };
+        save(&mut deps.storage, STAKING_RULES_KEY, &rules).unwrap();
+        // the skull contract reports every skull as owned except the ones prefixed with "x"
+        deps.querier.update_wasm(|query| {
+            let msg = match query {
+                WasmQuery::Smart { msg, .. } => msg.as_slice(),
+                _ => panic!("unexpected query"),
+            };
+            let owner = if String::from_utf8_lossy(msg).contains(r#""token_id":"x"#) {
+                "other"
+            } else {
+                "owner"
+            };
+            let server = r#"{"code_hash":"svghash","address":"svg"}"#;
+            let image = r#"{"current":[0],"previous":[0],"natural":[0],"svg_server":null}"#;
+            let json = format!(
+                r#"{{"image_info":{{"owner":"{}","server_used":{},"image_info":{}}}}}"#,
+                owner, server, image
+            );
+            SystemResult::Ok(ContractResult::Ok(Binary::from(json.as_bytes())))
+        });
+        let owner = Addr::unchecked("owner");
+        let ids = |list: &[&str]| list.iter().map(|i| i.to_string()).collect::<Vec<String>>();
+        let too_many = |res: StdResult<Response>| {
+            res.map_or_else(
+                |e| {
+                    e.to_string()
+                        .contains("only own enough skulls to stake up to 2")
+                },
+                |_| false,
+            )
+        };
+        let staked = ids(&["1", "2", "3"]);
+        // staking above the base limit without qualifying for a tier
+        let res = try_set_stake(
+            deps.as_mut(),
+            env.clone(),
+            &owner,
+            staked.clone(),
+            None,
+            None,
+        );
+        assert!(too_many(res));
+        // held skulls that are not owned, or are also being staked, do not count
+        let held = Some(ids(&["x1", "3"]));
+        let res = try_set_stake(
+            deps.as_mut(),
+            env.clone(),
+            &owner,
+            staked.clone(),
+            held,
+            None,
+        );
+        assert!(too_many(res));
+        // owning one more skull qualifies for the tier
+        let held = Some(ids(&["x1", "4"]));
+        try_set_stake(deps.as_mut(), env.clone(), &owner, staked, held, None).unwrap();
+        // no tier allows staking more than 3
+        let held = Some(ids(&["5", "6", "7"]));
+        let err = try_set_stake(
+            deps.as_mut(),
+            env,
+            &owner,
+            ids(&["1", "2", "3", "4"]),
+            held,
+            None,
+        )
+        .unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("You can only stake up to 3 skulls"));
+    }
+
+    #[test]
+    fn test_rank_user() {
+        let mut storage = MockStorage::new();
+        let user = |i: u8| CanonicalAddr::from(vec![i]);
+        // read a whole leaderboard as (user, score) pairs
+        let board = |storage: &MockStorage| {
+            let dir_store =
+                ReadonlyPrefixedStorage::multilevel(storage, &[PREFIX_LEADERBOARD, b""]);
+            let dir: Vec<StoredRankPage> = may_load(&dir_store, &[0]).unwrap().unwrap_or_default();
+            let page_store =
+                ReadonlyPrefixedStorage::multilevel(storage, &[PREFIX_LEADER_PAGES, b""]);
+            let mut ranks: Vec<(u8, u64)> = Vec::new();
+            for summary in dir.iter() {
+                let page: Vec<StoredRank> =
+                    load(&page_store, &rank_page_key(0, summary.id)).unwrap();
+                assert_eq!(summary.count as usize, page.len());
+                ranks.extend(page.iter().map(|r| (r.user.as_slice()[0], r.score)));
+            }
+            (dir.len(), ranks)
+        };
+        for i in 1..=120u8 {
+            rank_user(&mut storage, b"", 0, &user(i), None, Some(i as u64)).unwrap();
+        }
+        let (pages, ranks) = board(&storage);
+        assert!(pages > 1);
+        assert_eq!(ranks.len(), 120);
+        assert!(ranks.windows(2).all(|w| w[0].1 > w[1].1));
+        // a later tie ranks below the player who reached the score first
+        rank_user(&mut storage, b"", 0, &user(200), None, Some(50)).unwrap();
+        let (_, ranks) = board(&storage);
+        let pos = ranks.iter().position(|r| r.0 == 50).unwrap();
+        assert_eq!(ranks[pos + 1], (200, 50));
+        // removed players leave no gap, and everyone else stays ranked
+        rank_user(&mut storage, b"", 0, &user(120), Some(120), None).unwrap();
+        rank_user(&mut storage, b"", 0, &user(1), Some(1), Some(500)).unwrap();
+        let (_, ranks) = board(&storage);
+        assert_eq!(ranks.len(), 120);
+        assert_eq!(ranks[0], (1, 500));
+        assert_eq!(ranks[1], (119, 119));
+        assert!(!ranks.iter().any(|r| r.0 == 120));
+        assert!(ranks.windows(2).all(|w| w[0].1 >= w[1].1));
+    }
+
+    #[test]
+    fn test_wide_layer_indices() {
+        // the svg server answers with u16 indices once a category passes 255 variants
+        let json = br#"{"layer_indices":{"layers":[{"category":3,"variant":300},{"category":260,"variant":0}]}}"#;
+        let layers = from_binary::<LayerIndicesWrapper>(&Binary::from(&json[..]))
+            .unwrap()
+            .layer_indices
+            .layers;
+        assert_eq!(
+            layers,
+            vec![
+                StoredLayerId {
+                    category: 3,
+                    variant: 300
+                },
+                StoredLayerId {
+                    category: 260,
+                    variant: 0
+                },
+            ]
+        );
+        let json = br#"{"skull_type_plus":{"cyclops":{"category":5,"variant":256},"jawless":{"category":6,"variant":1},"skull_idx":257,"skull_variants":[{"idx":300,"name":"Gold"}]}}"#;
+        let st_plus = from_binary::<SkullTypePlusWrapper>(&Binary::from(&json[..]))
+            .unwrap()
+            .skull_type_plus;
+        assert_eq!(st_plus.skull_idx, 257);
+        assert_eq!(st_plus.cyclops.variant, 256);
+        assert_eq!(st_plus.skull_variants[0].idx, 300);
+        // the stored layer ids keep the wide indices
+        let mut storage = MockStorage::new();
+        let alc_st = AlchemyState {
+            halt: false,
+            cyclops: st_plus.cyclops.clone(),
+            jawless: st_plus.jawless,
+        };
+        save(&mut storage, ALCHEMY_STATE_KEY, &alc_st).unwrap();
+        let loaded: AlchemyState = load(&storage, ALCHEMY_STATE_KEY).unwrap();
+        assert_eq!(loaded, alc_st);
+        // a u8 image vector never matches a variant past 255
+        let boost = |category: u16, variant: u16| StoredBoost {
+            name: format!("{}-{}", category, variant),
+            layer: StoredLayerId { category, variant },
+            layer_name: LayerId {
+                category: "Eyes".to_string(),
+                variant: "Gold".to_string(),
+            },
+            extra_rolls: 0,
+            weights: Vec::new(),
+            bonus: None,
+        };
+        let boosts = vec![boost(1, 300), boost(1, 44), boost(2, 7)];
+        assert_eq!(get_boosts(&boosts, &[0, 44, 7]), vec![1, 2]);
+    }
+
+    #[test]
+    fn test_operator_expiry() {
+        let mut deps = mock_dependencies();
+        let mut env = mock_env();
+        env.block.time = Timestamp::from_seconds(1000);
+        let owner = Addr::unchecked("owner");
+        let claimer = Addr::unchecked("claimer");
+        let staker = Addr::unchecked("staker");
+        let staker_of = |deps: &OwnedDeps<MockStorage, MockApi, MockQuerier>,
+                         env: &Env,
+                         sender: &Addr,
+                         staking: bool| {
+            get_staker(
+                deps.as_ref(),
+                env,
+                sender,
+                Some("owner".to_string()),
+                staking,
+            )
+        };
+
+        // an authorization can not already be expired
+        let err = try_set_operator(
+            deps.as_mut(),
+            &env,
+            &owner,
+            "claimer",
+            OperatorScope::Claim,
+            Some(1000),
+            None,
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("already passed"));
+        let err = try_set_operator(
+            deps.as_mut(),
+            &env,
+            &owner,
+            "owner",
+            OperatorScope::Claim,
+            None,
+            None,
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("your own staking operator"));
+        try_set_operator(
+            deps.as_mut(),
+            &env,
+            &owner,
+            "claimer",
+            OperatorScope::Claim,
+            Some(2000),
+            Some("vault".to_string()),
+        )
+        .unwrap();
+        try_set_operator(
+            deps.as_mut(),
+            &env,
+            &owner,
+            "staker",
+            OperatorScope::ClaimAndStake,
+            Some(1500),
+            None,
+        )
+        .unwrap();
+
+        // operators act for the owner, sending rewards to the owner unless a recipient was set
+        let owner_raw = deps.api.addr_canonicalize("owner").unwrap();
+        let vault_raw = deps.api.addr_canonicalize("vault").unwrap();
+        let (hmn, raw, rcpt) = staker_of(&deps, &env, &claimer, false).unwrap();
+        assert_eq!(hmn, owner);
+        assert_eq!(raw, owner_raw);
+        assert_eq!(rcpt, vault_raw);
+        let (_, _, rcpt) = staker_of(&deps, &env, &staker, true).unwrap();
+        assert_eq!(rcpt, owner_raw);
+        // a claim-only operator can not set the staking list
+        let err = staker_of(&deps, &env, &claimer, true).unwrap_err();
+        assert!(err.to_string().contains("only authorized to claim"));
+        let err = staker_of(&deps, &env, &Addr::unchecked("stranger"), false).unwrap_err();
+        assert!(err.to_string().contains("not a staking operator"));
+
+        // authorizations stop working once they expire
+        env.block.time = Timestamp::from_seconds(1500);
+        assert!(staker_of(&deps, &env, &staker, true).is_err());
+        assert!(staker_of(&deps, &env, &claimer, false).is_ok());
+        env.block.time = Timestamp::from_seconds(2000);
+        assert!(staker_of(&deps, &env, &claimer, false).is_err());
+        // and are pruned the next time the owner sets an operator
+        env.block.time = Timestamp::from_seconds(1800);
+        try_set_operator(
+            deps.as_mut(),
+            &env,
+            &owner,
+            "claimer",
+            OperatorScope::ClaimAndStake,
+            None,
+            None,
+        )
+        .unwrap();
+        let op_store = ReadonlyPrefixedStorage::new(&deps.storage, PREFIX_STAKING_OPERATORS);
+        let operators: Vec<StoredOperator> = load(&op_store, owner_raw.as_slice()).unwrap();
+        assert_eq!(operators.len(), 1);
+        assert!(operators[0].expiration.is_none());
+        assert!(operators[0].recipient.is_none());
+        env.block.time = Timestamp::from_seconds(5000);
+        assert!(staker_of(&deps, &env, &claimer, true).is_ok());
+    }
+
+    #[test]
+    fn test_history_visibility() {
+        let mut deps = mock_dependencies();
+        let ingredients = vec!["Ash".to_string(), "Bone".to_string()];
+        save(&mut deps.storage, INGREDIENTS_KEY, &ingredients).unwrap();
+        let owner = deps.api.addr_canonicalize("owner").unwrap();
+        let vault = deps.api.addr_canonicalize("vault").unwrap();
+        let buyer = deps.api.addr_canonicalize("buyer").unwrap();
+        let token = vec!["1".to_string()];
+        let stake = |user: &CanonicalAddr, time: u64| StoredEvent {
+            user: user.clone(),
+            time,
+            action: StoredAction::SetStake {
+                token_ids: token.clone(),
+            },
+        };
+        record_event(&mut deps.storage, stake(&owner, 10), &[&owner], &token).unwrap();
+        let claim = StoredEvent {
+            user: owner.clone(),
+            time: 20,
+            action: StoredAction::Claim {
+                token_ids: token.clone(),
+                charges: 3,
+                rewards: vec![2, 0],
+                recipient: Some(vault.clone()),
+            },
+        };
+        record_event(&mut deps.storage, claim, &[&owner, &vault, &owner], &token).unwrap();
+        // the skull is sold and staked by its new owner
+        record_event(&mut deps.storage, stake(&buyer, 30), &[&buyer], &token).unwrap();
+        // returns the count, and the times and displayed users of a page of history
+        let history = |prefix: &[u8], key: &[u8], querier: Option<&CanonicalAddr>, page_size| {
+            let bin =
+                display_history(deps.as_ref(), prefix, key, querier, None, page_size).unwrap();
+            match from_binary(&bin).unwrap() {
+                QueryAnswer::History { count, events } => (
+                    count,
+                    events
+                        .into_iter()
+                        .map(|e| (e.time, e.user.map(|u| u.into_string())))
+                        .collect::<Vec<(u64, Option<String>)>>(),
+                ),
+                _ => panic!("unexpected answer"),
+            }
+        };
+
+        // users are not shown their own address, and duplicate users are only recorded once
+        let (count, events) = history(PREFIX_USER_HISTORY, owner.as_slice(), Some(&owner), None);
+        assert_eq!(count, 2);
+        assert_eq!(events, vec![(20, None), (10, None)]);
+        // a different reward recipient sees who claimed for them
+        let (count, events) = history(PREFIX_USER_HISTORY, vault.as_slice(), Some(&vault), None);
+        assert_eq!(count, 1);
+        assert_eq!(events, vec![(20, Some("owner".to_string()))]);
+        // a skull's new owner only sees the events they were involved in
+        let key = token_user_key(&buyer, "1");
+        let (count, events) = history(PREFIX_TOKEN_USER_HISTORY, &key, Some(&buyer), None);
+        assert_eq!(count, 1);
+        assert_eq!(events, vec![(30, None)]);
+        let key = token_user_key(&vault, "1");
+        let (_, events) = history(PREFIX_TOKEN_USER_HISTORY, &key, Some(&vault), None);
+        assert_eq!(events, vec![(20, Some("owner".to_string()))]);
+        // admins see the whole history of the skull, including who performed every event
+        let (count, events) = history(PREFIX_TOKEN_HISTORY, b"1", None, None);
+        assert_eq!(count, 3);
+        assert_eq!(
+            events,
+            vec![
+                (30, Some("buyer".to_string())),
+                (20, Some("owner".to_string())),
+                (10, Some("owner".to_string())),
+            ]
+        );
+        let (_, events) = history(PREFIX_TOKEN_HISTORY, b"1", None, Some(2));
+        assert_eq!(events.len(), 2);
+        let totals: StoredTotals = load(&deps.storage, HISTORY_TOTALS_KEY).unwrap();
+        assert_eq!(totals.stake_changes, 2);
+        assert_eq!(totals.claims, 1);
+        assert_eq!(totals.charges, 3);
+        assert_eq!(totals.rewarded, vec![2, 0]);
+    }
+
+    #[test]
+    fn test_stream_reserves() {
+        let mut deps = mock_dependencies();
+        let admin_raw = deps.api.addr_canonicalize("admin").unwrap();
+        save(&mut deps.storage, ADMINS_KEY, &vec![admin_raw]).unwrap();
+        let stream = |token: &str, per_second: bool, cap: u128| StoredStream {
+            token: StoreContractInfo {
+                code_hash: "tokenhash".to_string(),
+                address: deps.api.addr_canonicalize(token).unwrap(),
+            },
+            per_second,
+            rates: vec![10, 1],
+            cap,
+            reserve: 0,
+            distributed: 0,
+        };
+        let streams = vec![stream("sscrt", false, 1000), stream("shade", true, 100)];
+        save(&mut deps.storage, REWARD_STREAMS_KEY, &streams).unwrap();
+
+        // only admins can fund a stream, and only with its own token
+        let sscrt = Addr::unchecked("sscrt");
+        let amount = Uint128::new(150);
+        assert!(try_receive(deps.as_mut(), &sscrt, "stranger", amount).is_err());
+        let err =
+            try_receive(deps.as_mut(), &Addr::unchecked("fake"), "admin", amount).unwrap_err();
+        assert!(err.to_string().contains("not a reward stream token"));
+        try_receive(deps.as_mut(), &sscrt, "admin", amount).unwrap();
+        try_receive(deps.as_mut(), &sscrt, "admin", Uint128::new(50)).unwrap();
+        try_receive(deps.as_mut(), &Addr::unchecked("shade"), "admin", amount).unwrap();
+        let mut streams: Vec<StoredStream> = load(&deps.storage, REWARD_STREAMS_KEY).unwrap();
+        assert_eq!(streams[0].reserve, 200);
+        assert_eq!(streams[1].reserve, 150);
+        let err = try_receive(deps.as_mut(), &sscrt, "admin", Uint128::MAX).unwrap_err();
+        assert!(err.to_string().contains("reserve would overflow"));
+
+        // streams pay per charge or per second at the skull material's rate
+        let mut owed = vec![0u128, 0];
+        add_owed(&streams, &mut owed, 0, 30, 3600);
+        assert_eq!(owed, vec![300, 36000]);
+        // payouts are limited by the reserve and the remaining cap
+        streams[1].distributed = 40;
+        let paid = payable(&streams, &owed);
+        assert_eq!(paid, vec![200, 60]);
+        // only the part under the cap that the reserve could not cover is carried forward
+        let unpaid = carry_unpaid(&streams, &owed, &paid);
+        assert_eq!(unpaid.len(), 1);
+        assert_eq!(unpaid[0].token, streams[0].token.address);
+        assert_eq!(unpaid[0].amount, 100);
+        let user_key = b"user";
+        let mut unpaid_store = PrefixedStorage::new(&mut deps.storage, PREFIX_UNPAID_TOKENS);
+        save(&mut unpaid_store, user_key, &unpaid).unwrap();
+        assert_eq!(
+            load_unpaid(&deps.storage, user_key, &streams).unwrap(),
+            vec![100, 0]
+        );
+        // the carried rewards of removed streams are forfeited
+        let remaining = vec![streams.remove(1)];
+        assert_eq!(
+            load_unpaid(&deps.storage, user_key, &remaining).unwrap(),
+            vec![0]
+        );
+    }
+
+    #[test]
+    fn test_pity_thresholds() {
+        let mut deps = mock_dependencies();
+        let admin = Addr::unchecked("admin");
+        let admin_raw = deps.api.addr_canonicalize("admin").unwrap();
+        save(&mut deps.storage, ADMINS_KEY, &vec![admin_raw]).unwrap();
+        let materials = vec!["Gold".to_string(), "Bone".to_string()];
+        save(&mut deps.storage, MATERIALS_KEY, &materials).unwrap();
+        let sets = vec![
+            StoredIngrSet {
+                name: "Common".to_string(),
+                list: vec![0, 1],
+            },
+            StoredIngrSet {
+                name: "Rare".to_string(),
+                list: vec![2],
+            },
+        ];
+        save(&mut deps.storage, INGRED_SETS_KEY, &sets).unwrap();
+        let mut tbl_store = PrefixedStorage::new(&mut deps.storage, PREFIX_STAKING_TABLE);
+        let gold = vec![
+            StoredSetWeight {
+                set: 0,
+                weight: 65535,
+            },
+            StoredSetWeight { set: 1, weight: 1 },
+        ];
+        save(&mut tbl_store, &0u8.to_le_bytes(), &gold).unwrap();
+        let bone = vec![
+            StoredSetWeight { set: 0, weight: 5 },
+            StoredSetWeight { set: 1, weight: 0 },
+        ];
+        save(&mut tbl_store, &1u8.to_le_bytes(), &bone).unwrap();
+        let pity_tbl = |material: &str, sets: Vec<(&str, u16)>| PityTable {
+            material: material.to_string(),
+            sets: sets
+                .into_iter()
+                .map(|(set, threshold)| PitySet {
+                    ingredient_set: set.to_string(),
+                    threshold,
+                })
+                .collect(),
+        };
+
+        // thresholds can only guarantee sets the material's staking table can win
+        let err = try_set_pity(
+            deps.as_mut(),
+            &admin,
+            vec![pity_tbl("Bone", vec![("Rare", 3)])],
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("not in the Bone staking table"));
+        let err = try_set_pity(
+            deps.as_mut(),
+            &admin,
+            vec![pity_tbl("Gold", vec![("Rare", 0)])],
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("greater than 0"));
+        let tbl = pity_tbl("Gold", vec![("Rare", 3), ("Rare", 4)]);
+        let err = try_set_pity(deps.as_mut(), &admin, vec![tbl]).unwrap_err();
+        assert!(err.to_string().contains("duplicated"));
+        let tbl = pity_tbl("Iron", vec![("Rare", 3)]);
+        assert!(try_set_pity(deps.as_mut(), &admin, vec![tbl]).is_err());
+        let tbl = pity_tbl("Gold", vec![("Rare", 3)]);
+        try_set_pity(deps.as_mut(), &admin, vec![tbl]).unwrap();
+        let progress = display_pity(&deps.storage, b"user").unwrap();
+        assert_eq!(progress.len(), 1);
+        assert_eq!(progress[0].ingredient_set, "Rare");
+        assert_eq!(progress[0].charges_without_win, 0);
+        assert_eq!(progress[0].threshold, 3);
+
+        let env = mock_env();
+        let group = || ChargeGroup {
+            material: 0,
+            season: None,
+            charges: 1,
+            boosts: Vec::new(),
+        };
+        // a charge that misses the set counts towards the threshold
+        let mut pity = vec![StoredPityCount {
+            material: 0,
+            set: 1,
+            misses: 1,
+        }];
+        let (_, guaranteed) =
+            gen_resources(&deps.storage, &env, &[group()], &[1, 0], 3, &mut pity).unwrap();
+        assert!(guaranteed.is_empty());
+        assert_eq!(pity[0].misses, 2);
+        // reaching the threshold guarantees the next charge wins the set, resetting the counter
+        pity[0].misses = 3;
+        let (generated, guaranteed) =
+            gen_resources(&deps.storage, &env, &[group()], &[1, 0], 3, &mut pity).unwrap();
+        assert_eq!(guaranteed, vec![(0, 1)]);
+        assert!(generated[2] >= 1);
+        assert_eq!(pity[0].misses, 0);
+        let mut pity_store = PrefixedStorage::new(&mut deps.storage, PREFIX_USER_PITY);
+        save(&mut pity_store, b"user", &pity).unwrap();
+
+        // an empty list removes the material's thresholds
+        try_set_pity(deps.as_mut(), &admin, vec![pity_tbl("Gold", Vec::new())]).unwrap();
+        assert!(display_pity(&deps.storage, b"user").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_crate_metadata() {
+        let mut deps = mock_dependencies();
+        let ingredients = vec!["Ash".to_string(), "Bone".to_string(), "Coal".to_string()];
+        let crates_raw = deps.api.addr_canonicalize("crates").unwrap();
+        let entry = |name: &str, address: &CanonicalAddr, mint: bool| StoredCrateContract {
+            name: name.to_string(),
+            contract: StoreContractInfo {
+                code_hash: "cratehash".to_string(),
+                address: address.clone(),
+            },
+            mint,
+            uncrate: true,
+        };
+        let old_raw = deps.api.addr_canonicalize("oldcrates").unwrap();
+        let raw_crates = vec![
+            entry("crates", &crates_raw, true),
+            entry("retired", &old_raw, false),
+        ];
+        save(&mut deps.storage, CRATES_KEY, &raw_crates).unwrap();
+        save(&mut deps.storage, DEFAULT_CRATE_KEY, &"crates".to_string()).unwrap();
+        let crt_state = CrateState {
+            halt: false,
+            cnt: 7,
+        };
+        save(&mut deps.storage, CRATE_STATE_KEY, &crt_state).unwrap();
+        save(&mut deps.storage, CRATE_META_KEY, &Metadata::default()).unwrap();
+
+        // the hash lists the crated ingredients in order
+        let attrs = vec![
+            Trait {
+                trait_type: "Ash".to_string(),
+                value: "2".to_string(),
+            },
+            Trait {
+                trait_type: "Bone".to_string(),
+                value: "1".to_string(),
+            },
+        ];
+        let expected = sha_256(b"Ash:2;Bone:1")
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect::<String>();
+        assert_eq!(content_hash(&attrs), expected);
+        let reversed = attrs.iter().rev().cloned().collect::<Vec<Trait>>();
+        assert_ne!(content_hash(&reversed), expected);
+
+        // minting records the crate contract and content hash under the crate id
+        let owner = "owner".to_string();
+        mint_crate(
+            &mut deps.storage,
+            &deps.api,
+            owner.clone(),
+            &ingredients,
+            &[2, 1, 0],
+            None,
+        )
+        .unwrap();
+        let mint_store = ReadonlyPrefixedStorage::new(&deps.storage, PREFIX_MINTED_CRATES);
+        let minted: StoredCrate = load(&mint_store, &7u128.to_le_bytes()).unwrap();
+        assert_eq!(minted.contract, crates_raw);
+        assert_eq!(minted.hash, expected);
+        let crt_state: CrateState = load(&deps.storage, CRATE_STATE_KEY).unwrap();
+        assert_eq!(crt_state.cnt, 8);
+        let err = mint_crate(
+            &mut deps.storage,
+            &deps.api,
+            owner.clone(),
+            &ingredients,
+            &[0, 0, 0],
+            None,
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("empty crate"));
+        let err = mint_crate(
+            &mut deps.storage,
+            &deps.api,
+            owner,
+            &ingredients,
+            &[1, 0, 0],
+            Some("retired"),
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("has been disabled"));
+
+        // the image draws an icon per crated ingredient in the smallest square grid
+        let mut art = StoredCrateArt::default();
+        assert!(crate_image(&art, &[1, 1, 1]).is_none());
+        art.icons = vec![Some("<a/>".to_string()), None, Some("<c/>".to_string())];
+        let image = crate_image(&art, &[1, 1, 0]).unwrap();
+        assert!(image.contains(r#"viewBox="0 -0.5 24 24""#));
+        assert!(image.contains(r#"<g transform="translate(0 0)"><a/></g>"#));
+        art.background = Some("<bg/>".to_string());
+        let image = crate_image(&art, &[1, 0, 5]).unwrap();
+        assert!(image.contains(r#"viewBox="0 -0.5 48 48""#));
+        assert!(image.contains(r#"<g transform="scale(2)"><bg/></g>"#));
+        assert!(image.contains(r#"<g transform="translate(24 0)"><c/></g>"#));
+        assert!(crate_image(&art, &[0, 1, 0]).is_some());
+    }
+
+    #[test]
+    fn test_strict_uncrate() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        let ingredients = vec!["Ash".to_string(), "Bone".to_string()];
+        save(&mut deps.storage, INGREDIENTS_KEY, &ingredients).unwrap();
+        save(&mut deps.storage, NOTIFY_SECRET_KEY, &vec![7u8; 32]).unwrap();
+        let crates_raw = deps.api.addr_canonicalize("crates").unwrap();
+        let entry = |address: &CanonicalAddr| StoredCrateContract {
+            name: "crates".to_string(),
+            contract: StoreContractInfo {
+                code_hash: "cratehash".to_string(),
+                address: address.clone(),
+            },
+            mint: true,
+            uncrate: true,
+        };
+        save(&mut deps.storage, CRATES_KEY, &vec![entry(&crates_raw)]).unwrap();
+        save(&mut deps.storage, DEFAULT_CRATE_KEY, &"crates".to_string()).unwrap();
+        let crt_state = CrateState {
+            halt: false,
+            cnt: 20,
+        };
+        save(&mut deps.storage, CRATE_STATE_KEY, &crt_state).unwrap();
+        save(&mut deps.storage, CRATE_META_KEY, &Metadata::default()).unwrap();
+        // crates 0 through 4 were minted before crates were recorded
+        let mut lgcy_store = PrefixedStorage::new(&mut deps.storage, PREFIX_LEGACY_CRATES);
+        save(&mut lgcy_store, crates_raw.as_slice(), &5u64).unwrap();
+        let hash = |contents: &[(&str, &str)]| {
+            let attrs = contents
+                .iter()
+                .map(|(t, v)| Trait {
+                    trait_type: t.to_string(),
+                    value: v.to_string(),
+                })
+                .collect::<Vec<Trait>>();
+            content_hash(&attrs)
+        };
+        let recorded = hash(&[("Ash", "2"), ("Bone", "1")]);
+        let record = |storage: &mut MockStorage| {
+            let mut mint_store = PrefixedStorage::new(storage, PREFIX_MINTED_CRATES);
+            let minted = StoredCrate {
+                contract: crates_raw.clone(),
+                hash: recorded.clone(),
+            };
+            save(&mut mint_store, &7u128.to_le_bytes(), &minted).unwrap();
+        };
+        record(&mut deps.storage);
+        let dossier = |id: &str, attrs: &[(&str, &str)]| {
+            let traits = attrs
+                .iter()
+                .map(|(t, v)| format!(r#"{{"trait_type":"{}","value":"{}"}}"#, t, v))
+                .collect::<Vec<String>>()
+                .join(",");
+            format!(
+                r#"{{"token_id":"{}","public_metadata":{{"extension":{{"attributes":[{}]}}}}}}"#,
+                id, traits
+            )
+        };
+        // answers the crate contract's dossier query and sends it the crates
+        let send = |deps: &mut OwnedDeps<MockStorage, MockApi, MockQuerier>,
+                    address: &CanonicalAddr,
+                    dossiers: Vec<String>,
+                    msg: Option<Binary>| {
+            let json = format!(
+                r#"{{"batch_nft_dossier":{{"nft_dossiers":[{}]}}}}"#,
+                dossiers.join(",")
+            );
+            let bin = Binary::from(json.as_bytes());
+            deps.querier
+                .update_wasm(move |_| SystemResult::Ok(ContractResult::Ok(bin.clone())));
+            let token_ids = vec!["any".to_string()];
+            uncrate(
+                deps.as_mut(),
+                &env,
+                "crates".to_string(),
+                entry(address),
+                "owner",
+                token_ids,
+                msg,
+            )
+        };
+        let inventory = |deps: &OwnedDeps<MockStorage, MockApi, MockQuerier>| {
+            let owner_raw = deps.api.addr_canonicalize("owner").unwrap();
+            let inv_store = ReadonlyPrefixedStorage::new(&deps.storage, PREFIX_USER_INGR_INVENTORY);
+            may_load::<Vec<u32>>(&inv_store, owner_raw.as_slice())
+                .unwrap()
+                .unwrap_or_default()
+        };
+        let not_minted =
+            |err: StdError| err.to_string().contains("was not minted by this contract");
+
+        // legacy crates are only accepted below the recorded count, and must hold ingredients
+        let crt = dossier("3", &[("Ash", "4"), ("Total Items", "4")]);
+        send(&mut deps, &crates_raw, vec![crt], None).unwrap();
+        assert_eq!(inventory(&deps), vec![4, 0]);
+        let crt = dossier("5", &[("Ash", "4")]);
+        assert!(not_minted(
+            send(&mut deps, &crates_raw, vec![crt], None).unwrap_err()
+        ));
+        let crt = dossier("2", &[("Total Items", "0")]);
+        let err = send(&mut deps, &crates_raw, vec![crt], None).unwrap_err();
+        assert!(err.to_string().contains("does not contain any ingredients"));
+        let crt = dossier("1", &[("Gold", "1")]);
+        let err = send(&mut deps, &crates_raw, vec![crt], None).unwrap_err();
+        assert!(err.to_string().contains("not a known ingredient"));
+        // quantities that would overflow are rejected instead of panicking
+        let max = u32::MAX.to_string();
+        let crts = vec![
+            dossier("1", &[("Ash", max.as_str())]),
+            dossier("2", &[("Ash", "1")]),
+        ];
+        let err = send(&mut deps, &crates_raw, crts, None).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("total Ash in the crates sent is too large"));
+        let crt = dossier("1", &[("Ash", max.as_str())]);
+        let err = send(&mut deps, &crates_raw, vec![crt], None).unwrap_err();
+        assert!(err.to_string().contains("would overflow your inventory"));
+        assert_eq!(inventory(&deps), vec![4, 0]);
+
+        // recorded crates must match the contract and content hash they were minted with
+        let tampered = hash(&[("Ash", "3"), ("Bone", "1")]);
+        let crt = dossier(
+            "40",
+            &[
+                ("Ash", "3"),
+                ("Bone", "1"),
+                ("Crate Id", "7"),
+                ("Content Hash", recorded.as_str()),
+            ],
+        );
+        assert!(not_minted(
+            send(&mut deps, &crates_raw, vec![crt], None).unwrap_err()
+        ));
+        let crt = dossier(
+            "40",
+            &[
+                ("Ash", "3"),
+                ("Bone", "1"),
+                ("Crate Id", "7"),
+                ("Content Hash", tampered.as_str()),
+            ],
+        );
+        assert!(not_minted(
+            send(&mut deps, &crates_raw, vec![crt], None).unwrap_err()
+        ));
+        let crt = dossier("40", &[("Ash", "2"), ("Bone", "1"), ("Crate Id", "7")]);
+        assert!(not_minted(
+            send(&mut deps, &crates_raw, vec![crt], None).unwrap_err()
+        ));
+        let minted = |id: &str| {
+            dossier(
+                id,
+                &[
+                    ("Ash", "2"),
+                    ("Bone", "1"),
+                    ("Crate Id", "7"),
+                    ("Content Hash", recorded.as_str()),
+                ],
+            )
+        };
+        let other_raw = deps.api.addr_canonicalize("othercrates").unwrap();
+        assert!(not_minted(
+            send(&mut deps, &other_raw, vec![minted("40")], None).unwrap_err()
+        ));
+
+        // only the requested quantities are uncrated
+        let partial = |qty: u32| {
+            let ingredients = vec![IngredientQty {
+                ingredient: "Ash".to_string(),
+                quantity: qty,
+            }];
+            Some(to_binary(&UncrateMsg { ingredients }).unwrap())
+        };
+        let err = send(&mut deps, &crates_raw, vec![minted("40")], partial(3)).unwrap_err();
+        assert!(err.to_string().contains("only contain 2 Ash"));
+        record(&mut deps.storage);
+        let resp = send(&mut deps, &crates_raw, vec![minted("40")], partial(1)).unwrap();
+        assert_eq!(inventory(&deps), vec![5, 0]);
+        // the burn, and the mint of the remainder
+        assert_eq!(resp.messages.len(), 2);
+        let mint_store = ReadonlyPrefixedStorage::new(&deps.storage, PREFIX_MINTED_CRATES);
+        let recrated: StoredCrate = load(&mint_store, &20u128.to_le_bytes()).unwrap();
+        assert_eq!(recrated.hash, hash(&[("Ash", "1"), ("Bone", "1")]));
+        // a crate can only be uncrated once
+        assert!(not_minted(
+            send(&mut deps, &crates_raw, vec![minted("40")], None).unwrap_err()
+        ));
+    }
+
+    #[test]
+    fn test_crate_registry() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        let admin = Addr::unchecked("admin");
+        let admin_raw = deps.api.addr_canonicalize("admin").unwrap();
+        save(&mut deps.storage, ADMINS_KEY, &vec![admin_raw]).unwrap();
+        let crates_raw = deps.api.addr_canonicalize("crates").unwrap();
+        let raw_crates = vec![StoredCrateContract {
+            name: "crates".to_string(),
+            contract: StoreContractInfo {
+                code_hash: "cratehash".to_string(),
+                address: crates_raw,
+            },
+            mint: true,
+            uncrate: true,
+        }];
+        save(&mut deps.storage, CRATES_KEY, &raw_crates).unwrap();
+        save(&mut deps.storage, DEFAULT_CRATE_KEY, &"crates".to_string()).unwrap();
+        let crt_state = CrateState {
+            halt: false,
+            cnt: 0,
+        };
+        save(&mut deps.storage, CRATE_STATE_KEY, &crt_state).unwrap();
+        let contract = |address: &str, code_hash: &str| {
+            Some(ContractInfo {
+                code_hash: code_hash.to_string(),
+                address: address.to_string(),
+            })
+        };
+        let registry = |deps: &OwnedDeps<MockStorage, MockApi, MockQuerier>| {
+            display_crates(&deps.storage, &deps.api)
+                .unwrap()
+                .into_iter()
+                .map(|c| (c.name, c.mint_enabled, c.uncrate_enabled, c.is_default))
+                .collect::<Vec<(String, bool, bool, bool)>>()
+        };
+
+        // only admins can change the registry, and new entries need a contract
+        let stranger = Addr::unchecked("stranger");
+        let cntrct = contract("themed", "themehash");
+        let name = "themed".to_string();
+        assert!(try_set_crate_contract(
+            deps.as_mut(),
+            env.clone(),
+            &stranger,
+            name.clone(),
+            cntrct.clone(),
+            None,
+            None,
+            None,
+            None,
+        )
+        .is_err());
+        let err = try_set_crate_contract(
+            deps.as_mut(),
+            env.clone(),
+            &admin,
+            name.clone(),
+            None,
+            None,
+            None,
+            None,
+            None,
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("A contract must be provided"));
+        // new entries are enabled by default and register with their contract
+        let resp = try_set_crate_contract(
+            deps.as_mut(),
+            env.clone(),
+            &admin,
+            name.clone(),
+            cntrct,
+            None,
+            None,
+            None,
+            None,
+        )
+        .unwrap();
+        assert_eq!(resp.messages.len(), 1);
+        assert_eq!(
+            registry(&deps),
+            vec![
+                ("crates".to_string(), true, true, true),
+                ("themed".to_string(), true, true, false),
+            ]
+        );
+        let err = try_set_crate_contract(
+            deps.as_mut(),
+            env.clone(),
+            &admin,
+            "copy".to_string(),
+            contract("themed", "themehash"),
+            None,
+            None,
+            None,
+            None,
+        )
+        .unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("already registered under a different name"));
+        // an entry's address is fixed, but its code hash can be updated
+        let err = try_set_crate_contract(
+            deps.as_mut(),
+            env.clone(),
+            &admin,
+            name.clone(),
+            contract("moved", "themehash"),
+            None,
+            None,
+            None,
+            None,
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("can not be changed"));
+        try_set_crate_contract(
+            deps.as_mut(),
+            env.clone(),
+            &admin,
+            name.clone(),
+            contract("themed", "newhash"),
+            None,
+            None,
+            None,
+            Some(12),
+        )
+        .unwrap();
+        let raw_crates: Vec<StoredCrateContract> = load(&deps.storage, CRATES_KEY).unwrap();
+        assert_eq!(raw_crates[1].contract.code_hash, "newhash");
+        // entries added after instantiation can accept crates minted before they were recorded
+        let legacy = display_crates(&deps.storage, &deps.api)
+            .unwrap()
+            .into_iter()
+            .map(|c| c.legacy_crates)
+            .collect::<Vec<u64>>();
+        assert_eq!(legacy, vec![0, 12]);
+
+        // the default entry must be able to mint, and can not be removed
+        let err = try_set_crate_contract(
+            deps.as_mut(),
+            env.clone(),
+            &admin,
+            "crates".to_string(),
+            None,
+            Some(false),
+            None,
+            None,
+            None,
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("must have minting enabled"));
+        let resp = try_set_crate_contract(
+            deps.as_mut(),
+            env.clone(),
+            &admin,
+            name.clone(),
+            None,
+            None,
+            Some(false),
+            Some(true),
+            None,
+        )
+        .unwrap();
+        assert!(resp.messages.is_empty());
+        try_set_crate_contract(
+            deps.as_mut(),
+            env.clone(),
+            &admin,
+            "crates".to_string(),
+            None,
+            Some(false),
+            None,
+            None,
+            None,
+        )
+        .unwrap();
+        assert_eq!(
+            registry(&deps),
+            vec![
+                ("crates".to_string(), false, true, false),
+                ("themed".to_string(), true, false, true),
+            ]
+        );
+        let err = try_remove_crate_contract(deps.as_mut(), &admin, "themed").unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("default crate contract can not be removed"));
+
+        // crates can only be uncrated from contracts in the registry that allow it
+        let ids = vec!["1".to_string()];
+        let themed = Addr::unchecked("themed");
+        let err = try_batch_receive(
+            deps.as_mut(),
+            env.clone(),
+            themed,
+            "owner",
+            ids.clone(),
+            None,
+        )
+        .unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("Uncrating themed crates has been disabled"));
+        try_remove_crate_contract(deps.as_mut(), &admin, "crates").unwrap();
+        let err = try_remove_crate_contract(deps.as_mut(), &admin, "crates").unwrap_err();
+        assert!(err.to_string().contains("not a registered crate contract"));
+        let crates = Addr::unchecked("crates");
+        let err = try_batch_receive(deps.as_mut(), env, crates, "owner", ids, None).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("only be called by crate or potion contracts"));
+    }
+
+    #[test]
+    fn test_grant_ingredients() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        let admin = Addr::unchecked("admin");
+        let admin_raw = deps.api.addr_canonicalize("admin").unwrap();
+        save(&mut deps.storage, ADMINS_KEY, &vec![admin_raw]).unwrap();
+        save(&mut deps.storage, NOTIFY_SECRET_KEY, &vec![7u8; 32]).unwrap();
+        let ingredients = vec!["Ash".to_string(), "Bone".to_string()];
+        save(&mut deps.storage, INGREDIENTS_KEY, &ingredients).unwrap();
+        let grant = |address: &str, qtys: Vec<(&str, u32)>| IngredientGrant {
+            address: address.to_string(),
+            ingredients: qtys
+                .into_iter()
+                .map(|(ingredient, quantity)| IngredientQty {
+                    ingredient: ingredient.to_string(),
+                    quantity,
+                })
+                .collect(),
+        };
+        let inventory = |deps: &OwnedDeps<MockStorage, MockApi, MockQuerier>, user: &str| {
+            let raw = deps.api.addr_canonicalize(user).unwrap();
+            let inv_store = ReadonlyPrefixedStorage::new(&deps.storage, PREFIX_USER_INGR_INVENTORY);
+            load::<Vec<u32>>(&inv_store, raw.as_slice()).unwrap()
+        };
+        let memo = "compensation".to_string();
+
+        // every change must be explained
+        let grants = vec![grant("alice", vec![("Ash", 3)])];
+        let err =
+            try_grant_ingredients(deps.as_mut(), &env, &admin, grants, " ".to_string(), false)
+                .unwrap_err();
+        assert!(err.to_string().contains("memo"));
+        let grants = vec![grant("alice", vec![("Gold", 3)])];
+        let err = try_grant_ingredients(deps.as_mut(), &env, &admin, grants, memo.clone(), false)
+            .unwrap_err();
+        assert!(err.to_string().contains("not a known ingredient"));
+        let grants = vec![
+            grant("alice", vec![("Ash", 3), ("Ash", 3)]),
+            grant("bob", vec![("Bone", 1)]),
+        ];
+        try_grant_ingredients(deps.as_mut(), &env, &admin, grants, memo.clone(), false).unwrap();
+        assert_eq!(inventory(&deps, "alice"), vec![6, 0]);
+        assert_eq!(inventory(&deps, "bob"), vec![0, 1]);
+        let alice = deps.api.addr_canonicalize("alice").unwrap();
+        assert_eq!(
+            channel_counter(&deps.storage, &alice, INGREDIENTS_CHANNEL).unwrap(),
+            1
+        );
+
+        // grants can not overflow a single grant or an inventory
+        let grants = vec![grant("alice", vec![("Ash", u32::MAX), ("Ash", 1)])];
+        let err = try_grant_ingredients(deps.as_mut(), &env, &admin, grants, memo.clone(), false)
+            .unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("The total Ash granted to alice is too large"));
+        let grants = vec![grant("alice", vec![("Ash", u32::MAX)])];
+        let err = try_grant_ingredients(deps.as_mut(), &env, &admin, grants, memo.clone(), false)
+            .unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("would overflow the inventory of alice"));
+
+        // revocations can not take more than a user has, and are not notified
+        let grants = vec![grant("alice", vec![("Ash", 7)])];
+        let err = try_grant_ingredients(deps.as_mut(), &env, &admin, grants, memo.clone(), true)
+            .unwrap_err();
+        assert!(err.to_string().contains("alice does not have 7 Ash"));
+        let grants = vec![grant("alice", vec![("Ash", 6)])];
+        try_grant_ingredients(deps.as_mut(), &env, &admin, grants, memo.clone(), true).unwrap();
+        assert_eq!(inventory(&deps, "alice"), vec![0, 0]);
+        assert_eq!(
+            channel_counter(&deps.storage, &alice, INGREDIENTS_CHANNEL).unwrap(),
+            1
+        );
+
+        // every successful change is in the audit log
+        let cnt: u64 = load(&deps.storage, ADMIN_LOG_CNT_KEY).unwrap();
+        assert_eq!(cnt, 2);
+        let log_store = ReadonlyPrefixedStorage::new(&deps.storage, PREFIX_ADMIN_LOG);
+        let entry: StoredAdminEntry = load(&log_store, &1u64.to_le_bytes()).unwrap();
+        assert!(entry.revoke);
+        assert_eq!(entry.memo, memo);
+        assert_eq!(entry.grants.len(), 1);
+        assert_eq!(entry.grants[0].user, alice);
+        assert_eq!(entry.grants[0].quantities, vec![6, 0]);
+    }
+
+    #[test]
+    fn test_cap_notifications() {
+        let mut rules = load_rules(&MockStorage::new()).unwrap();
+        // the first skull to fill up caps a per-skull cap
+        assert_eq!(cap_time(&[], 100, &rules, &[0, 50]), Some(400));
+        assert_eq!(cap_time(&[], 100, &rules, &[]), None);
+        // a per-user cap counts the charges of all skulls
+        rules.cap_per_user = true;
+        assert_eq!(cap_time(&[], 100, &rules, &[0, 50]), Some(250));
+
+        let mut storage = MockStorage::new();
+        save(&mut storage, NOTIFY_SECRET_KEY, &vec![7u8; 32]).unwrap();
+        let (a, b, c) = (
+            CanonicalAddr::from(vec![1]),
+            CanonicalAddr::from(vec![2]),
+            CanonicalAddr::from(vec![3]),
+        );
+        schedule_cap_notification(&mut storage, &a, Some(4000), 0).unwrap();
+        schedule_cap_notification(&mut storage, &b, Some(3700), 0).unwrap();
+        schedule_cap_notification(&mut storage, &c, Some(8000), 0).unwrap();
+        // already capped users are not scheduled
+        schedule_cap_notification(&mut storage, &c, Some(10), 20).unwrap();
+        schedule_cap_notification(&mut storage, &c, Some(8000), 20).unwrap();
+        assert!(due_cap_notifications(&mut storage, 3600)
+            .unwrap()
+            .is_empty());
+        assert_eq!(due_cap_notifications(&mut storage, 3800).unwrap().len(), 1);
+        assert_eq!(channel_counter(&storage, &b, CHARGES_CHANNEL).unwrap(), 1);
+        // unstaking cancels the notification
+        schedule_cap_notification(&mut storage, &a, None, 3900).unwrap();
+        let hours: Vec<u64> = load(&storage, CAP_HOURS_KEY).unwrap();
+        assert_eq!(hours, vec![2]);
+        assert_eq!(due_cap_notifications(&mut storage, 9000).unwrap().len(), 1);
+        assert_eq!(channel_counter(&storage, &a, CHARGES_CHANNEL).unwrap(), 0);
+        assert_eq!(channel_counter(&storage, &c, CHARGES_CHANNEL).unwrap(), 1);
+        let hours: Vec<u64> = load(&storage, CAP_HOURS_KEY).unwrap();
+        assert!(hours.is_empty());
+    }
+
+    #[test]
+    fn test_receive_query_key() {
+        let mut deps = mock_dependencies();
+        let server = deps.api.addr_canonicalize("server").unwrap();
+        let svg = StoreContractInfo {
+            code_hash: "serverhash".to_string(),
+            address: server.clone(),
+        };
+        save(&mut deps.storage, SVG_SERVER_KEY, &svg).unwrap();
+        save(&mut deps.storage, MY_VIEWING_KEY, &"own".to_string()).unwrap();
+        let status = |resp: Response| match from_binary(&resp.data.unwrap()).unwrap() {
+            ExecuteAnswer::ReceiveQueryKey { status } => status,
+            _ => panic!("unexpected answer"),
+        };
+        let key_in_use = |deps: &OwnedDeps<MockStorage, MockApi, MockQuerier>| {
+            server_viewer(&deps.storage, &server, "alchemy".to_string())
+                .unwrap()
+                .viewing_key
+        };
+
+        // keys from anyone but the configured server are ignored
+        let attacker = Addr::unchecked("attacker");
+        let resp = try_receive_query_key(deps.as_mut(), &attacker, "forged".to_string()).unwrap();
+        assert_eq!(status(resp), "ignored");
+        assert_eq!(key_in_use(&deps), "own");
+
+        // the server's key replaces this contract's own key
+        let sender = Addr::unchecked("server");
+        let resp = try_receive_query_key(deps.as_mut(), &sender, "issued".to_string()).unwrap();
+        assert_eq!(status(resp), "success");
+        assert_eq!(key_in_use(&deps), "issued");
+
+        // a rotated key replaces the previous one
+        try_receive_query_key(deps.as_mut(), &sender, "rotated".to_string()).unwrap();
+        assert_eq!(key_in_use(&deps), "rotated");
+        try_receive_query_key(deps.as_mut(), &attacker, "forged".to_string()).unwrap();
+        assert_eq!(key_in_use(&deps), "rotated");
+    }
+
+    #[test]
+    fn test_season_leaderboard_ids() {
+        let mut deps = mock_dependencies();
+        let mut env = mock_env();
+        env.block.time = Timestamp::from_seconds(40);
+        let admin = Addr::unchecked("admin");
+        let admin_raw = deps.api.addr_canonicalize("admin").unwrap();
+        save(&mut deps.storage, ADMINS_KEY, &vec![admin_raw]).unwrap();
+        let season = |id: Option<u16>, name: &str, start: u64, end: u64| StakingSeason {
+            id,
+            name: name.to_string(),
+            start,
+            end,
+            tables: Vec::new(),
+            charge_time_percent: None,
+        };
+        let board = |deps: &OwnedDeps<MockStorage, MockApi, MockQuerier>, id: u16| {
+            let metric = LeaderboardMetric::ChargesClaimed;
+            let bin = query_leaderboard(deps.as_ref(), metric, Some(id), None, None)?;
+            match from_binary(&bin).unwrap() {
+                QueryAnswer::Leaderboard { entries, .. } => Ok(entries
+                    .into_iter()
+                    .map(|e| (e.player, e.score))
+                    .collect::<Vec<(String, u64)>>()),
+                _ => panic!("unexpected answer"),
+            }
+        };
+        let seasons = vec![
+            season(None, "Spring", 100, 200),
+            season(None, "Summer", 300, 400),
+        ];
+        try_set_seasons(deps.as_mut(), &env, &admin, seasons).unwrap();
+        let user = deps.api.addr_canonicalize("player").unwrap();
+        let mut opt_store = PrefixedStorage::new(&mut deps.storage, PREFIX_LEADER_OPT);
+        let opt_in = StoredOptIn {
+            alias: Some("ace".to_string()),
+        };
+        save(&mut opt_store, user.as_slice(), &opt_in).unwrap();
+        add_scores(&mut deps.storage, &user, 150, &[2, 0, 0]).unwrap();
+        assert_eq!(board(&deps, 0).unwrap(), vec![("ace".to_string(), 2)]);
+
+        // renaming and rescheduling a season keeps its leaderboards
+        let seasons = vec![
+            season(Some(0), "Early Spring", 50, 200),
+            season(None, "Fall", 500, 600),
+        ];
+        try_set_seasons(deps.as_mut(), &env, &admin, seasons).unwrap();
+        assert_eq!(board(&deps, 0).unwrap(), vec![("ace".to_string(), 2)]);
+        let stored: Vec<StoredSeason> = load(&deps.storage, STAKING_SEASONS_KEY).unwrap();
+        let ids = stored.iter().map(|s| s.id).collect::<Vec<u16>>();
+        assert_eq!(ids, vec![0, 2]);
+        // the leaderboards of removed seasons can still be queried, but never reused
+        assert!(board(&deps, 1).unwrap().is_empty());
+        assert!(board(&deps, 3).is_err());
+        let seasons = vec![season(Some(1), "Summer", 300, 400)];
+        let err = try_set_seasons(deps.as_mut(), &env, &admin, seasons).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("not the id of a scheduled staking season"));
+        let seasons = vec![
+            season(Some(2), "Fall", 500, 600),
+            season(Some(2), "Winter", 700, 800),
+        ];
+        let err = try_set_seasons(deps.as_mut(), &env, &admin, seasons).unwrap_err();
+        assert!(err.to_string().contains("has been duplicated"));
+    }
+
+    #[test]
+    fn test_started_seasons() {
+        let mut deps = mock_dependencies();
+        let mut env = mock_env();
+        env.block.time = Timestamp::from_seconds(50);
+        let admin = Addr::unchecked("admin");
+        let admin_raw = deps.api.addr_canonicalize("admin").unwrap();
+        save(&mut deps.storage, ADMINS_KEY, &vec![admin_raw]).unwrap();
+        let season = |id: Option<u16>, name: &str, start: u64, end: u64| StakingSeason {
+            id,
+            name: name.to_string(),
+            start,
+            end,
+            tables: Vec::new(),
+            charge_time_percent: None,
+        };
+        let seasons = vec![
+            season(None, "Spring", 100, 200),
+            season(None, "Summer", 300, 400),
+        ];
+        try_set_seasons(deps.as_mut(), &env, &admin, seasons).unwrap();
+        // seasons can not be scheduled to start in the past
+        let seasons = vec![season(Some(0), "Spring", 50, 200)];
+        let err = try_set_seasons(deps.as_mut(), &env, &admin, seasons).unwrap_err();
+        assert!(err.to_string().contains("Spring must start in the future"));
+
+        // once a season starts, only its end can be extended while it is in progress
+        env.block.time = Timestamp::from_seconds(150);
+        let rejected = |deps: &mut OwnedDeps<MockStorage, MockApi, MockQuerier>,
+                        env: &Env,
+                        seasons: Vec<StakingSeason>,
+                        msg: &str| {
+            let err = try_set_seasons(deps.as_mut(), env, &admin, seasons).unwrap_err();
+            assert!(err.to_string().contains(msg), "{}", err);
+        };
+        let started = "Spring has already started";
+        rejected(
+            &mut deps,
+            &env,
+            vec![season(Some(1), "Summer", 300, 400)],
+            started,
+        );
+        rejected(
+            &mut deps,
+            &env,
+            vec![season(Some(0), "Spring", 120, 200)],
+            started,
+        );
+        rejected(
+            &mut deps,
+            &env,
+            vec![season(Some(0), "Spring", 100, 180)],
+            started,
+        );
+        rejected(
+            &mut deps,
+            &env,
+            vec![season(Some(0), "Early", 100, 200)],
+            started,
+        );
+        let mut faster = season(Some(0), "Spring", 100, 200);
+        faster.charge_time_percent = Some(50);
+        rejected(&mut deps, &env, vec![faster], started);
+        // a new season in place of the one in progress is still in the past
+        rejected(
+            &mut deps,
+            &env,
+            vec![season(None, "Spring", 100, 200)],
+            "Spring must start in the future",
+        );
+        let seasons = vec![
+            season(Some(0), "Spring", 100, 250),
+            season(Some(1), "Summer", 300, 400),
+        ];
+        try_set_seasons(deps.as_mut(), &env, &admin, seasons).unwrap();
+        // an unstarted season can still be removed
+        let seasons = vec![season(Some(0), "Spring", 100, 250)];
+        try_set_seasons(deps.as_mut(), &env, &admin, seasons).unwrap();
+
+        // an ended season can not be changed at all
+        env.block.time = Timestamp::from_seconds(260);
+        let ended = vec![season(Some(0), "Spring", 100, 300)];
+        rejected(&mut deps, &env, ended, started);
+        let seasons = vec![
+            season(Some(0), "Spring", 100, 250),
+            season(None, "Fall", 500, 600),
+        ];
+        try_set_seasons(deps.as_mut(), &env, &admin, seasons).unwrap();
+        let stored: Vec<StoredSeason> = load(&deps.storage, STAKING_SEASONS_KEY).unwrap();
+        assert_eq!(active_season(&stored, 150), Some(0));
+        assert_eq!(active_season(&stored, 550), Some(1));
+        assert_eq!(active_season(&stored, 400), None);
+    }
+}
@@ -5,7 +5,9 @@
 pub mod contract;
 mod contract_info;
 pub mod msg;
+mod notify;
 mod server_msgs;
+mod snip20;
 mod snip721;
 pub mod state;
 mod storage;
@@ -1,6 +1,6 @@
 use crate::contract_info::ContractInfo;
 use crate::snip721::Metadata;
-use cosmwasm_std::{Addr, Binary, Uint128};
+use cosmwasm_std::{Addr, Binary, Decimal, Uint128};
 use schemars::JsonSchema;
 use secret_toolkit::permit::Permit;
 use serde::{Deserialize, Serialize};
@@ -16,25 +16,66 @@ pub struct InstantiateMsg {
     pub svg_server: ContractInfo,
     /// code hash and address of the skulls contract
     pub skulls_contract: ContractInfo,
-    /// code hash and address of a crate contract
+    /// code hash and address of a crate contract.  It is added to the crate contract registry
+    /// as the default entry, named "default"
     pub crate_contract: ContractInfo,
+    /// optional number of crates the crate contract minted before crates were recorded.  Crate
+    /// nfts without a Crate Id and Content Hash are only accepted if their token id (assigned
+    /// sequentially by the crate contract) is below this count
+    pub legacy_crates: Option<u64>,
     /// number of seconds to earn a staking charge (604800 for prod)
     pub charge_time: u64,
+    /// optional staking rules.  Defaults to staking up to 5 skulls, capping charges at 4 per
+    /// skull, and a 1 charge first-stake bonus
+    pub staking_rules: Option<StakingRules>,
 }
 
 /// Handle messages
 #[derive(Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum ExecuteMsg {
-    /// claim staking rewards
-    ClaimStake {},
+    /// claim staking rewards.  SNIP-20 rewards that previous claims could not pay can be claimed
+    /// even if no staked skulls have charges
+    ClaimStake {
+        /// optional address of the skull owner if claiming as their operator
+        owner: Option<String>,
+    },
     /// set the staking list
     SetStake {
-        /// list of skull token ids to stake (up to 5)
+        /// list of skull token ids to stake (limited by the staking rules)
         token_ids: Vec<String>,
+        /// optional list of additional skulls owned but not being staked, used to qualify for a
+        /// higher staking tier
+        held_token_ids: Option<Vec<String>>,
+        /// optional address of the skull owner if staking as their operator
+        owner: Option<String>,
+    },
+    /// authorize an address to claim and/or stake on the sender's behalf.  This replaces any
+    /// existing authorization for the same operator
+    SetStakingOperator {
+        /// address of the operator
+        operator: String,
+        /// actions the operator is allowed to perform
+        scope: OperatorScope,
+        /// optional time in seconds since epoch when the authorization expires
+        expiration: Option<u64>,
+        /// optional address whose inventory should receive rewards claimed by this operator.
+        /// Defaults to the owner's inventory
+        recipient: Option<String>,
+    },
+    /// revoke an operator's authorization
+    RevokeStakingOperator {
+        /// address of the operator
+        operator: String,
     },
     /// remove ingredients from a user's inventory to mint an nft containing them
-    CrateIngredients { ingredients: Vec<IngredientQty> },
+    CrateIngredients {
+        /// ingredients to crate
+        ingredients: Vec<IngredientQty>,
+        /// optional name of the crate contract registry entry to mint with.  Defaults to the
+        /// default crate contract
+        crate_contract: Option<String>,
+    },
     /// Create a viewing key
     CreateViewingKey { entropy: String },
     /// Set a viewing key
@@ -59,8 +100,40 @@ pub enum ExecuteMsg {
     AddIngredients { ingredients: Vec<String> },
     /// create named sets of ingredients for staking tables
     DefineIngredientSets { sets: Vec<IngredientSet> },
-    /// create staking tables for specified skull materials
+    /// create staking tables for specified skull materials.  A table is rejected if applying
+    /// every staking boost to it would overflow an ingredient set weight
     SetStakingTables { tables: Vec<StakingTable> },
+    /// set the staking boosts granted by skull traits.  This replaces any existing boosts
+    SetStakingBoosts { boosts: Vec<StakingBoost> },
+    /// set the pity thresholds of the staking tables for specified skull materials.  This
+    /// replaces any existing thresholds for those materials
+    SetPityThresholds { tables: Vec<PityTable> },
+    /// set the schedule of staking seasons.  This replaces any existing seasons, but seasons
+    /// that have started must be included unchanged, except that the season in progress may
+    /// have its end extended
+    SetStakingSeasons { seasons: Vec<StakingSeason> },
+    /// set the staking rules.  This also refreshes the skull type info from the svg server
+    SetStakingRules { rules: StakingRules },
+    /// add or update a SNIP-20 staking reward stream.  Updating an existing stream keeps its
+    /// reserve and the amount already distributed
+    SetRewardStream { stream: RewardStream },
+    /// remove a SNIP-20 staking reward stream and send its remaining reserve to the recipient.
+    /// Any rewards it owes users are forfeited
+    RemoveRewardStream {
+        /// address of the stream's SNIP-20 token
+        token: String,
+        /// optional address to receive the reserve.  Defaults to the sender
+        recipient: Option<String>,
+    },
+    /// withdraw tokens from the reserve of a SNIP-20 staking reward stream
+    WithdrawRewardReserve {
+        /// address of the stream's SNIP-20 token
+        token: String,
+        /// amount of tokens to withdraw
+        amount: Uint128,
+        /// optional address to receive the tokens.  Defaults to the sender
+        recipient: Option<String>,
+    },
     /// set halt status for staking, crating, and/or alchemy
     SetHaltStatus {
         /// optionally set staking halt status
@@ -81,18 +154,71 @@ pub enum ExecuteMsg {
         svg_server: Option<ContractInfo>,
         /// optional code hash and address of the skulls contract
         skulls_contract: Option<ContractInfo>,
-        /// optional crating contract (can either update the code hash of an existing one or add a new one)
-        crate_contract: Option<ContractInfo>,
+    },
+    /// add or update an entry of the crate contract registry
+    SetCrateContract {
+        /// name of the registry entry
+        name: String,
+        /// optional code hash and address of the crate contract.  Required when adding a new
+        /// entry.  An existing entry's address can not be changed, only its code hash
+        contract: Option<ContractInfo>,
+        /// optionally true if crates can be minted with this contract.  Defaults to true for
+        /// new entries.  Minting can not be disabled for the default entry
+        mint_enabled: Option<bool>,
+        /// optionally true if this contract's crates can be uncrated.  Defaults to true for
+        /// new entries
+        uncrate_enabled: Option<bool>,
+        /// optionally true if this entry should become the default crate contract.  The entry
+        /// must have minting enabled
+        make_default: Option<bool>,
+        /// optional number of crates the crate contract minted before crates were recorded.
+        /// Crate nfts without a Crate Id and Content Hash are only accepted if their token id is
+        /// below this count
+        legacy_crates: Option<u64>,
+    },
+    /// remove an entry from the crate contract registry.  The default entry can not be removed
+    RemoveCrateContract { name: String },
+    /// join or leave the public leaderboards
+    SetLeaderboardOptIn {
+        /// true if the sender should appear on the leaderboards
+        opt_in: bool,
+        /// optional alias displayed instead of the sender's address.  Aliases are unique and
+        /// may be up to 32 characters
+        alias: Option<String>,
+    },
+    /// add ingredients to the inventories of the specified addresses
+    GrantIngredients {
+        /// ingredients to add to each address' inventory
+        grants: Vec<IngredientGrant>,
+        /// reason for the grant recorded in the admin audit log
+        memo: String,
+    },
+    /// remove ingredients from the inventories of the specified addresses
+    RevokeIngredients {
+        /// ingredients to remove from each address' inventory
+        revocations: Vec<IngredientGrant>,
+        /// reason for the revocation recorded in the admin audit log
+        memo: String,
     },
     /// set the crate nft base metadata
     SetCrateMetadata { public_metadata: Metadata },
+    /// set the svg fragments used to generate crate images
+    SetCrateArt {
+        /// optional svg fragment drawn behind the ingredient icons in 24x24 coordinates.  An
+        /// empty string removes the background
+        background: Option<String>,
+        /// icons to add or replace, drawn in 24x24 coordinates.  An empty svg removes the
+        /// icon of that ingredient
+        icons: Vec<IngredientIcon>,
+    },
     /// BatchReceiveNft is called when this contract is sent an NFT (potion or crate)
     BatchReceiveNft {
         /// address of the previous owner of the token being sent
         from: String,
         /// list of tokens sent
         token_ids: Vec<String>,
-        /// base64 encoded msg to specify the skull the potion should be applied to (if applicable)
+        /// base64 encoded msg to specify the skull the potion should be applied to (if applicable),
+        /// or a base64 encoded UncrateMsg to only uncrate some of the crated ingredients
         msg: Option<Binary>,
     },
     /// ReceiveNft is only included to maintatin CW721 compliance.  Hopefully everyone uses the
@@ -106,6 +232,27 @@ pub enum ExecuteMsg {
         /// base64 encoded msg to specify the skull the potion should be applied to (if applicable)
         msg: Option<Binary>,
     },
+    /// Receive is called when this contract is sent SNIP-20 tokens.  Only admins may send reward
+    /// tokens, which fund the reserve of that token's reward stream
+    Receive {
+        /// address that sent the tokens
+        sender: String,
+        /// address of the previous owner of the tokens
+        from: String,
+        /// amount of tokens sent
+        amount: Uint128,
+        /// optional memo
+        memo: Option<String>,
+        /// optional msg
+        msg: Option<Binary>,
+    },
+    /// receive a viewing key issued by a server that has this contract on its viewer allowlist.
+    /// The key is only ever used when querying the server that sent it, and keys sent by
+    /// anything other than the svg server are ignored
+    ReceiveQueryKey {
+        /// the viewing key
+        key: String,
+    },
     /// disallow the use of a permit
     RevokePermit {
         /// name of the permit that is no longer valid
@@ -136,6 +283,34 @@ pub enum ExecuteAnswer {
     },
     /// response from creating staking tables for specified skull materials
     SetStakingTables { status: String },
+    /// response from setting the staking boosts
+    SetStakingBoosts { boosts: Vec<StakingBoost> },
+    /// response from setting pity thresholds
+    SetPityThresholds { status: String },
+    /// response from setting the staking rules
+    SetStakingRules { rules: StakingRules },
+    /// response from adding or updating a reward stream
+    SetRewardStream { stream: StreamInfo },
+    /// response from funding a reward stream
+    FundRewardStream {
+        /// the stream's reserve after funding
+        reserve: Uint128,
+    },
+    /// response from removing a reward stream
+    RemoveRewardStream {
+        /// amount of the reserve that was withdrawn
+        withdrawn: Uint128,
+    },
+    /// response from withdrawing from a reward stream's reserve
+    WithdrawRewardReserve {
+        /// the stream's reserve after withdrawing
+        reserve: Uint128,
+    },
+    /// response from setting the staking seasons
+    SetStakingSeasons {
+        /// number of staking seasons
+        count: u16,
+    },
     /// response from setting halt status for staking, crating, and/or alchemy
     SetHaltStatus {
         /// true if staking is halted
@@ -147,6 +322,8 @@ pub enum ExecuteAnswer {
     },
     /// response from setting the crate nft base metadata
     SetCrateMetadata { public_metadata: Metadata },
+    /// response from setting the crate art
+    SetCrateArt { status: String },
     /// response from removing ingredients from a user's inventory to mint an nft containing them
     CrateIngredients {
         updated_inventory: Vec<IngredientQty>,
@@ -157,6 +334,11 @@ pub enum ExecuteAnswer {
         charge_infos: Vec<ChargeInfo>,
         /// ingredients rewarded in this tx
         rewards: Vec<IngredientQty>,
+        /// SNIP-20 tokens rewarded in this tx.  Rewards a stream's reserve could not cover are
+        /// paid on a later claim
+        token_rewards: Vec<TokenReward>,
+        /// ingredient set wins that were guaranteed by pity thresholds in this tx
+        pity_drops: Vec<PityDrop>,
     },
     /// response from setting charging time for staking
     SetChargeTime {
@@ -169,9 +351,33 @@ pub enum ExecuteAnswer {
         svg_server: ContractInfo,
         /// code hash and address of the skulls contract
         skulls_contract: ContractInfo,
-        /// crate contracts
-        crate_contracts: Vec<ContractInfo>,
+        /// crate contract registry
+        crate_contracts: Vec<CrateContractInfo>,
+    },
+    /// response from joining or leaving the leaderboards
+    SetLeaderboardOptIn {
+        /// true if the sender appears on the leaderboards
+        opted_in: bool,
+        /// alias displayed instead of the sender's address
+        alias: Option<String>,
+    },
+    /// response from granting ingredients
+    GrantIngredients { status: String },
+    /// response from revoking ingredients
+    RevokeIngredients { status: String },
+    /// response from setting or removing a crate contract registry entry
+    CrateContracts {
+        /// crate contract registry
+        crate_contracts: Vec<CrateContractInfo>,
     },
+    /// response from setting or revoking a staking operator
+    StakingOperators {
+        /// the sender's current operators
+        operators: Vec<OperatorInfo>,
+    },
+    /// response from receiving a server issued viewing key.  The status is "ignored" if the
+    /// sender is not the svg server
+    ReceiveQueryKey { status: String },
     /// response from revoking a permit
     RevokePermit { status: String },
 }
@@ -219,6 +425,62 @@ pub enum QueryMsg {
         /// are provided, the viewer will be ignored
         permit: Option<Permit>,
     },
+    /// lists the operators authorized to claim and/or stake on the user's behalf
+    StakingOperators {
+        /// optional address and viewing key of a user
+        viewer: Option<ViewerInfo>,
+        /// optional permit used to verify user identity.  If both viewer and permit
+        /// are provided, the viewer will be ignored
+        permit: Option<Permit>,
+    },
+    /// displays the user's staking, claiming, crating, and uncrating history (most recent first)
+    MyHistory {
+        /// optional address and viewing key of a user
+        viewer: Option<ViewerInfo>,
+        /// optional permit used to verify user identity.  If both viewer and permit
+        /// are provided, the viewer will be ignored
+        permit: Option<Permit>,
+        /// optional page number to display.  Defaults to 0 (first page) if not provided
+        page: Option<u32>,
+        /// optional limit to the number of events to show.  Defaults to 30 if not specified
+        page_size: Option<u32>,
+    },
+    /// displays the staking and claiming history of a skull (most recent first).  Only viewable
+    /// by the skull's current owner or an admin.  Owners only see the events they performed or
+    /// received rewards from
+    TokenHistory {
+        /// optional address and viewing key of the owner or an admin
+        viewer: Option<ViewerInfo>,
+        /// optional permit used to verify identity.  If both viewer and permit
+        /// are provided, the viewer will be ignored
+        permit: Option<Permit>,
+        /// token id of the skull
+        token_id: String,
+        /// optional page number to display.  Defaults to 0 (first page) if not provided
+        page: Option<u32>,
+        /// optional limit to the number of events to show.  Defaults to 30 if not specified
+        page_size: Option<u32>,
+    },
+    /// displays a page of the admin audit log of ingredient grants and revocations
+    AdminLog {
+        /// optional address and viewing key of an admin
+        viewer: Option<ViewerInfo>,
+        /// optional permit used to verify admin identity.  If both viewer and permit
+        /// are provided, the viewer will be ignored
+        permit: Option<Permit>,
+        /// optional page to display.  Defaults to 0
+        page: Option<u32>,
+        /// optional number of entries per page.  Defaults to 30
+        page_size: Option<u32>,
+    },
+    /// displays the aggregate totals of all recorded history
+    HistoryTotals {
+        /// optional address and viewing key of an admin
+        viewer: Option<ViewerInfo>,
+        /// optional permit used to verify admin identity.  If both viewer and permit
+        /// are provided, the viewer will be ignored
+        permit: Option<Permit>,
+    },
     /// displays if the user is eligible for a first time staking bonus
     UserEligibleForBonus {
         /// optional address and viewing key of a user
@@ -270,6 +532,73 @@ pub enum QueryMsg {
         by_name: Option<String>,
         /// optionally display by the material index
         by_index: Option<u8>,
+        /// optionally display the table used during the named staking season
+        season: Option<String>,
+    },
+    /// displays the current and upcoming staking seasons
+    StakingSeasons {},
+    /// displays the staking rules
+    StakingRules {},
+    /// displays the SNIP-20 staking reward streams and their reserves
+    RewardStreams {},
+    /// displays the svg fragments used to generate crate images
+    CrateArt {},
+    /// lists the notification channels.  The "ingredients" channel is notified when ingredients
+    /// are credited, and the "charges" channel when staked skulls reach their charge cap.  Cap
+    /// notifications are scheduled when the user stakes or claims, and are delivered by the
+    /// first staking transaction of any user after the cap is reached
+    ListChannels {},
+    /// displays the notification seed and counter of the querier for the specified channels
+    ChannelInfo {
+        /// names of the channels
+        channels: Vec<String>,
+        /// optional address and viewing key of a user
+        viewer: Option<ViewerInfo>,
+        /// optional permit used to verify user identity.  If both viewer and permit
+        /// are provided, the viewer will be ignored
+        permit: Option<Permit>,
+    },
+    /// displays a page of a leaderboard.  All opted-in players with a score are ranked
+    Leaderboard {
+        /// the ranked activity
+        metric: LeaderboardMetric,
+        /// optional id of a staking season.  Defaults to the all-time leaderboard
+        season_id: Option<u16>,
+        /// optional page to display.  Defaults to 0
+        page: Option<u32>,
+        /// optional number of entries per page.  Defaults to 30
+        page_size: Option<u32>,
+    },
+    /// displays the pity thresholds of all staking tables
+    PityThresholds {
+        /// optional address and viewing key of an admin
+        viewer: Option<ViewerInfo>,
+        /// optional permit used to verify admin identity.  If both viewer and permit
+        /// are provided, the viewer will be ignored
+        permit: Option<Permit>,
+    },
+    /// displays the staking boosts granted by skull traits
+    StakingBoosts {
+        /// optional address and viewing key of an admin
+        viewer: Option<ViewerInfo>,
+        /// optional permit used to verify admin identity.  If both viewer and permit
+        /// are provided, the viewer will be ignored
+        permit: Option<Permit>,
+    },
+    /// displays the expected quantity of each ingredient that would be rewarded for claiming
+    /// the specified number of charges
+    ExpectedRewards {
+        /// optional address and viewing key of a user
+        viewer: Option<ViewerInfo>,
+        /// optional permit used to verify user identity.  If both viewer and permit
+        /// are provided, the viewer will be ignored
+        permit: Option<Permit>,
+        /// optional list of skull token ids owned by the user to evaluate
+        token_ids: Option<Vec<String>>,
+        /// optional list of skull materials and quantities to evaluate (admin only)
+        materials: Option<Vec<MaterialQty>>,
+        /// number of charges each skull would be claiming (capped by the staking rules)
+        charges: u8,
     },
 }
 
@@ -309,8 +638,8 @@ pub enum QueryAnswer {
         svg_server: ContractInfo,
         /// code hash and address of the skulls contract
         skulls_contract: ContractInfo,
-        /// crate contracts
-        crate_contracts: Vec<ContractInfo>,
+        /// crate contract registry
+        crate_contracts: Vec<CrateContractInfo>,
     },
     /// displays the ingredients
     Ingredients { ingredients: Vec<String> },
@@ -323,6 +652,11 @@ pub enum QueryAnswer {
         charge_infos: Vec<ChargeInfo>,
         /// user's ingredient inventory
         inventory: Vec<IngredientQty>,
+        /// SNIP-20 tokens that would be rewarded for claiming now, including unpaid rewards from
+        /// previous claims
+        pending_token_rewards: Vec<TokenReward>,
+        /// progress towards guaranteed ingredient set wins
+        pity_progress: Vec<PityProgress>,
         /// true if staking is halted (so getting empty arrays for charges)
         staking_is_halted: bool,
     },
@@ -339,6 +673,84 @@ pub enum QueryAnswer {
     IngredientSets { ingredient_sets: Vec<IngredientSet> },
     /// displays the staking table for a specified skull material
     StakingTable { staking_table: StakingTable },
+    /// displays the staking boosts granted by skull traits
+    StakingBoosts { boosts: Vec<StakingBoost> },
+    /// displays the pity thresholds of all staking tables
+    PityThresholds { tables: Vec<PityTable> },
+    /// lists the operators authorized to claim and/or stake on the user's behalf
+    StakingOperators { operators: Vec<OperatorInfo> },
+    /// displays the staking rules
+    StakingRules { rules: StakingRules },
+    /// displays the SNIP-20 staking reward streams and their reserves
+    RewardStreams { streams: Vec<StreamInfo> },
+    /// lists the notification channels
+    ListChannels { channels: Vec<String> },
+    /// displays the notification info of the querier for the requested channels
+    ChannelInfo { channels: Vec<ChannelInfoData> },
+    /// displays a page of a leaderboard
+    Leaderboard {
+        /// total number of ranked players
+        count: u32,
+        /// ranked players in this page
+        entries: Vec<LeaderboardEntry>,
+    },
+    /// displays the svg fragments used to generate crate images
+    CrateArt {
+        /// svg fragment drawn behind the ingredient icons
+        background: Option<String>,
+        /// ingredient icons
+        icons: Vec<IngredientIcon>,
+    },
+    /// displays a page of a user's or skull's history
+    History {
+        /// total number of events in the history viewable by the querier
+        count: u32,
+        /// events in this page (most recent first)
+        events: Vec<HistoryEvent>,
+    },
+    /// displays a page of the admin audit log
+    AdminLog {
+        /// total number of entries in the log
+        count: u64,
+        /// entries in this page (most recent first)
+        entries: Vec<AdminLogEntry>,
+    },
+    /// displays the aggregate totals of all recorded history
+    HistoryTotals {
+        /// number of times a staking list was set
+        stake_changes: u64,
+        /// number of claims
+        claims: u64,
+        /// number of charges claimed
+        charges_claimed: u64,
+        /// number of first-stake bonuses given out
+        first_stake_bonuses: u64,
+        /// number of crates minted
+        crates: u64,
+        /// number of uncrating transactions
+        uncrates: u64,
+        /// total ingredients rewarded from claims and first-stake bonuses
+        rewarded: Vec<IngredientTotal>,
+        /// total ingredients crated
+        crated: Vec<IngredientTotal>,
+        /// total ingredients uncrated
+        uncrated: Vec<IngredientTotal>,
+    },
+    /// displays the current and upcoming staking seasons
+    StakingSeasons {
+        /// the staking season currently in progress
+        current: Option<SeasonInfo>,
+        /// staking seasons that have not started yet
+        upcoming: Vec<SeasonInfo>,
+    },
+    /// displays the expected quantity of each ingredient that would be rewarded for claiming
+    /// the specified number of charges
+    ExpectedRewards {
+        /// number of charges per skull used in the calculation
+        charges: u8,
+        /// expected quantities of each ingredient that could be rewarded
+        expected_rewards: Vec<ExpectedQty>,
+    },
 }
 
 /// the address and viewing key making an authenticated query request
@@ -377,6 +789,90 @@ pub struct StakingTable {
     pub ingredient_set_weights: Vec<IngrSetWeight>,
 }
 
+/// an ingredient set and its chance of being won
+#[derive(Serialize, Deserialize, JsonSchema, Clone, PartialEq, Eq, Debug)]
+pub struct IngrSetChance {
+    /// name of the set
+    pub ingredient_set: String,
+    /// chance out of 10000
+    pub chance: u16,
+}
+
+/// staking boost given to skulls whose current image has a specific layer
+#[derive(Serialize, Deserialize, JsonSchema, Clone, PartialEq, Eq, Debug)]
+pub struct StakingBoost {
+    /// name of the boost
+    pub name: String,
+    /// the layer that grants this boost
+    pub layer: LayerId,
+    /// number of additional rolls for each charge
+    pub extra_rolls: u8,
+    /// weights added to the staking table of the skull's material
+    pub ingredient_set_weights: Vec<IngrSetWeight>,
+    /// optional chance each charge has to win an additional ingredient from a set
+    pub bonus_set: Option<IngrSetChance>,
+}
+
+/// a staking season that temporarily overrides staking tables and/or the charge time
+#[derive(Serialize, Deserialize, JsonSchema, Clone, PartialEq, Eq, Debug)]
+pub struct StakingSeason {
+    /// optional id of a scheduled season this replaces.  Omit it to add a new season.  A
+    /// season's leaderboards are kept by id, so they survive renaming or rescheduling it
+    pub id: Option<u16>,
+    /// name of the season
+    pub name: String,
+    /// start time of the season in seconds since epoch (inclusive)
+    pub start: u64,
+    /// end time of the season in seconds since epoch (exclusive)
+    pub end: u64,
+    /// staking tables used instead of the normal tables for the specified materials by charges
+    /// that mature during this season
+    pub tables: Vec<StakingTable>,
+    /// optional percentage of the normal charge time needed to earn a charge that starts
+    /// during this season.  Defaults to 100
+    pub charge_time_percent: Option<u16>,
+}
+
+/// displayable info about a staking season
+#[derive(Serialize, Deserialize, JsonSchema, Clone, PartialEq, Eq, Debug)]
+pub struct SeasonInfo {
+    /// id of the season, used to query its leaderboards
+    pub id: u16,
+    /// name of the season
+    pub name: String,
+    /// start time of the season in seconds since epoch (inclusive)
+    pub start: u64,
+    /// end time of the season in seconds since epoch (exclusive)
+    pub end: u64,
+    /// number of seconds to earn a charge during this season
+    pub charge_time: u64,
+    /// skull materials whose staking tables are changed during this season
+    pub materials: Vec<String>,
+}
+
+/// actions a staking operator is allowed to perform
+#[derive(Serialize, Deserialize, JsonSchema, Clone, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum OperatorScope {
+    /// only claim staking rewards
+    Claim,
+    /// claim staking rewards and set the staking list
+    ClaimAndStake,
+}
+
+/// an operator authorized to claim and/or stake on an owner's behalf
+#[derive(Serialize, Deserialize, JsonSchema, Clone, PartialEq, Eq, Debug)]
+pub struct OperatorInfo {
+    /// address of the operator
+    pub operator: Addr,
+    /// actions the operator is allowed to perform
+    pub scope: OperatorScope,
+    /// optional time in seconds since epoch when the authorization expires
+    pub expiration: Option<u64>,
+    /// optional address whose inventory receives rewards claimed by this operator
+    pub recipient: Option<Addr>,
+}
+
 /// a skull's token id and info about its accrued charges
 #[derive(Serialize, Deserialize, JsonSchema, Clone, PartialEq, Eq, Debug)]
 pub struct ChargeInfo {
@@ -386,6 +882,8 @@ pub struct ChargeInfo {
     pub charge_start: u64,
     /// whole number of charges accrued since charge_start (game cap at 4)
     pub charges: u8,
+    /// names of the staking boosts this skull receives
+    pub boosts: Vec<String>,
 }
 
 /// an ingredient and its quantity
@@ -397,17 +895,316 @@ pub struct IngredientQty {
     pub quantity: u32,
 }
 
+/// an entry of the crate contract registry
+#[derive(Serialize, Deserialize, JsonSchema, Clone, PartialEq, Eq, Debug)]
+pub struct CrateContractInfo {
+    /// name of the entry
+    pub name: String,
+    /// code hash and address of the crate contract
+    pub contract: ContractInfo,
+    /// true if crates can be minted with this contract
+    pub mint_enabled: bool,
+    /// true if this contract's crates can be uncrated
+    pub uncrate_enabled: bool,
+    /// true if this is the default crate contract
+    pub is_default: bool,
+    /// number of crates the crate contract minted before crates were recorded
+    pub legacy_crates: u64,
+}
+
+/// msg that can be sent along with crate nfts to only uncrate some of their ingredients.  The
+/// remaining ingredients will be re-crated into a new crate nft
+#[derive(Serialize, Deserialize, JsonSchema, Clone, PartialEq, Eq, Debug)]
+pub struct UncrateMsg {
+    /// quantities of ingredients to add to the inventory
+    pub ingredients: Vec<IngredientQty>,
+}
+
+/// an ingredient and the svg fragment used to draw it on crate images
+#[derive(Serialize, Deserialize, JsonSchema, Clone, PartialEq, Eq, Debug)]
+pub struct IngredientIcon {
+    /// name of the ingredient
+    pub ingredient: String,
+    /// svg fragment of the icon
+    pub svg: String,
+}
+
+/// an ingredient and its aggregate quantity
+#[derive(Serialize, Deserialize, JsonSchema, Clone, PartialEq, Eq, Debug)]
+pub struct IngredientTotal {
+    /// name of the ingredient
+    pub ingredient: String,
+    /// total quantity of this ingredient
+    pub quantity: u64,
+}
+
+/// a recorded event
+#[derive(Serialize, Deserialize, JsonSchema, Clone, PartialEq, Eq, Debug)]
+pub struct HistoryEvent {
+    /// time of the event in seconds since epoch
+    pub time: u64,
+    /// address that performed the event.  Only displayed to admins, or to a reward recipient
+    /// when it differs from the owner
+    pub user: Option<Addr>,
+    /// what happened
+    pub action: HistoryAction,
+}
+
+/// a user's notification info for a channel.  Notifications are emitted as plaintext
+/// attributes keyed by `snip52:` followed by the base64 encoded notification id, where the id of
+/// the notification after `counter` previous ones is the HMAC-SHA256 of `{channel}:{counter}`
+/// keyed by the seed
+#[derive(Serialize, Deserialize, JsonSchema, Clone, PartialEq, Eq, Debug)]
+pub struct ChannelInfoData {
+    /// name of the channel
+    pub channel: String,
+    /// the user's notification seed
+    pub seed: Binary,
+    /// number of notifications the user has been sent on this channel
+    pub counter: u64,
+    /// id of the next notification on this channel
+    pub next_id: Binary,
+}
+
+/// activities ranked by the leaderboards
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum LeaderboardMetric {
+    ChargesClaimed,
+    IngredientsEarned,
+    CratesMinted,
+}
+
+/// a ranked player
+#[derive(Serialize, Deserialize, JsonSchema, Clone, PartialEq, Eq, Debug)]
+pub struct LeaderboardEntry {
+    /// position on the leaderboard starting at 1
+    pub rank: u32,
+    /// the player's alias, or address if no alias was chosen
+    pub player: String,
+    /// the player's score
+    pub score: u64,
+}
+
+/// ingredients granted to or revoked from an address
+#[derive(Serialize, Deserialize, JsonSchema, Clone, PartialEq, Eq, Debug)]
+pub struct IngredientGrant {
+    /// address whose inventory is changed
+    pub address: String,
+    /// ingredients granted or revoked
+    pub ingredients: Vec<IngredientQty>,
+}
+
+/// types of admin inventory changes
+#[derive(Serialize, Deserialize, JsonSchema, Clone, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum AdminAction {
+    Grant,
+    Revoke,
+}
+
+/// an entry of the admin audit log
+#[derive(Serialize, Deserialize, JsonSchema, Clone, PartialEq, Eq, Debug)]
+pub struct AdminLogEntry {
+    /// admin that made the change
+    pub admin: Addr,
+    /// timestamp of the change in seconds since epoch 01/01/1970
+    pub time: u64,
+    /// type of change
+    pub action: AdminAction,
+    /// reason given for the change
+    pub memo: String,
+    /// ingredients granted or revoked
+    pub grants: Vec<IngredientGrant>,
+}
+
+/// types of recorded events
+#[derive(Serialize, Deserialize, JsonSchema, Clone, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum HistoryAction {
+    /// the staking list was set
+    SetStake {
+        /// skulls in the new staking list
+        token_ids: Vec<String>,
+    },
+    /// staking rewards were claimed
+    Claim {
+        /// skulls that had charges
+        token_ids: Vec<String>,
+        /// number of charges claimed
+        charges: u16,
+        /// ingredients rewarded
+        rewards: Vec<IngredientQty>,
+        /// address that received the rewards if not the owner
+        recipient: Option<Addr>,
+    },
+    /// first-stake bonus was rewarded
+    FirstStakeBonus {
+        /// skulls that were eligible for the bonus
+        token_ids: Vec<String>,
+        /// ingredients rewarded
+        rewards: Vec<IngredientQty>,
+        /// address that received the rewards if not the owner
+        recipient: Option<Addr>,
+    },
+    /// ingredients were crated
+    Crate {
+        /// ingredients removed from the inventory
+        ingredients: Vec<IngredientQty>,
+    },
+    /// crates were opened
+    Uncrate {
+        /// token ids of the crates
+        token_ids: Vec<String>,
+        /// ingredients added to the inventory
+        ingredients: Vec<IngredientQty>,
+    },
+}
+
+/// a skull material and the number of skulls of that material
+#[derive(Serialize, Deserialize, JsonSchema, Clone, PartialEq, Eq, Debug)]
+pub struct MaterialQty {
+    /// name of the skull material
+    pub material: String,
+    /// number of skulls of this material
+    pub quantity: u8,
+}
+
+/// an ingredient and its expected quantity
+#[derive(Serialize, Deserialize, JsonSchema, Clone, PartialEq, Eq, Debug)]
+pub struct ExpectedQty {
+    /// name of the ingredient
+    pub ingredient: String,
+    /// expected quantity of this ingredient
+    pub quantity: Decimal,
+}
+
 /// info about staking state
 #[derive(Serialize, Deserialize, JsonSchema, Clone, PartialEq, Eq, Debug)]
 pub struct StakingState {
     /// true if staking is halted
     pub halt: bool,
     /// skull category index
-    pub skull_idx: u8,
+    pub skull_idx: u16,
     /// cooldown period
     pub cooldown: u64,
 }
 
+/// rules that govern staking
+#[derive(Serialize, Deserialize, JsonSchema, Clone, PartialEq, Eq, Debug)]
+pub struct StakingRules {
+    /// maximum number of skulls a user can stake
+    pub max_staked: u8,
+    /// tiers that raise the maximum number of staked skulls based on the number of skulls owned
+    pub tiers: Vec<StakingTier>,
+    /// maximum number of charges that can accrue
+    pub charge_cap: u8,
+    /// true if the charge cap applies to the total charges of all of a user's skulls instead of
+    /// each skull individually
+    pub cap_per_user: bool,
+    /// number of charges each eligible skull receives as the first-stake bonus (0 disables it)
+    pub first_stake_bonus: u8,
+}
+
+/// the pity thresholds of a skull material's staking table
+#[derive(Serialize, Deserialize, JsonSchema, Clone, PartialEq, Eq, Debug)]
+pub struct PityTable {
+    /// skull material that uses this table
+    pub material: String,
+    /// ingredient sets that are guaranteed after enough charges without winning them
+    pub sets: Vec<PitySet>,
+}
+
+/// an ingredient set that is guaranteed after enough charges without winning it
+#[derive(Serialize, Deserialize, JsonSchema, Clone, PartialEq, Eq, Debug)]
+pub struct PitySet {
+    /// name of the ingredient set
+    pub ingredient_set: String,
+    /// number of charges without a win after which the next roll is guaranteed to win this set
+    pub threshold: u16,
+}
+
+/// a user's progress towards a guaranteed ingredient set win
+#[derive(Serialize, Deserialize, JsonSchema, Clone, PartialEq, Eq, Debug)]
+pub struct PityProgress {
+    /// skull material
+    pub material: String,
+    /// name of the ingredient set
+    pub ingredient_set: String,
+    /// number of charges claimed since this set was last won
+    pub charges_without_win: u16,
+    /// number of charges without a win after which the next roll is guaranteed
+    pub threshold: u16,
+}
+
+/// an ingredient set win that was guaranteed by a pity threshold
+#[derive(Serialize, Deserialize, JsonSchema, Clone, PartialEq, Eq, Debug)]
+pub struct PityDrop {
+    /// skull material
+    pub material: String,
+    /// name of the ingredient set
+    pub ingredient_set: String,
+}
+
+/// a SNIP-20 token paid to stakers
+#[derive(Serialize, Deserialize, JsonSchema, Clone, PartialEq, Eq, Debug)]
+pub struct RewardStream {
+    /// code hash and address of the SNIP-20 token
+    pub token: ContractInfo,
+    /// true if the rates are paid per second of matured staking time instead of per charge
+    /// claimed
+    pub per_second: bool,
+    /// amount of tokens paid per charge (or per second) for skulls of each material.  Materials
+    /// not listed do not earn this token
+    pub rates: Vec<MaterialRate>,
+    /// maximum total amount of tokens this stream will ever pay out
+    pub cap: Uint128,
+}
+
+/// a skull material's emission rate
+#[derive(Serialize, Deserialize, JsonSchema, Clone, PartialEq, Eq, Debug)]
+pub struct MaterialRate {
+    /// skull material
+    pub material: String,
+    /// amount of tokens paid per charge or per second
+    pub rate: Uint128,
+}
+
+/// displayable info about a reward stream
+#[derive(Serialize, Deserialize, JsonSchema, Clone, PartialEq, Eq, Debug)]
+pub struct StreamInfo {
+    /// code hash and address of the SNIP-20 token
+    pub token: ContractInfo,
+    /// true if the rates are paid per second of matured staking time instead of per charge
+    pub per_second: bool,
+    /// emission rates for each skull material
+    pub rates: Vec<MaterialRate>,
+    /// maximum total amount of tokens this stream will ever pay out
+    pub cap: Uint128,
+    /// amount of tokens funded but not yet paid out
+    pub reserve: Uint128,
+    /// amount of tokens paid out so far
+    pub distributed: Uint128,
+}
+
+/// an amount of SNIP-20 tokens
+#[derive(Serialize, Deserialize, JsonSchema, Clone, PartialEq, Eq, Debug)]
+pub struct TokenReward {
+    /// address of the SNIP-20 token
+    pub token: String,
+    /// amount of tokens
+    pub amount: Uint128,
+}
+
+/// a staking tier
+#[derive(Serialize, Deserialize, JsonSchema, Clone, PartialEq, Eq, Debug)]
+pub struct StakingTier {
+    /// minimum number of skulls a user must own to qualify for this tier
+    pub min_owned: u16,
+    /// maximum number of skulls that can be staked in this tier
+    pub max_staked: u8,
+}
+
 /// info about alchemy state
 #[derive(Serialize, Deserialize, JsonSchema, Clone, PartialEq, Eq, Debug)]
 pub struct AlchemyState {
@@ -441,8 +1238,8 @@ pub struct LayerId {
 #[derive(Serialize, Deserialize, JsonSchema, Clone, PartialEq, Eq, Debug)]
 pub struct StoredLayerId {
     /// the layer category
-    pub category: u8,
-    pub variant: u8,
+    pub category: u16,
+    pub variant: u16,
 }
 
 /// first time staking bonus eligibility for a token
@@ -460,7 +1257,7 @@ pub struct EligibilityInfo {
 #[derive(Serialize, Deserialize, JsonSchema, Clone, PartialEq, Eq, Debug)]
 pub struct VariantIdxName {
     /// index of the variant
-    pub idx: u8,
+    pub idx: u16,
     /// display name of the variant
     pub name: String,
 }
@@ -21,6 +21,13 @@ pub enum ServerQueryMsg {
         /// address and viewing key of the alchemy contract
         viewer: ViewerInfo,
     },
+    /// display the StoredLayerIds of the specified layers
+    LayerIndices {
+        /// address and viewing key of the alchemy contract
+        viewer: ViewerInfo,
+        /// layers to look up
+        layers: Vec<LayerId>,
+    },
 }
 
 impl Query for ServerQueryMsg {
@@ -35,7 +42,7 @@ pub struct SkullTypePlus {
     /// jawless layer
     pub jawless: StoredLayerId,
     /// skull category index
-    pub skull_idx: u8,
+    pub skull_idx: u16,
     /// list of all skull materials
     pub skull_variants: Vec<VariantIdxName>,
 }
@@ -58,3 +65,16 @@ pub struct Transmute {
 pub struct TransmuteWrapper {
     pub transmute: Transmute,
 }
+
+/// display the StoredLayerIds of the requested layers
+#[derive(Deserialize)]
+pub struct LayerIndices {
+    /// StoredLayerIds in the same order as requested
+    pub layers: Vec<StoredLayerId>,
+}
+
+/// wrapper to deserialize LayerIndices responses
+#[derive(Deserialize)]
+pub struct LayerIndicesWrapper {
+    pub layer_indices: LayerIndices,
+}
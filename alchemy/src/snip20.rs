@@ -0,0 +1,32 @@
+use crate::contract::BLOCK_SIZE;
+use cosmwasm_std::Uint128;
+use secret_toolkit::utils::HandleCallback;
+use serde::Serialize;
+
+/// snip20 handle msgs
+#[derive(Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Snip20HandleMsg {
+    /// register this contract's code hash with the snip20
+    RegisterReceive {
+        /// this contract's code hash
+        code_hash: String,
+        /// optional padding
+        padding: Option<String>,
+    },
+    /// transfer tokens
+    Transfer {
+        /// address receiving the tokens
+        recipient: String,
+        /// amount of tokens
+        amount: Uint128,
+        /// optional memo
+        memo: Option<String>,
+        /// optional padding
+        padding: Option<String>,
+    },
+}
+
+impl HandleCallback for Snip20HandleMsg {
+    const BLOCK_SIZE: usize = BLOCK_SIZE;
+}
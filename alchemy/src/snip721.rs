@@ -83,7 +83,7 @@ pub struct NftInfoWrapper {
 /// snip721 BatchNftDossier query item
 #[derive(Deserialize)]
 pub struct BatchNftDossierElement {
-    //    pub token_id: String,
+    pub token_id: String,
     pub public_metadata: Metadata,
 }
 
@@ -1,3 +1,5 @@
+use crate::contract_info::StoreContractInfo;
+use crate::msg::{LayerId, OperatorScope, StoredLayerId};
 use cosmwasm_std::CanonicalAddr;
 use serde::{Deserialize, Serialize};
 
@@ -15,14 +17,22 @@ pub const STAKING_STATE_KEY: &[u8] = b"stkst";
 pub const CRATE_STATE_KEY: &[u8] = b"crtst";
 /// storage key for the crating base metadata
 pub const CRATE_META_KEY: &[u8] = b"metcrt";
+/// storage key for the crate art
+pub const CRATE_ART_KEY: &[u8] = b"crtart";
 /// storage key for the AlchemyState
 pub const ALCHEMY_STATE_KEY: &[u8] = b"alcst";
 /// storage key for the skulls contract info
 pub const SKULL_721_KEY: &[u8] = b"sk721";
-/// storage key for crate contract infos
+/// storage key for the crate contract registry
 pub const CRATES_KEY: &[u8] = b"crat";
+/// storage key for the name of the default crate contract
+pub const DEFAULT_CRATE_KEY: &[u8] = b"dfcrt";
 /// storage key for the svg server contract info
 pub const SVG_SERVER_KEY: &[u8] = b"srvr";
+/// storage prefix for the records of crates minted by this contract
+pub const PREFIX_MINTED_CRATES: &[u8] = b"mntcrt";
+/// storage prefix for the number of crates a crate contract minted before crates were recorded
+pub const PREFIX_LEGACY_CRATES: &[u8] = b"lgcrt";
 /// storage prefix for the user's ingredient inventory
 pub const PREFIX_USER_INGR_INVENTORY: &[u8] = b"usinv";
 /// storage prefix for the staking set of a user
@@ -31,8 +41,66 @@ pub const PREFIX_USER_STAKE: &[u8] = b"usrsk";
 pub const PREFIX_SKULL_STAKE: &[u8] = b"sklstk";
 /// storage key for this contract's viewing key with other contracts
 pub const MY_VIEWING_KEY: &[u8] = b"myview";
+/// storage key for the staking boosts
+pub const STAKING_BOOSTS_KEY: &[u8] = b"boost";
+/// storage key for the staking rules
+pub const STAKING_RULES_KEY: &[u8] = b"rules";
+/// storage key for the SNIP-20 reward streams
+pub const REWARD_STREAMS_KEY: &[u8] = b"strms";
+/// storage prefix for the SNIP-20 rewards a user earned that the reserves could not cover
+pub const PREFIX_UNPAID_TOKENS: &[u8] = b"unpaid";
+/// storage key for the staking seasons
+pub const STAKING_SEASONS_KEY: &[u8] = b"season";
+/// storage key for the id given to the next new staking season
+pub const SEASON_ID_KEY: &[u8] = b"ssnid";
+/// storage prefix for the staking operators of an owner
+pub const PREFIX_STAKING_OPERATORS: &[u8] = b"stkop";
+/// storage key for the number of recorded events
+pub const HISTORY_CNT_KEY: &[u8] = b"hstcnt";
+/// storage key for the aggregate history totals
+pub const HISTORY_TOTALS_KEY: &[u8] = b"hsttot";
+/// storage key for the number of events in a user's or token's history
+pub const HIST_CNT_KEY: &[u8] = b"count";
+/// storage prefix for the leaderboard opt-in of a user
+pub const PREFIX_LEADER_OPT: &[u8] = b"ldropt";
+/// storage prefix for the users of leaderboard aliases
+pub const PREFIX_LEADER_ALIAS: &[u8] = b"ldrals";
+/// storage prefix for the leaderboard scores of a user in a season
+pub const PREFIX_LEADER_SCORES: &[u8] = b"ldrscr";
+/// storage prefix for the page directories of the leaderboards of a season
+pub const PREFIX_LEADERBOARD: &[u8] = b"ldrbrd";
+/// storage prefix for the pages of sorted ranks of the leaderboards of a season
+pub const PREFIX_LEADER_PAGES: &[u8] = b"ldrpg";
+/// storage key for the secret notification seeds are derived from
+pub const NOTIFY_SECRET_KEY: &[u8] = b"ntfsec";
+/// storage prefix for the notification counters of a channel
+pub const PREFIX_NOTIFY_COUNTERS: &[u8] = b"ntfcnt";
+/// storage key for the sorted hours that have charge cap notifications scheduled
+pub const CAP_HOURS_KEY: &[u8] = b"caphrs";
+/// storage prefix for the charge cap notifications scheduled in an hour
+pub const PREFIX_CAP_SCHEDULE: &[u8] = b"capsch";
+/// storage prefix for the time a user's scheduled charge cap notification is due
+pub const PREFIX_CAP_DUE: &[u8] = b"capdue";
+/// storage key for the number of admin audit log entries
+pub const ADMIN_LOG_CNT_KEY: &[u8] = b"admcnt";
+/// storage prefix for the admin audit log
+pub const PREFIX_ADMIN_LOG: &[u8] = b"admlog";
+/// storage prefix for recorded events
+pub const PREFIX_HISTORY: &[u8] = b"hist";
+/// storage prefix for the event indices of a user's history
+pub const PREFIX_USER_HISTORY: &[u8] = b"ushist";
+/// storage prefix for the event indices of a skull's history
+pub const PREFIX_TOKEN_HISTORY: &[u8] = b"tkhist";
+/// storage prefix for the event indices of a skull's history that involve a specific user
+pub const PREFIX_TOKEN_USER_HISTORY: &[u8] = b"tuhist";
+/// prefix for the storage of staking table pity thresholds
+pub const PREFIX_PITY_TABLE: &[u8] = b"tbpity";
+/// storage prefix for a user's pity counters
+pub const PREFIX_USER_PITY: &[u8] = b"uspity";
 /// prefix for the storage of staking tables
 pub const PREFIX_STAKING_TABLE: &[u8] = b"tbstk";
+/// prefix for the storage of viewing keys issued to this contract by servers
+pub const PREFIX_SERVER_KEYS: &[u8] = b"srvkeys";
 /// prefix for the storage of revoked permits
 pub const PREFIX_REVOKED_PERMITS: &str = "revoke";
 
@@ -46,7 +114,7 @@ pub struct StoredIngrSet {
 }
 
 /// ingredient sets and their staking weight
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
 pub struct StoredSetWeight {
     /// idx of the set
     pub set: u8,
@@ -54,6 +122,239 @@ pub struct StoredSetWeight {
     pub weight: u16,
 }
 
+/// an ingredient set and its chance (out of 10000) of being won
+#[derive(Serialize, Deserialize, Clone)]
+pub struct StoredSetChance {
+    /// idx of the set
+    pub set: u8,
+    /// chance out of 10000
+    pub chance: u16,
+}
+
+/// staking boost given to skulls whose current image has a specific layer
+#[derive(Serialize, Deserialize)]
+pub struct StoredBoost {
+    /// name of the boost
+    pub name: String,
+    /// the layer that grants this boost
+    pub layer: StoredLayerId,
+    /// the names of the layer that grants this boost
+    pub layer_name: LayerId,
+    /// additional rolls per charge
+    pub extra_rolls: u8,
+    /// weights added to the staking table
+    pub weights: Vec<StoredSetWeight>,
+    /// optional chance per charge to win an additional ingredient from a set
+    pub bonus: Option<StoredSetChance>,
+}
+
+/// an ingredient set that is guaranteed after enough charges without winning it
+#[derive(Serialize, Deserialize, Clone)]
+pub struct StoredPity {
+    /// idx of the set
+    pub set: u8,
+    /// number of charges without a win before a guaranteed win
+    pub threshold: u16,
+}
+
+/// a user's number of charges without winning a pity set
+#[derive(Serialize, Deserialize)]
+pub struct StoredPityCount {
+    /// idx of the skull material
+    pub material: u8,
+    /// idx of the set
+    pub set: u8,
+    /// number of charges since the set was last won
+    pub misses: u16,
+}
+
+/// a SNIP-20 staking reward stream
+#[derive(Serialize, Deserialize)]
+pub struct StoredStream {
+    /// code hash and address of the token
+    pub token: StoreContractInfo,
+    /// true if paid per second instead of per charge
+    pub per_second: bool,
+    /// emission rates indexed by skull material
+    pub rates: Vec<u128>,
+    /// maximum total payout
+    pub cap: u128,
+    /// funded amount not yet paid out
+    pub reserve: u128,
+    /// amount paid out
+    pub distributed: u128,
+}
+
+/// SNIP-20 rewards owed to a user that a stream's reserve could not cover
+#[derive(Serialize, Deserialize)]
+pub struct StoredUnpaid {
+    /// address of the token
+    pub token: CanonicalAddr,
+    /// amount owed
+    pub amount: u128,
+}
+
+/// a staking table that overrides a material's table during a season
+#[derive(Serialize, Deserialize, PartialEq)]
+pub struct StoredSeasonTable {
+    /// idx of the skull material
+    pub material: u8,
+    /// ingredient sets and their weights
+    pub weights: Vec<StoredSetWeight>,
+}
+
+/// a staking season
+#[derive(Serialize, Deserialize)]
+pub struct StoredSeason {
+    /// id of the season, which keys its leaderboards
+    pub id: u16,
+    /// name of the season
+    pub name: String,
+    /// start time in seconds
+    pub start: u64,
+    /// end time in seconds
+    pub end: u64,
+    /// staking table overrides
+    pub tables: Vec<StoredSeasonTable>,
+    /// percentage of the normal charge time needed to earn a charge
+    pub charge_pct: u16,
+}
+
+/// an operator authorized to claim and/or stake on an owner's behalf
+#[derive(Serialize, Deserialize)]
+pub struct StoredOperator {
+    /// address of the operator
+    pub operator: CanonicalAddr,
+    /// actions the operator is allowed to perform
+    pub scope: OperatorScope,
+    /// optional expiration time in seconds
+    pub expiration: Option<u64>,
+    /// optional address whose inventory receives the claimed rewards
+    pub recipient: Option<CanonicalAddr>,
+}
+
+/// a user's leaderboard opt-in
+#[derive(Serialize, Deserialize)]
+pub struct StoredOptIn {
+    /// optional alias displayed instead of the user's address
+    pub alias: Option<String>,
+}
+
+/// a ranked user on a leaderboard
+#[derive(Serialize, Deserialize)]
+pub struct StoredRank {
+    /// the user's address
+    pub user: CanonicalAddr,
+    /// the user's score
+    pub score: u64,
+}
+
+/// a scheduled charge cap notification
+#[derive(Serialize, Deserialize)]
+pub struct StoredCapDue {
+    /// the user to notify
+    pub user: CanonicalAddr,
+    /// time the user's charges reach their cap
+    pub time: u64,
+}
+
+/// summary of a page of a leaderboard
+#[derive(Serialize, Deserialize)]
+pub struct StoredRankPage {
+    /// storage id of the page
+    pub id: u32,
+    /// number of ranked users in the page
+    pub count: u32,
+    /// highest score in the page
+    pub high: u64,
+    /// lowest score in the page
+    pub low: u64,
+}
+
+/// an entry of the admin audit log
+#[derive(Serialize, Deserialize)]
+pub struct StoredAdminEntry {
+    /// admin that made the change
+    pub admin: CanonicalAddr,
+    /// timestamp of the change
+    pub time: u64,
+    /// true if ingredients were revoked
+    pub revoke: bool,
+    /// reason given for the change
+    pub memo: String,
+    /// changed inventories
+    pub grants: Vec<StoredGrant>,
+}
+
+/// ingredients granted to or revoked from an address
+#[derive(Serialize, Deserialize)]
+pub struct StoredGrant {
+    /// address whose inventory changed
+    pub user: CanonicalAddr,
+    /// quantities indexed by ingredient
+    pub quantities: Vec<u32>,
+}
+
+/// a recorded event
+#[derive(Serialize, Deserialize)]
+pub struct StoredEvent {
+    /// address that performed the event
+    pub user: CanonicalAddr,
+    /// time of the event in seconds
+    pub time: u64,
+    /// what happened
+    pub action: StoredAction,
+}
+
+/// types of recorded events.  Ingredient quantities are indexed by ingredient
+#[derive(Serialize, Deserialize)]
+pub enum StoredAction {
+    SetStake {
+        token_ids: Vec<String>,
+    },
+    Claim {
+        token_ids: Vec<String>,
+        charges: u16,
+        rewards: Vec<u32>,
+        recipient: Option<CanonicalAddr>,
+    },
+    FirstStakeBonus {
+        token_ids: Vec<String>,
+        rewards: Vec<u32>,
+        recipient: Option<CanonicalAddr>,
+    },
+    Crate {
+        ingredients: Vec<u32>,
+    },
+    Uncrate {
+        token_ids: Vec<String>,
+        ingredients: Vec<u32>,
+    },
+}
+
+/// aggregate totals of all recorded history
+#[derive(Serialize, Deserialize, Default)]
+pub struct StoredTotals {
+    /// number of times a staking list was set
+    pub stake_changes: u64,
+    /// number of claims
+    pub claims: u64,
+    /// number of charges claimed
+    pub charges: u64,
+    /// number of first-stake bonuses
+    pub first_stakes: u64,
+    /// number of crates minted
+    pub crates: u64,
+    /// number of uncrating transactions
+    pub uncrates: u64,
+    /// ingredients rewarded indexed by ingredient
+    pub rewarded: Vec<u64>,
+    /// ingredients crated indexed by ingredient
+    pub crated: Vec<u64>,
+    /// ingredients uncrated indexed by ingredient
+    pub uncrated: Vec<u64>,
+}
+
 /// the latest staker, stake start, and claim time of a skull
 #[derive(Serialize, Deserialize)]
 pub struct SkullStakeInfo {
@@ -62,6 +363,37 @@ pub struct SkullStakeInfo {
     pub claim: u64,
 }
 
+/// svg fragments used to generate crate images
+#[derive(Serialize, Deserialize, Default)]
+pub struct StoredCrateArt {
+    /// svg fragment drawn behind the ingredient icons
+    pub background: Option<String>,
+    /// icons indexed by ingredient
+    pub icons: Vec<Option<String>>,
+}
+
+/// an entry of the crate contract registry
+#[derive(Serialize, Deserialize)]
+pub struct StoredCrateContract {
+    /// name of the entry
+    pub name: String,
+    /// code hash and address of the crate contract
+    pub contract: StoreContractInfo,
+    /// true if crates can be minted with this contract
+    pub mint: bool,
+    /// true if this contract's crates can be uncrated
+    pub uncrate: bool,
+}
+
+/// record of a crate minted by this contract
+#[derive(Serialize, Deserialize)]
+pub struct StoredCrate {
+    /// address of the crate contract
+    pub contract: CanonicalAddr,
+    /// hash of the crate's contents
+    pub hash: String,
+}
+
 /// info about crating state
 #[derive(Serialize, Deserialize)]
 pub struct CrateState {
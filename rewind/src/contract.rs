@@ -19,8 +19,8 @@ use crate::snip721::{
     ViewerInfo,
 };
 use crate::state::{
-    Config, CONFIG_KEY, MY_ADDRESS_KEY, PREFIX_REVOKED_PERMITS, PREFIX_TIMESTAMP, PREFIX_VIEW_KEY,
-    PRNG_SEED_KEY,
+    Config, CONFIG_KEY, MY_ADDRESS_KEY, PREFIX_KNOWN_SERVERS, PREFIX_REVOKED_PERMITS,
+    PREFIX_SERVER_KEYS, PREFIX_TIMESTAMP, PREFIX_VIEW_KEY, PRNG_SEED_KEY,
 };
 use crate::storage::{load, may_load, save};
 use crate::viewing_key::{ViewingKey, VIEWING_KEY_SIZE};
@@ -60,6 +60,9 @@ pub fn init<S: Storage, A: Api, Q: Querier>(
         cooldown: msg.cooldown,
     };
     save(&mut deps.storage, CONFIG_KEY, &config)?;
+    let server_raw = deps.api.canonical_address(&msg.svg_server.address)?;
+    let mut srv_store = PrefixedStorage::new(PREFIX_KNOWN_SERVERS, &mut deps.storage);
+    save(&mut srv_store, server_raw.as_slice(), &true)?;
 
     Ok(InitResponse {
         messages: vec![
@@ -110,6 +113,7 @@ pub fn handle<S: Storage, A: Api, Q: Querier>(
         HandleMsg::SetKeyWithServer { svg_server } => {
             try_set_key_w_server(deps, &env.message.sender, svg_server)
         }
+        HandleMsg::ReceiveQueryKey { key } => try_receive_query_key(deps, &env.message.sender, key),
         HandleMsg::Rewind { token_id } => try_rewind(deps, env, token_id),
     };
     pad_handle_result(response, BLOCK_SIZE)
@@ -188,7 +192,9 @@ fn try_rewind<S: Storage, A: Api, Q: Querier>(
     }
     save(&mut time_store, token_key, &env.block.time)?;
     // get the svg server info
-    let svr_msg = ServerQueryMsg::ServeAlchemy { viewer };
+    let svr_msg = ServerQueryMsg::ServeAlchemy {
+        viewer: server_viewer(deps, &image.server_used.address, viewer)?,
+    };
     let svr_wrap: ServeAlchemyWrapper = svr_msg.query(
         &deps.querier,
         image.server_used.code_hash,
@@ -275,6 +281,10 @@ fn try_set_key_w_server<S: Storage, A: Api, Q: Querier>(
     if !config.admins.contains(&sender_raw) {
         return Err(StdError::unauthorized());
     }
+    // remember the server so it can issue this contract a query key
+    let server_raw = deps.api.canonical_address(&svg_server.address)?;
+    let mut srv_store = PrefixedStorage::new(PREFIX_KNOWN_SERVERS, &mut deps.storage);
+    save(&mut srv_store, server_raw.as_slice(), &true)?;
 
     Ok(HandleResponse {
         messages: vec![set_viewing_key_msg(
@@ -465,6 +475,70 @@ fn try_set_key<S: Storage, A: Api, Q: Querier>(
     })
 }
 
+/// Returns HandleResult
+///
+/// saves a viewing key issued by the sender for this contract to use when querying the sender.
+/// Keys sent by anything other than an svg server this contract set a viewing key with are ignored
+///
+/// # Arguments
+///
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `sender` - a reference to the message sender
+/// * `key` - the issued viewing key
+fn try_receive_query_key<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    sender: &HumanAddr,
+    key: String,
+) -> HandleResult {
+    let sender_raw = deps.api.canonical_address(sender)?;
+    let srv_store = ReadonlyPrefixedStorage::new(PREFIX_KNOWN_SERVERS, &deps.storage);
+    let known: bool = may_load(&srv_store, sender_raw.as_slice())?.unwrap_or(false);
+    let status = if known {
+        let mut key_store = PrefixedStorage::new(PREFIX_SERVER_KEYS, &mut deps.storage);
+        save(&mut key_store, sender_raw.as_slice(), &key)?;
+        "success"
+    } else {
+        "ignored"
+    };
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![],
+        data: Some(to_binary(&HandleAnswer::ReceiveQueryKey {
+            status: status.to_string(),
+        })?),
+    })
+}
+
+/// Returns StdResult<ViewerInfo>
+///
+/// the viewer info to use when querying an svg server.  A viewing key issued by the server is
+/// preferred over this contract's own viewing key
+///
+/// # Arguments
+///
+/// * `deps` - a reference to Extern containing all the contract's external dependencies
+/// * `server` - a reference to the svg server's address
+/// * `viewer` - the viewer info with this contract's own viewing key
+fn server_viewer<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    server: &HumanAddr,
+    viewer: ViewerInfo,
+) -> StdResult<ViewerInfo> {
+    let server_raw = deps.api.canonical_address(server)?;
+    let key_store = ReadonlyPrefixedStorage::new(PREFIX_SERVER_KEYS, &deps.storage);
+    Ok(
+        if let Some(viewing_key) = may_load(&key_store, server_raw.as_slice())? {
+            ViewerInfo {
+                address: viewer.address,
+                viewing_key,
+            }
+        } else {
+            viewer
+        },
+    )
+}
+
 /// Returns HandleResult
 ///
 /// revoke the ability to use a specified permit
@@ -694,3 +768,49 @@ fn check_admin<S: Storage, A: Api, Q: Querier>(
     }
     Ok((config, my_addr))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cosmwasm_std::from_binary;
+    use cosmwasm_std::testing::{mock_dependencies, MockApi, MockQuerier, MockStorage};
+
+    #[test]
+    fn test_receive_query_key() {
+        let mut deps = mock_dependencies(20, &[]);
+        let server = HumanAddr("server".to_string());
+        let server_raw = deps.api.canonical_address(&server).unwrap();
+        let mut srv_store = PrefixedStorage::new(PREFIX_KNOWN_SERVERS, &mut deps.storage);
+        save(&mut srv_store, server_raw.as_slice(), &true).unwrap();
+        let own = ViewerInfo {
+            address: HumanAddr("rewind".to_string()),
+            viewing_key: "own".to_string(),
+        };
+        let status = |resp: HandleResponse| match from_binary(&resp.data.unwrap()).unwrap() {
+            HandleAnswer::ReceiveQueryKey { status } => status,
+            _ => panic!("unexpected answer"),
+        };
+        let key_in_use = |deps: &Extern<MockStorage, MockApi, MockQuerier>| {
+            server_viewer(deps, &server, own.clone())
+                .unwrap()
+                .viewing_key
+        };
+
+        // keys from unknown servers are ignored
+        let attacker = HumanAddr("attacker".to_string());
+        let resp = try_receive_query_key(&mut deps, &attacker, "forged".to_string()).unwrap();
+        assert_eq!(status(resp), "ignored");
+        assert_eq!(key_in_use(&deps), "own");
+
+        // the server's key replaces this contract's own key
+        let resp = try_receive_query_key(&mut deps, &server, "issued".to_string()).unwrap();
+        assert_eq!(status(resp), "success");
+        assert_eq!(key_in_use(&deps), "issued");
+
+        // a rotated key replaces the previous one
+        try_receive_query_key(&mut deps, &server, "rotated".to_string()).unwrap();
+        assert_eq!(key_in_use(&deps), "rotated");
+        try_receive_query_key(&mut deps, &attacker, "forged".to_string()).unwrap();
+        assert_eq!(key_in_use(&deps), "rotated");
+    }
+}
@@ -66,6 +66,13 @@ pub enum HandleMsg {
         /// name of the permit that is no longer valid
         permit_name: String,
     },
+    /// receive a viewing key issued by an svg server that has this contract on its viewer
+    /// allowlist.  The key is only ever used when querying the server that sent it, and keys
+    /// from servers this contract has not set a viewing key with are ignored
+    ReceiveQueryKey {
+        /// the viewing key
+        key: String,
+    },
 }
 
 /// Responses from handle functions
@@ -93,6 +100,11 @@ pub enum HandleAnswer {
     RevokePermit {
         status: String,
     },
+    /// response from receiving a server issued viewing key.  The status is "ignored" if the
+    /// sender is not a known svg server
+    ReceiveQueryKey {
+        status: String,
+    },
     /// response of attempting a rewind
     Rewind {
         /// the trait categories rewound
@@ -22,7 +22,7 @@ impl Query for ServerQueryMsg {
 #[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
 pub struct ServeAlchemyResponse {
     /// categories that are skipped when rolling/revealing
-    pub skip: Vec<u8>,
+    pub skip: Vec<u16>,
     /// variant display dependencies
     pub dependencies: Vec<StoredDependencies>,
     /// category names
@@ -39,8 +39,8 @@ pub struct ServeAlchemyWrapper {
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
 pub struct StoredLayerId {
     /// the layer category
-    pub category: u8,
-    pub variant: u8,
+    pub category: u16,
+    pub variant: u16,
 }
 
 /// describes a trait that has multiple layers
@@ -51,3 +51,28 @@ pub struct StoredDependencies {
     /// the other layers that are correlated to this variant
     pub correlated: Vec<StoredLayerId>,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cosmwasm_std::{from_slice, to_vec};
+
+    #[test]
+    fn test_serve_alchemy_wide_indices() {
+        // the svg server answers with u16 indices once a category passes 255 variants
+        let json = br#"{"serve_alchemy":{"skip":[0,300],"dependencies":[{"id":{"category":2,"variant":300},"correlated":[{"category":301,"variant":1}]}],"category_names":["Background"]}}"#;
+        let wrapper: ServeAlchemyWrapper = from_slice(json).unwrap();
+        let resp = &wrapper.serve_alchemy;
+        assert_eq!(resp.skip, vec![0, 300]);
+        assert_eq!(
+            resp.dependencies[0].id,
+            StoredLayerId {
+                category: 2,
+                variant: 300
+            }
+        );
+        assert_eq!(resp.dependencies[0].correlated[0].category, 301);
+        let round_trip: ServeAlchemyWrapper = from_slice(&to_vec(&wrapper).unwrap()).unwrap();
+        assert_eq!(round_trip, wrapper);
+    }
+}
@@ -13,6 +13,10 @@ pub const PRNG_SEED_KEY: &[u8] = b"prngseed";
 pub const PREFIX_VIEW_KEY: &[u8] = b"viewkeys";
 /// prefix for storage of timestamp of rewinds
 pub const PREFIX_TIMESTAMP: &[u8] = b"time";
+/// prefix for storage of viewing keys issued to this contract by svg servers
+pub const PREFIX_SERVER_KEYS: &[u8] = b"srvkeys";
+/// prefix for storage of the svg servers this contract has set a viewing key with
+pub const PREFIX_KNOWN_SERVERS: &[u8] = b"knownsrv";
 /// prefix for the storage of revoked permits
 pub const PREFIX_REVOKED_PERMITS: &str = "revoke";
 
@@ -1,32 +1,47 @@
 use base64::{engine::general_purpose, Engine as _};
 use cosmwasm_std::{
-    entry_point, to_binary, Addr, Api, Binary, CanonicalAddr, Deps, DepsMut, Env, MessageInfo,
-    Response, StdError, StdResult, Storage,
+    entry_point, to_binary, Addr, Api, Binary, CanonicalAddr, CosmosMsg, Deps, DepsMut, Env,
+    MessageInfo, Response, StdError, StdResult, Storage,
 };
 use cosmwasm_storage::{PrefixedStorage, ReadonlyPrefixedStorage};
 use std::cmp::min;
 
 use secret_toolkit::{
-    crypto::sha_256,
+    crypto::{sha_256, ContractPrng},
     permit::{validate, Permit, RevokedPermits},
-    utils::{pad_handle_result, pad_query_result},
+    utils::{pad_handle_result, pad_query_result, HandleCallback},
     viewing_key::{ViewingKey, ViewingKeyStore},
 };
 
-use crate::metadata::{Metadata, Trait};
+use crate::contract_info::{ContractInfo, StoreContractInfo};
+use crate::metadata::{Extension, MediaFile, Metadata, Trait};
 use crate::msg::{
-    AddVariantInfo, CategoryInfo, CommonMetadata, Dependencies, ExecuteAnswer, ExecuteMsg,
-    InstantiateMsg, LayerId, QueryAnswer, QueryMsg, StoredDependencies, StoredLayerId,
-    VariantIdxName, VariantInfo, VariantInfoPlus, VariantModInfo, ViewerInfo,
+    AddVariantInfo, BatchMetadata, CategoryInfo, CategoryWeights, CommonMetadata,
+    ConditionalWeight, ConflictPolicy, Dependencies, Effect, ExecuteAnswer, ExecuteMsg,
+    ImageVector, InstantiateMsg, LayerId, MigrateMsg, Palette, QueryAnswer, QueryMsg, RenderConfig,
+    RenderMode, Rule, StoredDependencies, StoredEffect, StoredEffectTrigger, StoredLayerId,
+    StoredRule, SvgFragment, VariantIdxName, VariantInfo, VariantInfoPlus, VariantModInfo,
+    VariantStatus, VariantStatusInfo, VariantWeight, ViewerInfo, IMAGE_VERSION, UNREVEALED,
 };
 use crate::state::{
-    Category, State, ADMINS_KEY, DEPENDENCIES_KEY, METADATA_KEY, MINTERS_KEY, PREFIX_CATEGORY,
-    PREFIX_CATEGORY_MAP, PREFIX_REVOKED_PERMITS, PREFIX_VARIANT, PREFIX_VARIANT_MAP, STATE_KEY,
-    VIEWERS_KEY,
+    Category, LegacyCategory, LegacyDependencies, LegacyLayerId, LegacyState, State,
+    StoredConditionalWeight, StoredVariantStatus, StoredVariantWeight, ADMINS_KEY,
+    CONFLICT_POLICY_KEY, DEPENDENCIES_KEY, EFFECTS_KEY, LAYOUT_VERSION_KEY, METADATA_KEY,
+    MINTERS_KEY, PALETTES_KEY, PREFIX_CATEGORY, PREFIX_CATEGORY_MAP, PREFIX_GENESIS_WEIGHTS,
+    PREFIX_REVOKED_PERMITS, PREFIX_VARIANT, PREFIX_VARIANT_MAP, PRNG_SEED_KEY, RENDER_CONFIG_KEY,
+    RULES_KEY, STATE_KEY, SVG_FRAGMENTS_KEY, U16_LAYOUT, VARIANT_STATUS_KEY, VIEWERS_KEY,
+    VIEWER_CONTRACTS_KEY,
 };
-use crate::storage::{load, may_load, remove, save};
+use crate::storage::{idx_key, load, may_load, remove, save};
+use crate::viewer_msgs::ViewerHandleMsg;
 
 pub const BLOCK_SIZE: usize = 256;
+/// maximum number of images that can be rolled in one query
+pub const MAX_ROLLS: u16 = 100;
+/// opening svg tag used unless the render config specifies one
+pub const DEFAULT_SVG_HEADER: &str = r###"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 -0.5 24 24" shape-rendering="crispEdges">"###;
+/// start of a reference to a shared svg fragment
+pub const FRAGMENT_TAG: &str = "{frag:";
 
 ////////////////////////////////////// Instantiate ///////////////////////////////////////
 /// Returns StdResult<Response>
@@ -53,16 +68,90 @@ pub fn instantiate(
             .as_bytes(),
     );
     ViewingKey::set_seed(deps.storage, &prng_seed);
+    save(
+        deps.storage,
+        PRNG_SEED_KEY,
+        &sha_256(&[prng_seed.as_slice(), b"genesis"].concat()).to_vec(),
+    )?;
     let mut admins = vec![sender_raw];
     if let Some(addrs) = msg.admins {
         add_addrs_to_auth(deps.api, &mut admins, &addrs)?;
     }
     save(deps.storage, ADMINS_KEY, &admins)?;
     let state = State {
-        cat_cnt: 0u8,
+        cat_cnt: 0u16,
         skip: Vec::new(),
     };
     save(deps.storage, STATE_KEY, &state)?;
+    save(deps.storage, LAYOUT_VERSION_KEY, &U16_LAYOUT)?;
+
+    Ok(Response::default())
+}
+
+///////////////////////////////////// Migrate //////////////////////////////////////
+/// Returns StdResult<Response>
+///
+/// converts the storage of an instance created before category and variant indices were
+/// widened to u16.  Categories and variants keep their storage keys, so only the State, the
+/// category records, the name to index maps and the dependencies are rewritten
+///
+/// # Arguments
+///
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `_env` - Env of contract's environment
+/// * `_msg` - MigrateMsg passed in with the migrate message
+#[entry_point]
+pub fn migrate(deps: DepsMut, _env: Env, _msg: MigrateMsg) -> StdResult<Response> {
+    // nothing to convert if the storage already uses u16 indices
+    if may_load::<u8>(deps.storage, LAYOUT_VERSION_KEY)?.is_some() {
+        return Ok(Response::default());
+    }
+    let legacy: LegacyState = load(deps.storage, STATE_KEY)?;
+    for cat_idx in 0..legacy.cat_cnt as u16 {
+        let cat_key = idx_key(cat_idx);
+        let mut cat_store = PrefixedStorage::new(deps.storage, PREFIX_CATEGORY);
+        let cat: LegacyCategory = load(&cat_store, &cat_key)?;
+        save(
+            &mut cat_store,
+            &cat_key,
+            &Category {
+                name: cat.name.clone(),
+                skip: cat.skip,
+                cnt: cat.cnt as u16,
+            },
+        )?;
+        let mut cat_map = PrefixedStorage::new(deps.storage, PREFIX_CATEGORY_MAP);
+        save(&mut cat_map, cat.name.as_bytes(), &cat_idx)?;
+        for var_idx in 0..cat.cnt as u16 {
+            let var_store =
+                ReadonlyPrefixedStorage::multilevel(deps.storage, &[PREFIX_VARIANT, &cat_key]);
+            let var: VariantInfo = load(&var_store, &idx_key(var_idx))?;
+            let mut var_map =
+                PrefixedStorage::multilevel(deps.storage, &[PREFIX_VARIANT_MAP, &cat_key]);
+            save(&mut var_map, var.name.as_bytes(), &var_idx)?;
+        }
+    }
+    let widen = |id: &LegacyLayerId| StoredLayerId {
+        category: id.category as u16,
+        variant: id.variant as u16,
+    };
+    if let Some(legacy_deps) = may_load::<Vec<LegacyDependencies>>(deps.storage, DEPENDENCIES_KEY)?
+    {
+        let depends: Vec<StoredDependencies> = legacy_deps
+            .iter()
+            .map(|d| StoredDependencies {
+                id: widen(&d.id),
+                correlated: d.correlated.iter().map(widen).collect(),
+            })
+            .collect();
+        save(deps.storage, DEPENDENCIES_KEY, &depends)?;
+    }
+    let state = State {
+        cat_cnt: legacy.cat_cnt as u16,
+        skip: legacy.skip.iter().map(|u| *u as u16).collect(),
+    };
+    save(deps.storage, STATE_KEY, &state)?;
+    save(deps.storage, LAYOUT_VERSION_KEY, &U16_LAYOUT)?;
 
     Ok(Response::default())
 }
@@ -93,6 +182,12 @@ pub fn execute(deps: DepsMut, env: Env, info: MessageInfo, msg: ExecuteMsg) -> S
         ExecuteMsg::ModifyVariants { modifications } => {
             try_modify_variants(deps, &info.sender, modifications)
         }
+        ExecuteMsg::SetVariantStatus { statuses } => {
+            try_set_variant_status(deps, &info.sender, statuses)
+        }
+        ExecuteMsg::SetGenesisWeights { weights } => {
+            try_set_genesis_weights(deps, &info.sender, weights)
+        }
         ExecuteMsg::SetMetadata {
             public_metadata,
             private_metadata,
@@ -109,6 +204,15 @@ pub fn execute(deps: DepsMut, env: Env, info: MessageInfo, msg: ExecuteMsg) -> S
         ExecuteMsg::RemoveViewers { viewers } => {
             try_process_auth_list(deps, &info.sender, &viewers, false, AddrType::Viewer)
         }
+        ExecuteMsg::AddViewerContracts { contracts, entropy } => {
+            try_add_viewer_contracts(deps, &env, &info, contracts, &entropy)
+        }
+        ExecuteMsg::RemoveViewerContracts { contracts } => {
+            try_remove_viewer_contracts(deps, &info.sender, &contracts)
+        }
+        ExecuteMsg::RotateViewerKeys { entropy } => {
+            try_rotate_viewer_keys(deps, &env, &info, &entropy)
+        }
         ExecuteMsg::AddMinters { minters } => {
             try_process_auth_list(deps, &info.sender, &minters, true, AddrType::Minter)
         }
@@ -124,9 +228,40 @@ pub fn execute(deps: DepsMut, env: Env, info: MessageInfo, msg: ExecuteMsg) -> S
         ExecuteMsg::ModifyDependencies { dependencies } => {
             try_process_dep_list(deps, &info.sender, &dependencies, Action::Modify)
         }
+        ExecuteMsg::SetRenderConfig { config } => try_set_render_config(deps, &info.sender, config),
+        ExecuteMsg::SetSvgFragments { fragments } => {
+            try_set_svg_fragments(deps, &info.sender, fragments)
+        }
+        ExecuteMsg::SetPalettes { palettes } => try_set_palettes(deps, &info.sender, palettes),
+        ExecuteMsg::SetEffects { effects } => try_set_effects(deps, &info.sender, effects),
+        ExecuteMsg::RemoveEffects { names } => try_remove_effects(deps, &info.sender, &names),
+        ExecuteMsg::RemoveSvgLibraryItems {
+            fragments,
+            palettes,
+        } => try_remove_svg_library_items(deps, &info.sender, &fragments, &palettes),
+        ExecuteMsg::AddRules { rules } => {
+            try_process_rule_list(deps, &info.sender, &rules, Action::Add)
+        }
+        ExecuteMsg::RemoveRules { rules } => {
+            try_process_rule_list(deps, &info.sender, &rules, Action::Remove)
+        }
+        ExecuteMsg::ModifyRules { rules } => {
+            try_process_rule_list(deps, &info.sender, &rules, Action::Modify)
+        }
+        ExecuteMsg::SetConflictPolicy { policy } => {
+            try_set_conflict_policy(deps, &info.sender, policy)
+        }
         ExecuteMsg::RevokePermit { permit_name } => {
             revoke_permit(deps.storage, &info.sender, &permit_name)
         }
+        ExecuteMsg::RollImage { entropy, legacy } => {
+            try_roll_images(deps, &env, &info.sender, &entropy, None, legacy)
+        }
+        ExecuteMsg::RollImages {
+            entropy,
+            count,
+            legacy,
+        } => try_roll_images(deps, &env, &info.sender, &entropy, Some(count), legacy),
     };
     pad_handle_result(response, BLOCK_SIZE)
 }
@@ -207,9 +342,9 @@ fn try_modify_category(
 
     let cat_name_key = name.as_bytes();
     let mut cat_map = PrefixedStorage::new(deps.storage, PREFIX_CATEGORY_MAP);
-    if let Some(cat_idx) = may_load::<u8>(&cat_map, cat_name_key)? {
+    if let Some(cat_idx) = may_load::<u16>(&cat_map, cat_name_key)? {
         let mut save_cat = false;
-        let cat_key = cat_idx.to_le_bytes();
+        let cat_key = idx_key(cat_idx);
         let mut may_cat: Option<Category> = None;
         if let Some(new_nm) = new_name {
             if new_nm != name {
@@ -298,10 +433,11 @@ fn try_add_categories(
     check_admin_tx(deps.as_ref(), sender)?;
 
     let mut state: State = load(deps.storage, STATE_KEY)?;
+    let library = load_svg_library(deps.storage)?;
     for cat_inf in categories.into_iter() {
         let cat_name_key = cat_inf.name.as_bytes();
         let cat_map = ReadonlyPrefixedStorage::new(deps.storage, PREFIX_CATEGORY_MAP);
-        if may_load::<u8>(&cat_map, cat_name_key)?.is_some() {
+        if may_load::<u16>(&cat_map, cat_name_key)?.is_some() {
             return Err(StdError::generic_err(format!(
                 "Category name:  {} already exists",
                 cat_inf.name
@@ -310,7 +446,7 @@ fn try_add_categories(
         // add the entry to the category map for this category name
         let mut cat_map = PrefixedStorage::new(deps.storage, PREFIX_CATEGORY_MAP);
         save(&mut cat_map, cat_name_key, &state.cat_cnt)?;
-        let cat_key = state.cat_cnt.to_le_bytes();
+        let cat_key = idx_key(state.cat_cnt);
         let mut cat = Category {
             name: cat_inf.name,
             skip: cat_inf.skip,
@@ -319,7 +455,7 @@ fn try_add_categories(
         if cat.skip && !state.skip.contains(&state.cat_cnt) {
             state.skip.push(state.cat_cnt);
         }
-        add_variants(deps.storage, &cat_key, cat_inf.variants, &mut cat)?;
+        add_variants(deps.storage, &cat_key, cat_inf.variants, &mut cat, &library)?;
         let mut cat_store = PrefixedStorage::new(deps.storage, PREFIX_CATEGORY);
         save(&mut cat_store, &cat_key, &cat)?;
         state.cat_cnt = state
@@ -352,19 +488,21 @@ fn try_modify_variants(
 ) -> StdResult<Response> {
     // only allow admins to do this
     check_admin_tx(deps.as_ref(), sender)?;
+    let library = load_svg_library(deps.storage)?;
 
     for cat_inf in modifications.into_iter() {
         let cat_name = cat_inf.category;
         let cat_name_key = cat_name.as_bytes();
         let cat_map = ReadonlyPrefixedStorage::new(deps.storage, PREFIX_CATEGORY_MAP);
         // if valid category name
-        if let Some(cat_idx) = may_load::<u8>(&cat_map, cat_name_key)? {
-            let cat_key = cat_idx.to_le_bytes();
+        if let Some(cat_idx) = may_load::<u16>(&cat_map, cat_name_key)? {
+            let cat_key = idx_key(cat_idx);
             for var_mod in cat_inf.modifications.into_iter() {
+                check_variant_svg(&var_mod.modified_variant, &cat_name, &library)?;
                 let var_name_key = var_mod.name.as_bytes();
                 let mut var_map =
                     PrefixedStorage::multilevel(deps.storage, &[PREFIX_VARIANT_MAP, &cat_key]);
-                let var_idx: u8 = may_load(&var_map, var_name_key)?.ok_or_else(|| {
+                let var_idx: u16 = may_load(&var_map, var_name_key)?.ok_or_else(|| {
                     StdError::generic_err(format!(
                         "Category {} does not have a variant named {}",
                         &cat_name, var_mod.name
@@ -382,11 +520,7 @@ fn try_modify_variants(
                 }
                 let mut var_store =
                     PrefixedStorage::multilevel(deps.storage, &[PREFIX_VARIANT, &cat_key]);
-                save(
-                    &mut var_store,
-                    &var_idx.to_le_bytes(),
-                    &var_mod.modified_variant,
-                )?;
+                save(&mut var_store, &idx_key(var_idx), &var_mod.modified_variant)?;
             }
         } else {
             return Err(StdError::generic_err(format!(
@@ -405,46 +539,65 @@ fn try_modify_variants(
 
 /// Returns StdResult<Response>
 ///
-/// adds new trait variants to existing categories
+/// sets whether variants are active, hidden from rolls, or retired
 ///
 /// # Arguments
 ///
 /// * `deps` - a mutable reference to Extern containing all the contract's external dependencies
 /// * `sender` - a reference to the message sender
-/// * `variants` - the new trait variants and the categories they belong to
-fn try_add_variants(
+/// * `statuses` - the variants and their new statuses
+fn try_set_variant_status(
     deps: DepsMut,
     sender: &Addr,
-    variants: Vec<AddVariantInfo>,
+    statuses: Vec<VariantStatusInfo>,
 ) -> StdResult<Response> {
     // only allow admins to do this
     check_admin_tx(deps.as_ref(), sender)?;
 
-    for cat_inf in variants.into_iter() {
-        let cat_name_key = cat_inf.category_name.as_bytes();
-        let cat_map = ReadonlyPrefixedStorage::new(deps.storage, PREFIX_CATEGORY_MAP);
-        if let Some(cat_idx) = may_load::<u8>(&cat_map, cat_name_key)? {
-            let cat_key = cat_idx.to_le_bytes();
-            let cat_store = ReadonlyPrefixedStorage::new(deps.storage, PREFIX_CATEGORY);
-            let mut cat: Category = may_load(&cat_store, &cat_key)?.ok_or_else(|| {
-                StdError::generic_err(format!(
-                    "Category storage for {} is corrupt",
-                    cat_inf.category_name
-                ))
-            })?;
-            add_variants(deps.storage, &cat_key, cat_inf.variants, &mut cat)?;
-            let mut cat_store = PrefixedStorage::new(deps.storage, PREFIX_CATEGORY);
-            save(&mut cat_store, &cat_key, &cat)?;
-        } else {
-            return Err(StdError::generic_err(format!(
-                "Category name:  {} does not exist",
-                cat_inf.category_name
-            )));
+    let mut stored: Vec<StoredVariantStatus> =
+        may_load(deps.storage, VARIANT_STATUS_KEY)?.unwrap_or_default();
+    for info in statuses.into_iter() {
+        let id = info.id.to_stored(deps.storage)?;
+        stored.retain(|s| s.id != id);
+        match info.status {
+            VariantStatus::Active => {}
+            VariantStatus::Hidden => stored.push(StoredVariantStatus {
+                id,
+                replacement: None,
+            }),
+            VariantStatus::Retired { replacement } => {
+                let repl = LayerId {
+                    category: info.id.category.clone(),
+                    variant: replacement.clone(),
+                }
+                .to_stored(deps.storage)?;
+                if repl == id {
+                    return Err(StdError::generic_err(format!(
+                        "Variant {} can not be its own replacement",
+                        info.id.variant
+                    )));
+                }
+                // replacements must be renderable, which also keeps retirements acyclic
+                if stored
+                    .iter()
+                    .any(|s| s.id == repl && s.replacement.is_some())
+                {
+                    return Err(StdError::generic_err(format!(
+                        "Variant {} can not replace {} because it is retired",
+                        replacement, info.id.variant
+                    )));
+                }
+                stored.push(StoredVariantStatus {
+                    id,
+                    replacement: Some(repl.variant),
+                });
+            }
         }
     }
+    save(deps.storage, VARIANT_STATUS_KEY, &stored)?;
 
     Ok(
-        Response::new().set_data(to_binary(&ExecuteAnswer::AddVariants {
+        Response::new().set_data(to_binary(&ExecuteAnswer::SetVariantStatus {
             status: "success".to_string(),
         })?),
     )
@@ -452,1147 +605,3243 @@ fn try_add_variants(
 
 /// Returns StdResult<Response>
 ///
-/// creates a viewing key
+/// adds svg fragments to the shared library, replacing any with the same names
 ///
 /// # Arguments
 ///
-/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
-/// * `env` - a reference to the Env of contract's environment
-/// * `info` - calling message information MessageInfo
-/// * `entropy` - string slice of the input String to be used as entropy in randomization
-fn try_create_key(
+/// * `deps` - a mutable reference to Extern containing all the contract's external dependencies
+/// * `sender` - a reference to the message sender
+/// * `fragments` - the new fragments
+fn try_set_svg_fragments(
     deps: DepsMut,
-    env: &Env,
-    info: &MessageInfo,
-    entropy: &str,
+    sender: &Addr,
+    fragments: Vec<SvgFragment>,
 ) -> StdResult<Response> {
-    let key = ViewingKey::create(
-        deps.storage,
-        info,
-        env,
-        info.sender.as_str(),
-        entropy.as_ref(),
-    );
-    Ok(Response::new().set_data(to_binary(&ExecuteAnswer::ViewingKey { key })?))
+    // only allow admins to do this
+    check_admin_tx(deps.as_ref(), sender)?;
+    let old_library = load_svg_library(deps.storage)?;
+    let mut library: Vec<SvgFragment> = old_library.fragments.clone();
+    for frag in fragments.into_iter() {
+        check_library_name(&frag.name)?;
+        if frag.svg.contains(FRAGMENT_TAG) {
+            return Err(StdError::generic_err(format!(
+                "SVG fragment {} can not reference other fragments",
+                frag.name
+            )));
+        }
+        library.retain(|f| f.name != frag.name);
+        library.push(frag);
+    }
+    let new_library = SvgLibrary {
+        fragments: library,
+        palettes: old_library.palettes.clone(),
+    };
+    check_library_change(deps.storage, &old_library, &new_library)?;
+    save(deps.storage, SVG_FRAGMENTS_KEY, &new_library.fragments)?;
+
+    Ok(
+        Response::new().set_data(to_binary(&ExecuteAnswer::SetSvgFragments {
+            status: "success".to_string(),
+        })?),
+    )
 }
 
 /// Returns StdResult<Response>
 ///
-/// sets the viewing key to the input String
+/// adds color palettes to the shared library, replacing any with the same names
 ///
 /// # Arguments
 ///
-/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `deps` - a mutable reference to Extern containing all the contract's external dependencies
 /// * `sender` - a reference to the message sender
-/// * `key` - String to be used as the viewing key
-fn try_set_key(deps: DepsMut, sender: &Addr, key: String) -> StdResult<Response> {
-    ViewingKey::set(deps.storage, sender.as_str(), &key);
+/// * `palettes` - the new palettes
+fn try_set_palettes(deps: DepsMut, sender: &Addr, palettes: Vec<Palette>) -> StdResult<Response> {
+    // only allow admins to do this
+    check_admin_tx(deps.as_ref(), sender)?;
+    let old_library = load_svg_library(deps.storage)?;
+    let mut library: Vec<Palette> = old_library.palettes.clone();
+    for palette in palettes.into_iter() {
+        check_library_name(&palette.name)?;
+        library.retain(|p| p.name != palette.name);
+        library.push(palette);
+    }
+    let new_library = SvgLibrary {
+        fragments: old_library.fragments.clone(),
+        palettes: library,
+    };
+    // a palette may not shrink below what the variants using it need
+    check_library_change(deps.storage, &old_library, &new_library)?;
+    save(deps.storage, PALETTES_KEY, &new_library.palettes)?;
 
-    Ok(Response::new().set_data(to_binary(&ExecuteAnswer::ViewingKey { key })?))
+    Ok(
+        Response::new().set_data(to_binary(&ExecuteAnswer::SetPalettes {
+            status: "success".to_string(),
+        })?),
+    )
 }
 
 /// Returns StdResult<Response>
 ///
-/// revoke the ability to use a specified permit
+/// removes svg fragments and color palettes from the shared library.  Items that variants still
+/// reference can not be removed
 ///
 /// # Arguments
 ///
-/// * `storage` - mutable reference to the contract's storage
-/// * `sender` - a reference to the message sender address
-/// * `permit_name` - string slice of the name of the permit to revoke
-fn revoke_permit(
-    storage: &mut dyn Storage,
+/// * `deps` - a mutable reference to Extern containing all the contract's external dependencies
+/// * `sender` - a reference to the message sender
+/// * `fragments` - names of the fragments to remove
+/// * `palettes` - names of the palettes to remove
+fn try_remove_svg_library_items(
+    deps: DepsMut,
     sender: &Addr,
-    permit_name: &str,
+    fragments: &[String],
+    palettes: &[String],
 ) -> StdResult<Response> {
-    RevokedPermits::revoke_permit(
-        storage,
-        PREFIX_REVOKED_PERMITS,
-        sender.as_str(),
-        permit_name,
-    );
+    // only allow admins to do this
+    check_admin_tx(deps.as_ref(), sender)?;
+    let old_library = load_svg_library(deps.storage)?;
+    let new_library = SvgLibrary {
+        fragments: old_library
+            .fragments
+            .iter()
+            .filter(|f| !fragments.contains(&f.name))
+            .cloned()
+            .collect(),
+        palettes: old_library
+            .palettes
+            .iter()
+            .filter(|p| !palettes.contains(&p.name))
+            .cloned()
+            .collect(),
+    };
+    check_library_change(deps.storage, &old_library, &new_library)?;
+    if new_library.fragments.len() != old_library.fragments.len() {
+        save(deps.storage, SVG_FRAGMENTS_KEY, &new_library.fragments)?;
+    }
+    if new_library.palettes.len() != old_library.palettes.len() {
+        save(deps.storage, PALETTES_KEY, &new_library.palettes)?;
+    }
 
     Ok(
-        Response::new().set_data(to_binary(&ExecuteAnswer::RevokePermit {
+        Response::new().set_data(to_binary(&ExecuteAnswer::RemoveSvgLibraryItems {
             status: "success".to_string(),
         })?),
     )
 }
 
-/////////////////////////////////////// Query /////////////////////////////////////
-/// Returns StdResult<Binary>
+/// Returns StdResult<Response>
+///
+/// adds effect layers, replacing any with the same names
 ///
 /// # Arguments
 ///
-/// * `deps` - reference to Extern containing all the contract's external dependencies
-/// * `env` - Env of contract's environment
-/// * `msg` - QueryMsg passed in with the query call
-#[entry_point]
-pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
-    let response = match msg {
-        QueryMsg::AuthorizedAddresses { viewer, permit } => {
-            query_addresses(deps, viewer, permit, &env.contract.address)
-        }
-        QueryMsg::Category {
-            viewer,
-            permit,
-            name,
-            index,
-            start_at,
-            limit,
-            display_svg,
-        } => query_category(
-            deps,
-            viewer,
-            permit,
-            name.as_deref(),
-            index,
-            start_at,
-            limit,
-            display_svg,
-            &env.contract.address,
-        ),
-        QueryMsg::Variant {
-            viewer,
-            permit,
-            by_name,
-            by_index,
-            display_svg,
-        } => query_variant(
-            deps,
-            viewer,
-            permit,
-            by_name.as_ref(),
-            by_index,
-            display_svg,
-            &env.contract.address,
-        ),
-        QueryMsg::CommonMetadata { viewer, permit } => {
-            query_common_metadata(deps, viewer, permit, &env.contract.address)
+/// * `deps` - a mutable reference to Extern containing all the contract's external dependencies
+/// * `sender` - a reference to the message sender
+/// * `effects` - the new effects
+fn try_set_effects(deps: DepsMut, sender: &Addr, effects: Vec<Effect>) -> StdResult<Response> {
+    // only allow admins to do this
+    check_admin_tx(deps.as_ref(), sender)?;
+    let mut stored: Vec<StoredEffect> = may_load(deps.storage, EFFECTS_KEY)?.unwrap_or_default();
+    for effect in effects.iter() {
+        if effect.triggers.is_empty() {
+            return Err(StdError::generic_err(format!(
+                "Effect {} must have at least one trigger",
+                effect.name
+            )));
         }
-        QueryMsg::State { viewer, permit } => {
-            query_state(deps, viewer, permit, &env.contract.address)
+        if let Some(filter) = effect.filter.as_ref() {
+            check_library_name(filter)?;
         }
-        QueryMsg::Dependencies {
-            viewer,
-            permit,
-            start_at,
-            limit,
-        } => query_dependencies(deps, viewer, permit, start_at, limit, &env.contract.address),
-        QueryMsg::TokenMetadata {
-            viewer,
-            permit,
-            image,
-        } => query_token_metadata(deps, viewer, permit, &image, &env.contract.address),
-        QueryMsg::ServeAlchemy { viewer } => query_serve_alchemy(deps, viewer),
-        QueryMsg::SkullType { viewer, image } => query_skull_type(deps, viewer, &image),
-        QueryMsg::SkullTypePlus { viewer } => query_type_plus(deps, viewer),
-        QueryMsg::Transmute {
-            viewer,
-            current,
-            new_layers,
-        } => query_transmute(deps, viewer, current, &new_layers),
-    };
-    pad_query_result(response, BLOCK_SIZE)
+        let new_effect = effect.to_stored(deps.storage)?;
+        stored.retain(|e| e.name != effect.name);
+        stored.push(new_effect);
+    }
+    save(deps.storage, EFFECTS_KEY, &stored)?;
+
+    Ok(
+        Response::new().set_data(to_binary(&ExecuteAnswer::SetEffects {
+            status: "success".to_string(),
+        })?),
+    )
 }
 
-/// Returns StdResult<Binary> which displays the new image vec after transmuting as requested
+/// Returns StdResult<Response>
+///
+/// removes effect layers
 ///
 /// # Arguments
 ///
-/// * `deps` - reference to Extern containing all the contract's external dependencies
-/// * `viewer` - address and key making an authenticated query request
-/// * `current` - the current image indices
-/// * `new_layers` - the new image layers to incorporate
-fn query_transmute(
-    deps: Deps,
-    viewer: ViewerInfo,
-    mut current: Vec<u8>,
-    new_layers: &[LayerId],
-) -> StdResult<Binary> {
-    // only allow viewers to call this
-    check_viewer(deps, viewer)?;
-
-    // can only transmute fully revealed skulls
-    if current.iter().any(|u| *u == 255) {
-        return Err(StdError::generic_err(
-            "Only fully revealed skulls may be transmuted",
-        ));
-    }
-    // change to the transmuted background if it isn't already
-    if current[0] < 6 {
-        let back_idx_key = 0u8.to_le_bytes();
-        let back_var_store =
-            ReadonlyPrefixedStorage::multilevel(deps.storage, &[PREFIX_VARIANT, &back_idx_key]);
-        let var: VariantInfo = may_load(&back_var_store, &current[0].to_le_bytes())?
-            .ok_or_else(|| StdError::generic_err("Variant storage is corrupt"))?;
-        let new_back = format!("Background.{}.Transmuted", &var.display_name);
-        let back_var_map =
-            ReadonlyPrefixedStorage::multilevel(deps.storage, &[PREFIX_VARIANT_MAP, &back_idx_key]);
-        current[0] = may_load(&back_var_map, new_back.as_bytes())?.ok_or_else(|| {
-            StdError::generic_err(format!("Did not find Background variant {}", &new_back))
-        })?;
-    }
-    let dependencies: Vec<StoredDependencies> =
-        may_load(deps.storage, DEPENDENCIES_KEY)?.unwrap_or_default();
-    let state: State = load(deps.storage, STATE_KEY)?;
-    let mut cat_cache: Vec<BackCache> = Vec::new();
-    let mut var_caches: Vec<Vec<BackCache>> = vec![Vec::new(); state.cat_cnt as usize];
-    // update each requested layer
-    for layer in new_layers.iter() {
-        replace_layer(
-            deps.storage,
-            &mut current,
-            layer,
-            &dependencies,
-            &mut cat_cache,
-            &mut var_caches,
-        )?;
+/// * `deps` - a mutable reference to Extern containing all the contract's external dependencies
+/// * `sender` - a reference to the message sender
+/// * `names` - names of the effects to remove
+fn try_remove_effects(deps: DepsMut, sender: &Addr, names: &[String]) -> StdResult<Response> {
+    // only allow admins to do this
+    check_admin_tx(deps.as_ref(), sender)?;
+    let mut stored: Vec<StoredEffect> = may_load(deps.storage, EFFECTS_KEY)?.unwrap_or_default();
+    let old_len = stored.len();
+    stored.retain(|e| !names.contains(&e.name));
+    // only save if the list changed
+    if old_len != stored.len() {
+        save(deps.storage, EFFECTS_KEY, &stored)?;
     }
 
-    to_binary(&QueryAnswer::Transmute { image: current })
+    Ok(
+        Response::new().set_data(to_binary(&ExecuteAnswer::RemoveEffects {
+            status: "success".to_string(),
+        })?),
+    )
 }
 
-/// Returns StdResult<Binary> which displays if a skull is a cyclops and if it is jawless
+/// Returns StdResult<()>
+///
+/// verifies a fragment or palette name can be used in references and svg ids
 ///
 /// # Arguments
 ///
-/// * `deps` - reference to Extern containing all the contract's external dependencies
-/// * `viewer` - address and key making an authenticated query request
-/// * `image` - the image indices
-fn query_skull_type(deps: Deps, viewer: ViewerInfo, image: &[u8]) -> StdResult<Binary> {
-    // only allow viewers to call this
-    check_viewer(deps, viewer)?;
-    let (cyclops, jawless) = get_type_layers(deps.storage)?;
-
-    let is_jawless = image[jawless.category as usize] == jawless.variant;
-    let is_cyclops = image[cyclops.category as usize] == cyclops.variant;
-
-    to_binary(&QueryAnswer::SkullType {
-        is_cyclops,
-        is_jawless,
-    })
+/// * `name` - the name to check
+fn check_library_name(name: &str) -> StdResult<()> {
+    if name.is_empty()
+        || !name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        return Err(StdError::generic_err(format!(
+            "{} is not a valid name.  Names may only contain letters, digits, '-', and '_'",
+            name
+        )));
+    }
+    Ok(())
 }
 
-/// Returns StdResult<Binary> which displays the StoredLayerIds for cyclops and jawless
-/// and displays all skull materials and their indices
+/// Returns StdResult<Response>
+///
+/// sets how token images are rendered in metadata
 ///
 /// # Arguments
 ///
-/// * `deps` - reference to Extern containing all the contract's external dependencies
-/// * `viewer` - address and key making an authenticated query request
-fn query_type_plus(deps: Deps, viewer: ViewerInfo) -> StdResult<Binary> {
-    // only allow viewers to call this
-    check_viewer(deps, viewer)?;
-    // get cyclops and jawless layers
-    let (cyclops, jawless) = get_type_layers(deps.storage)?;
-    // get the skull index
-    let cat_map = ReadonlyPrefixedStorage::new(deps.storage, PREFIX_CATEGORY_MAP);
-    let skull_idx: u8 = may_load(&cat_map, "Skull".as_bytes())?
-        .ok_or_else(|| StdError::generic_err("Skull layer category not found"))?;
-    let skull_key = skull_idx.to_le_bytes();
-    // get the skull category
-    let cat_store = ReadonlyPrefixedStorage::new(deps.storage, PREFIX_CATEGORY);
-    let cat: Category = may_load(&cat_store, &skull_key)?
-        .ok_or_else(|| StdError::generic_err("Skull Category storage is corrupt"))?;
-    let var_store =
-        ReadonlyPrefixedStorage::multilevel(deps.storage, &[PREFIX_VARIANT, &skull_key]);
-    let mut skull_variants: Vec<VariantIdxName> = Vec::new();
-    for idx in 0..cat.cnt {
-        let variant_info: VariantInfo = may_load(&var_store, &idx.to_le_bytes())?
-            .ok_or_else(|| StdError::generic_err("Skull Variant storage is corrupt"))?;
-        skull_variants.push(VariantIdxName {
-            idx,
-            name: variant_info.display_name,
-        });
+/// * `deps` - a mutable reference to Extern containing all the contract's external dependencies
+/// * `sender` - a reference to the message sender
+/// * `config` - the new render config
+fn try_set_render_config(
+    deps: DepsMut,
+    sender: &Addr,
+    config: RenderConfig,
+) -> StdResult<Response> {
+    // only allow admins to do this
+    check_admin_tx(deps.as_ref(), sender)?;
+    if config.mode != RenderMode::Inline && config.base_url.is_none() {
+        return Err(StdError::generic_err(
+            "A base url is required to render image urls",
+        ));
     }
+    if config
+        .svg_header
+        .as_ref()
+        .map_or(false, |h| !h.starts_with("<svg") || !h.ends_with('>'))
+    {
+        return Err(StdError::generic_err(
+            "The svg header must be an opening <svg> tag",
+        ));
+    }
+    save(deps.storage, RENDER_CONFIG_KEY, &config)?;
 
-    to_binary(&QueryAnswer::SkullTypePlus {
-        cyclops,
-        jawless,
-        skull_idx,
-        skull_variants,
-    })
+    Ok(
+        Response::new().set_data(to_binary(&ExecuteAnswer::SetRenderConfig {
+            status: "success".to_string(),
+        })?),
+    )
 }
 
-/// Returns StdResult<Binary> which provides the info needed by alchemy/reveal contracts
+/// Returns StdResult<Response>
+///
+/// sets the default policy used to resolve rule conflicts when transmuting
 ///
 /// # Arguments
 ///
-/// * `deps` - reference to Extern containing all the contract's external dependencies
-/// * `viewer` - address and key making an authenticated query request
-fn query_serve_alchemy(deps: Deps, viewer: ViewerInfo) -> StdResult<Binary> {
-    // only allow viewers to call this
-    check_viewer(deps, viewer)?;
-
-    let state: State = load(deps.storage, STATE_KEY)?;
-    let cat_store = ReadonlyPrefixedStorage::new(deps.storage, PREFIX_CATEGORY);
-    let category_names = (0..state.cat_cnt)
-        .map(|u| {
-            may_load::<Category>(&cat_store, &u.to_le_bytes())?
-                .ok_or_else(|| StdError::generic_err("Category storage is corrupt"))
-                .map(|r| r.name)
-        })
-        .collect::<StdResult<Vec<String>>>()?;
-    let dependencies: Vec<StoredDependencies> =
-        may_load(deps.storage, DEPENDENCIES_KEY)?.unwrap_or_default();
+/// * `deps` - a mutable reference to Extern containing all the contract's external dependencies
+/// * `sender` - a reference to the message sender
+/// * `policy` - the new default policy
+fn try_set_conflict_policy(
+    deps: DepsMut,
+    sender: &Addr,
+    policy: ConflictPolicy,
+) -> StdResult<Response> {
+    // only allow admins to do this
+    check_admin_tx(deps.as_ref(), sender)?;
+    save(deps.storage, CONFLICT_POLICY_KEY, &policy)?;
 
-    to_binary(&QueryAnswer::ServeAlchemy {
-        skip: state.skip,
-        dependencies,
-        category_names,
-    })
+    Ok(
+        Response::new().set_data(to_binary(&ExecuteAnswer::SetConflictPolicy {
+            status: "success".to_string(),
+        })?),
+    )
 }
 
-/// Returns StdResult<Binary> displaying the number of categories and which ones are skipped
-/// when rolling
+/// Returns StdResult<Response>
+///
+/// sets the genesis roll weights of categories
 ///
 /// # Arguments
 ///
-/// * `deps` - reference to Extern containing all the contract's external dependencies
-/// * `viewer` - optional address and key making an authenticated query request
-/// * `permit` - optional permit with "owner" permission
-/// * `my_addr` - a reference to this contract's address
-fn query_state(
-    deps: Deps,
-    viewer: Option<ViewerInfo>,
-    permit: Option<Permit>,
-    my_addr: &Addr,
-) -> StdResult<Binary> {
+/// * `deps` - a mutable reference to Extern containing all the contract's external dependencies
+/// * `sender` - a reference to the message sender
+/// * `weights` - the categories' new weights
+fn try_set_genesis_weights(
+    deps: DepsMut,
+    sender: &Addr,
+    weights: Vec<CategoryWeights>,
+) -> StdResult<Response> {
     // only allow admins to do this
-    check_admin_query(deps, viewer, permit, my_addr)?;
-    let state: State = load(deps.storage, STATE_KEY)?;
-    // map indices to string names
-    let cat_store = ReadonlyPrefixedStorage::new(deps.storage, PREFIX_CATEGORY);
-    let skip = state
-        .skip
-        .iter()
-        .map(|u| {
-            may_load::<Category>(&cat_store, &u.to_le_bytes())?
-                .ok_or_else(|| StdError::generic_err("Category storage is corrupt"))
-                .map(|r| r.name)
-        })
-        .collect::<StdResult<Vec<String>>>()?;
+    check_admin_tx(deps.as_ref(), sender)?;
 
-    to_binary(&QueryAnswer::State {
-        category_count: state.cat_cnt,
-        skip,
-    })
+    for cat_wgts in weights.into_iter() {
+        let cat_map = ReadonlyPrefixedStorage::new(deps.storage, PREFIX_CATEGORY_MAP);
+        let cat_idx: u16 = may_load(&cat_map, cat_wgts.category.as_bytes())?.ok_or_else(|| {
+            StdError::generic_err(format!(
+                "Category name:  {} does not exist",
+                &cat_wgts.category
+            ))
+        })?;
+        let mut stored: Vec<StoredVariantWeight> = Vec::new();
+        for var_wgt in cat_wgts.variants.into_iter() {
+            let variant = LayerId {
+                category: cat_wgts.category.clone(),
+                variant: var_wgt.name.clone(),
+            }
+            .to_stored(deps.storage)?
+            .variant;
+            if stored.iter().any(|s| s.variant == variant) {
+                return Err(StdError::generic_err(format!(
+                    "Variant {} has been given more than one weight",
+                    var_wgt.name
+                )));
+            }
+            let mut conditional: Vec<StoredConditionalWeight> = Vec::new();
+            for cond in var_wgt.conditional.into_iter() {
+                let layer = cond.layer.to_stored(deps.storage)?;
+                // categories are rolled in index order
+                if layer.category >= cat_idx {
+                    return Err(StdError::generic_err(format!(
+                        "The weight of {} can only depend on categories rolled before {}",
+                        var_wgt.name, &cat_wgts.category
+                    )));
+                }
+                conditional.push(StoredConditionalWeight {
+                    layer,
+                    weight: cond.weight,
+                });
+            }
+            stored.push(StoredVariantWeight {
+                variant,
+                weight: var_wgt.weight,
+                conditional,
+            });
+        }
+        let mut wgt_store = PrefixedStorage::new(deps.storage, PREFIX_GENESIS_WEIGHTS);
+        save(&mut wgt_store, &idx_key(cat_idx), &stored)?;
+    }
+
+    Ok(
+        Response::new().set_data(to_binary(&ExecuteAnswer::SetGenesisWeights {
+            status: "success".to_string(),
+        })?),
+    )
 }
 
-/// Returns StdResult<Binary> displaying the trait variants that require other trait variants
+/// Returns StdResult<Response>
+///
+/// adds new trait variants to existing categories
 ///
 /// # Arguments
 ///
-/// * `deps` - reference to Extern containing all the contract's external dependencies
-/// * `viewer` - optional address and key making an authenticated query request
-/// * `permit` - optional permit with "owner" permission
-/// * `start_at` - optional dependency index to start the display
-/// * `limit` - optional max number of dependencies to display
-/// * `my_addr` - a reference to this contract's address
-fn query_dependencies(
-    deps: Deps,
-    viewer: Option<ViewerInfo>,
-    permit: Option<Permit>,
-    start_at: Option<u16>,
-    limit: Option<u16>,
-    my_addr: &Addr,
-) -> StdResult<Binary> {
+/// * `deps` - a mutable reference to Extern containing all the contract's external dependencies
+/// * `sender` - a reference to the message sender
+/// * `variants` - the new trait variants and the categories they belong to
+fn try_add_variants(
+    deps: DepsMut,
+    sender: &Addr,
+    variants: Vec<AddVariantInfo>,
+) -> StdResult<Response> {
     // only allow admins to do this
-    check_admin_query(deps, viewer, permit, my_addr)?;
-    let max = limit.unwrap_or(100);
-    let start = start_at.unwrap_or(0);
-    let dependencies: Vec<StoredDependencies> =
-        may_load(deps.storage, DEPENDENCIES_KEY)?.unwrap_or_default();
-    let count = dependencies.len() as u16;
-    to_binary(&QueryAnswer::Dependencies {
-        count,
-        dependencies: dependencies
-            .iter()
-            .skip(start as usize)
-            .take(max as usize)
-            .map(|d| d.to_display(deps.storage))
-            .collect::<StdResult<Vec<Dependencies>>>()?,
-    })
+    check_admin_tx(deps.as_ref(), sender)?;
+    let library = load_svg_library(deps.storage)?;
+
+    for cat_inf in variants.into_iter() {
+        let cat_name_key = cat_inf.category_name.as_bytes();
+        let cat_map = ReadonlyPrefixedStorage::new(deps.storage, PREFIX_CATEGORY_MAP);
+        if let Some(cat_idx) = may_load::<u16>(&cat_map, cat_name_key)? {
+            let cat_key = idx_key(cat_idx);
+            let cat_store = ReadonlyPrefixedStorage::new(deps.storage, PREFIX_CATEGORY);
+            let mut cat: Category = may_load(&cat_store, &cat_key)?.ok_or_else(|| {
+                StdError::generic_err(format!(
+                    "Category storage for {} is corrupt",
+                    cat_inf.category_name
+                ))
+            })?;
+            add_variants(deps.storage, &cat_key, cat_inf.variants, &mut cat, &library)?;
+            let mut cat_store = PrefixedStorage::new(deps.storage, PREFIX_CATEGORY);
+            save(&mut cat_store, &cat_key, &cat)?;
+        } else {
+            return Err(StdError::generic_err(format!(
+                "Category name:  {} does not exist",
+                cat_inf.category_name
+            )));
+        }
+    }
+
+    Ok(
+        Response::new().set_data(to_binary(&ExecuteAnswer::AddVariants {
+            status: "success".to_string(),
+        })?),
+    )
 }
 
-/// Returns StdResult<Binary> displaying a layer variant
+/// Returns StdResult<Response>
+///
+/// creates a viewing key
 ///
 /// # Arguments
 ///
-/// * `deps` - reference to Extern containing all the contract's external dependencies
-/// * `viewer` - optional address and key making an authenticated query request
-/// * `permit` - optional permit with "owner" permission
-/// * `by_name` - optional reference to the LayerId using string names
-/// * `by_index` - optional StoredLayerId using indices
-/// * `display_svg` - optionally true if svgs should be displayed
-/// * `my_addr` - a reference to this contract's address
-fn query_variant(
-    deps: Deps,
-    viewer: Option<ViewerInfo>,
-    permit: Option<Permit>,
-    by_name: Option<&LayerId>,
-    by_index: Option<StoredLayerId>,
-    display_svg: Option<bool>,
-    my_addr: &Addr,
-) -> StdResult<Binary> {
-    // only allow admins to do this
-    check_admin_query(deps, viewer, permit, my_addr)?;
-    let svgs = display_svg.unwrap_or(false);
-    let layer_id = if let Some(id) = by_index {
-        id
-    } else if let Some(id) = by_name {
-        id.to_stored(deps.storage)?
-    } else {
-        return Err(StdError::generic_err(
-            "Must specify a layer ID by either names or indices",
-        ));
-    };
-    // get the dependencies and hiders lists
-    let depends: Vec<StoredDependencies> =
-        may_load(deps.storage, DEPENDENCIES_KEY)?.unwrap_or_default();
-    let var_inf = displ_variant(deps.storage, &layer_id, &depends, svgs)?;
-    to_binary(&QueryAnswer::Variant {
-        category_index: layer_id.category,
-        info: var_inf,
-    })
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `env` - a reference to the Env of contract's environment
+/// * `info` - calling message information MessageInfo
+/// * `entropy` - string slice of the input String to be used as entropy in randomization
+fn try_create_key(
+    deps: DepsMut,
+    env: &Env,
+    info: &MessageInfo,
+    entropy: &str,
+) -> StdResult<Response> {
+    let key = ViewingKey::create(
+        deps.storage,
+        info,
+        env,
+        info.sender.as_str(),
+        entropy.as_ref(),
+    );
+    Ok(Response::new().set_data(to_binary(&ExecuteAnswer::ViewingKey { key })?))
 }
 
-/// Returns StdResult<Binary> displaying a trait category
+/// Returns StdResult<Response>
+///
+/// sets the viewing key to the input String
 ///
 /// # Arguments
 ///
-/// * `deps` - reference to Extern containing all the contract's external dependencies
-/// * `viewer` - optional address and key making an authenticated query request
-/// * `permit` - optional permit with "owner" permission
-/// * `name` - optional name of the category to display
-/// * `index` - optional index of the category to display
-/// * `start_at` - optional variant index to start the display
-/// * `limit` - optional max number of variants to display
-/// * `display_svg` - optionally true if svgs should be displayed
-/// * `my_addr` - a reference to this contract's address
-fn query_category(
-    deps: Deps,
-    viewer: Option<ViewerInfo>,
-    permit: Option<Permit>,
-    name: Option<&str>,
-    index: Option<u8>,
-    start_at: Option<u8>,
-    limit: Option<u8>,
-    display_svg: Option<bool>,
-    my_addr: &Addr,
-) -> StdResult<Binary> {
-    // only allow admins to do this
-    check_admin_query(deps, viewer, permit, my_addr)?;
-    let svgs = display_svg.unwrap_or(false);
-    let max = limit.unwrap_or(if svgs { 5 } else { 30 });
-    let start = start_at.unwrap_or(0);
-    let state: State = load(deps.storage, STATE_KEY)?;
-    let cat_idx = if let Some(nm) = name {
-        let cat_map = ReadonlyPrefixedStorage::new(deps.storage, PREFIX_CATEGORY_MAP);
-        may_load::<u8>(&cat_map, nm.as_bytes())?.ok_or_else(|| {
-            StdError::generic_err(format!("Category name:  {} does not exist", nm))
-        })?
-    } else if let Some(i) = index {
-        if i >= state.cat_cnt {
-            return Err(StdError::generic_err(format!(
-                "There are only {} categories",
-                state.cat_cnt
-            )));
-        }
-        i
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `sender` - a reference to the message sender
+/// * `key` - String to be used as the viewing key
+fn try_set_key(deps: DepsMut, sender: &Addr, key: String) -> StdResult<Response> {
+    ViewingKey::set(deps.storage, sender.as_str(), &key);
+
+    Ok(Response::new().set_data(to_binary(&ExecuteAnswer::ViewingKey { key })?))
+}
+
+/// Returns StdResult<Response>
+///
+/// rolls new genesis images and advances the prng seed, so every roll is only seen once
+///
+/// # Arguments
+///
+/// * `deps` - a mutable reference to Extern containing all the contract's external dependencies
+/// * `env` - a reference to the Env of contract's environment
+/// * `sender` - a reference to the message sender
+/// * `entropy` - entropy used to roll the images
+/// * `count` - optional number of images to roll.  Only one image is rolled if None
+/// * `legacy` - optionally true if the images should be legacy u8 vectors
+fn try_roll_images(
+    deps: DepsMut,
+    env: &Env,
+    sender: &Addr,
+    entropy: &str,
+    count: Option<u16>,
+    legacy: Option<bool>,
+) -> StdResult<Response> {
+    // only allow minters to call this
+    let sender_raw = deps.api.addr_canonicalize(sender.as_str())?;
+    let minters: Vec<CanonicalAddr> = may_load(deps.storage, MINTERS_KEY)?.unwrap_or_default();
+    if !minters.contains(&sender_raw) {
+        return Err(StdError::generic_err("Not a minter"));
+    }
+    let roll_cnt = count.unwrap_or(1);
+    if roll_cnt > MAX_ROLLS {
+        return Err(StdError::generic_err(format!(
+            "Can not roll more than {} images at once",
+            MAX_ROLLS
+        )));
+    }
+    let format = if legacy.unwrap_or(false) {
+        ImageVector::Legacy(Vec::new())
     } else {
-        0u8
+        ImageVector::Versioned {
+            version: IMAGE_VERSION,
+            indices: Vec::new(),
+        }
     };
-    let depends: Vec<StoredDependencies> =
+    let state: State = load(deps.storage, STATE_KEY)?;
+    let dependencies: Vec<StoredDependencies> =
         may_load(deps.storage, DEPENDENCIES_KEY)?.unwrap_or_default();
-    let cat_key = cat_idx.to_le_bytes();
-    let cat_store = ReadonlyPrefixedStorage::new(deps.storage, PREFIX_CATEGORY);
-    let cat: Category = may_load(&cat_store, &cat_key)?
-        .ok_or_else(|| StdError::generic_err("Category storage is corrupt"))?;
-    let end = min(start + max, cat.cnt);
-    let mut variants: Vec<VariantInfoPlus> = Vec::new();
-    for idx in start..end {
-        let layer_id = StoredLayerId {
-            category: cat_idx,
-            variant: idx,
-        };
-        let var_inf = displ_variant(deps.storage, &layer_id, &depends, svgs)?;
-        variants.push(var_inf);
+    let statuses: Vec<StoredVariantStatus> =
+        may_load(deps.storage, VARIANT_STATUS_KEY)?.unwrap_or_default();
+    let rules: Vec<StoredRule> = may_load(deps.storage, RULES_KEY)?.unwrap_or_default();
+    let wgt_store = ReadonlyPrefixedStorage::new(deps.storage, PREFIX_GENESIS_WEIGHTS);
+    let weights = (0..state.cat_cnt)
+        .map(|u| may_load::<Vec<StoredVariantWeight>>(&wgt_store, &idx_key(u)))
+        .collect::<StdResult<Vec<Option<Vec<StoredVariantWeight>>>>>()?;
+    let seed: Vec<u8> = load(deps.storage, PRNG_SEED_KEY)?;
+    let rng_entropy = [
+        entropy.as_bytes(),
+        &env.block.height.to_be_bytes(),
+        &env.block.time.nanos().to_be_bytes(),
+        sender_raw.as_slice(),
+    ]
+    .concat();
+    let mut rng = ContractPrng::new(&seed, &rng_entropy);
+    let mut images: Vec<ImageVector> = Vec::new();
+    for _ in 0..roll_cnt {
+        let mut image: Vec<u16> = vec![UNREVEALED; state.cat_cnt as usize];
+        for cat_idx in 0..state.cat_cnt {
+            let pos = cat_idx as usize;
+            // skip categories that are not rolled or were already set by a dependency
+            if image[pos] != UNREVEALED {
+                continue;
+            }
+            if state.skip.contains(&cat_idx) {
+                image[pos] = 0;
+                continue;
+            }
+            let cat_wgts = weights[pos].as_ref().ok_or_else(|| {
+                StdError::generic_err(format!(
+                    "No genesis weights have been set for category index {}",
+                    cat_idx
+                ))
+            })?;
+            let winner = roll_variant(
+                &mut rng,
+                &image,
+                cat_idx,
+                cat_wgts,
+                &statuses,
+                &dependencies,
+                &rules,
+            )?;
+            set_variant(&mut image, cat_idx, winner, &dependencies);
+        }
+        images.push(format.with_indices(image)?);
     }
+    // later rolls must not repeat these, even with the same entropy in the same block
+    save(deps.storage, PRNG_SEED_KEY, &rng.rand_bytes().to_vec())?;
 
-    to_binary(&QueryAnswer::Category {
-        category_count: state.cat_cnt,
-        index: cat_idx,
-        name: cat.name,
-        skip: cat.skip,
-        variant_count: cat.cnt,
-        variants,
-    })
+    let resp = if count.is_some() {
+        ExecuteAnswer::RollImages { images }
+    } else {
+        ExecuteAnswer::RollImage {
+            image: images
+                .pop()
+                .ok_or_else(|| StdError::generic_err("No image was rolled"))?,
+        }
+    };
+    Ok(Response::new().set_data(to_binary(&resp)?))
 }
 
-/// Returns StdResult<Binary> displaying the admin, minter, and viewer lists
+/// Returns StdResult<Response>
+///
+/// revoke the ability to use a specified permit
 ///
 /// # Arguments
 ///
-/// * `deps` - reference to Extern containing all the contract's external dependencies
-/// * `viewer` - optional address and key making an authenticated query request
-/// * `permit` - optional permit with "owner" permission
-/// * `my_addr` - a reference to this contract's address
-fn query_addresses(
-    deps: Deps,
-    viewer: Option<ViewerInfo>,
-    permit: Option<Permit>,
-    my_addr: &Addr,
-) -> StdResult<Binary> {
-    // only allow admins to do this
-    let admins = check_admin_query(deps, viewer, permit, my_addr)?;
-    let minters: Vec<CanonicalAddr> = may_load(deps.storage, MINTERS_KEY)?.unwrap_or_default();
-    let viewers: Vec<CanonicalAddr> = may_load(deps.storage, VIEWERS_KEY)?.unwrap_or_default();
-    to_binary(&QueryAnswer::AuthorizedAddresses {
-        admins: admins
-            .iter()
-            .map(|a| deps.api.addr_humanize(a))
-            .collect::<StdResult<Vec<Addr>>>()?,
-        minters: minters
-            .iter()
-            .map(|a| deps.api.addr_humanize(a))
-            .collect::<StdResult<Vec<Addr>>>()?,
-        viewers: viewers
-            .iter()
-            .map(|a| deps.api.addr_humanize(a))
-            .collect::<StdResult<Vec<Addr>>>()?,
-    })
+/// * `storage` - mutable reference to the contract's storage
+/// * `sender` - a reference to the message sender address
+/// * `permit_name` - string slice of the name of the permit to revoke
+fn revoke_permit(
+    storage: &mut dyn Storage,
+    sender: &Addr,
+    permit_name: &str,
+) -> StdResult<Response> {
+    RevokedPermits::revoke_permit(
+        storage,
+        PREFIX_REVOKED_PERMITS,
+        sender.as_str(),
+        permit_name,
+    );
+
+    Ok(
+        Response::new().set_data(to_binary(&ExecuteAnswer::RevokePermit {
+            status: "success".to_string(),
+        })?),
+    )
 }
 
-/// Returns StdResult<Binary> displaying the metadata for an NFT's image vector
+/////////////////////////////////////// Query /////////////////////////////////////
+/// Returns StdResult<Binary>
 ///
 /// # Arguments
 ///
 /// * `deps` - reference to Extern containing all the contract's external dependencies
-/// * `viewer` - optional address and key making an authenticated query request
-/// * `permit` - optional permit with "owner" permission
-/// * `image` - list of image indices
-/// * `my_addr` - a reference to this contract's address
-fn query_token_metadata(
-    deps: Deps,
-    viewer: Option<ViewerInfo>,
-    permit: Option<Permit>,
-    image: &[u8],
-    my_addr: &Addr,
-) -> StdResult<Binary> {
-    // only allow authorized addresses to do this
-    let querier = get_querier(deps, viewer, permit, my_addr)?;
-    let viewers: Vec<CanonicalAddr> = may_load(deps.storage, VIEWERS_KEY)?.unwrap_or_default();
-    if !viewers.contains(&querier) {
-        let minters: Vec<CanonicalAddr> = may_load(deps.storage, MINTERS_KEY)?.unwrap_or_default();
-        if !minters.contains(&querier) {
-            let admins: Vec<CanonicalAddr> = load(deps.storage, ADMINS_KEY)?;
-            if !admins.contains(&querier) {
-                return Err(StdError::generic_err("Not authorized"));
-            }
+/// * `env` - Env of contract's environment
+/// * `msg` - QueryMsg passed in with the query call
+#[entry_point]
+pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
+    let response = match msg {
+        QueryMsg::AuthorizedAddresses { viewer, permit } => {
+            query_addresses(deps, viewer, permit, &env.contract.address)
         }
-    }
-    let common: CommonMetadata = may_load(deps.storage, METADATA_KEY)?.unwrap_or(CommonMetadata {
-        public: None,
-        private: None,
-    });
-    let mut public_metadata = common.public.unwrap_or(Metadata {
-        token_uri: None,
-        extension: None,
-    });
-    let mut xten = public_metadata.extension.unwrap_or_default();
-    let state: State = load(deps.storage, STATE_KEY)?;
-    let mut image_data = r###"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 -0.5 24 24" shape-rendering="crispEdges">"###.to_string();
-    let mut attributes: Vec<Trait> = Vec::new();
-    let cat_store = ReadonlyPrefixedStorage::new(deps.storage, PREFIX_CATEGORY);
-    let mut trait_cnt = 0u8;
-    let mut revealed = 0u8;
-    let mut none_cnt = 0u8;
-    // get the hair category index
-    let cat_map = ReadonlyPrefixedStorage::new(deps.storage, PREFIX_CATEGORY_MAP);
-    let hair_idx: u8 = may_load(&cat_map, "Hair".as_bytes())?
-        .ok_or_else(|| StdError::generic_err("Hair layer category not found"))?;
-
-    for (cat_idx, var_idx) in image.iter().enumerate() {
-        let cat_key = (cat_idx as u8).to_le_bytes();
-        let cat: Category = may_load(&cat_store, &cat_key)?
-            .ok_or_else(|| StdError::generic_err("Category storage is corrupt"))?;
-        let disp_trait = !state.skip.contains(&(cat_idx as u8));
-        // 255 means not revealed
-        if *var_idx != 255 || cat_idx == hair_idx as usize {
-            let (mod_var_idx, is_unknown) = if *var_idx == 255 {
-                // if this is unknown Hair
-                let var_map = ReadonlyPrefixedStorage::multilevel(
-                    deps.storage,
-                    &[PREFIX_VARIANT_MAP, &cat_key],
-                );
-                (
-                    may_load(&var_map, "None".as_bytes())?.ok_or_else(|| {
-                        StdError::generic_err("Missing None variant of Hair Category")
-                    })?,
-                    true,
-                )
-            // otherwise it is just a revealed trait
-            } else {
-                if disp_trait {
-                    revealed += 1;
+        QueryMsg::Category {
+            viewer,
+            permit,
+            name,
+            index,
+            start_at,
+            limit,
+            display_svg,
+        } => query_category(
+            deps,
+            viewer,
+            permit,
+            name.as_deref(),
+            index,
+            start_at,
+            limit,
+            display_svg,
+            &env.contract.address,
+        ),
+        QueryMsg::Variant {
+            viewer,
+            permit,
+            by_name,
+            by_index,
+            display_svg,
+        } => query_variant(
+            deps,
+            viewer,
+            permit,
+            by_name.as_ref(),
+            by_index,
+            display_svg,
+            &env.contract.address,
+        ),
+        QueryMsg::CommonMetadata { viewer, permit } => {
+            query_common_metadata(deps, viewer, permit, &env.contract.address)
+        }
+        QueryMsg::State { viewer, permit } => {
+            query_state(deps, viewer, permit, &env.contract.address)
+        }
+        QueryMsg::Dependencies {
+            viewer,
+            permit,
+            start_at,
+            limit,
+        } => query_dependencies(deps, viewer, permit, start_at, limit, &env.contract.address),
+        QueryMsg::ValidateCatalog { viewer, permit } => {
+            query_validate_catalog(deps, viewer, permit, &env.contract.address)
+        }
+        QueryMsg::Rules {
+            viewer,
+            permit,
+            start_at,
+            limit,
+        } => query_rules(deps, viewer, permit, start_at, limit, &env.contract.address),
+        QueryMsg::TokenMetadata {
+            viewer,
+            permit,
+            image,
+            token_id,
+        } => query_token_metadata(
+            deps,
+            viewer,
+            permit,
+            &image,
+            token_id.as_deref(),
+            &env.contract.address,
+        ),
+        QueryMsg::Effects { viewer, permit } => {
+            query_effects(deps, viewer, permit, &env.contract.address)
+        }
+        QueryMsg::SvgLibrary { viewer, permit } => {
+            query_svg_library(deps, viewer, permit, &env.contract.address)
+        }
+        QueryMsg::RenderConfig { viewer, permit } => {
+            query_render_config(deps, viewer, permit, &env.contract.address)
+        }
+        QueryMsg::BatchTokenMetadata {
+            viewer,
+            permit,
+            images,
+            token_ids,
+        } => query_batch_token_metadata(
+            deps,
+            viewer,
+            permit,
+            &images,
+            token_ids.as_deref(),
+            &env.contract.address,
+        ),
+        QueryMsg::ServeAlchemy { viewer } => query_serve_alchemy(deps, viewer),
+        QueryMsg::SkullType { viewer, image } => query_skull_type(deps, viewer, &image),
+        QueryMsg::SkullTypePlus { viewer } => query_type_plus(deps, viewer),
+        QueryMsg::LayerIndices { viewer, layers } => query_layer_indices(deps, viewer, &layers),
+        QueryMsg::GenesisWeights {
+            viewer,
+            permit,
+            category,
+        } => query_genesis_weights(deps, viewer, permit, &category, &env.contract.address),
+        QueryMsg::Transmute {
+            viewer,
+            current,
+            new_layers,
+            policy,
+        } => query_transmute(deps, &env, viewer, current, &new_layers, policy),
+    };
+    pad_query_result(response, BLOCK_SIZE)
+}
+
+/// Returns StdResult<Binary> which displays the new image vec after transmuting as requested
+///
+/// # Arguments
+///
+/// * `deps` - reference to Extern containing all the contract's external dependencies
+/// * `env` - a reference to the Env of contract's environment
+/// * `viewer` - address and key making an authenticated query request
+/// * `image` - the current image indices
+/// * `new_layers` - the new image layers to incorporate
+/// * `policy` - optional policy used to resolve rule conflicts
+fn query_transmute(
+    deps: Deps,
+    env: &Env,
+    viewer: ViewerInfo,
+    image: ImageVector,
+    new_layers: &[LayerId],
+    policy: Option<ConflictPolicy>,
+) -> StdResult<Binary> {
+    // only allow viewers to call this
+    check_viewer(deps, viewer)?;
+
+    let mut current = image.to_indices()?;
+    // can only transmute fully revealed skulls
+    if current.iter().any(|u| *u == UNREVEALED) {
+        return Err(StdError::generic_err(
+            "Only fully revealed skulls may be transmuted",
+        ));
+    }
+    // change to the transmuted background if it isn't already
+    if current[0] < 6 {
+        let back_idx_key = idx_key(0u16);
+        let back_var_store =
+            ReadonlyPrefixedStorage::multilevel(deps.storage, &[PREFIX_VARIANT, &back_idx_key]);
+        let var: VariantInfo = may_load(&back_var_store, &idx_key(current[0]))?
+            .ok_or_else(|| StdError::generic_err("Variant storage is corrupt"))?;
+        let new_back = format!("Background.{}.Transmuted", &var.display_name);
+        let back_var_map =
+            ReadonlyPrefixedStorage::multilevel(deps.storage, &[PREFIX_VARIANT_MAP, &back_idx_key]);
+        current[0] = may_load(&back_var_map, new_back.as_bytes())?.ok_or_else(|| {
+            StdError::generic_err(format!("Did not find Background variant {}", &new_back))
+        })?;
+    }
+    let dependencies: Vec<StoredDependencies> =
+        may_load(deps.storage, DEPENDENCIES_KEY)?.unwrap_or_default();
+    let state: State = load(deps.storage, STATE_KEY)?;
+    let mut cat_cache: Vec<BackCache> = Vec::new();
+    let mut var_caches: Vec<Vec<BackCache>> = vec![Vec::new(); state.cat_cnt as usize];
+    let before = current.clone();
+    // update each requested layer
+    for layer in new_layers.iter() {
+        replace_layer(
+            deps.storage,
+            &mut current,
+            layer,
+            &dependencies,
+            &mut cat_cache,
+            &mut var_caches,
+        )?;
+    }
+    // swap any retired variants for their replacements
+    let statuses: Vec<StoredVariantStatus> =
+        may_load(deps.storage, VARIANT_STATUS_KEY)?.unwrap_or_default();
+    for (cat_idx, var_idx) in current.iter_mut().enumerate() {
+        *var_idx = resolve_variant(&statuses, cat_idx as u16, *var_idx);
+    }
+    // resolve any broken compatibility rules
+    let rules: Vec<StoredRule> = may_load(deps.storage, RULES_KEY)?.unwrap_or_default();
+    if !rules.is_empty() {
+        let policy = if let Some(plcy) = policy {
+            plcy
+        } else {
+            may_load(deps.storage, CONFLICT_POLICY_KEY)?.unwrap_or(ConflictPolicy::Reject)
+        };
+        // the transmuted layers must be kept
+        let cat_map = ReadonlyPrefixedStorage::new(deps.storage, PREFIX_CATEGORY_MAP);
+        let mut fixed = new_layers
+            .iter()
+            .map(|l| use_back_cache(&cat_map, &l.category, &mut cat_cache))
+            .collect::<StdResult<Vec<u16>>>()?;
+        fixed.extend(
+            before
+                .iter()
+                .zip(current.iter())
+                .enumerate()
+                .filter(|(_, (old, new))| old != new)
+                .map(|(i, _)| i as u16),
+        );
+        // only resolve conflicts the transmutation introduced
+        let existing = rule_violations(&rules, &before);
+        let mut rng = if policy == ConflictPolicy::Reroll {
+            let seed: Vec<u8> = load(deps.storage, PRNG_SEED_KEY)?;
+            let rng_entropy = [
+                current
+                    .iter()
+                    .flat_map(|u| u.to_le_bytes())
+                    .collect::<Vec<u8>>()
+                    .as_slice(),
+                &env.block.height.to_be_bytes(),
+                &env.block.time.nanos().to_be_bytes(),
+            ]
+            .concat();
+            Some(ContractPrng::new(&seed, &rng_entropy))
+        } else {
+            None
+        };
+        // each pass resolves one conflict
+        for _ in 0..state.cat_cnt {
+            let violation = match rule_violations(&rules, &current)
+                .into_iter()
+                .find(|v| !existing.contains(v))
+            {
+                Some(v) => v,
+                None => break,
+            };
+            if policy == ConflictPolicy::Reject {
+                return Err(StdError::generic_err(describe_violation(
+                    deps.storage,
+                    &violation,
+                )?));
+            }
+            // change the conflicting layer that was not transmuted
+            let target = violation
+                .conflict
+                .iter()
+                .chain(std::iter::once(&violation.layer))
+                .find(|l| !fixed.contains(&l.category))
+                .cloned();
+            let target = match target {
+                Some(t) => t,
+                None => {
+                    return Err(StdError::generic_err(describe_violation(
+                        deps.storage,
+                        &violation,
+                    )?))
                 }
-                (*var_idx, false)
             };
-            let var_store =
-                ReadonlyPrefixedStorage::multilevel(deps.storage, &[PREFIX_VARIANT, &cat_key]);
-            let var: VariantInfo = may_load(&var_store, &mod_var_idx.to_le_bytes())?
-                .ok_or_else(|| StdError::generic_err("Variant storage is corrupt"))?;
-            image_data.push_str(&var.svg.unwrap_or_default());
-            let value = if is_unknown {
-                "???".to_string()
+            let new_variant = if let Some(prng) = rng.as_mut() {
+                let cat_key = idx_key(target.category);
+                let wgt_store = ReadonlyPrefixedStorage::new(deps.storage, PREFIX_GENESIS_WEIGHTS);
+                let cat_wgts: Vec<StoredVariantWeight> = may_load(&wgt_store, &cat_key)?
+                    .ok_or_else(|| {
+                        StdError::generic_err(format!(
+                            "No genesis weights have been set for category index {}",
+                            target.category
+                        ))
+                    })?;
+                let winner = roll_variant(
+                    prng,
+                    &current,
+                    target.category,
+                    &cat_wgts,
+                    &statuses,
+                    &dependencies,
+                    &rules,
+                )?;
+                let var_store =
+                    ReadonlyPrefixedStorage::multilevel(deps.storage, &[PREFIX_VARIANT, &cat_key]);
+                may_load::<VariantInfo>(&var_store, &idx_key(winner))?
+                    .ok_or_else(|| StdError::generic_err("Variant storage is corrupt"))?
+                    .name
             } else {
-                var.display_name
+                "None".to_string()
             };
-            if disp_trait {
-                // tally the Nones
-                if value == *"None" {
-                    none_cnt += 1;
-                }
-                attributes.push(Trait {
-                    display_type: None,
-                    trait_type: Some(cat.name),
-                    value,
-                    max_value: None,
-                });
-                trait_cnt += 1;
-            }
-        } else if disp_trait {
-            attributes.push(Trait {
-                display_type: None,
-                trait_type: Some(cat.name),
-                value: "???".to_string(),
-                max_value: None,
+            let new_layer = LayerId {
+                category: target.to_display(deps.storage)?.category,
+                variant: new_variant,
+            };
+            replace_layer(
+                deps.storage,
+                &mut current,
+                &new_layer,
+                &dependencies,
+                &mut cat_cache,
+                &mut var_caches,
+            )?;
+        }
+        if let Some(violation) = rule_violations(&rules, &current)
+            .iter()
+            .find(|v| !existing.contains(v))
+        {
+            return Err(StdError::generic_err(format!(
+                "Unable to resolve rule conflict.  {}",
+                describe_violation(deps.storage, violation)?
+            )));
+        }
+    }
+
+    to_binary(&QueryAnswer::Transmute {
+        image: image.with_indices(current)?,
+    })
+}
+
+/// Returns StdResult<Binary> displaying the genesis weights of a category
+///
+/// # Arguments
+///
+/// * `deps` - reference to Extern containing all the contract's external dependencies
+/// * `viewer` - optional address and key making an authenticated query request
+/// * `permit` - optional permit with "owner" permission
+/// * `category` - name of the category
+/// * `my_addr` - a reference to this contract's address
+fn query_genesis_weights(
+    deps: Deps,
+    viewer: Option<ViewerInfo>,
+    permit: Option<Permit>,
+    category: &str,
+    my_addr: &Addr,
+) -> StdResult<Binary> {
+    // only allow admins to do this
+    check_admin_query(deps, viewer, permit, my_addr)?;
+    let cat_map = ReadonlyPrefixedStorage::new(deps.storage, PREFIX_CATEGORY_MAP);
+    let cat_idx: u16 = may_load(&cat_map, category.as_bytes())?.ok_or_else(|| {
+        StdError::generic_err(format!("Category name:  {} does not exist", category))
+    })?;
+    let cat_key = idx_key(cat_idx);
+    let wgt_store = ReadonlyPrefixedStorage::new(deps.storage, PREFIX_GENESIS_WEIGHTS);
+    let stored: Vec<StoredVariantWeight> = may_load(&wgt_store, &cat_key)?.unwrap_or_default();
+    let var_store = ReadonlyPrefixedStorage::multilevel(deps.storage, &[PREFIX_VARIANT, &cat_key]);
+    let variants = stored
+        .into_iter()
+        .map(|s| {
+            Ok(VariantWeight {
+                name: may_load::<VariantInfo>(&var_store, &idx_key(s.variant))?
+                    .ok_or_else(|| StdError::generic_err("Variant storage is corrupt"))?
+                    .name,
+                weight: s.weight,
+                conditional: s
+                    .conditional
+                    .into_iter()
+                    .map(|c| {
+                        Ok(ConditionalWeight {
+                            layer: c.layer.to_display(deps.storage)?,
+                            weight: c.weight,
+                        })
+                    })
+                    .collect::<StdResult<Vec<ConditionalWeight>>>()?,
+            })
+        })
+        .collect::<StdResult<Vec<VariantWeight>>>()?;
+
+    to_binary(&QueryAnswer::GenesisWeights {
+        weights: CategoryWeights {
+            category: category.to_string(),
+            variants,
+        },
+    })
+}
+
+/// Returns StdResult<Binary> which displays if a skull is a cyclops and if it is jawless
+///
+/// # Arguments
+///
+/// * `deps` - reference to Extern containing all the contract's external dependencies
+/// * `viewer` - address and key making an authenticated query request
+/// * `image` - the image indices
+fn query_skull_type(deps: Deps, viewer: ViewerInfo, image: &ImageVector) -> StdResult<Binary> {
+    // only allow viewers to call this
+    check_viewer(deps, viewer)?;
+    let (cyclops, jawless) = get_type_layers(deps.storage)?;
+    let image = image.to_indices()?;
+
+    let is_jawless = image[jawless.category as usize] == jawless.variant;
+    let is_cyclops = image[cyclops.category as usize] == cyclops.variant;
+
+    to_binary(&QueryAnswer::SkullType {
+        is_cyclops,
+        is_jawless,
+    })
+}
+
+/// Returns StdResult<Binary> which displays the StoredLayerIds for cyclops and jawless
+/// and displays all skull materials and their indices
+///
+/// # Arguments
+///
+/// * `deps` - reference to Extern containing all the contract's external dependencies
+/// * `viewer` - address and key making an authenticated query request
+fn query_type_plus(deps: Deps, viewer: ViewerInfo) -> StdResult<Binary> {
+    // only allow viewers to call this
+    check_viewer(deps, viewer)?;
+    // get cyclops and jawless layers
+    let (cyclops, jawless) = get_type_layers(deps.storage)?;
+    // get the skull index
+    let cat_map = ReadonlyPrefixedStorage::new(deps.storage, PREFIX_CATEGORY_MAP);
+    let skull_idx: u16 = may_load(&cat_map, "Skull".as_bytes())?
+        .ok_or_else(|| StdError::generic_err("Skull layer category not found"))?;
+    let skull_key = idx_key(skull_idx);
+    // get the skull category
+    let cat_store = ReadonlyPrefixedStorage::new(deps.storage, PREFIX_CATEGORY);
+    let cat: Category = may_load(&cat_store, &skull_key)?
+        .ok_or_else(|| StdError::generic_err("Skull Category storage is corrupt"))?;
+    let var_store =
+        ReadonlyPrefixedStorage::multilevel(deps.storage, &[PREFIX_VARIANT, &skull_key]);
+    let mut skull_variants: Vec<VariantIdxName> = Vec::new();
+    for idx in 0..cat.cnt {
+        let variant_info: VariantInfo = may_load(&var_store, &idx_key(idx))?
+            .ok_or_else(|| StdError::generic_err("Skull Variant storage is corrupt"))?;
+        skull_variants.push(VariantIdxName {
+            idx,
+            name: variant_info.display_name,
+        });
+    }
+
+    to_binary(&QueryAnswer::SkullTypePlus {
+        cyclops,
+        jawless,
+        skull_idx,
+        skull_variants,
+    })
+}
+
+/// Returns StdResult<Binary> which displays the StoredLayerIds of the requested layers
+///
+/// # Arguments
+///
+/// * `deps` - reference to Extern containing all the contract's external dependencies
+/// * `viewer` - address and key making an authenticated query request
+/// * `layers` - the LayerIds to look up
+fn query_layer_indices(deps: Deps, viewer: ViewerInfo, layers: &[LayerId]) -> StdResult<Binary> {
+    // only allow viewers to call this
+    check_viewer(deps, viewer)?;
+
+    to_binary(&QueryAnswer::LayerIndices {
+        layers: layers
+            .iter()
+            .map(|l| l.to_stored(deps.storage))
+            .collect::<StdResult<Vec<StoredLayerId>>>()?,
+    })
+}
+
+/// Returns StdResult<Binary> which provides the info needed by alchemy/reveal contracts
+///
+/// # Arguments
+///
+/// * `deps` - reference to Extern containing all the contract's external dependencies
+/// * `viewer` - address and key making an authenticated query request
+fn query_serve_alchemy(deps: Deps, viewer: ViewerInfo) -> StdResult<Binary> {
+    // only allow viewers to call this
+    check_viewer(deps, viewer)?;
+
+    let state: State = load(deps.storage, STATE_KEY)?;
+    let cat_store = ReadonlyPrefixedStorage::new(deps.storage, PREFIX_CATEGORY);
+    let category_names = (0..state.cat_cnt)
+        .map(|u| {
+            may_load::<Category>(&cat_store, &idx_key(u))?
+                .ok_or_else(|| StdError::generic_err("Category storage is corrupt"))
+                .map(|r| r.name)
+        })
+        .collect::<StdResult<Vec<String>>>()?;
+    let dependencies: Vec<StoredDependencies> =
+        may_load(deps.storage, DEPENDENCIES_KEY)?.unwrap_or_default();
+
+    to_binary(&QueryAnswer::ServeAlchemy {
+        skip: state.skip,
+        dependencies,
+        category_names,
+    })
+}
+
+/// Returns StdResult<Binary> displaying the number of categories and which ones are skipped
+/// when rolling
+///
+/// # Arguments
+///
+/// * `deps` - reference to Extern containing all the contract's external dependencies
+/// * `viewer` - optional address and key making an authenticated query request
+/// * `permit` - optional permit with "owner" permission
+/// * `my_addr` - a reference to this contract's address
+fn query_state(
+    deps: Deps,
+    viewer: Option<ViewerInfo>,
+    permit: Option<Permit>,
+    my_addr: &Addr,
+) -> StdResult<Binary> {
+    // only allow admins to do this
+    check_admin_query(deps, viewer, permit, my_addr)?;
+    let state: State = load(deps.storage, STATE_KEY)?;
+    // map indices to string names
+    let cat_store = ReadonlyPrefixedStorage::new(deps.storage, PREFIX_CATEGORY);
+    let skip = state
+        .skip
+        .iter()
+        .map(|u| {
+            may_load::<Category>(&cat_store, &idx_key(*u))?
+                .ok_or_else(|| StdError::generic_err("Category storage is corrupt"))
+                .map(|r| r.name)
+        })
+        .collect::<StdResult<Vec<String>>>()?;
+
+    to_binary(&QueryAnswer::State {
+        category_count: state.cat_cnt,
+        skip,
+    })
+}
+
+/// Returns StdResult<Binary> displaying the trait variants that require other trait variants
+///
+/// # Arguments
+///
+/// * `deps` - reference to Extern containing all the contract's external dependencies
+/// * `viewer` - optional address and key making an authenticated query request
+/// * `permit` - optional permit with "owner" permission
+/// * `start_at` - optional dependency index to start the display
+/// * `limit` - optional max number of dependencies to display
+/// * `my_addr` - a reference to this contract's address
+fn query_dependencies(
+    deps: Deps,
+    viewer: Option<ViewerInfo>,
+    permit: Option<Permit>,
+    start_at: Option<u16>,
+    limit: Option<u16>,
+    my_addr: &Addr,
+) -> StdResult<Binary> {
+    // only allow admins to do this
+    check_admin_query(deps, viewer, permit, my_addr)?;
+    let max = limit.unwrap_or(100);
+    let start = start_at.unwrap_or(0);
+    let dependencies: Vec<StoredDependencies> =
+        may_load(deps.storage, DEPENDENCIES_KEY)?.unwrap_or_default();
+    let count = dependencies.len() as u16;
+    to_binary(&QueryAnswer::Dependencies {
+        count,
+        dependencies: dependencies
+            .iter()
+            .skip(start as usize)
+            .take(max as usize)
+            .map(|d| d.to_display(deps.storage))
+            .collect::<StdResult<Vec<Dependencies>>>()?,
+    })
+}
+
+/// Returns StdResult<Binary> listing every inconsistency found in the trait catalog
+///
+/// # Arguments
+///
+/// * `deps` - reference to Extern containing all the contract's external dependencies
+/// * `viewer` - optional address and key making an authenticated query request
+/// * `permit` - optional permit with "owner" permission
+/// * `my_addr` - a reference to this contract's address
+fn query_validate_catalog(
+    deps: Deps,
+    viewer: Option<ViewerInfo>,
+    permit: Option<Permit>,
+    my_addr: &Addr,
+) -> StdResult<Binary> {
+    // only allow admins to do this
+    check_admin_query(deps, viewer, permit, my_addr)?;
+    let dependencies: Vec<StoredDependencies> =
+        may_load(deps.storage, DEPENDENCIES_KEY)?.unwrap_or_default();
+    let mut problems = dependency_problems(deps.storage, &dependencies, None)?;
+    let cat_store = ReadonlyPrefixedStorage::new(deps.storage, PREFIX_CATEGORY);
+    let has_variant = |cat_idx: u16, name: &str| -> StdResult<bool> {
+        let var_map = ReadonlyPrefixedStorage::multilevel(
+            deps.storage,
+            &[PREFIX_VARIANT_MAP, &idx_key(cat_idx)],
+        );
+        Ok(may_load::<u16>(&var_map, name.as_bytes())?.is_some())
+    };
+    // replacing a variant clears its correlated layers to None
+    let mut cleared: Vec<u16> = Vec::new();
+    for layer in dependencies.iter().flat_map(|d| d.correlated.iter()) {
+        if !cleared.contains(&layer.category) {
+            cleared.push(layer.category);
+            if !has_variant(layer.category, "None")? {
+                let cat: Category = may_load(&cat_store, &idx_key(layer.category))?
+                    .ok_or_else(|| StdError::generic_err("Category storage is corrupt"))?;
+                problems.push(format!(
+                    "Category: {} has correlated layers but no None variant to clear them",
+                    cat.name
+                ));
+            }
+        }
+    }
+    let cat_map = ReadonlyPrefixedStorage::new(deps.storage, PREFIX_CATEGORY_MAP);
+    let skull_idx: Option<u16> = may_load(&cat_map, "Skull".as_bytes())?;
+    let chin_idx: Option<u16> = may_load(&cat_map, "Jaw Type".as_bytes())?;
+    // transmuting a skull recolors its jaw unless it is jawless
+    if let Some(skull) = skull_idx {
+        if let Some(chin) = chin_idx {
+            if !has_variant(chin, "None")? {
+                problems
+                    .push("Category: Jaw Type has no None variant for jawless skulls".to_string());
+            }
+            let skull_cat: Category = may_load(&cat_store, &idx_key(skull))?
+                .ok_or_else(|| StdError::generic_err("Category storage is corrupt"))?;
+            let var_store = ReadonlyPrefixedStorage::multilevel(
+                deps.storage,
+                &[PREFIX_VARIANT, &idx_key(skull)],
+            );
+            for idx in 0..skull_cat.cnt {
+                let var: VariantInfo = may_load(&var_store, &idx_key(idx))?
+                    .ok_or_else(|| StdError::generic_err("Variant storage is corrupt"))?;
+                if !has_variant(chin, &var.name)? {
+                    problems.push(format!(
+                        "Category: Jaw Type has no variant matching Skull variant: {}",
+                        var.name
+                    ));
+                }
+            }
+        } else {
+            problems.push("There is a Skull category but no Jaw Type category".to_string());
+        }
+    }
+    // transmuting swaps the first backgrounds for their transmuted versions
+    if let Some(back_cat) = may_load::<Category>(&cat_store, &idx_key(0u16))? {
+        let var_store =
+            ReadonlyPrefixedStorage::multilevel(deps.storage, &[PREFIX_VARIANT, &idx_key(0u16)]);
+        for idx in 0..min(6, back_cat.cnt) {
+            let var: VariantInfo = may_load(&var_store, &idx_key(idx))?
+                .ok_or_else(|| StdError::generic_err("Variant storage is corrupt"))?;
+            let transmuted = format!("Background.{}.Transmuted", &var.display_name);
+            if !has_variant(0, &transmuted)? {
+                problems.push(format!(
+                    "Category: {} has no transmuted variant named {}",
+                    back_cat.name, transmuted
+                ));
+            }
+        }
+    }
+
+    // every fragment reference must resolve
+    let library = load_svg_library(deps.storage)?;
+    problems.extend(fragment_problems(deps.storage, &library, None)?);
+
+    to_binary(&QueryAnswer::ValidateCatalog { problems })
+}
+
+/// Returns StdResult<Binary> displaying the trait compatibility rules
+///
+/// # Arguments
+///
+/// * `deps` - reference to Extern containing all the contract's external dependencies
+/// * `viewer` - optional address and key making an authenticated query request
+/// * `permit` - optional permit with "owner" permission
+/// * `start_at` - optional rule index to start the display
+/// * `limit` - optional max number of rules to display
+/// * `my_addr` - a reference to this contract's address
+fn query_rules(
+    deps: Deps,
+    viewer: Option<ViewerInfo>,
+    permit: Option<Permit>,
+    start_at: Option<u16>,
+    limit: Option<u16>,
+    my_addr: &Addr,
+) -> StdResult<Binary> {
+    // only allow authorized addresses to do this
+    let querier = get_querier(deps, viewer, permit, my_addr)?;
+    let viewers: Vec<CanonicalAddr> = may_load(deps.storage, VIEWERS_KEY)?.unwrap_or_default();
+    if !viewers.contains(&querier) {
+        let minters: Vec<CanonicalAddr> = may_load(deps.storage, MINTERS_KEY)?.unwrap_or_default();
+        if !minters.contains(&querier) {
+            check_admin(deps.storage, &querier)?;
+        }
+    }
+    let max = limit.unwrap_or(100);
+    let start = start_at.unwrap_or(0);
+    let rules: Vec<StoredRule> = may_load(deps.storage, RULES_KEY)?.unwrap_or_default();
+    let count = rules.len() as u16;
+    to_binary(&QueryAnswer::Rules {
+        count,
+        rules: rules
+            .iter()
+            .skip(start as usize)
+            .take(max as usize)
+            .map(|r| r.to_display(deps.storage))
+            .collect::<StdResult<Vec<Rule>>>()?,
+        policy: may_load(deps.storage, CONFLICT_POLICY_KEY)?.unwrap_or(ConflictPolicy::Reject),
+    })
+}
+
+/// Returns StdResult<Binary> displaying a layer variant
+///
+/// # Arguments
+///
+/// * `deps` - reference to Extern containing all the contract's external dependencies
+/// * `viewer` - optional address and key making an authenticated query request
+/// * `permit` - optional permit with "owner" permission
+/// * `by_name` - optional reference to the LayerId using string names
+/// * `by_index` - optional StoredLayerId using indices
+/// * `display_svg` - optionally true if svgs should be displayed
+/// * `my_addr` - a reference to this contract's address
+fn query_variant(
+    deps: Deps,
+    viewer: Option<ViewerInfo>,
+    permit: Option<Permit>,
+    by_name: Option<&LayerId>,
+    by_index: Option<StoredLayerId>,
+    display_svg: Option<bool>,
+    my_addr: &Addr,
+) -> StdResult<Binary> {
+    // only allow admins to do this
+    check_admin_query(deps, viewer, permit, my_addr)?;
+    let svgs = display_svg.unwrap_or(false);
+    let layer_id = if let Some(id) = by_index {
+        id
+    } else if let Some(id) = by_name {
+        id.to_stored(deps.storage)?
+    } else {
+        return Err(StdError::generic_err(
+            "Must specify a layer ID by either names or indices",
+        ));
+    };
+    // get the dependencies and hiders lists
+    let depends: Vec<StoredDependencies> =
+        may_load(deps.storage, DEPENDENCIES_KEY)?.unwrap_or_default();
+    let statuses: Vec<StoredVariantStatus> =
+        may_load(deps.storage, VARIANT_STATUS_KEY)?.unwrap_or_default();
+    let var_inf = displ_variant(deps.storage, &layer_id, &depends, &statuses, svgs)?;
+    to_binary(&QueryAnswer::Variant {
+        category_index: layer_id.category,
+        info: var_inf,
+    })
+}
+
+/// Returns StdResult<Binary> displaying a trait category
+///
+/// # Arguments
+///
+/// * `deps` - reference to Extern containing all the contract's external dependencies
+/// * `viewer` - optional address and key making an authenticated query request
+/// * `permit` - optional permit with "owner" permission
+/// * `name` - optional name of the category to display
+/// * `index` - optional index of the category to display
+/// * `start_at` - optional variant index to start the display
+/// * `limit` - optional max number of variants to display
+/// * `display_svg` - optionally true if svgs should be displayed
+/// * `my_addr` - a reference to this contract's address
+fn query_category(
+    deps: Deps,
+    viewer: Option<ViewerInfo>,
+    permit: Option<Permit>,
+    name: Option<&str>,
+    index: Option<u16>,
+    start_at: Option<u16>,
+    limit: Option<u16>,
+    display_svg: Option<bool>,
+    my_addr: &Addr,
+) -> StdResult<Binary> {
+    // only allow admins to do this
+    check_admin_query(deps, viewer, permit, my_addr)?;
+    let svgs = display_svg.unwrap_or(false);
+    let max = limit.unwrap_or(if svgs { 5 } else { 30 });
+    let start = start_at.unwrap_or(0);
+    let state: State = load(deps.storage, STATE_KEY)?;
+    let cat_idx = if let Some(nm) = name {
+        let cat_map = ReadonlyPrefixedStorage::new(deps.storage, PREFIX_CATEGORY_MAP);
+        may_load::<u16>(&cat_map, nm.as_bytes())?.ok_or_else(|| {
+            StdError::generic_err(format!("Category name:  {} does not exist", nm))
+        })?
+    } else if let Some(i) = index {
+        if i >= state.cat_cnt {
+            return Err(StdError::generic_err(format!(
+                "There are only {} categories",
+                state.cat_cnt
+            )));
+        }
+        i
+    } else {
+        0u16
+    };
+    let depends: Vec<StoredDependencies> =
+        may_load(deps.storage, DEPENDENCIES_KEY)?.unwrap_or_default();
+    let statuses: Vec<StoredVariantStatus> =
+        may_load(deps.storage, VARIANT_STATUS_KEY)?.unwrap_or_default();
+    let cat_key = idx_key(cat_idx);
+    let cat_store = ReadonlyPrefixedStorage::new(deps.storage, PREFIX_CATEGORY);
+    let cat: Category = may_load(&cat_store, &cat_key)?
+        .ok_or_else(|| StdError::generic_err("Category storage is corrupt"))?;
+    let end = min(start + max, cat.cnt);
+    let mut variants: Vec<VariantInfoPlus> = Vec::new();
+    for idx in start..end {
+        let layer_id = StoredLayerId {
+            category: cat_idx,
+            variant: idx,
+        };
+        let var_inf = displ_variant(deps.storage, &layer_id, &depends, &statuses, svgs)?;
+        variants.push(var_inf);
+    }
+
+    to_binary(&QueryAnswer::Category {
+        category_count: state.cat_cnt,
+        index: cat_idx,
+        name: cat.name,
+        skip: cat.skip,
+        variant_count: cat.cnt,
+        variants,
+    })
+}
+
+/// Returns StdResult<Binary> displaying the admin, minter, and viewer lists
+///
+/// # Arguments
+///
+/// * `deps` - reference to Extern containing all the contract's external dependencies
+/// * `viewer` - optional address and key making an authenticated query request
+/// * `permit` - optional permit with "owner" permission
+/// * `my_addr` - a reference to this contract's address
+fn query_addresses(
+    deps: Deps,
+    viewer: Option<ViewerInfo>,
+    permit: Option<Permit>,
+    my_addr: &Addr,
+) -> StdResult<Binary> {
+    // only allow admins to do this
+    let admins = check_admin_query(deps, viewer, permit, my_addr)?;
+    let minters: Vec<CanonicalAddr> = may_load(deps.storage, MINTERS_KEY)?.unwrap_or_default();
+    let viewers: Vec<CanonicalAddr> = may_load(deps.storage, VIEWERS_KEY)?.unwrap_or_default();
+    let viewer_contracts: Vec<StoreContractInfo> =
+        may_load(deps.storage, VIEWER_CONTRACTS_KEY)?.unwrap_or_default();
+    to_binary(&QueryAnswer::AuthorizedAddresses {
+        admins: admins
+            .iter()
+            .map(|a| deps.api.addr_humanize(a))
+            .collect::<StdResult<Vec<Addr>>>()?,
+        minters: minters
+            .iter()
+            .map(|a| deps.api.addr_humanize(a))
+            .collect::<StdResult<Vec<Addr>>>()?,
+        viewers: viewers
+            .iter()
+            .map(|a| deps.api.addr_humanize(a))
+            .collect::<StdResult<Vec<Addr>>>()?,
+        viewer_contracts: viewer_contracts
+            .iter()
+            .map(|c| c.get_humanized(deps.api))
+            .collect::<StdResult<Vec<ContractInfo>>>()?,
+    })
+}
+
+/// Returns StdResult<Binary> displaying the metadata for an NFT's image vector
+///
+/// # Arguments
+///
+/// * `deps` - reference to Extern containing all the contract's external dependencies
+/// * `viewer` - optional address and key making an authenticated query request
+/// * `permit` - optional permit with "owner" permission
+/// * `image` - list of image indices
+/// * `token_id` - optional token id used to fill in metadata templates
+/// * `my_addr` - a reference to this contract's address
+fn query_token_metadata(
+    deps: Deps,
+    viewer: Option<ViewerInfo>,
+    permit: Option<Permit>,
+    image: &ImageVector,
+    token_id: Option<&str>,
+    my_addr: &Addr,
+) -> StdResult<Binary> {
+    // only allow authorized addresses to do this
+    check_metadata_querier(deps, viewer, permit, my_addr)?;
+    let mut cache = new_metadata_cache(deps.storage)?;
+    let (public_metadata, private_metadata) =
+        image_metadata(deps.storage, &mut cache, image, token_id)?;
+
+    to_binary(&QueryAnswer::Metadata {
+        public_metadata,
+        private_metadata,
+    })
+}
+
+/// Returns StdResult<Binary> displaying the effect layers
+///
+/// # Arguments
+///
+/// * `deps` - reference to Extern containing all the contract's external dependencies
+/// * `viewer` - optional address and key making an authenticated query request
+/// * `permit` - optional permit with "owner" permission
+/// * `my_addr` - a reference to this contract's address
+fn query_effects(
+    deps: Deps,
+    viewer: Option<ViewerInfo>,
+    permit: Option<Permit>,
+    my_addr: &Addr,
+) -> StdResult<Binary> {
+    // only allow admins to do this
+    check_admin_query(deps, viewer, permit, my_addr)?;
+    let stored: Vec<StoredEffect> = may_load(deps.storage, EFFECTS_KEY)?.unwrap_or_default();
+
+    to_binary(&QueryAnswer::Effects {
+        effects: stored
+            .iter()
+            .map(|e| e.to_display(deps.storage))
+            .collect::<StdResult<Vec<Effect>>>()?,
+    })
+}
+
+/// Returns StdResult<Binary> displaying the shared svg fragments and color palettes
+///
+/// # Arguments
+///
+/// * `deps` - reference to Extern containing all the contract's external dependencies
+/// * `viewer` - optional address and key making an authenticated query request
+/// * `permit` - optional permit with "owner" permission
+/// * `my_addr` - a reference to this contract's address
+fn query_svg_library(
+    deps: Deps,
+    viewer: Option<ViewerInfo>,
+    permit: Option<Permit>,
+    my_addr: &Addr,
+) -> StdResult<Binary> {
+    // only allow admins to do this
+    check_admin_query(deps, viewer, permit, my_addr)?;
+    let library = load_svg_library(deps.storage)?;
+
+    to_binary(&QueryAnswer::SvgLibrary {
+        fragments: library.fragments,
+        palettes: library.palettes,
+    })
+}
+
+/// Returns StdResult<Binary> displaying how token images are rendered in metadata
+///
+/// # Arguments
+///
+/// * `deps` - reference to Extern containing all the contract's external dependencies
+/// * `viewer` - optional address and key making an authenticated query request
+/// * `permit` - optional permit with "owner" permission
+/// * `my_addr` - a reference to this contract's address
+fn query_render_config(
+    deps: Deps,
+    viewer: Option<ViewerInfo>,
+    permit: Option<Permit>,
+    my_addr: &Addr,
+) -> StdResult<Binary> {
+    // only allow authorized addresses to do this
+    check_metadata_querier(deps, viewer, permit, my_addr)?;
+
+    to_binary(&QueryAnswer::RenderConfig {
+        config: load_render_config(deps.storage)?,
+    })
+}
+
+/// Returns StdResult<RenderConfig> which is the render config, defaulting to inline svgs
+///
+/// # Arguments
+///
+/// * `storage` - a reference to the contract's storage
+fn load_render_config(storage: &dyn Storage) -> StdResult<RenderConfig> {
+    Ok(
+        may_load(storage, RENDER_CONFIG_KEY)?.unwrap_or(RenderConfig {
+            mode: RenderMode::Inline,
+            base_url: None,
+            include_media: false,
+            fragment_defs: false,
+            svg_header: None,
+        }),
+    )
+}
+
+/// Returns StdResult<Binary> displaying the metadata for multiple image vectors
+///
+/// # Arguments
+///
+/// * `deps` - reference to Extern containing all the contract's external dependencies
+/// * `viewer` - optional address and key making an authenticated query request
+/// * `permit` - optional permit with "owner" permission
+/// * `images` - list of image vectors
+/// * `token_ids` - optional token ids in the same order as the images
+/// * `my_addr` - a reference to this contract's address
+fn query_batch_token_metadata(
+    deps: Deps,
+    viewer: Option<ViewerInfo>,
+    permit: Option<Permit>,
+    images: &[ImageVector],
+    token_ids: Option<&[String]>,
+    my_addr: &Addr,
+) -> StdResult<Binary> {
+    // only allow authorized addresses to do this
+    check_metadata_querier(deps, viewer, permit, my_addr)?;
+    if token_ids.map_or(false, |t| t.len() != images.len()) {
+        return Err(StdError::generic_err(
+            "The number of token ids must match the number of images",
+        ));
+    }
+    let mut cache = new_metadata_cache(deps.storage)?;
+    let metadata = images
+        .iter()
+        .enumerate()
+        .map(|(i, image)| {
+            let token_id = token_ids.and_then(|t| t.get(i)).map(|t| t.as_str());
+            match image_metadata(deps.storage, &mut cache, image, token_id) {
+                Ok((public_metadata, private_metadata)) => BatchMetadata {
+                    public_metadata,
+                    private_metadata,
+                    error: None,
+                },
+                Err(e) => BatchMetadata {
+                    public_metadata: None,
+                    private_metadata: None,
+                    error: Some(e.to_string()),
+                },
+            }
+        })
+        .collect::<Vec<BatchMetadata>>();
+
+    to_binary(&QueryAnswer::BatchTokenMetadata { metadata })
+}
+
+/// Returns StdResult<()> after verifying the querier is an admin, minter, or viewer
+///
+/// # Arguments
+///
+/// * `deps` - a reference to Extern containing all the contract's external dependencies
+/// * `viewer` - optional address and key making an authenticated query request
+/// * `permit` - optional permit with "owner" permission
+/// * `my_addr` - a reference to this contract's address
+fn check_metadata_querier(
+    deps: Deps,
+    viewer: Option<ViewerInfo>,
+    permit: Option<Permit>,
+    my_addr: &Addr,
+) -> StdResult<()> {
+    let querier = get_querier(deps, viewer, permit, my_addr)?;
+    let viewers: Vec<CanonicalAddr> = may_load(deps.storage, VIEWERS_KEY)?.unwrap_or_default();
+    if !viewers.contains(&querier) {
+        let minters: Vec<CanonicalAddr> = may_load(deps.storage, MINTERS_KEY)?.unwrap_or_default();
+        if !minters.contains(&querier) {
+            let admins: Vec<CanonicalAddr> = load(deps.storage, ADMINS_KEY)?;
+            if !admins.contains(&querier) {
+                return Err(StdError::generic_err("Not authorized"));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Returns StdResult<(Option<Metadata>, Option<Metadata>)>
+///
+/// creates the public and private metadata of an image vector
+///
+/// # Arguments
+///
+/// * `storage` - a reference to the contract's storage
+/// * `cache` - a mutable reference to the MetadataCache
+/// * `image` - the image vector
+/// * `token_id` - optional token id used to fill in metadata templates
+fn image_metadata(
+    storage: &dyn Storage,
+    cache: &mut MetadataCache,
+    image: &ImageVector,
+    token_id: Option<&str>,
+) -> StdResult<(Option<Metadata>, Option<Metadata>)> {
+    let mut public_metadata = cache.common.public.clone().unwrap_or(Metadata {
+        token_uri: None,
+        extension: None,
+    });
+    let mut xten = public_metadata.extension.take().unwrap_or_default();
+    let mut image_data = cache
+        .render
+        .svg_header
+        .clone()
+        .unwrap_or_else(|| DEFAULT_SVG_HEADER.to_string());
+    let header_len = image_data.len();
+    // the media hash only covers the trait layers the renderer url encodes, with fragments inlined
+    let hash_layers = cache.render.include_media
+        && cache.render.base_url.is_some()
+        && cache.render.mode != RenderMode::Inline;
+    let mut layer_svg = String::new();
+    let mut defs: Vec<(String, String)> = Vec::new();
+    let mut attributes: Vec<Trait> = Vec::new();
+    let image = image.to_indices()?;
+    let mut rendered: Vec<u16> = vec![UNREVEALED; image.len()];
+    let mut trait_cnt = 0u16;
+    let mut revealed = 0u16;
+    let mut none_cnt = 0u16;
+
+    for (cat_idx, var_idx) in image.iter().enumerate() {
+        let cat_key = idx_key(cat_idx as u16);
+        let cat_name = cache
+            .cat_names
+            .get(cat_idx)
+            .ok_or_else(|| StdError::generic_err("Category storage is corrupt"))?
+            .clone();
+        let disp_trait = !cache.state.skip.contains(&(cat_idx as u16));
+        if *var_idx != UNREVEALED || cat_idx == cache.hair_idx as usize {
+            let (mod_var_idx, is_unknown) = if *var_idx == UNREVEALED {
+                // if this is unknown Hair
+                let var_map =
+                    ReadonlyPrefixedStorage::multilevel(storage, &[PREFIX_VARIANT_MAP, &cat_key]);
+                (
+                    use_back_cache(&var_map, "None", &mut cache.hair_cache).map_err(|_| {
+                        StdError::generic_err("Missing None variant of Hair Category")
+                    })?,
+                    true,
+                )
+            // otherwise it is just a revealed trait
+            } else {
+                if disp_trait {
+                    revealed += 1;
+                }
+                (
+                    resolve_variant(&cache.statuses, cat_idx as u16, *var_idx),
+                    false,
+                )
+            };
+            let var = use_variant_cache(storage, cat_idx as u16, mod_var_idx, &mut cache.variants)?;
+            let svg = var.svg.unwrap_or_default();
+            if svg.contains(FRAGMENT_TAG) {
+                if cache.library.is_none() {
+                    cache.library = Some(load_svg_library(storage)?);
+                }
+                if let Some(library) = cache.library.as_ref() {
+                    if cache.render.fragment_defs {
+                        image_data.push_str(&expand_fragments(&svg, library, Some(&mut defs))?);
+                        if hash_layers {
+                            layer_svg.push_str(&expand_fragments(&svg, library, None)?);
+                        }
+                    } else {
+                        let expanded = expand_fragments(&svg, library, None)?;
+                        if hash_layers {
+                            layer_svg.push_str(&expanded);
+                        }
+                        image_data.push_str(&expanded);
+                    }
+                }
+            } else {
+                image_data.push_str(&svg);
+                if hash_layers {
+                    layer_svg.push_str(&svg);
+                }
+            }
+            rendered[cat_idx] = mod_var_idx;
+            let value = if is_unknown {
+                "???".to_string()
+            } else {
+                var.display_name
+            };
+            if disp_trait {
+                // tally the Nones
+                if value == *"None" {
+                    none_cnt += 1;
+                }
+                attributes.push(Trait {
+                    display_type: None,
+                    trait_type: Some(cat_name),
+                    value,
+                    max_value: None,
+                });
+                trait_cnt += 1;
+            }
+        } else if disp_trait {
+            attributes.push(Trait {
+                display_type: None,
+                trait_type: Some(cat_name),
+                value: "???".to_string(),
+                max_value: None,
+            });
+            trait_cnt += 1;
+        }
+    }
+    let hidden = trait_cnt - revealed;
+    attributes.push(Trait {
+        display_type: None,
+        trait_type: Some("Unrevealed Trait Categories".to_string()),
+        value: format!("{}", hidden),
+        max_value: None,
+    });
+    // display the trait count if fully revealed
+    if hidden == 0 {
+        attributes.push(Trait {
+            display_type: None,
+            trait_type: Some("Trait Count".to_string()),
+            value: format!("{}", trait_cnt - none_cnt),
+            max_value: None,
+        });
+    } else {
+        // count of nones if there are still unrevealed traits
+        attributes.push(Trait {
+            display_type: None,
+            trait_type: Some("Clean Traits (Nones) Currently Revealed".to_string()),
+            value: format!("{}", none_cnt),
+            max_value: None,
+        });
+    }
+    // set the alchemical status
+    let value = if image.first().map_or(false, |b| *b > 5) {
+        "Transmuted".to_string()
+    } else {
+        "Raw".to_string()
+    };
+    let status = value.clone();
+    attributes.push(Trait {
+        display_type: None,
+        trait_type: Some("Alchemical Status".to_string()),
+        value,
+        max_value: None,
+    });
+    // composite any effects triggered by the drawn layers or the alchemical status
+    let mut def_elems = defs.into_iter().map(|(_, e)| e).collect::<String>();
+    let mut underlays = String::new();
+    let mut overlays = String::new();
+    let mut layers = image_data.split_off(header_len);
+    for effect in cache.effects.iter().filter(|e| {
+        e.triggers.iter().any(|t| match t {
+            StoredEffectTrigger::Variant { layer } => {
+                rendered.get(layer.category as usize) == Some(&layer.variant)
+            }
+            StoredEffectTrigger::AlchemicalStatus { status: trig } => *trig == status,
+        })
+    }) {
+        def_elems.push_str(effect.defs.as_deref().unwrap_or_default());
+        underlays.push_str(effect.underlay.as_deref().unwrap_or_default());
+        overlays.push_str(effect.overlay.as_deref().unwrap_or_default());
+        if let Some(filter) = effect.filter.as_ref() {
+            layers = format!(r##"<g filter="url(#{})">{}</g>"##, filter, layers);
+        }
+    }
+    if !def_elems.is_empty() {
+        image_data.push_str(&format!("<defs>{}</defs>", def_elems));
+    }
+    image_data.push_str(&underlays);
+    image_data.push_str(&layers);
+    image_data.push_str(&overlays);
+    image_data.push_str("</svg>");
+    // fill in any per token templates
+    let mut private_metadata = cache.common.private.clone();
+    if let Some(private) = private_metadata.as_mut() {
+        fill_metadata(private, token_id, &attributes);
+    }
+    fill_metadata(&mut public_metadata, token_id, &attributes);
+    fill_extension(&mut xten, token_id, &attributes);
+    // point to the off-chain renderer if configured
+    if let Some(base_url) = cache
+        .render
+        .base_url
+        .as_ref()
+        .filter(|_| cache.render.mode != RenderMode::Inline)
+    {
+        let encoded = [
+            vec![IMAGE_VERSION],
+            rendered
+                .iter()
+                .flat_map(|u| u.to_le_bytes())
+                .collect::<Vec<u8>>(),
+        ]
+        .concat();
+        let url = format!(
+            "{}{}",
+            base_url,
+            general_purpose::URL_SAFE_NO_PAD.encode(encoded)
+        );
+        if hash_layers {
+            let hash = sha_256(layer_svg.as_bytes())
+                .iter()
+                .map(|b| format!("{:02x}", b))
+                .collect::<String>();
+            xten.media.get_or_insert_with(Vec::new).push(MediaFile {
+                file_type: Some("image".to_string()),
+                extension: Some("svg".to_string()),
+                authentication: None,
+                url: format!("{}#sha256={}", url, hash),
+            });
+        }
+        xten.image = Some(url);
+    }
+    if cache.render.mode != RenderMode::Url {
+        xten.image_data = Some(image_data);
+    }
+    xten.attributes = Some(attributes);
+    public_metadata.extension = Some(xten);
+
+    Ok((Some(public_metadata), private_metadata))
+}
+
+/// Returns StdResult<Binary> displaying the metadata common to all NFTs
+///
+/// # Arguments
+///
+/// * `deps` - reference to Extern containing all the contract's external dependencies
+/// * `viewer` - optional address and key making an authenticated query request
+/// * `permit` - optional permit with "owner" permission
+/// * `my_addr` - a reference to this contract's address
+fn query_common_metadata(
+    deps: Deps,
+    viewer: Option<ViewerInfo>,
+    permit: Option<Permit>,
+    my_addr: &Addr,
+) -> StdResult<Binary> {
+    // only allow authorized addresses to do this
+    let querier = get_querier(deps, viewer, permit, my_addr)?;
+    let minters: Vec<CanonicalAddr> = may_load(deps.storage, MINTERS_KEY)?.unwrap_or_default();
+    if !minters.contains(&querier) {
+        let viewers: Vec<CanonicalAddr> = may_load(deps.storage, VIEWERS_KEY)?.unwrap_or_default();
+        if !viewers.contains(&querier) {
+            let admins: Vec<CanonicalAddr> = load(deps.storage, ADMINS_KEY)?;
+            if !admins.contains(&querier) {
+                return Err(StdError::generic_err("Not authorized"));
+            }
+        }
+    }
+    let common: CommonMetadata = may_load(deps.storage, METADATA_KEY)?.unwrap_or(CommonMetadata {
+        public: None,
+        private: None,
+    });
+
+    to_binary(&QueryAnswer::Metadata {
+        public_metadata: common.public,
+        private_metadata: common.private,
+    })
+}
+
+/// Returns StdResult<CanonicalAddr> from determining the querying address
+/// (if possible) either from a Permit or a ViewerInfo
+///
+/// # Arguments
+///
+/// * `deps` - a reference to Extern containing all the contract's external dependencies
+/// * `viewer` - optional address and key making an authenticated query request
+/// * `permit` - optional permit with "owner" permission
+/// * `my_addr` - a reference to this contract's address
+fn get_querier(
+    deps: Deps,
+    viewer: Option<ViewerInfo>,
+    permit: Option<Permit>,
+    my_addr: &Addr,
+) -> StdResult<CanonicalAddr> {
+    if let Some(pmt) = permit {
+        // Validate permit content
+        let querier = validate(
+            deps,
+            PREFIX_REVOKED_PERMITS,
+            &pmt,
+            my_addr.to_string(),
+            Some("secret"),
+        )
+        .and_then(|a| deps.api.addr_validate(&a))
+        .and_then(|a| deps.api.addr_canonicalize(a.as_str()))?;
+        if !pmt.check_permission(&secret_toolkit::permit::TokenPermissions::Owner) {
+            return Err(StdError::generic_err(format!(
+                "Owner permission is required for queries, got permissions {:?}",
+                pmt.params.permissions
+            )));
+        }
+        return Ok(querier);
+    }
+    if let Some(vwr) = viewer {
+        let hmn = deps.api.addr_validate(&vwr.address)?;
+        let raw = deps.api.addr_canonicalize(hmn.as_str())?;
+        ViewingKey::check(deps.storage, hmn.as_str(), &vwr.viewing_key).map_err(|_| {
+            StdError::generic_err("Wrong viewing key for this address or viewing key not set")
+        })?;
+        return Ok(raw);
+    }
+    Err(StdError::generic_err(
+        "A permit or viewing key must be provided",
+    ))
+}
+
+/// Returns StdResult<()> after verifying the querier is a Viewer
+///
+/// # Arguments
+///
+/// * `deps` - a reference to Extern containing all the contract's external dependencies
+/// * `viewer` - address and key making an authenticated query request
+fn check_viewer(deps: Deps, viewer: ViewerInfo) -> StdResult<()> {
+    let querier = get_querier(deps, Some(viewer), None, &Addr::unchecked("Not Used"))?;
+    // only allow viewers to call this
+    let viewers: Vec<CanonicalAddr> = may_load(deps.storage, VIEWERS_KEY)?.unwrap_or_default();
+    if !viewers.contains(&querier) {
+        return Err(StdError::generic_err("Not a viewer"));
+    }
+    Ok(())
+}
+
+/// Returns StdResult<Vec<CanonicalAddr>> which is the admin list and checks if the querier is an admin
+///
+/// # Arguments
+///
+/// * `deps` - a reference to Extern containing all the contract's external dependencies
+/// * `viewer` - optional address and key making an authenticated query request
+/// * `permit` - optional permit with "owner" permission
+/// * `my_addr` - a reference to this contract's address
+fn check_admin_query(
+    deps: Deps,
+    viewer: Option<ViewerInfo>,
+    permit: Option<Permit>,
+    my_addr: &Addr,
+) -> StdResult<Vec<CanonicalAddr>> {
+    let address = get_querier(deps, viewer, permit, my_addr)?;
+    check_admin(deps.storage, &address)
+}
+
+/// Returns StdResult<Vec<CanonicalAddr>> which is the admin list and checks if the message
+/// sender is an admin
+///
+/// # Arguments
+///
+/// * `deps` - a reference to Extern containing all the contract's external dependencies
+/// * `sender` - a reference to the message sender
+fn check_admin_tx(deps: Deps, sender: &Addr) -> StdResult<Vec<CanonicalAddr>> {
+    let sender_raw = deps.api.addr_canonicalize(sender.as_str())?;
+    check_admin(deps.storage, &sender_raw)
+}
+
+/// Returns StdResult<Vec<CanonicalAddr>> which is the admin list and checks if the address
+/// is an admin
+///
+/// # Arguments
+///
+/// * `storage` - a reference to this contract's storage
+/// * `address` - a reference to the address in question
+fn check_admin(storage: &dyn Storage, address: &CanonicalAddr) -> StdResult<Vec<CanonicalAddr>> {
+    let admins: Vec<CanonicalAddr> = load(storage, ADMINS_KEY)?;
+    if !admins.contains(address) {
+        return Err(StdError::generic_err("Not an admin"));
+    }
+    Ok(admins)
+}
+
+pub enum AddrType {
+    Admin,
+    Viewer,
+    Minter,
+}
+
+/// Returns StdResult<Response>
+///
+/// updates the admin, viewer, or minter authorization list
+///
+/// # Arguments
+///
+/// * `deps` - a mutable reference to Extern containing all the contract's external dependencies
+/// * `sender` - a reference to the message sender
+/// * `update_list` - list of addresses to use for update
+/// * `is_add` - true if the update is for adding to the list
+/// * `list` - AddrType to determine which list to update
+fn try_process_auth_list(
+    deps: DepsMut,
+    sender: &Addr,
+    update_list: &[String],
+    is_add: bool,
+    list: AddrType,
+) -> StdResult<Response> {
+    // only allow admins to do this
+    let admins = check_admin_tx(deps.as_ref(), sender)?;
+
+    // get the right authorization list info
+    let (mut current_list, key) = match list {
+        AddrType::Admin => (admins, ADMINS_KEY),
+        AddrType::Viewer => (
+            may_load::<Vec<CanonicalAddr>>(deps.storage, VIEWERS_KEY)?.unwrap_or_default(),
+            VIEWERS_KEY,
+        ),
+        AddrType::Minter => (
+            may_load::<Vec<CanonicalAddr>>(deps.storage, MINTERS_KEY)?.unwrap_or_default(),
+            MINTERS_KEY,
+        ),
+    };
+    // update the authorization list if needed
+    let save_it = if is_add {
+        add_addrs_to_auth(deps.api, &mut current_list, update_list)?
+    } else {
+        remove_addrs_from_auth(deps.api, &mut current_list, update_list)?
+    };
+    // save list if it changed
+    if save_it {
+        save(deps.storage, key, &current_list)?;
+    }
+    let new_list = current_list
+        .iter()
+        .map(|a| deps.api.addr_humanize(a))
+        .collect::<StdResult<Vec<Addr>>>()?;
+    let resp = match list {
+        AddrType::Admin => ExecuteAnswer::AdminsList { admins: new_list },
+        AddrType::Viewer => ExecuteAnswer::ViewersList { viewers: new_list },
+        AddrType::Minter => ExecuteAnswer::MintersList { minters: new_list },
+    };
+    Ok(Response::new().set_data(to_binary(&resp)?))
+}
+
+/// Returns StdResult<Response>
+///
+/// adds contracts to the viewer allowlist and issues each of them a viewing key
+///
+/// # Arguments
+///
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `env` - a reference to the Env of contract's environment
+/// * `info` - calling message information MessageInfo
+/// * `contracts` - code hashes and addresses of the contracts to add
+/// * `entropy` - string slice of the input String to be used as entropy in randomization
+fn try_add_viewer_contracts(
+    deps: DepsMut,
+    env: &Env,
+    info: &MessageInfo,
+    contracts: Vec<ContractInfo>,
+    entropy: &str,
+) -> StdResult<Response> {
+    // only allow admins to do this
+    check_admin_tx(deps.as_ref(), &info.sender)?;
+    let mut viewer_contracts: Vec<StoreContractInfo> =
+        may_load(deps.storage, VIEWER_CONTRACTS_KEY)?.unwrap_or_default();
+    let mut viewers: Vec<CanonicalAddr> = may_load(deps.storage, VIEWERS_KEY)?.unwrap_or_default();
+    let mut messages: Vec<CosmosMsg> = Vec::new();
+    for contract in contracts.into_iter() {
+        let raw = contract.get_store(deps.api)?;
+        if !viewers.contains(&raw.address) {
+            viewers.push(raw.address.clone());
+        }
+        if let Some(existing) = viewer_contracts
+            .iter_mut()
+            .find(|v| v.address == raw.address)
+        {
+            existing.code_hash = raw.code_hash;
+        } else {
+            viewer_contracts.push(raw);
+        }
+        messages.push(issue_viewer_key(
+            deps.storage,
+            env,
+            info,
+            contract,
+            entropy,
+        )?);
+    }
+    save(deps.storage, VIEWERS_KEY, &viewers)?;
+    save(deps.storage, VIEWER_CONTRACTS_KEY, &viewer_contracts)?;
+
+    Ok(Response::new().add_messages(messages).set_data(to_binary(
+        &ExecuteAnswer::ViewerContracts {
+            viewer_contracts: viewer_contracts
+                .iter()
+                .map(|c| c.get_humanized(deps.api))
+                .collect::<StdResult<Vec<ContractInfo>>>()?,
+        },
+    )?))
+}
+
+/// Returns StdResult<Response>
+///
+/// removes contracts from the viewer allowlist
+///
+/// # Arguments
+///
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `sender` - a reference to the message sender
+/// * `contracts` - addresses of the contracts to remove
+fn try_remove_viewer_contracts(
+    deps: DepsMut,
+    sender: &Addr,
+    contracts: &[String],
+) -> StdResult<Response> {
+    // only allow admins to do this
+    check_admin_tx(deps.as_ref(), sender)?;
+    let mut viewer_contracts: Vec<StoreContractInfo> =
+        may_load(deps.storage, VIEWER_CONTRACTS_KEY)?.unwrap_or_default();
+    let mut viewers: Vec<CanonicalAddr> = may_load(deps.storage, VIEWERS_KEY)?.unwrap_or_default();
+    let removals = contracts
+        .iter()
+        .map(|c| deps.api.addr_canonicalize(c))
+        .collect::<StdResult<Vec<CanonicalAddr>>>()?;
+    viewer_contracts.retain(|v| !removals.contains(&v.address));
+    viewers.retain(|v| !removals.contains(v));
+    save(deps.storage, VIEWERS_KEY, &viewers)?;
+    save(deps.storage, VIEWER_CONTRACTS_KEY, &viewer_contracts)?;
+
+    Ok(
+        Response::new().set_data(to_binary(&ExecuteAnswer::ViewerContracts {
+            viewer_contracts: viewer_contracts
+                .into_iter()
+                .map(|c| c.into_humanized(deps.api))
+                .collect::<StdResult<Vec<ContractInfo>>>()?,
+        })?),
+    )
+}
+
+/// Returns StdResult<Response>
+///
+/// issues new viewing keys to every contract on the viewer allowlist
+///
+/// # Arguments
+///
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `env` - a reference to the Env of contract's environment
+/// * `info` - calling message information MessageInfo
+/// * `entropy` - string slice of the input String to be used as entropy in randomization
+fn try_rotate_viewer_keys(
+    deps: DepsMut,
+    env: &Env,
+    info: &MessageInfo,
+    entropy: &str,
+) -> StdResult<Response> {
+    // only allow admins to do this
+    check_admin_tx(deps.as_ref(), &info.sender)?;
+    let viewer_contracts = may_load::<Vec<StoreContractInfo>>(deps.storage, VIEWER_CONTRACTS_KEY)?
+        .unwrap_or_default()
+        .into_iter()
+        .map(|c| c.into_humanized(deps.api))
+        .collect::<StdResult<Vec<ContractInfo>>>()?;
+    let mut messages: Vec<CosmosMsg> = Vec::new();
+    for contract in viewer_contracts.iter() {
+        messages.push(issue_viewer_key(
+            deps.storage,
+            env,
+            info,
+            contract.clone(),
+            entropy,
+        )?);
+    }
+
+    Ok(Response::new().add_messages(messages).set_data(to_binary(
+        &ExecuteAnswer::ViewerContracts { viewer_contracts },
+    )?))
+}
+
+/// Returns StdResult<CosmosMsg>
+///
+/// creates a new viewing key for a viewer contract and the message that delivers it
+///
+/// # Arguments
+///
+/// * `storage` - a mutable reference to this contract's storage
+/// * `env` - a reference to the Env of contract's environment
+/// * `info` - calling message information MessageInfo
+/// * `contract` - code hash and address of the viewer contract
+/// * `entropy` - string slice of the input String to be used as entropy in randomization
+fn issue_viewer_key(
+    storage: &mut dyn Storage,
+    env: &Env,
+    info: &MessageInfo,
+    contract: ContractInfo,
+    entropy: &str,
+) -> StdResult<CosmosMsg> {
+    // mix in the address so each contract in the same tx gets a different key
+    let key = ViewingKey::create(
+        storage,
+        info,
+        env,
+        &contract.address,
+        format!("{}{}", entropy, contract.address).as_bytes(),
+    );
+    ViewerHandleMsg::ReceiveQueryKey { key }.to_cosmos_msg(
+        contract.code_hash,
+        contract.address,
+        None,
+    )
+}
+
+/// Returns StdResult<bool>
+///
+/// adds to an authorization list of addresses and returns true if the list changed
+///
+/// # Arguments
+///
+/// * `api` - a reference to the Api used to convert human and canonical addresses
+/// * `addresses` - current mutable list of addresses
+/// * `addrs_to_add` - list of addresses to add
+fn add_addrs_to_auth(
+    api: &dyn Api,
+    addresses: &mut Vec<CanonicalAddr>,
+    addrs_to_add: &[String],
+) -> StdResult<bool> {
+    let mut save_it = false;
+    for addr in addrs_to_add.iter() {
+        let raw = api
+            .addr_validate(addr)
+            .and_then(|a| api.addr_canonicalize(a.as_str()))?;
+        if !addresses.contains(&raw) {
+            addresses.push(raw);
+            save_it = true;
+        }
+    }
+    Ok(save_it)
+}
+
+/// Returns StdResult<bool>
+///
+/// removes from an authorization list of addresses and returns true if the list changed
+///
+/// # Arguments
+///
+/// * `api` - a reference to the Api used to convert human and canonical addresses
+/// * `addresses` - current mutable list of addresses
+/// * `addrs_to_remove` - list of addresses to remove
+fn remove_addrs_from_auth(
+    api: &dyn Api,
+    addresses: &mut Vec<CanonicalAddr>,
+    addrs_to_remove: &[String],
+) -> StdResult<bool> {
+    let old_len = addresses.len();
+    let rem_list = addrs_to_remove
+        .iter()
+        .map(|a| {
+            api.addr_validate(a)
+                .and_then(|a| api.addr_canonicalize(a.as_str()))
+        })
+        .collect::<StdResult<Vec<CanonicalAddr>>>()?;
+    addresses.retain(|a| !rem_list.contains(a));
+    // only save if the list changed
+    Ok(old_len != addresses.len())
+}
+
+/// Returns StdResult<()>
+///
+/// adds new trait variants to the specified category index
+///
+/// # Arguments
+///
+/// * `storage` - a mutable reference to the contract's storage
+/// * `cat_key` - index of the category these variants belong to
+/// * `variants` - variants to add to this category
+/// * `cat` - a mutable reference to this trait category
+/// * `library` - a reference to the shared svg fragments and palettes
+fn add_variants(
+    storage: &mut dyn Storage,
+    cat_key: &[u8],
+    variants: Vec<VariantInfo>,
+    cat: &mut Category,
+    library: &SvgLibrary,
+) -> StdResult<()> {
+    for var in variants.into_iter() {
+        check_variant_svg(&var, &cat.name, library)?;
+        let var_name_key = var.name.as_bytes();
+        let mut var_map = PrefixedStorage::multilevel(storage, &[PREFIX_VARIANT_MAP, cat_key]);
+        if may_load::<u16>(&var_map, var_name_key)?.is_some() {
+            return Err(StdError::generic_err(format!(
+                "Variant name:  {} already exists under category:  {}",
+                &var.name, &cat.name
+            )));
+        }
+        save(&mut var_map, var_name_key, &cat.cnt)?;
+        let mut var_store = PrefixedStorage::multilevel(storage, &[PREFIX_VARIANT, cat_key]);
+        save(&mut var_store, &idx_key(cat.cnt), &var)?;
+        cat.cnt = cat.cnt.checked_add(1).ok_or_else(|| {
+            StdError::generic_err(format!(
+                "Reached maximum number of variants for category: {}",
+                &cat.name
+            ))
+        })?;
+    }
+    Ok(())
+}
+
+/// Returns StdResult<Option<Metadata>>
+///
+/// filter metadata to error if both token_uri and extension are present, or to be
+/// None if neither are present
+///
+/// # Arguments
+///
+/// * `metadata` - Metadata being screened
+fn filter_metadata(metadata: Metadata) -> StdResult<Option<Metadata>> {
+    let has_uri = metadata.token_uri.is_some();
+    let has_xten = metadata.extension.is_some();
+    // if you have both or have neither
+    let new_meta = if has_uri == has_xten {
+        // if both
+        if has_uri {
+            return Err(StdError::generic_err(
+                "Metadata can not have BOTH token_uri AND extension",
+            ));
+        }
+        // delete the existing if all fields are None
+        None
+    } else {
+        Some(metadata)
+    };
+    Ok(new_meta)
+}
+
+pub enum Action {
+    Add,
+    Remove,
+    Modify,
+}
+
+/// Returns StdResult<Response>
+///
+/// updates the dependencies list
+///
+/// # Arguments
+///
+/// * `deps` - a mutable reference to Extern containing all the contract's external dependencies
+/// * `sender` - a reference to the message sender
+/// * `update_list` - list of dependencies to use for update
+/// * `action` - Action to perform on the dependency list
+fn try_process_dep_list(
+    deps: DepsMut,
+    sender: &Addr,
+    update_list: &[Dependencies],
+    action: Action,
+) -> StdResult<Response> {
+    // only allow admins to do this
+    check_admin_tx(deps.as_ref(), sender)?;
+
+    let mut depends: Vec<StoredDependencies> =
+        may_load(deps.storage, DEPENDENCIES_KEY)?.unwrap_or_default();
+    let mut save_dep = false;
+    // the variants whose dependencies were added or modified
+    let mut touched: Vec<StoredLayerId> = Vec::new();
+    let status = "success".to_string();
+    let resp = match &action {
+        Action::Add => {
+            for dep in update_list.iter() {
+                let stored = dep.to_stored(deps.storage)?;
+                // add if this variant does not already have dependencies
+                if !depends.iter().any(|d| d.id == stored.id) {
+                    touched.push(stored.id.clone());
+                    depends.push(stored);
+                    save_dep = true;
+                }
+            }
+            ExecuteAnswer::AddDependencies { status }
+        }
+        Action::Remove => {
+            let old_len = depends.len();
+            let rem_list = update_list
+                .iter()
+                .map(|d| d.to_stored(deps.storage))
+                .collect::<StdResult<Vec<StoredDependencies>>>()?;
+            depends.retain(|d| !rem_list.iter().any(|r| r.id == d.id));
+            // only save if the list changed
+            if old_len != depends.len() {
+                save_dep = true;
+            }
+            ExecuteAnswer::RemoveDependencies { status }
+        }
+        Action::Modify => {
+            for dep in update_list.iter() {
+                let stored = dep.to_stored(deps.storage)?;
+                let existing = depends.iter_mut().find(|d| d.id == stored.id);
+                if let Some(update) = existing {
+                    touched.push(stored.id.clone());
+                    *update = stored;
+                    save_dep = true;
+                } else {
+                    return Err(StdError::generic_err(format!(
+                        "No existing dependencies for Variant: {} in Category: {}",
+                        dep.id.variant, dep.id.category
+                    )));
+                }
+            }
+            ExecuteAnswer::ModifyDependencies { status }
+        }
+    };
+    if save_dep {
+        // removals can not introduce problems, but additions and modifications can.  Only
+        // problems involving the updated variants are rejected
+        if !matches!(action, Action::Remove) {
+            let problems = dependency_problems(deps.storage, &depends, Some(&touched))?;
+            if !problems.is_empty() {
+                return Err(StdError::generic_err(problems.join("; ")));
+            }
+        }
+        save(deps.storage, DEPENDENCIES_KEY, &depends)?;
+    }
+
+    Ok(Response::new().set_data(to_binary(&resp)?))
+}
+
+/// Returns StdResult<Response>
+///
+/// updates the compatibility rules list
+///
+/// # Arguments
+///
+/// * `deps` - a mutable reference to Extern containing all the contract's external dependencies
+/// * `sender` - a reference to the message sender
+/// * `update_list` - list of rules to use for update
+/// * `action` - Action to perform on the rules list
+fn try_process_rule_list(
+    deps: DepsMut,
+    sender: &Addr,
+    update_list: &[Rule],
+    action: Action,
+) -> StdResult<Response> {
+    // only allow admins to do this
+    check_admin_tx(deps.as_ref(), sender)?;
+
+    let mut rules: Vec<StoredRule> = may_load(deps.storage, RULES_KEY)?.unwrap_or_default();
+    let mut save_rules = false;
+    let status = "success".to_string();
+    let resp = match action {
+        Action::Add => {
+            for rule in update_list.iter() {
+                let stored = rule.to_stored(deps.storage)?;
+                // add if this variant does not already have rules
+                if !rules.iter().any(|r| r.id == stored.id) {
+                    rules.push(stored);
+                    save_rules = true;
+                }
+            }
+            ExecuteAnswer::AddRules { status }
+        }
+        Action::Remove => {
+            let old_len = rules.len();
+            let rem_list = update_list
+                .iter()
+                .map(|r| r.id.to_stored(deps.storage))
+                .collect::<StdResult<Vec<StoredLayerId>>>()?;
+            rules.retain(|r| !rem_list.contains(&r.id));
+            // only save if the list changed
+            if old_len != rules.len() {
+                save_rules = true;
+            }
+            ExecuteAnswer::RemoveRules { status }
+        }
+        Action::Modify => {
+            for rule in update_list.iter() {
+                let stored = rule.to_stored(deps.storage)?;
+                let existing = rules.iter_mut().find(|r| r.id == stored.id);
+                if let Some(update) = existing {
+                    *update = stored;
+                    save_rules = true;
+                } else {
+                    return Err(StdError::generic_err(format!(
+                        "No existing rules for Variant: {} in Category: {}",
+                        rule.id.variant, rule.id.category
+                    )));
+                }
+            }
+            ExecuteAnswer::ModifyRules { status }
+        }
+    };
+    if save_rules {
+        save(deps.storage, RULES_KEY, &rules)?;
+    }
+
+    Ok(Response::new().set_data(to_binary(&resp)?))
+}
+
+/// a broken compatibility rule
+#[derive(PartialEq)]
+pub struct Violation {
+    /// the layer whose rule is broken
+    pub layer: StoredLayerId,
+    /// the excluded layer it is combined with, or None if it is missing a required layer
+    pub conflict: Option<StoredLayerId>,
+}
+
+/// Returns Vec<Violation>
+///
+/// lists the compatibility rules an image breaks.  Unrevealed categories never conflict, and a
+/// requirement is only broken once all its required categories are revealed
+///
+/// # Arguments
+///
+/// * `rules` - slice of the compatibility rules
+/// * `image` - the image indices
+fn rule_violations(rules: &[StoredRule], image: &[u16]) -> Vec<Violation> {
+    let has = |l: &StoredLayerId| image.get(l.category as usize) == Some(&l.variant);
+    let mut violations: Vec<Violation> = Vec::new();
+    for rule in rules.iter().filter(|r| has(&r.id)) {
+        for excl in rule.excludes.iter().filter(|l| has(l)) {
+            violations.push(Violation {
+                layer: rule.id.clone(),
+                conflict: Some(excl.clone()),
+            });
+        }
+        if !rule.requires.is_empty()
+            && rule.requires.iter().all(|l| {
+                image
+                    .get(l.category as usize)
+                    .map_or(false, |v| *v != UNREVEALED)
+            })
+            && !rule.requires.iter().any(has)
+        {
+            violations.push(Violation {
+                layer: rule.id.clone(),
+                conflict: None,
             });
-            trait_cnt += 1;
         }
     }
-    let hidden = trait_cnt - revealed;
-    attributes.push(Trait {
-        display_type: None,
-        trait_type: Some("Unrevealed Trait Categories".to_string()),
-        value: format!("{}", hidden),
-        max_value: None,
-    });
-    // display the trait count if fully revealed
-    if hidden == 0 {
-        attributes.push(Trait {
-            display_type: None,
-            trait_type: Some("Trait Count".to_string()),
-            value: format!("{}", trait_cnt - none_cnt),
-            max_value: None,
-        });
+    violations
+}
+
+/// Returns StdResult<String>
+///
+/// describes a broken compatibility rule
+///
+/// # Arguments
+///
+/// * `storage` - a reference to the contract's storage
+/// * `violation` - a reference to the broken rule
+fn describe_violation(storage: &dyn Storage, violation: &Violation) -> StdResult<String> {
+    let layer = violation.layer.to_display(storage)?;
+    if let Some(conflict) = violation.conflict.as_ref() {
+        let conflict = conflict.to_display(storage)?;
+        Ok(format!(
+            "Variant: {} in Category: {} can not be combined with Variant: {} in Category: {}",
+            layer.variant, layer.category, conflict.variant, conflict.category
+        ))
     } else {
-        // count of nones if there are still unrevealed traits
-        attributes.push(Trait {
-            display_type: None,
-            trait_type: Some("Clean Traits (Nones) Currently Revealed".to_string()),
-            value: format!("{}", none_cnt),
-            max_value: None,
-        });
+        Ok(format!(
+            "Variant: {} in Category: {} is missing a required layer",
+            layer.variant, layer.category
+        ))
     }
-    // set the alchemical status
-    let value = if image[0] > 5 {
-        "Transmuted".to_string()
-    } else {
-        "Raw".to_string()
-    };
-    attributes.push(Trait {
-        display_type: None,
-        trait_type: Some("Alchemical Status".to_string()),
-        value,
-        max_value: None,
-    });
-    image_data.push_str("</svg>");
-    xten.image_data = Some(image_data);
-    xten.attributes = Some(attributes);
-    public_metadata.extension = Some(xten);
-
-    to_binary(&QueryAnswer::Metadata {
-        public_metadata: Some(public_metadata),
-        private_metadata: common.private,
-    })
 }
 
-/// Returns StdResult<Binary> displaying the metadata common to all NFTs
+/// Returns StdResult<u16>
+///
+/// rolls a variant of a category using its genesis weights, skipping variants that are not
+/// active or that would break a compatibility rule
 ///
 /// # Arguments
 ///
-/// * `deps` - reference to Extern containing all the contract's external dependencies
-/// * `viewer` - optional address and key making an authenticated query request
-/// * `permit` - optional permit with "owner" permission
-/// * `my_addr` - a reference to this contract's address
-fn query_common_metadata(
-    deps: Deps,
-    viewer: Option<ViewerInfo>,
-    permit: Option<Permit>,
-    my_addr: &Addr,
-) -> StdResult<Binary> {
-    // only allow authorized addresses to do this
-    let querier = get_querier(deps, viewer, permit, my_addr)?;
-    let minters: Vec<CanonicalAddr> = may_load(deps.storage, MINTERS_KEY)?.unwrap_or_default();
-    if !minters.contains(&querier) {
-        let viewers: Vec<CanonicalAddr> = may_load(deps.storage, VIEWERS_KEY)?.unwrap_or_default();
-        if !viewers.contains(&querier) {
-            let admins: Vec<CanonicalAddr> = load(deps.storage, ADMINS_KEY)?;
-            if !admins.contains(&querier) {
-                return Err(StdError::generic_err("Not authorized"));
+/// * `rng` - a mutable reference to the prng
+/// * `image` - the image indices
+/// * `category` - index of the category to roll
+/// * `weights` - the category's genesis weights
+/// * `statuses` - list of variants that are not active
+/// * `dependencies` - slice of the defined dependencies
+/// * `rules` - slice of the compatibility rules
+fn roll_variant(
+    rng: &mut ContractPrng,
+    image: &[u16],
+    category: u16,
+    weights: &[StoredVariantWeight],
+    statuses: &[StoredVariantStatus],
+    dependencies: &[StoredDependencies],
+    rules: &[StoredRule],
+) -> StdResult<u16> {
+    // broken rules that do not involve this category
+    let mut baseline = image.to_vec();
+    baseline[category as usize] = UNREVEALED;
+    let existing = rule_violations(rules, &baseline);
+    let mut candidates: Vec<(u16, u64)> = Vec::new();
+    let mut total_weight = 0u64;
+    for var_wgt in weights.iter() {
+        if statuses
+            .iter()
+            .any(|s| s.id.category == category && s.id.variant == var_wgt.variant)
+        {
+            continue;
+        }
+        let weight = var_wgt
+            .conditional
+            .iter()
+            .find(|c| image[c.layer.category as usize] == c.layer.variant)
+            .map_or(var_wgt.weight, |c| c.weight) as u64;
+        if weight == 0 {
+            continue;
+        }
+        if !rules.is_empty() {
+            let mut trial = image.to_vec();
+            set_variant(&mut trial, category, var_wgt.variant, dependencies);
+            if rule_violations(rules, &trial)
+                .iter()
+                .any(|v| !existing.contains(v))
+            {
+                continue;
             }
         }
+        total_weight += weight;
+        candidates.push((var_wgt.variant, total_weight));
     }
-    let common: CommonMetadata = may_load(deps.storage, METADATA_KEY)?.unwrap_or(CommonMetadata {
-        public: None,
-        private: None,
-    });
+    if total_weight == 0 {
+        return Err(StdError::generic_err(format!(
+            "No variant of category index {} can be rolled for this image",
+            category
+        )));
+    }
+    let mut rdm = [0u8; 8];
+    rdm.copy_from_slice(&rng.rand_bytes()[..8]);
+    let winning_num = u64::from_le_bytes(rdm) % total_weight;
+    candidates
+        .iter()
+        .find(|(_, tally)| *tally > winning_num)
+        .map(|(v, _)| *v)
+        .ok_or_else(|| StdError::generic_err("Weight tally is corrupt"))
+}
 
-    to_binary(&QueryAnswer::Metadata {
-        public_metadata: common.public,
-        private_metadata: common.private,
-    })
+/// sets a variant in the image along with any layers it requires
+///
+/// # Arguments
+///
+/// * `image` - a mutable reference to the image indices
+/// * `category` - index of the variant's category
+/// * `variant` - index of the variant
+/// * `dependencies` - slice of the defined dependencies
+fn set_variant(
+    image: &mut [u16],
+    category: u16,
+    variant: u16,
+    dependencies: &[StoredDependencies],
+) {
+    image[category as usize] = variant;
+    let id = StoredLayerId { category, variant };
+    if let Some(dep) = dependencies.iter().find(|d| d.id == id) {
+        for layer in dep.correlated.iter() {
+            image[layer.category as usize] = layer.variant;
+        }
+    }
 }
 
-/// Returns StdResult<CanonicalAddr> from determining the querying address
-/// (if possible) either from a Permit or a ViewerInfo
+/// Returns StdResult<Vec<String>>
+///
+/// describes every problem with the dependency definitions.  A variant's correlated layers
+/// must be in other categories, one per category, and must not have dependencies of their own.
+/// Variants of different categories may not both set layers of the same category
 ///
 /// # Arguments
 ///
-/// * `deps` - a reference to Extern containing all the contract's external dependencies
-/// * `viewer` - optional address and key making an authenticated query request
-/// * `permit` - optional permit with "owner" permission
-/// * `my_addr` - a reference to this contract's address
-fn get_querier(
-    deps: Deps,
-    viewer: Option<ViewerInfo>,
-    permit: Option<Permit>,
-    my_addr: &Addr,
-) -> StdResult<CanonicalAddr> {
-    if let Some(pmt) = permit {
-        // Validate permit content
-        let querier = validate(
-            deps,
-            PREFIX_REVOKED_PERMITS,
-            &pmt,
-            my_addr.to_string(),
-            Some("secret"),
-        )
-        .and_then(|a| deps.api.addr_validate(&a))
-        .and_then(|a| deps.api.addr_canonicalize(a.as_str()))?;
-        if !pmt.check_permission(&secret_toolkit::permit::TokenPermissions::Owner) {
-            return Err(StdError::generic_err(format!(
-                "Owner permission is required for queries, got permissions {:?}",
-                pmt.params.permissions
-            )));
+/// * `storage` - a reference to the contract's storage
+/// * `depends` - list of traits that have multiple layers
+/// * `touched` - optional list of variants whose problems should be described.  All problems
+///   are described if None
+fn dependency_problems(
+    storage: &dyn Storage,
+    depends: &[StoredDependencies],
+    touched: Option<&[StoredLayerId]>,
+) -> StdResult<Vec<String>> {
+    let involves = |id: &StoredLayerId| touched.map_or(true, |t| t.contains(id));
+    let mut problems: Vec<String> = Vec::new();
+    for (i, dep) in depends.iter().enumerate() {
+        let root = dep.id.to_display(storage)?;
+        let dep_involved = involves(&dep.id);
+        let mut cats: Vec<u16> = Vec::new();
+        for layer in dep.correlated.iter() {
+            let lyr = layer.to_display(storage)?;
+            if dep_involved && layer.category == dep.id.category {
+                problems.push(format!(
+                    "Variant: {} in Category: {} can not depend on a variant of its own category",
+                    root.variant, root.category
+                ));
+            } else if dep_involved && cats.contains(&layer.category) {
+                problems.push(format!(
+                    "Variant: {} in Category: {} depends on more than one variant of Category: {}",
+                    root.variant, root.category, lyr.category
+                ));
+            }
+            cats.push(layer.category);
+            // dependencies are only applied one level deep
+            if (dep_involved || involves(layer)) && depends.iter().any(|d| d.id == *layer) {
+                let cycle = if dependency_leads_to(depends, layer, &dep.id) {
+                    ", which creates a cycle"
+                } else {
+                    ""
+                };
+                problems.push(format!(
+                    "Variant: {} in Category: {} depends on Variant: {} in Category: {} that has its own dependencies{}",
+                    root.variant, root.category, lyr.variant, lyr.category, cycle
+                ));
+            }
+            // see if a variant of another category also sets this layer's category
+            for other in depends.iter().skip(i + 1).filter(|d| {
+                (dep_involved || involves(&d.id))
+                    && d.id.category != dep.id.category
+                    && d.correlated.iter().any(|l| l.category == layer.category)
+            }) {
+                let oth = other.id.to_display(storage)?;
+                problems.push(format!(
+                    "Variant: {} in Category: {} and Variant: {} in Category: {} both set Category: {}",
+                    root.variant, root.category, oth.variant, oth.category, lyr.category
+                ));
+            }
         }
-        return Ok(querier);
     }
-    if let Some(vwr) = viewer {
-        let hmn = deps.api.addr_validate(&vwr.address)?;
-        let raw = deps.api.addr_canonicalize(hmn.as_str())?;
-        ViewingKey::check(deps.storage, hmn.as_str(), &vwr.viewing_key).map_err(|_| {
-            StdError::generic_err("Wrong viewing key for this address or viewing key not set")
-        })?;
-        return Ok(raw);
+    Ok(problems)
+}
+
+/// Returns bool
+///
+/// true if following dependencies from a layer reaches the target layer
+///
+/// # Arguments
+///
+/// * `depends` - list of traits that have multiple layers
+/// * `start` - a reference to the layer to start from
+/// * `target` - a reference to the layer to look for
+fn dependency_leads_to(
+    depends: &[StoredDependencies],
+    start: &StoredLayerId,
+    target: &StoredLayerId,
+) -> bool {
+    let mut stack = vec![start.clone()];
+    let mut visited: Vec<StoredLayerId> = Vec::new();
+    while let Some(id) = stack.pop() {
+        if id == *target {
+            return true;
+        }
+        if visited.contains(&id) {
+            continue;
+        }
+        if let Some(dep) = depends.iter().find(|d| d.id == id) {
+            stack.extend(dep.correlated.iter().cloned());
+        }
+        visited.push(id);
     }
-    Err(StdError::generic_err(
-        "A permit or viewing key must be provided",
-    ))
+    false
 }
 
-/// Returns StdResult<()> after verifying the querier is a Viewer
+/// used to cache index lookups
+#[derive(Clone)]
+pub struct BackCache {
+    pub id: String,
+    pub index: u16,
+}
+
+/// Returns StdResult<VariantInfoPlus>
+///
+/// creates the VariantInfoPlus of a specified layer variant
 ///
 /// # Arguments
 ///
-/// * `deps` - a reference to Extern containing all the contract's external dependencies
-/// * `viewer` - address and key making an authenticated query request
-fn check_viewer(deps: Deps, viewer: ViewerInfo) -> StdResult<()> {
-    let querier = get_querier(deps, Some(viewer), None, &Addr::unchecked("Not Used"))?;
-    // only allow viewers to call this
-    let viewers: Vec<CanonicalAddr> = may_load(deps.storage, VIEWERS_KEY)?.unwrap_or_default();
-    if !viewers.contains(&querier) {
-        return Err(StdError::generic_err("Not a viewer"));
+/// * `storage` - a reference to the contract's storage
+/// * `id` - a reference to the StoredLayerId of the variant to display
+/// * `depends` - list of traits that have multiple layers
+/// * `statuses` - list of variants that are not active
+/// * `svgs` - true if svgs should be displayed
+fn displ_variant(
+    storage: &dyn Storage,
+    id: &StoredLayerId,
+    depends: &[StoredDependencies],
+    statuses: &[StoredVariantStatus],
+    svgs: bool,
+) -> StdResult<VariantInfoPlus> {
+    let var_store =
+        ReadonlyPrefixedStorage::multilevel(storage, &[PREFIX_VARIANT, &idx_key(id.category)]);
+    // see if this variant requires other layer variants
+    let includes = if let Some(dep) = depends.iter().find(|d| d.id == *id) {
+        dep.correlated
+            .iter()
+            .map(|l| l.to_display(storage))
+            .collect::<StdResult<Vec<LayerId>>>()?
+    } else {
+        Vec::new()
+    };
+    let mut variant_info: VariantInfo = may_load(&var_store, &idx_key(id.variant))?
+        .ok_or_else(|| StdError::generic_err("Variant storage is corrupt"))?;
+    if !svgs {
+        variant_info.svg = None;
     }
-    Ok(())
+    let status = match statuses.iter().find(|s| s.id == *id) {
+        None => VariantStatus::Active,
+        Some(StoredVariantStatus {
+            replacement: None, ..
+        }) => VariantStatus::Hidden,
+        Some(StoredVariantStatus {
+            replacement: Some(repl),
+            ..
+        }) => VariantStatus::Retired {
+            replacement: may_load::<VariantInfo>(&var_store, &idx_key(*repl))?
+                .ok_or_else(|| StdError::generic_err("Variant storage is corrupt"))?
+                .name,
+        },
+    };
+    let var_plus = VariantInfoPlus {
+        index: id.variant,
+        variant_info,
+        includes,
+        status,
+    };
+    Ok(var_plus)
 }
 
-/// Returns StdResult<Vec<CanonicalAddr>> which is the admin list and checks if the querier is an admin
+/// Returns u16
+///
+/// the index a variant renders as, following retirements to the active replacement
 ///
 /// # Arguments
 ///
-/// * `deps` - a reference to Extern containing all the contract's external dependencies
-/// * `viewer` - optional address and key making an authenticated query request
-/// * `permit` - optional permit with "owner" permission
-/// * `my_addr` - a reference to this contract's address
-fn check_admin_query(
-    deps: Deps,
-    viewer: Option<ViewerInfo>,
-    permit: Option<Permit>,
-    my_addr: &Addr,
-) -> StdResult<Vec<CanonicalAddr>> {
-    let address = get_querier(deps, viewer, permit, my_addr)?;
-    check_admin(deps.storage, &address)
+/// * `statuses` - list of variants that are not active
+/// * `category` - index of the variant's category
+/// * `variant` - index of the variant
+fn resolve_variant(statuses: &[StoredVariantStatus], category: u16, variant: u16) -> u16 {
+    let mut resolved = variant;
+    while let Some(repl) = statuses
+        .iter()
+        .find(|s| s.id.category == category && s.id.variant == resolved)
+        .and_then(|s| s.replacement)
+    {
+        resolved = repl;
+    }
+    resolved
 }
 
-/// Returns StdResult<Vec<CanonicalAddr>> which is the admin list and checks if the message
-/// sender is an admin
+/// Returns StdResult<u16>
+///
+/// either retrieves a known cat/variant's index or determines it and adds it to
+/// the cache
 ///
 /// # Arguments
 ///
-/// * `deps` - a reference to Extern containing all the contract's external dependencies
-/// * `sender` - a reference to the message sender
-fn check_admin_tx(deps: Deps, sender: &Addr) -> StdResult<Vec<CanonicalAddr>> {
-    let sender_raw = deps.api.addr_canonicalize(sender.as_str())?;
-    check_admin(deps.storage, &sender_raw)
+/// * `map` - a reference to the cat/variant map
+/// * `id` - cat/variant name
+/// * `back_cache` - a mutable reference to the cat/variant name cache
+fn use_back_cache(map: &dyn Storage, id: &str, back_cache: &mut Vec<BackCache>) -> StdResult<u16> {
+    if let Some(bg) = back_cache.iter().find(|b| b.id == id) {
+        Ok(bg.index)
+    } else {
+        let index: u16 = may_load(map, id.as_bytes())?.ok_or_else(|| {
+            StdError::generic_err(format!("Did not find a category/variant named {}", id))
+        })?;
+        let entry = BackCache {
+            id: id.to_string(),
+            index,
+        };
+        back_cache.push(entry);
+        Ok(index)
+    }
 }
 
-/// Returns StdResult<Vec<CanonicalAddr>> which is the admin list and checks if the address
-/// is an admin
+/// fills in the templates of a token's metadata
 ///
 /// # Arguments
 ///
-/// * `storage` - a reference to this contract's storage
-/// * `address` - a reference to the address in question
-fn check_admin(storage: &dyn Storage, address: &CanonicalAddr) -> StdResult<Vec<CanonicalAddr>> {
-    let admins: Vec<CanonicalAddr> = load(storage, ADMINS_KEY)?;
-    if !admins.contains(address) {
-        return Err(StdError::generic_err("Not an admin"));
+/// * `metadata` - a mutable reference to the metadata
+/// * `token_id` - optional token id
+/// * `attributes` - the token's traits
+fn fill_metadata(metadata: &mut Metadata, token_id: Option<&str>, attributes: &[Trait]) {
+    if let Some(uri) = metadata.token_uri.as_mut() {
+        *uri = fill_template(uri, token_id, attributes);
+    }
+    if let Some(xten) = metadata.extension.as_mut() {
+        fill_extension(xten, token_id, attributes);
     }
-    Ok(admins)
-}
-
-pub enum AddrType {
-    Admin,
-    Viewer,
-    Minter,
 }
 
-/// Returns StdResult<Response>
-///
-/// updates the admin, viewer, or minter authorization list
+/// fills in the templates of a token's name, description, and external url
 ///
 /// # Arguments
 ///
-/// * `deps` - a mutable reference to Extern containing all the contract's external dependencies
-/// * `sender` - a reference to the message sender
-/// * `update_list` - list of addresses to use for update
-/// * `is_add` - true if the update is for adding to the list
-/// * `list` - AddrType to determine which list to update
-fn try_process_auth_list(
-    deps: DepsMut,
-    sender: &Addr,
-    update_list: &[String],
-    is_add: bool,
-    list: AddrType,
-) -> StdResult<Response> {
-    // only allow admins to do this
-    let admins = check_admin_tx(deps.as_ref(), sender)?;
-
-    // get the right authorization list info
-    let (mut current_list, key) = match list {
-        AddrType::Admin => (admins, ADMINS_KEY),
-        AddrType::Viewer => (
-            may_load::<Vec<CanonicalAddr>>(deps.storage, VIEWERS_KEY)?.unwrap_or_default(),
-            VIEWERS_KEY,
-        ),
-        AddrType::Minter => (
-            may_load::<Vec<CanonicalAddr>>(deps.storage, MINTERS_KEY)?.unwrap_or_default(),
-            MINTERS_KEY,
-        ),
-    };
-    // update the authorization list if needed
-    let save_it = if is_add {
-        add_addrs_to_auth(deps.api, &mut current_list, update_list)?
-    } else {
-        remove_addrs_from_auth(deps.api, &mut current_list, update_list)?
-    };
-    // save list if it changed
-    if save_it {
-        save(deps.storage, key, &current_list)?;
+/// * `xten` - a mutable reference to the metadata extension
+/// * `token_id` - optional token id
+/// * `attributes` - the token's traits
+fn fill_extension(xten: &mut Extension, token_id: Option<&str>, attributes: &[Trait]) {
+    for field in [
+        &mut xten.name,
+        &mut xten.description,
+        &mut xten.external_url,
+    ] {
+        if let Some(text) = field.as_mut() {
+            *text = fill_template(text, token_id, attributes);
+        }
     }
-    let new_list = current_list
-        .iter()
-        .map(|a| deps.api.addr_humanize(a))
-        .collect::<StdResult<Vec<Addr>>>()?;
-    let resp = match list {
-        AddrType::Admin => ExecuteAnswer::AdminsList { admins: new_list },
-        AddrType::Viewer => ExecuteAnswer::ViewersList { viewers: new_list },
-        AddrType::Minter => ExecuteAnswer::MintersList { minters: new_list },
-    };
-    Ok(Response::new().set_data(to_binary(&resp)?))
 }
 
-/// Returns StdResult<bool>
+/// Returns String
 ///
-/// adds to an authorization list of addresses and returns true if the list changed
+/// replaces the templates in a metadata string.  `{token_id}` becomes the token id (or nothing
+/// if no id was given), `{alchemical_status}` becomes the alchemical status, and
+/// `{trait:<category name>}` becomes that trait's value.  Anything else is left unchanged
 ///
 /// # Arguments
 ///
-/// * `api` - a reference to the Api used to convert human and canonical addresses
-/// * `addresses` - current mutable list of addresses
-/// * `addrs_to_add` - list of addresses to add
-fn add_addrs_to_auth(
-    api: &dyn Api,
-    addresses: &mut Vec<CanonicalAddr>,
-    addrs_to_add: &[String],
-) -> StdResult<bool> {
-    let mut save_it = false;
-    for addr in addrs_to_add.iter() {
-        let raw = api
-            .addr_validate(addr)
-            .and_then(|a| api.addr_canonicalize(a.as_str()))?;
-        if !addresses.contains(&raw) {
-            addresses.push(raw);
-            save_it = true;
+/// * `template` - the string to fill in
+/// * `token_id` - optional token id
+/// * `attributes` - the token's traits
+fn fill_template(template: &str, token_id: Option<&str>, attributes: &[Trait]) -> String {
+    let mut filled = String::new();
+    let mut rest = template;
+    while let Some(open) = rest.find('{') {
+        filled.push_str(&rest[..open]);
+        let tail = &rest[open..];
+        if let Some(close) = tail.find('}') {
+            let key = &tail[1..close];
+            let value = if key == "token_id" {
+                Some(token_id.unwrap_or_default().to_string())
+            } else {
+                let trait_type = if key == "alchemical_status" {
+                    Some("Alchemical Status")
+                } else {
+                    key.strip_prefix("trait:")
+                };
+                trait_type
+                    .and_then(|t| {
+                        attributes
+                            .iter()
+                            .find(|a| a.trait_type.as_deref() == Some(t))
+                    })
+                    .map(|a| a.value.clone())
+            };
+            filled.push_str(value.as_deref().unwrap_or(&tail[..=close]));
+            rest = &tail[close + 1..];
+        } else {
+            filled.push_str(tail);
+            rest = "";
         }
     }
-    Ok(save_it)
+    filled.push_str(rest);
+    filled
 }
 
-/// Returns StdResult<bool>
+/// used to cache the data shared by the metadata of every image
+pub struct MetadataCache {
+    /// the metadata common to all NFTs
+    pub common: CommonMetadata,
+    /// the State
+    pub state: State,
+    /// index of the Hair category
+    pub hair_idx: u16,
+    /// the variants that are not active
+    pub statuses: Vec<StoredVariantStatus>,
+    /// names of the categories
+    pub cat_names: Vec<String>,
+    /// the Hair variant name lookups
+    pub hair_cache: Vec<BackCache>,
+    /// how images are rendered
+    pub render: RenderConfig,
+    /// the shared svg fragments and palettes, loaded when first referenced
+    pub library: Option<SvgLibrary>,
+    /// the effect layers
+    pub effects: Vec<StoredEffect>,
+    /// the variants already loaded from each category
+    pub variants: Vec<Vec<(u16, VariantInfo)>>,
+}
+
+/// Returns StdResult<MetadataCache>
 ///
-/// removes from an authorization list of addresses and returns true if the list changed
+/// loads the data shared by the metadata of every image
 ///
 /// # Arguments
 ///
-/// * `api` - a reference to the Api used to convert human and canonical addresses
-/// * `addresses` - current mutable list of addresses
-/// * `addrs_to_remove` - list of addresses to remove
-fn remove_addrs_from_auth(
-    api: &dyn Api,
-    addresses: &mut Vec<CanonicalAddr>,
-    addrs_to_remove: &[String],
-) -> StdResult<bool> {
-    let old_len = addresses.len();
-    let rem_list = addrs_to_remove
-        .iter()
-        .map(|a| {
-            api.addr_validate(a)
-                .and_then(|a| api.addr_canonicalize(a.as_str()))
+/// * `storage` - a reference to the contract's storage
+fn new_metadata_cache(storage: &dyn Storage) -> StdResult<MetadataCache> {
+    let state: State = load(storage, STATE_KEY)?;
+    let cat_map = ReadonlyPrefixedStorage::new(storage, PREFIX_CATEGORY_MAP);
+    let hair_idx: u16 = may_load(&cat_map, "Hair".as_bytes())?
+        .ok_or_else(|| StdError::generic_err("Hair layer category not found"))?;
+    let cat_store = ReadonlyPrefixedStorage::new(storage, PREFIX_CATEGORY);
+    let cat_names = (0..state.cat_cnt)
+        .map(|u| {
+            may_load::<Category>(&cat_store, &idx_key(u))?
+                .map(|c| c.name)
+                .ok_or_else(|| StdError::generic_err("Category storage is corrupt"))
         })
-        .collect::<StdResult<Vec<CanonicalAddr>>>()?;
-    addresses.retain(|a| !rem_list.contains(a));
-    // only save if the list changed
-    Ok(old_len != addresses.len())
+        .collect::<StdResult<Vec<String>>>()?;
+    Ok(MetadataCache {
+        common: may_load(storage, METADATA_KEY)?.unwrap_or(CommonMetadata {
+            public: None,
+            private: None,
+        }),
+        variants: vec![Vec::new(); state.cat_cnt as usize],
+        state,
+        hair_idx,
+        statuses: may_load(storage, VARIANT_STATUS_KEY)?.unwrap_or_default(),
+        cat_names,
+        hair_cache: Vec::new(),
+        render: load_render_config(storage)?,
+        library: None,
+        effects: may_load(storage, EFFECTS_KEY)?.unwrap_or_default(),
+    })
 }
 
-/// Returns StdResult<()>
+/// the shared svg fragments and color palettes
+pub struct SvgLibrary {
+    /// svg fragments
+    pub fragments: Vec<SvgFragment>,
+    /// color palettes
+    pub palettes: Vec<Palette>,
+}
+
+/// Returns StdResult<SvgLibrary>
 ///
-/// adds new trait variants to the specified category index
+/// loads the shared svg fragments and color palettes
 ///
 /// # Arguments
 ///
-/// * `storage` - a mutable reference to the contract's storage
-/// * `cat_key` - index of the category these variants belong to
-/// * `variants` - variants to add to this category
-/// * `cat` - a mutable reference to this trait category
-fn add_variants(
-    storage: &mut dyn Storage,
-    cat_key: &[u8],
-    variants: Vec<VariantInfo>,
-    cat: &mut Category,
-) -> StdResult<()> {
-    for var in variants.into_iter() {
-        let var_name_key = var.name.as_bytes();
-        let mut var_map = PrefixedStorage::multilevel(storage, &[PREFIX_VARIANT_MAP, cat_key]);
-        if may_load::<u8>(&var_map, var_name_key)?.is_some() {
-            return Err(StdError::generic_err(format!(
-                "Variant name:  {} already exists under category:  {}",
-                &var.name, &cat.name
-            )));
-        }
-        save(&mut var_map, var_name_key, &cat.cnt)?;
-        let mut var_store = PrefixedStorage::multilevel(storage, &[PREFIX_VARIANT, cat_key]);
-        save(&mut var_store, &cat.cnt.to_le_bytes(), &var)?;
-        cat.cnt = cat.cnt.checked_add(1).ok_or_else(|| {
-            StdError::generic_err(format!(
-                "Reached maximum number of variants for category: {}",
-                &cat.name
-            ))
-        })?;
-    }
-    Ok(())
+/// * `storage` - a reference to the contract's storage
+fn load_svg_library(storage: &dyn Storage) -> StdResult<SvgLibrary> {
+    Ok(SvgLibrary {
+        fragments: may_load(storage, SVG_FRAGMENTS_KEY)?.unwrap_or_default(),
+        palettes: may_load(storage, PALETTES_KEY)?.unwrap_or_default(),
+    })
 }
 
-/// Returns StdResult<Option<Metadata>>
+/// Returns Option<String>
 ///
-/// filter metadata to error if both token_uri and extension are present, or to be
-/// None if neither are present
+/// describes the problem if a variant's fragment references do not resolve with the shared
+/// library
 ///
 /// # Arguments
 ///
-/// * `metadata` - Metadata being screened
-fn filter_metadata(metadata: Metadata) -> StdResult<Option<Metadata>> {
-    let has_uri = metadata.token_uri.is_some();
-    let has_xten = metadata.extension.is_some();
-    // if you have both or have neither
-    let new_meta = if has_uri == has_xten {
-        // if both
-        if has_uri {
-            return Err(StdError::generic_err(
-                "Metadata can not have BOTH token_uri AND extension",
-            ));
-        }
-        // delete the existing if all fields are None
-        None
-    } else {
-        Some(metadata)
-    };
-    Ok(new_meta)
-}
-
-pub enum Action {
-    Add,
-    Remove,
-    Modify,
+/// * `var` - a reference to the variant
+/// * `cat_name` - name of the variant's category
+/// * `library` - a reference to the shared svg fragments and palettes
+fn variant_svg_problem(var: &VariantInfo, cat_name: &str, library: &SvgLibrary) -> Option<String> {
+    let svg = var.svg.as_ref().filter(|s| s.contains(FRAGMENT_TAG))?;
+    expand_fragments(svg, library, None).err().map(|e| {
+        format!(
+            "Variant: {} in Category: {} has an invalid svg: {}",
+            var.name, cat_name, e
+        )
+    })
 }
 
-/// Returns StdResult<Response>
-///
-/// updates the dependencies list
+/// Returns StdResult<()>
 ///
-/// # Arguments
+/// verifies that a variant's fragment references resolve with the shared library
 ///
-/// * `deps` - a mutable reference to Extern containing all the contract's external dependencies
-/// * `sender` - a reference to the message sender
-/// * `update_list` - list of dependencies to use for update
-/// * `action` - Action to perform on the dependency list
-fn try_process_dep_list(
-    deps: DepsMut,
-    sender: &Addr,
-    update_list: &[Dependencies],
-    action: Action,
-) -> StdResult<Response> {
-    // only allow admins to do this
-    check_admin_tx(deps.as_ref(), sender)?;
-
-    let mut depends: Vec<StoredDependencies> =
-        may_load(deps.storage, DEPENDENCIES_KEY)?.unwrap_or_default();
-    let mut save_dep = false;
-    let status = "success".to_string();
-    let resp = match action {
-        Action::Add => {
-            for dep in update_list.iter() {
-                let stored = dep.to_stored(deps.storage)?;
-                // add if this variant does not already have dependencies
-                if !depends.iter().any(|d| d.id == stored.id) {
-                    depends.push(stored);
-                    save_dep = true;
-                }
-            }
-            ExecuteAnswer::AddDependencies { status }
-        }
-        Action::Remove => {
-            let old_len = depends.len();
-            let rem_list = update_list
-                .iter()
-                .map(|d| d.to_stored(deps.storage))
-                .collect::<StdResult<Vec<StoredDependencies>>>()?;
-            depends.retain(|d| !rem_list.iter().any(|r| r.id == d.id));
-            // only save if the list changed
-            if old_len != depends.len() {
-                save_dep = true;
-            }
-            ExecuteAnswer::RemoveDependencies { status }
-        }
-        Action::Modify => {
-            for dep in update_list.iter() {
-                let stored = dep.to_stored(deps.storage)?;
-                let existing = depends.iter_mut().find(|d| d.id == stored.id);
-                if let Some(update) = existing {
-                    *update = stored;
-                    save_dep = true;
-                } else {
-                    return Err(StdError::generic_err(format!(
-                        "No existing dependencies for Variant: {} in Category: {}",
-                        dep.id.variant, dep.id.category
-                    )));
-                }
-            }
-            ExecuteAnswer::ModifyDependencies { status }
-        }
-    };
-    if save_dep {
-        save(deps.storage, DEPENDENCIES_KEY, &depends)?;
+/// # Arguments
+///
+/// * `var` - a reference to the variant
+/// * `cat_name` - name of the variant's category
+/// * `library` - a reference to the shared svg fragments and palettes
+fn check_variant_svg(var: &VariantInfo, cat_name: &str, library: &SvgLibrary) -> StdResult<()> {
+    match variant_svg_problem(var, cat_name, library) {
+        Some(problem) => Err(StdError::generic_err(problem)),
+        None => Ok(()),
     }
-
-    Ok(Response::new().set_data(to_binary(&resp)?))
 }
 
-/// used to cache index lookups
-#[derive(Clone)]
-pub struct BackCache {
-    pub id: String,
-    pub index: u8,
+/// Returns StdResult<Vec<String>>
+///
+/// describes every variant whose fragment references do not resolve with the shared library
+///
+/// # Arguments
+///
+/// * `storage` - a reference to the contract's storage
+/// * `library` - a reference to the shared svg fragments and palettes
+/// * `previous` - optional reference to a previous library.  Variants whose references did not
+///   resolve with it are not described
+fn fragment_problems(
+    storage: &dyn Storage,
+    library: &SvgLibrary,
+    previous: Option<&SvgLibrary>,
+) -> StdResult<Vec<String>> {
+    let mut problems: Vec<String> = Vec::new();
+    let state: State = load(storage, STATE_KEY)?;
+    let cat_store = ReadonlyPrefixedStorage::new(storage, PREFIX_CATEGORY);
+    for cat_idx in 0..state.cat_cnt {
+        let cat_key = idx_key(cat_idx);
+        let cat: Category = may_load(&cat_store, &cat_key)?
+            .ok_or_else(|| StdError::generic_err("Category storage is corrupt"))?;
+        let var_store = ReadonlyPrefixedStorage::multilevel(storage, &[PREFIX_VARIANT, &cat_key]);
+        for var_idx in 0..cat.cnt {
+            let var: VariantInfo = may_load(&var_store, &idx_key(var_idx))?
+                .ok_or_else(|| StdError::generic_err("Variant storage is corrupt"))?;
+            if previous.map_or(false, |p| variant_svg_problem(&var, &cat.name, p).is_some()) {
+                continue;
+            }
+            problems.extend(variant_svg_problem(&var, &cat.name, library));
+        }
+    }
+    Ok(problems)
 }
 
-/// Returns StdResult<VariantInfoPlus>
+/// Returns StdResult<()>
 ///
-/// creates the VariantInfoPlus of a specified layer variant
+/// verifies that changing the shared library does not break any variant's fragment references
 ///
 /// # Arguments
 ///
 /// * `storage` - a reference to the contract's storage
-/// * `id` - a reference to the StoredLayerId of the variant to display
-/// * `depends` - list of traits that have multiple layers
-/// * `svgs` - true if svgs should be displayed
-fn displ_variant(
+/// * `old` - a reference to the current library
+/// * `new` - a reference to the updated library
+fn check_library_change(
     storage: &dyn Storage,
-    id: &StoredLayerId,
-    depends: &[StoredDependencies],
-    svgs: bool,
-) -> StdResult<VariantInfoPlus> {
-    let var_store =
-        ReadonlyPrefixedStorage::multilevel(storage, &[PREFIX_VARIANT, &id.category.to_le_bytes()]);
-    // see if this variant requires other layer variants
-    let includes = if let Some(dep) = depends.iter().find(|d| d.id == *id) {
-        dep.correlated
+    old: &SvgLibrary,
+    new: &SvgLibrary,
+) -> StdResult<()> {
+    let problems = fragment_problems(storage, new, Some(old))?;
+    if !problems.is_empty() {
+        return Err(StdError::generic_err(problems.join("; ")));
+    }
+    Ok(())
+}
+
+/// Returns StdResult<String>
+///
+/// expands the fragment references in a variant's svg.  If `defs` is provided, each referenced
+/// fragment is added to it once as a `<g>` element and drawn with `<use>`, otherwise it is
+/// inlined
+///
+/// # Arguments
+///
+/// * `svg` - the variant's svg
+/// * `library` - a reference to the shared svg fragments and palettes
+/// * `defs` - optional mutable reference to the (id, element) list of fragment definitions
+fn expand_fragments(
+    svg: &str,
+    library: &SvgLibrary,
+    mut defs: Option<&mut Vec<(String, String)>>,
+) -> StdResult<String> {
+    let mut expanded = String::new();
+    let mut rest = svg;
+    while let Some(open) = rest.find(FRAGMENT_TAG) {
+        expanded.push_str(&rest[..open]);
+        let tail = &rest[open + FRAGMENT_TAG.len()..];
+        let close = tail
+            .find('}')
+            .ok_or_else(|| StdError::generic_err("Unterminated svg fragment reference"))?;
+        let (frag_name, pal_name) = match tail[..close].split_once(':') {
+            Some((frag, pal)) => (frag, Some(pal)),
+            None => (&tail[..close], None),
+        };
+        let frag = library
+            .fragments
             .iter()
-            .map(|l| l.to_display(storage))
-            .collect::<StdResult<Vec<LayerId>>>()?
-    } else {
-        Vec::new()
-    };
-    let mut variant_info: VariantInfo = may_load(&var_store, &id.variant.to_le_bytes())?
-        .ok_or_else(|| StdError::generic_err("Variant storage is corrupt"))?;
-    if !svgs {
-        variant_info.svg = None;
+            .find(|f| f.name == frag_name)
+            .ok_or_else(|| {
+                StdError::generic_err(format!("SVG fragment {} not found", frag_name))
+            })?;
+        let (id, snippet) = if let Some(pal_name) = pal_name {
+            let palette = library
+                .palettes
+                .iter()
+                .find(|p| p.name == pal_name)
+                .ok_or_else(|| StdError::generic_err(format!("Palette {} not found", pal_name)))?;
+            let mut colored = frag.svg.clone();
+            for (i, color) in palette.colors.iter().enumerate() {
+                colored = colored.replace(&format!("{{color:{}}}", i), color);
+            }
+            if colored.contains("{color:") {
+                return Err(StdError::generic_err(format!(
+                    "Palette {} does not have enough colors for SVG fragment {}",
+                    pal_name, frag_name
+                )));
+            }
+            (format!("frag-{}-{}", frag_name, pal_name), colored)
+        } else {
+            (format!("frag-{}", frag_name), frag.svg.clone())
+        };
+        if let Some(def_list) = defs.as_mut() {
+            if !def_list.iter().any(|(d, _)| *d == id) {
+                let elem = format!(r#"<g id="{}">{}</g>"#, id, snippet);
+                def_list.push((id.clone(), elem));
+            }
+            expanded.push_str(&format!(r##"<use href="#{}"/>"##, id));
+        } else {
+            expanded.push_str(&snippet);
+        }
+        rest = &tail[close + 1..];
     }
-    let var_plus = VariantInfoPlus {
-        index: id.variant,
-        variant_info,
-        includes,
-    };
-    Ok(var_plus)
+    expanded.push_str(rest);
+    Ok(expanded)
 }
 
-/// Returns StdResult<u8>
+/// Returns StdResult<VariantInfo>
 ///
-/// either retrieves a known cat/variant's index or determines it and adds it to
-/// the cache
+/// either retrieves an already loaded variant or loads it and adds it to the cache
 ///
 /// # Arguments
 ///
-/// * `map` - a reference to the cat/variant map
-/// * `id` - cat/variant name
-/// * `back_cache` - a mutable reference to the cat/variant name cache
-fn use_back_cache(map: &dyn Storage, id: &str, back_cache: &mut Vec<BackCache>) -> StdResult<u8> {
-    if let Some(bg) = back_cache.iter().find(|b| b.id == id) {
-        Ok(bg.index)
+/// * `storage` - a reference to the contract's storage
+/// * `category` - index of the variant's category
+/// * `variant` - index of the variant
+/// * `caches` - a mutable reference to the loaded variants of each category
+fn use_variant_cache(
+    storage: &dyn Storage,
+    category: u16,
+    variant: u16,
+    caches: &mut [Vec<(u16, VariantInfo)>],
+) -> StdResult<VariantInfo> {
+    let cache = caches
+        .get_mut(category as usize)
+        .ok_or_else(|| StdError::generic_err("Variant caches improperly initialized"))?;
+    if let Some((_, var)) = cache.iter().find(|(idx, _)| *idx == variant) {
+        Ok(var.clone())
     } else {
-        let index: u8 = may_load(map, id.as_bytes())?.ok_or_else(|| {
-            StdError::generic_err(format!("Did not find a category/variant named {}", id))
-        })?;
-        let entry = BackCache {
-            id: id.to_string(),
-            index,
-        };
-        back_cache.push(entry);
-        Ok(index)
+        let var_store =
+            ReadonlyPrefixedStorage::multilevel(storage, &[PREFIX_VARIANT, &idx_key(category)]);
+        let var: VariantInfo = may_load(&var_store, &idx_key(variant))?
+            .ok_or_else(|| StdError::generic_err("Variant storage is corrupt"))?;
+        cache.push((variant, var.clone()));
+        Ok(var)
     }
 }
 
@@ -1610,7 +3859,7 @@ fn use_back_cache(map: &dyn Storage, id: &str, back_cache: &mut Vec<BackCache>)
 /// * `var_caches` - a mutable reference to the Vec of BackCaches of variants
 fn replace_layer(
     storage: &dyn Storage,
-    image: &mut [u8],
+    image: &mut [u16],
     new_layer: &LayerId,
     dependencies: &[StoredDependencies],
     cat_cache: &mut Vec<BackCache>,
@@ -1622,7 +3871,7 @@ fn replace_layer(
         let skull_idx = use_back_cache(&cat_map, "Skull", cat_cache)?;
         let skull_var_map = ReadonlyPrefixedStorage::multilevel(
             storage,
-            &[PREFIX_VARIANT_MAP, &skull_idx.to_le_bytes()],
+            &[PREFIX_VARIANT_MAP, &idx_key(skull_idx)],
         );
         let skull_cache = var_caches
             .get_mut(skull_idx as usize)
@@ -1633,7 +3882,7 @@ fn replace_layer(
             let chin_idx = use_back_cache(&cat_map, "Jaw Type", cat_cache)?;
             let chin_var_map = ReadonlyPrefixedStorage::multilevel(
                 storage,
-                &[PREFIX_VARIANT_MAP, &chin_idx.to_le_bytes()],
+                &[PREFIX_VARIANT_MAP, &idx_key(chin_idx)],
             );
             let chin_cache = var_caches
                 .get_mut(chin_idx as usize)
@@ -1651,10 +3900,8 @@ fn replace_layer(
     // any category except Skull
     } else {
         let cat_idx = use_back_cache(&cat_map, &new_layer.category, cat_cache)?;
-        let var_map = ReadonlyPrefixedStorage::multilevel(
-            storage,
-            &[PREFIX_VARIANT_MAP, &cat_idx.to_le_bytes()],
-        );
+        let var_map =
+            ReadonlyPrefixedStorage::multilevel(storage, &[PREFIX_VARIANT_MAP, &idx_key(cat_idx)]);
         let var_cache = var_caches
             .get_mut(cat_idx as usize)
             .ok_or_else(|| StdError::generic_err("Variant caches improperly initialized"))?;
@@ -1674,7 +3921,7 @@ fn replace_layer(
                     // set each dependency layer to None
                     let dep_var_map = ReadonlyPrefixedStorage::multilevel(
                         storage,
-                        &[PREFIX_VARIANT_MAP, &dep.category.to_le_bytes()],
+                        &[PREFIX_VARIANT_MAP, &idx_key(dep.category)],
                     );
                     let dep_var_cache =
                         var_caches.get_mut(dep.category as usize).ok_or_else(|| {
@@ -1706,22 +3953,18 @@ fn replace_layer(
 /// * `storage` - a reference to the contract's storage
 fn get_type_layers(storage: &dyn Storage) -> StdResult<(StoredLayerId, StoredLayerId)> {
     let cat_map = ReadonlyPrefixedStorage::new(storage, PREFIX_CATEGORY_MAP);
-    let eye_type_idx: u8 = may_load(&cat_map, "Eye Type".as_bytes())?
+    let eye_type_idx: u16 = may_load(&cat_map, "Eye Type".as_bytes())?
         .ok_or_else(|| StdError::generic_err("Eye Type layer category not found"))?;
-    let chin_idx: u8 = may_load(&cat_map, "Jaw Type".as_bytes())?
+    let chin_idx: u16 = may_load(&cat_map, "Jaw Type".as_bytes())?
         .ok_or_else(|| StdError::generic_err("Jaw Type layer category not found"))?;
-    let chin_var_map = ReadonlyPrefixedStorage::multilevel(
-        storage,
-        &[PREFIX_VARIANT_MAP, &chin_idx.to_le_bytes()],
-    );
-    let jawless_idx: u8 = may_load(&chin_var_map, "None".as_bytes())?.ok_or_else(|| {
+    let chin_var_map =
+        ReadonlyPrefixedStorage::multilevel(storage, &[PREFIX_VARIANT_MAP, &idx_key(chin_idx)]);
+    let jawless_idx: u16 = may_load(&chin_var_map, "None".as_bytes())?.ok_or_else(|| {
         StdError::generic_err("Did not find expected None variant for Jaw Type layer category")
     })?;
-    let et_var_map = ReadonlyPrefixedStorage::multilevel(
-        storage,
-        &[PREFIX_VARIANT_MAP, &eye_type_idx.to_le_bytes()],
-    );
-    let cyclops_idx: u8 =
+    let et_var_map =
+        ReadonlyPrefixedStorage::multilevel(storage, &[PREFIX_VARIANT_MAP, &idx_key(eye_type_idx)]);
+    let cyclops_idx: u16 =
         may_load(&et_var_map, "Eye Type.Cyclops".as_bytes())?.ok_or_else(|| {
             StdError::generic_err(
                 "Did not find expected Eye Type.Cyclops variant for Eye Type layer category",
@@ -1739,3 +3982,943 @@ fn get_type_layers(storage: &dyn Storage) -> StdResult<(StoredLayerId, StoredLay
         },
     ))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::msg::EffectTrigger;
+    use cosmwasm_std::testing::{
+        mock_dependencies, mock_env, mock_info, MockApi, MockQuerier, MockStorage,
+    };
+    use cosmwasm_std::{from_binary, OwnedDeps, WasmMsg};
+
+    type MockDeps = OwnedDeps<MockStorage, MockApi, MockQuerier>;
+
+    // the opening svg tag images have always been drawn with
+    const BASELINE_HEADER: &str = r###"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 -0.5 24 24" shape-rendering="crispEdges">"###;
+
+    // store categories with their variants' names
+    fn add_catalog(storage: &mut dyn Storage, catalog: &[(&str, &[&str])]) {
+        for (cat_idx, (cat_name, variants)) in catalog.iter().enumerate() {
+            let cat_key = idx_key(cat_idx as u16);
+            let cat = Category {
+                name: cat_name.to_string(),
+                skip: false,
+                cnt: variants.len() as u16,
+            };
+            let mut cat_store = PrefixedStorage::new(storage, PREFIX_CATEGORY);
+            save(&mut cat_store, &cat_key, &cat).unwrap();
+            for (var_idx, var_name) in variants.iter().enumerate() {
+                let var = VariantInfo {
+                    name: var_name.to_string(),
+                    display_name: var_name.to_string(),
+                    svg: None,
+                };
+                let mut var_store =
+                    PrefixedStorage::multilevel(storage, &[PREFIX_VARIANT, &cat_key]);
+                save(&mut var_store, &idx_key(var_idx as u16), &var).unwrap();
+            }
+        }
+    }
+
+    fn exec(deps: &mut MockDeps, sender: &str, msg: ExecuteMsg) -> StdResult<Response> {
+        execute(deps.as_mut(), mock_env(), mock_info(sender, &[]), msg)
+    }
+
+    fn svg_var(name: &str, svg: &str) -> VariantInfo {
+        VariantInfo {
+            name: name.to_string(),
+            display_name: name.to_string(),
+            svg: Some(svg.to_string()),
+        }
+    }
+
+    fn layer(category: &str, variant: &str) -> LayerId {
+        LayerId {
+            category: category.to_string(),
+            variant: variant.to_string(),
+        }
+    }
+
+    fn viewer() -> ViewerInfo {
+        ViewerInfo {
+            address: "viewer".to_string(),
+            viewing_key: "key".to_string(),
+        }
+    }
+
+    // a Background with 6 raw variants and a transmuted one, Hair, and Eyes
+    fn test_catalog() -> Vec<CategoryInfo> {
+        let mut backgrounds: Vec<VariantInfo> = (0..6)
+            .map(|i| svg_var(&format!("Raw{}", i), &format!(r#"<rect id="b{}"/>"#, i)))
+            .collect();
+        backgrounds.push(svg_var("Transmuted", r#"<rect id="bt"/>"#));
+        vec![
+            CategoryInfo {
+                name: "Background".to_string(),
+                skip: false,
+                variants: backgrounds,
+            },
+            CategoryInfo {
+                name: "Hair".to_string(),
+                skip: false,
+                variants: vec![
+                    svg_var("None", ""),
+                    svg_var("Mohawk", r#"<g id="mohawk"/>"#),
+                ],
+            },
+            CategoryInfo {
+                name: "Eyes".to_string(),
+                skip: false,
+                variants: vec![
+                    svg_var("Blue", r#"<g id="blue"/>"#),
+                    svg_var("Red", r#"<g id="red"/>"#),
+                    svg_var("Gold", r#"<g id="gold"/>"#),
+                ],
+            },
+        ]
+    }
+
+    // instantiate as admin, add the catalog, and give the viewer a key
+    fn init_server(categories: Vec<CategoryInfo>) -> MockDeps {
+        let mut deps = mock_dependencies();
+        let msg = InstantiateMsg {
+            admins: None,
+            entropy: "entropy".to_string(),
+        };
+        instantiate(deps.as_mut(), mock_env(), mock_info("admin", &[]), msg).unwrap();
+        exec(&mut deps, "admin", ExecuteMsg::AddCategories { categories }).unwrap();
+        let viewers = vec!["viewer".to_string()];
+        exec(&mut deps, "admin", ExecuteMsg::AddViewers { viewers }).unwrap();
+        let msg = ExecuteMsg::SetViewingKey {
+            key: "key".to_string(),
+            padding: None,
+        };
+        exec(&mut deps, "viewer", msg).unwrap();
+        deps
+    }
+
+    // the public metadata extension of an image
+    fn token_extension(deps: &MockDeps, image: Vec<u8>) -> Extension {
+        let msg = QueryMsg::TokenMetadata {
+            viewer: Some(viewer()),
+            permit: None,
+            image: ImageVector::Legacy(image),
+            token_id: None,
+        };
+        match from_binary(&query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap() {
+            QueryAnswer::Metadata {
+                public_metadata, ..
+            } => public_metadata.unwrap().extension.unwrap(),
+            _ => panic!("unexpected query answer"),
+        }
+    }
+
+    fn render_config(
+        mode: RenderMode,
+        base_url: Option<&str>,
+        include_media: bool,
+    ) -> RenderConfig {
+        RenderConfig {
+            mode,
+            base_url: base_url.map(|u| u.to_string()),
+            include_media,
+            fragment_defs: false,
+            svg_header: None,
+        }
+    }
+
+    // the value of an image's trait
+    fn trait_value(xten: &Extension, trait_type: &str) -> String {
+        xten.attributes
+            .as_ref()
+            .unwrap()
+            .iter()
+            .find(|t| t.trait_type.as_deref() == Some(trait_type))
+            .unwrap()
+            .value
+            .clone()
+    }
+
+    #[test]
+    fn test_migrate_u8_layout() {
+        let mut deps = mock_dependencies();
+        // legacy records are bincode encoded like tuples of their fields
+        save(&mut deps.storage, STATE_KEY, &(1u8, vec![0u8])).unwrap();
+        let mut cat_store = PrefixedStorage::new(&mut deps.storage, PREFIX_CATEGORY);
+        save(
+            &mut cat_store,
+            &[0],
+            &("Background".to_string(), false, 2u8),
+        )
+        .unwrap();
+        let mut cat_map = PrefixedStorage::new(&mut deps.storage, PREFIX_CATEGORY_MAP);
+        save(&mut cat_map, b"Background", &0u8).unwrap();
+        for (idx, name) in ["Red", "Blue"].into_iter().enumerate() {
+            let mut var_store =
+                PrefixedStorage::multilevel(&mut deps.storage, &[PREFIX_VARIANT, &[0]]);
+            let var = VariantInfo {
+                name: name.to_string(),
+                display_name: name.to_string(),
+                svg: None,
+            };
+            save(&mut var_store, &[idx as u8], &var).unwrap();
+            let mut var_map =
+                PrefixedStorage::multilevel(&mut deps.storage, &[PREFIX_VARIANT_MAP, &[0]]);
+            save(&mut var_map, name.as_bytes(), &(idx as u8)).unwrap();
+        }
+        let legacy_deps = vec![((0u8, 1u8), vec![(0u8, 0u8)])];
+        save(&mut deps.storage, DEPENDENCIES_KEY, &legacy_deps).unwrap();
+
+        migrate(deps.as_mut(), mock_env(), MigrateMsg {}).unwrap();
+        let state: State = load(&deps.storage, STATE_KEY).unwrap();
+        assert_eq!((state.cat_cnt, state.skip), (1, vec![0]));
+        let cat_store = ReadonlyPrefixedStorage::new(&deps.storage, PREFIX_CATEGORY);
+        let cat: Category = load(&cat_store, &idx_key(0)).unwrap();
+        assert_eq!((cat.name.as_str(), cat.cnt), ("Background", 2));
+        let cat_map = ReadonlyPrefixedStorage::new(&deps.storage, PREFIX_CATEGORY_MAP);
+        assert_eq!(load::<u16>(&cat_map, b"Background").unwrap(), 0);
+        let var_map =
+            ReadonlyPrefixedStorage::multilevel(&deps.storage, &[PREFIX_VARIANT_MAP, &idx_key(0)]);
+        assert_eq!(load::<u16>(&var_map, b"Blue").unwrap(), 1);
+        let depends: Vec<StoredDependencies> = load(&deps.storage, DEPENDENCIES_KEY).unwrap();
+        assert_eq!(
+            depends[0].correlated,
+            vec![StoredLayerId {
+                category: 0,
+                variant: 0
+            }]
+        );
+        assert_eq!(depends[0].id.variant, 1);
+        // migrating again leaves the converted storage alone
+        migrate(deps.as_mut(), mock_env(), MigrateMsg {}).unwrap();
+        let state: State = load(&deps.storage, STATE_KEY).unwrap();
+        assert_eq!(state.cat_cnt, 1);
+    }
+
+    #[test]
+    fn test_rule_violations() {
+        let id = |category: u16, variant: u16| StoredLayerId { category, variant };
+        let rules = vec![
+            StoredRule {
+                id: id(0, 1),
+                excludes: vec![id(1, 2)],
+                requires: Vec::new(),
+            },
+            StoredRule {
+                id: id(2, 0),
+                excludes: Vec::new(),
+                requires: vec![id(1, 1), id(3, 1)],
+            },
+        ];
+        // an excluded layer conflicts, but a requirement is pending while a category is hidden
+        let found = rule_violations(&rules, &[1, 2, 0, UNREVEALED]);
+        assert!(
+            found
+                == vec![Violation {
+                    layer: id(0, 1),
+                    conflict: Some(id(1, 2)),
+                }]
+        );
+        // having any one of the required layers satisfies a requirement
+        assert!(rule_violations(&rules, &[1, 1, 0, 0]).is_empty());
+        let found = rule_violations(&rules, &[0, 0, 0, 0]);
+        assert!(
+            found
+                == vec![Violation {
+                    layer: id(2, 0),
+                    conflict: None,
+                }]
+        );
+        // unrevealed categories never conflict
+        assert!(rule_violations(&rules, &[1, UNREVEALED, 0, 1]).is_empty());
+    }
+
+    #[test]
+    fn test_dependency_problems() {
+        let mut storage = MockStorage::new();
+        add_catalog(
+            &mut storage,
+            &[
+                ("Eyes", &["A", "B"]),
+                ("Hat", &["None", "X"]),
+                ("Hair", &["None", "Y"]),
+            ],
+        );
+        let id = |category: u16, variant: u16| StoredLayerId { category, variant };
+        let depends = vec![
+            StoredDependencies {
+                id: id(0, 0),
+                correlated: vec![id(1, 1)],
+            },
+            StoredDependencies {
+                id: id(0, 1),
+                correlated: vec![id(0, 0)],
+            },
+            StoredDependencies {
+                id: id(2, 1),
+                correlated: vec![id(1, 1)],
+            },
+        ];
+        let both_set = "Variant: A in Category: Eyes and Variant: Y in Category: Hair both set \
+                        Category: Hat"
+            .to_string();
+        assert_eq!(
+            dependency_problems(&storage, &depends, None).unwrap(),
+            vec![
+                both_set.clone(),
+                "Variant: B in Category: Eyes can not depend on a variant of its own category"
+                    .to_string(),
+                "Variant: B in Category: Eyes depends on Variant: A in Category: Eyes that has \
+                 its own dependencies"
+                    .to_string(),
+            ]
+        );
+        // only problems involving the touched variants are described
+        assert_eq!(
+            dependency_problems(&storage, &depends, Some(&[id(2, 1)])).unwrap(),
+            vec![both_set]
+        );
+        assert!(dependency_problems(&storage, &depends[..1], None)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_fill_template() {
+        let attr = |trait_type: &str, value: &str| Trait {
+            display_type: None,
+            trait_type: Some(trait_type.to_string()),
+            value: value.to_string(),
+            max_value: None,
+        };
+        let attributes = vec![
+            attr("Alchemical Status", "Transmuted"),
+            attr("Eye Type", "Cyclops"),
+        ];
+        assert_eq!(
+            fill_template(
+                "Skull #{token_id}: {alchemical_status} {trait:Eye Type}",
+                Some("42"),
+                &attributes
+            ),
+            "Skull #42: Transmuted Cyclops"
+        );
+        // a missing token id is left out, and unknown or unclosed templates are kept as is
+        assert_eq!(
+            fill_template("#{token_id} {trait:Hat} {other} {open", None, &attributes),
+            "# {trait:Hat} {other} {open"
+        );
+        assert_eq!(
+            fill_template("no templates", Some("1"), &[]),
+            "no templates"
+        );
+    }
+
+    #[test]
+    fn test_idx_key() {
+        // indices that fit the legacy layout keep their single byte keys
+        assert_eq!(idx_key(0), vec![0]);
+        assert_eq!(idx_key(254), vec![254]);
+        assert_eq!(idx_key(255), vec![255, 255, 0]);
+        assert_eq!(idx_key(300), vec![255, 44, 1]);
+        assert_eq!(idx_key(UNREVEALED), vec![255, 255, 255]);
+    }
+
+    #[test]
+    fn test_wide_layer_indices_answer() {
+        // alchemy and rewind parse this exact shape with u16 fields
+        let answer = QueryAnswer::LayerIndices {
+            layers: vec![
+                StoredLayerId {
+                    category: 3,
+                    variant: 300,
+                },
+                StoredLayerId {
+                    category: 260,
+                    variant: 0,
+                },
+            ],
+        };
+        assert_eq!(
+            to_binary(&answer).unwrap(),
+            Binary::from(
+                &br#"{"layer_indices":{"layers":[{"category":3,"variant":300},{"category":260,"variant":0}]}}"#[..]
+            )
+        );
+    }
+
+    #[test]
+    fn test_variant_status() {
+        let mut deps = init_server(test_catalog());
+        let set_status = |variant: &str, status: VariantStatus| ExecuteMsg::SetVariantStatus {
+            statuses: vec![VariantStatusInfo {
+                id: layer("Eyes", variant),
+                status,
+            }],
+        };
+        let retire = |replacement: &str| VariantStatus::Retired {
+            replacement: replacement.to_string(),
+        };
+        // a variant can not replace itself
+        let err = exec(&mut deps, "admin", set_status("Red", retire("Red"))).unwrap_err();
+        assert!(err.to_string().contains("can not be its own replacement"));
+        exec(&mut deps, "admin", set_status("Red", retire("Gold"))).unwrap();
+        // nor can a variant be replaced by a retired one
+        let err = exec(&mut deps, "admin", set_status("Blue", retire("Red"))).unwrap_err();
+        assert!(err.to_string().contains("because it is retired"));
+        // retiring a replacement extends the chain to an active variant
+        exec(&mut deps, "admin", set_status("Gold", retire("Blue"))).unwrap();
+        let statuses: Vec<StoredVariantStatus> = load(&deps.storage, VARIANT_STATUS_KEY).unwrap();
+        assert_eq!(resolve_variant(&statuses, 2, 1), 0);
+        assert_eq!(resolve_variant(&statuses, 2, 2), 0);
+        assert_eq!(resolve_variant(&statuses, 1, 1), 1);
+        // which keeps the chain acyclic
+        let err = exec(&mut deps, "admin", set_status("Blue", retire("Gold"))).unwrap_err();
+        assert!(err.to_string().contains("because it is retired"));
+
+        // a retired index renders as its final replacement
+        let xten = token_extension(&deps, vec![0, 1, 1]);
+        assert_eq!(trait_value(&xten, "Eyes"), "Blue");
+        let svg = xten.image_data.unwrap();
+        assert!(svg.contains(r#"<g id="blue"/>"#));
+        assert!(!svg.contains(r#"<g id="red"/>"#));
+        // and is swapped out of transmuted images
+        let msg = QueryMsg::Transmute {
+            viewer: viewer(),
+            current: ImageVector::Legacy(vec![6, 1, 1]),
+            new_layers: vec![layer("Hair", "None")],
+            policy: None,
+        };
+        match from_binary(&query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap() {
+            QueryAnswer::Transmute { image } => {
+                assert_eq!(image, ImageVector::Legacy(vec![6, 0, 0]))
+            }
+            _ => panic!("unexpected query answer"),
+        }
+
+        // a hidden variant still renders, and reactivating a variant clears its status
+        exec(
+            &mut deps,
+            "admin",
+            set_status("Gold", VariantStatus::Hidden),
+        )
+        .unwrap();
+        let xten = token_extension(&deps, vec![0, 1, 2]);
+        assert_eq!(trait_value(&xten, "Eyes"), "Gold");
+        exec(&mut deps, "admin", set_status("Red", VariantStatus::Active)).unwrap();
+        let xten = token_extension(&deps, vec![0, 1, 1]);
+        assert_eq!(trait_value(&xten, "Eyes"), "Red");
+    }
+
+    #[test]
+    fn test_render_modes() {
+        let mut deps = init_server(test_catalog());
+        let set_config = |config: RenderConfig| ExecuteMsg::SetRenderConfig { config };
+        // inline svgs are the default
+        let xten = token_extension(&deps, vec![0, 1, 2]);
+        assert_eq!(xten.image, None);
+        assert_eq!(xten.media, None);
+        assert_eq!(
+            xten.image_data.unwrap(),
+            format!(
+                r#"{}<rect id="b0"/><g id="mohawk"/><g id="gold"/></svg>"#,
+                BASELINE_HEADER
+            )
+        );
+        // image urls need a renderer
+        let msg = set_config(render_config(RenderMode::Url, None, false));
+        let err = exec(&mut deps, "admin", msg).unwrap_err();
+        assert!(err.to_string().contains("A base url is required"));
+
+        // the url encodes the version and the little endian u16 indices, url-safe and unpadded
+        let msg = set_config(render_config(RenderMode::Url, Some("https://r.io/"), false));
+        exec(&mut deps, "admin", msg).unwrap();
+        let xten = token_extension(&deps, vec![0, 1, 2]);
+        assert_eq!(xten.image.as_deref(), Some("https://r.io/AgAAAQACAA"));
+        assert_eq!(xten.image_data, None);
+        assert_eq!(xten.media, None);
+        // layers that are not drawn are encoded as 65535
+        let xten = token_extension(&deps, vec![0, 1, 255]);
+        assert_eq!(xten.image.as_deref(), Some("https://r.io/AgAAAQD__w"));
+
+        // the media hash covers only the drawn trait layers
+        let msg = set_config(render_config(RenderMode::Both, Some("https://r.io/"), true));
+        exec(&mut deps, "admin", msg).unwrap();
+        let xten = token_extension(&deps, vec![0, 1, 2]);
+        assert_eq!(xten.image.as_deref(), Some("https://r.io/AgAAAQACAA"));
+        assert!(xten
+            .image_data
+            .unwrap()
+            .ends_with(r#"<g id="gold"/></svg>"#));
+        let media = xten.media.unwrap();
+        assert_eq!(media.len(), 1);
+        assert_eq!(media[0].extension.as_deref(), Some("svg"));
+        assert_eq!(
+            media[0].url,
+            "https://r.io/AgAAAQACAA#sha256=1cf6abdeec00e76e1598aebf6ae12b5a8c323c79c9b87357e46ca27cd204c4bb"
+        );
+        let xten = token_extension(&deps, vec![0, 1, 255]);
+        assert_eq!(
+            xten.media.unwrap()[0].url,
+            "https://r.io/AgAAAQD__w#sha256=078f4c7cd7e67e716833c4f20d197e01c94c775f286f6f72f588d64e5b3002e9"
+        );
+        // inline mode ignores the base url and media flag
+        let msg = set_config(render_config(
+            RenderMode::Inline,
+            Some("https://r.io/"),
+            true,
+        ));
+        exec(&mut deps, "admin", msg).unwrap();
+        let xten = token_extension(&deps, vec![0, 1, 2]);
+        assert_eq!((xten.image, xten.media), (None, None));
+        assert!(xten.image_data.is_some());
+    }
+
+    #[test]
+    fn test_svg_fragments() {
+        let mut deps = init_server(test_catalog());
+        let fragments = vec![
+            SvgFragment {
+                name: "eye".to_string(),
+                svg: r#"<circle fill="{color:0}" stroke="{color:1}"/>"#.to_string(),
+            },
+            SvgFragment {
+                name: "dot".to_string(),
+                svg: r#"<rect id="dot"/>"#.to_string(),
+            },
+        ];
+        exec(
+            &mut deps,
+            "admin",
+            ExecuteMsg::SetSvgFragments { fragments },
+        )
+        .unwrap();
+        let palette = |name: &str, colors: &[&str]| Palette {
+            name: name.to_string(),
+            colors: colors.iter().map(|c| c.to_string()).collect(),
+        };
+        let palettes = vec![
+            palette("gold", &["#fc0", "#000"]),
+            palette("mono", &["#fff"]),
+        ];
+        exec(&mut deps, "admin", ExecuteMsg::SetPalettes { palettes }).unwrap();
+        // variants without fragment references render exactly as they always have
+        let baseline = format!(
+            r#"{}<rect id="b0"/><g id="mohawk"/><g id="gold"/></svg>"#,
+            BASELINE_HEADER
+        );
+        assert_eq!(
+            token_extension(&deps, vec![0, 1, 2]).image_data,
+            Some(baseline.clone())
+        );
+        let library = load_svg_library(&deps.storage).unwrap();
+        assert_eq!(
+            expand_fragments(r#"<g id="gold"/>"#, &library, None).unwrap(),
+            r#"<g id="gold"/>"#
+        );
+
+        // palette colors fill the numbered slots
+        assert_eq!(
+            expand_fragments("<g>{frag:eye:gold}</g>", &library, None).unwrap(),
+            r##"<g><circle fill="#fc0" stroke="#000"/></g>"##
+        );
+        let err = expand_fragments("{frag:eye:mono}", &library, None).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("Palette mono does not have enough colors for SVG fragment eye"));
+        // variants can not be added with a palette that is too short
+        let add = |category: &str, name: &str, svg: &str| ExecuteMsg::AddVariants {
+            variants: vec![AddVariantInfo {
+                category_name: category.to_string(),
+                variants: vec![svg_var(name, svg)],
+            }],
+        };
+        let err = exec(&mut deps, "admin", add("Eyes", "Pale", "{frag:eye:mono}")).unwrap_err();
+        assert!(err.to_string().contains("does not have enough colors"));
+        exec(
+            &mut deps,
+            "admin",
+            add("Hair", "Curly", "<g>{frag:eye:gold}</g>"),
+        )
+        .unwrap();
+        exec(
+            &mut deps,
+            "admin",
+            add("Eyes", "Fancy", "{frag:eye:gold}{frag:dot}"),
+        )
+        .unwrap();
+        // and a palette in use can not lose the colors its variants need
+        let palettes = vec![palette("gold", &["#fc0"])];
+        let err = exec(&mut deps, "admin", ExecuteMsg::SetPalettes { palettes }).unwrap_err();
+        assert!(err.to_string().contains("does not have enough colors"));
+
+        // fragments are inlined by default
+        assert_eq!(
+            token_extension(&deps, vec![0, 2, 3]).image_data.unwrap(),
+            format!(
+                r##"{}<rect id="b0"/><g><circle fill="#fc0" stroke="#000"/></g><circle fill="#fc0" stroke="#000"/><rect id="dot"/></svg>"##,
+                BASELINE_HEADER
+            )
+        );
+        // or defined once and drawn with use elements
+        let mut config = render_config(RenderMode::Inline, None, false);
+        config.fragment_defs = true;
+        exec(&mut deps, "admin", ExecuteMsg::SetRenderConfig { config }).unwrap();
+        assert_eq!(
+            token_extension(&deps, vec![0, 2, 3]).image_data.unwrap(),
+            format!(
+                r##"{}<defs><g id="frag-eye-gold"><circle fill="#fc0" stroke="#000"/></g><g id="frag-dot"><rect id="dot"/></g></defs><rect id="b0"/><g><use href="#frag-eye-gold"/></g><use href="#frag-eye-gold"/><use href="#frag-dot"/></svg>"##,
+                BASELINE_HEADER
+            )
+        );
+        assert_eq!(
+            token_extension(&deps, vec![0, 1, 2]).image_data,
+            Some(baseline)
+        );
+    }
+
+    #[test]
+    fn test_effects() {
+        let mut deps = init_server(test_catalog());
+        let effect = |name: &str, triggers: Vec<EffectTrigger>| Effect {
+            name: name.to_string(),
+            triggers,
+            defs: None,
+            underlay: None,
+            overlay: None,
+            filter: None,
+        };
+        // effects need a trigger and a usable filter id
+        let effects = vec![effect("empty", Vec::new())];
+        let err = exec(&mut deps, "admin", ExecuteMsg::SetEffects { effects }).unwrap_err();
+        assert!(err.to_string().contains("must have at least one trigger"));
+        let transmuted = EffectTrigger::AlchemicalStatus {
+            status: "Transmuted".to_string(),
+        };
+        let mut bad = effect("bad", vec![transmuted.clone()]);
+        bad.filter = Some(r#"x" onload="y"#.to_string());
+        let err = exec(
+            &mut deps,
+            "admin",
+            ExecuteMsg::SetEffects { effects: vec![bad] },
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("is not a valid name"));
+
+        let mut glow = effect(
+            "glow",
+            vec![EffectTrigger::Variant {
+                layer: layer("Eyes", "Gold"),
+            }],
+        );
+        glow.defs = Some(r#"<filter id="glow"/>"#.to_string());
+        glow.underlay = Some(r#"<rect id="aura"/>"#.to_string());
+        glow.overlay = Some(r#"<g id="sparkle"/>"#.to_string());
+        glow.filter = Some("glow".to_string());
+        let mut shine = effect("shine", vec![transmuted]);
+        shine.overlay = Some(r#"<g id="shine"/>"#.to_string());
+        shine.filter = Some("shade".to_string());
+        let effects = vec![glow, shine];
+        exec(&mut deps, "admin", ExecuteMsg::SetEffects { effects }).unwrap();
+
+        // untriggered effects leave the image alone
+        assert_eq!(
+            token_extension(&deps, vec![0, 1, 1]).image_data.unwrap(),
+            format!(
+                r#"{}<rect id="b0"/><g id="mohawk"/><g id="red"/></svg>"#,
+                BASELINE_HEADER
+            )
+        );
+        // a drawn layer adds defs, an underlay, an overlay, and filters the trait layers
+        assert_eq!(
+            token_extension(&deps, vec![0, 1, 2]).image_data.unwrap(),
+            format!(
+                r##"{}<defs><filter id="glow"/></defs><rect id="aura"/><g filter="url(#glow)"><rect id="b0"/><g id="mohawk"/><g id="gold"/></g><g id="sparkle"/></svg>"##,
+                BASELINE_HEADER
+            )
+        );
+        // the alchemical status triggers too, and filters nest in effect order
+        assert_eq!(
+            token_extension(&deps, vec![6, 1, 2]).image_data.unwrap(),
+            format!(
+                r##"{}<defs><filter id="glow"/></defs><rect id="aura"/><g filter="url(#shade)"><g filter="url(#glow)"><rect id="bt"/><g id="mohawk"/><g id="gold"/></g></g><g id="sparkle"/><g id="shine"/></svg>"##,
+                BASELINE_HEADER
+            )
+        );
+        // variant triggers match the layer that is drawn, not the stored index
+        let msg = ExecuteMsg::SetVariantStatus {
+            statuses: vec![VariantStatusInfo {
+                id: layer("Eyes", "Red"),
+                status: VariantStatus::Retired {
+                    replacement: "Gold".to_string(),
+                },
+            }],
+        };
+        exec(&mut deps, "admin", msg).unwrap();
+        assert!(token_extension(&deps, vec![0, 1, 1])
+            .image_data
+            .unwrap()
+            .contains(r##"<g filter="url(#glow)">"##));
+        let names = vec!["glow".to_string(), "shine".to_string()];
+        exec(&mut deps, "admin", ExecuteMsg::RemoveEffects { names }).unwrap();
+        assert_eq!(
+            token_extension(&deps, vec![0, 1, 2]).image_data.unwrap(),
+            format!(
+                r#"{}<rect id="b0"/><g id="mohawk"/><g id="gold"/></svg>"#,
+                BASELINE_HEADER
+            )
+        );
+
+        // the header must be an opening svg tag
+        let mut config = render_config(RenderMode::Inline, None, false);
+        config.svg_header = Some("<g>".to_string());
+        let err = exec(&mut deps, "admin", ExecuteMsg::SetRenderConfig { config }).unwrap_err();
+        assert!(err.to_string().contains("must be an opening <svg> tag"));
+        let header = r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 48 48">"#;
+        let mut config = render_config(RenderMode::Inline, None, false);
+        config.svg_header = Some(header.to_string());
+        exec(&mut deps, "admin", ExecuteMsg::SetRenderConfig { config }).unwrap();
+        assert_eq!(
+            token_extension(&deps, vec![0, 1, 2]).image_data.unwrap(),
+            format!(
+                r#"{}<rect id="b0"/><g id="mohawk"/><g id="gold"/></svg>"#,
+                header
+            )
+        );
+    }
+
+    #[test]
+    fn test_viewer_contract_keys() {
+        #[derive(serde::Deserialize)]
+        #[serde(rename_all = "snake_case")]
+        enum Received {
+            ReceiveQueryKey { key: String },
+        }
+        let mut deps = init_server(test_catalog());
+        // the keys issued to the allowlisted contracts
+        let issued = |resp: Response| {
+            resp.messages
+                .into_iter()
+                .map(|m| match m.msg {
+                    CosmosMsg::Wasm(WasmMsg::Execute {
+                        contract_addr,
+                        code_hash,
+                        msg,
+                        ..
+                    }) => {
+                        assert_eq!(code_hash, format!("{}hash", contract_addr));
+                        let Received::ReceiveQueryKey { key } = from_binary(&msg).unwrap();
+                        (contract_addr, key)
+                    }
+                    _ => panic!("unexpected message"),
+                })
+                .collect::<Vec<(String, String)>>()
+        };
+        let serve = |deps: &MockDeps, address: &str, viewing_key: &str| {
+            let viewer = ViewerInfo {
+                address: address.to_string(),
+                viewing_key: viewing_key.to_string(),
+            };
+            query(deps.as_ref(), mock_env(), QueryMsg::ServeAlchemy { viewer })
+        };
+        let contracts = vec![
+            ContractInfo {
+                code_hash: "alchemyhash".to_string(),
+                address: "alchemy".to_string(),
+            },
+            ContractInfo {
+                code_hash: "rewindhash".to_string(),
+                address: "rewind".to_string(),
+            },
+        ];
+        let msg = ExecuteMsg::AddViewerContracts {
+            contracts: contracts.clone(),
+            entropy: "first".to_string(),
+        };
+        assert!(exec(&mut deps, "viewer", msg).is_err());
+        let msg = ExecuteMsg::AddViewerContracts {
+            contracts,
+            entropy: "first".to_string(),
+        };
+        let keys = issued(exec(&mut deps, "admin", msg).unwrap());
+        assert_eq!(keys.len(), 2);
+        assert_eq!(
+            (keys[0].0.as_str(), keys[1].0.as_str()),
+            ("alchemy", "rewind")
+        );
+        assert_ne!(keys[0].1, keys[1].1);
+        // each contract can query with the key it was sent
+        serve(&deps, "alchemy", &keys[0].1).unwrap();
+        serve(&deps, "rewind", &keys[1].1).unwrap();
+        assert!(serve(&deps, "alchemy", &keys[1].1).is_err());
+
+        // rotation issues new keys to every contract and invalidates the old ones
+        let msg = ExecuteMsg::RotateViewerKeys {
+            entropy: "second".to_string(),
+        };
+        let rotated = issued(exec(&mut deps, "admin", msg).unwrap());
+        assert_eq!(rotated.len(), 2);
+        assert_ne!(rotated[0].1, keys[0].1);
+        assert!(serve(&deps, "alchemy", &keys[0].1).is_err());
+        serve(&deps, "alchemy", &rotated[0].1).unwrap();
+
+        // removed contracts can no longer view
+        let msg = ExecuteMsg::RemoveViewerContracts {
+            contracts: vec!["alchemy".to_string()],
+        };
+        exec(&mut deps, "admin", msg).unwrap();
+        let err = serve(&deps, "alchemy", &rotated[0].1).unwrap_err();
+        assert!(err.to_string().contains("Not a viewer"));
+        let msg = ExecuteMsg::RotateViewerKeys {
+            entropy: "third".to_string(),
+        };
+        let rotated = issued(exec(&mut deps, "admin", msg).unwrap());
+        assert_eq!(rotated.len(), 1);
+        assert_eq!(rotated[0].0, "rewind");
+    }
+
+    #[test]
+    fn test_batch_token_metadata() {
+        let mut deps = init_server(test_catalog());
+        let msg = ExecuteMsg::SetMetadata {
+            public_metadata: Some(Metadata {
+                token_uri: None,
+                extension: Some(Extension {
+                    name: Some("Skull #{token_id}: {trait:Eyes}".to_string()),
+                    ..Extension::default()
+                }),
+            }),
+            private_metadata: None,
+        };
+        exec(&mut deps, "admin", msg).unwrap();
+        let batch = |deps: &MockDeps, images: Vec<Vec<u8>>, token_ids: Option<Vec<&str>>| {
+            let msg = QueryMsg::BatchTokenMetadata {
+                viewer: Some(viewer()),
+                permit: None,
+                images: images.into_iter().map(ImageVector::Legacy).collect(),
+                token_ids: token_ids.map(|t| t.iter().map(|i| i.to_string()).collect()),
+            };
+            query(deps.as_ref(), mock_env(), msg).map(|bin| match from_binary(&bin).unwrap() {
+                QueryAnswer::BatchTokenMetadata { metadata } => metadata,
+                _ => panic!("unexpected query answer"),
+            })
+        };
+        let images = vec![vec![0, 1, 0], vec![1, 1, 9], vec![2, 1, 1]];
+
+        // a bad image only fails its own entry, and the others keep their order
+        let metadata = batch(&deps, images.clone(), Some(vec!["1", "2", "3"])).unwrap();
+        assert_eq!(metadata.len(), 3);
+        assert!(metadata[1].error.is_some());
+        assert_eq!(metadata[1].public_metadata, None);
+        assert_eq!(metadata[1].private_metadata, None);
+        let names = [&metadata[0], &metadata[2]]
+            .iter()
+            .map(|m| {
+                assert_eq!(m.error, None);
+                let xten = m.public_metadata.clone().unwrap().extension.unwrap();
+                xten.name.unwrap()
+            })
+            .collect::<Vec<String>>();
+        assert_eq!(names, vec!["Skull #1: Blue", "Skull #3: Red"]);
+        // each entry matches the single token query
+        let single = token_extension(&deps, vec![2, 1, 1]);
+        let xten = metadata[2]
+            .public_metadata
+            .clone()
+            .unwrap()
+            .extension
+            .unwrap();
+        assert_eq!(xten.image_data, single.image_data);
+
+        // token ids are optional, but must pair with every image when given
+        let metadata = batch(&deps, images.clone(), None).unwrap();
+        let xten = metadata[0]
+            .public_metadata
+            .clone()
+            .unwrap()
+            .extension
+            .unwrap();
+        assert_eq!(xten.name.as_deref(), Some("Skull #: Blue"));
+        let err = batch(&deps, images, Some(vec!["1", "2"])).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("The number of token ids must match the number of images"));
+    }
+
+    #[test]
+    fn test_roll_images() {
+        let mut deps = init_server(test_catalog());
+        let weights = [
+            (
+                "Background",
+                vec!["Raw0", "Raw1", "Raw2", "Raw3", "Raw4", "Raw5"],
+            ),
+            ("Hair", vec!["None", "Mohawk"]),
+            ("Eyes", vec!["Blue", "Red", "Gold"]),
+        ]
+        .into_iter()
+        .map(|(category, names)| CategoryWeights {
+            category: category.to_string(),
+            variants: names
+                .into_iter()
+                .map(|name| VariantWeight {
+                    name: name.to_string(),
+                    weight: 1,
+                    conditional: Vec::new(),
+                })
+                .collect(),
+        })
+        .collect();
+        exec(
+            &mut deps,
+            "admin",
+            ExecuteMsg::SetGenesisWeights { weights },
+        )
+        .unwrap();
+        let minters = vec!["minter".to_string()];
+        exec(&mut deps, "admin", ExecuteMsg::AddMinters { minters }).unwrap();
+        let roll = |deps: &mut MockDeps, sender: &str, count: u16| {
+            let msg = ExecuteMsg::RollImages {
+                entropy: "same".to_string(),
+                count,
+                legacy: None,
+            };
+            exec(deps, sender, msg).map(|resp| match from_binary(&resp.data.unwrap()).unwrap() {
+                ExecuteAnswer::RollImages { images } => images
+                    .iter()
+                    .map(|i| i.to_indices().unwrap())
+                    .collect::<Vec<Vec<u16>>>(),
+                _ => panic!("unexpected answer"),
+            })
+        };
+        let err = roll(&mut deps, "viewer", 1).unwrap_err();
+        assert!(err.to_string().contains("Not a minter"));
+        assert!(roll(&mut deps, "minter", MAX_ROLLS + 1).is_err());
+
+        // every roll advances the seed, so repeating one with the same entropy in the same
+        // block rolls new images
+        let seed: Vec<u8> = load(&deps.storage, PRNG_SEED_KEY).unwrap();
+        let first = roll(&mut deps, "minter", 20).unwrap();
+        let rolled_seed: Vec<u8> = load(&deps.storage, PRNG_SEED_KEY).unwrap();
+        assert_ne!(seed, rolled_seed);
+        let second = roll(&mut deps, "minter", 20).unwrap();
+        assert_ne!(first, second);
+        // variants without a weight, like Transmuted, are never rolled
+        for image in first.iter().chain(second.iter()) {
+            assert!(image[0] < 6 && image[1] < 2 && image[2] < 3);
+        }
+
+        let msg = ExecuteMsg::RollImage {
+            entropy: "single".to_string(),
+            legacy: Some(true),
+        };
+        let resp = exec(&mut deps, "minter", msg).unwrap();
+        match from_binary(&resp.data.unwrap()).unwrap() {
+            ExecuteAnswer::RollImage {
+                image: ImageVector::Legacy(image),
+            } => assert_eq!(image.len(), 3),
+            _ => panic!("unexpected answer"),
+        }
+    }
+}
@@ -3,7 +3,9 @@
 #![allow(clippy::large_enum_variant)]
 #![allow(dead_code)]
 pub mod contract;
+mod contract_info;
 mod metadata;
 pub mod msg;
 pub mod state;
 mod storage;
+mod viewer_msgs;
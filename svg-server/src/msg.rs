@@ -1,8 +1,9 @@
+use crate::contract_info::ContractInfo;
 use crate::metadata::Metadata;
 use crate::state::{
     Category, PREFIX_CATEGORY, PREFIX_CATEGORY_MAP, PREFIX_VARIANT, PREFIX_VARIANT_MAP,
 };
-use crate::storage::may_load;
+use crate::storage::{idx_key, may_load};
 use cosmwasm_std::{Addr, StdError, StdResult, Storage};
 use cosmwasm_storage::ReadonlyPrefixedStorage;
 use schemars::JsonSchema;
@@ -18,6 +19,10 @@ pub struct InstantiateMsg {
     pub entropy: String,
 }
 
+/// Migration message
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct MigrateMsg {}
+
 /// Handle messages
 #[derive(Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "snake_case")]
@@ -50,7 +55,27 @@ pub enum ExecuteMsg {
         /// list of address to revoke viewing priveleges from
         viewers: Vec<String>,
     },
-    /// allows an admin to add minters
+    /// allows an admin to add contracts to the viewer allowlist.  The server issues each contract
+    /// a viewing key and delivers it with a ReceiveQueryKey message, so the contract never has to
+    /// set a key of its own
+    AddViewerContracts {
+        /// code hashes and addresses of the contracts
+        contracts: Vec<ContractInfo>,
+        /// entropy used to generate the viewing keys
+        entropy: String,
+    },
+    /// allows an admin to remove contracts from the viewer allowlist
+    RemoveViewerContracts {
+        /// addresses of the contracts
+        contracts: Vec<String>,
+    },
+    /// allows an admin to issue new viewing keys to every contract on the viewer allowlist.  All
+    /// contracts receive their new key in the same transaction
+    RotateViewerKeys {
+        /// entropy used to generate the viewing keys
+        entropy: String,
+    },
+    /// allows an admin to add minters.  Minters can roll genesis images
     AddMinters {
         /// list of new addresses with viewing priveleges
         minters: Vec<String>,
@@ -75,13 +100,43 @@ pub enum ExecuteMsg {
     },
     /// modify existing trait variants
     ModifyVariants { modifications: Vec<VariantModInfo> },
-    /// set the common metadata for the collection
+    /// set whether variants are active, hidden from rolls, or retired
+    SetVariantStatus { statuses: Vec<VariantStatusInfo> },
+    /// set the genesis roll weights of categories.  This replaces all existing weights of each
+    /// specified category
+    SetGenesisWeights { weights: Vec<CategoryWeights> },
+    /// set the common metadata for the collection.  The token_uri, and the extension's name,
+    /// description, and external_url may contain the templates `{token_id}`,
+    /// `{alchemical_status}`, and `{trait:<category name>}`, which are filled in per token
     SetMetadata {
         /// common public metadata
         public_metadata: Option<Metadata>,
         /// common private metadata
         private_metadata: Option<Metadata>,
     },
+    /// set how token images are rendered in metadata
+    SetRenderConfig { config: RenderConfig },
+    /// add svg fragments to the shared library, replacing any with the same names.  A replacement
+    /// may not break the svg of a variant that references it
+    SetSvgFragments { fragments: Vec<SvgFragment> },
+    /// add color palettes to the shared library, replacing any with the same names.  A replacement
+    /// must still have enough colors for the variants that use it
+    SetPalettes { palettes: Vec<Palette> },
+    /// add effect layers, replacing any with the same names
+    SetEffects { effects: Vec<Effect> },
+    /// remove effect layers
+    RemoveEffects {
+        /// names of the effects to remove
+        names: Vec<String>,
+    },
+    /// remove svg fragments and color palettes from the shared library.  Items that variants
+    /// still reference can not be removed
+    RemoveSvgLibraryItems {
+        /// names of the fragments to remove
+        fragments: Vec<String>,
+        /// names of the palettes to remove
+        palettes: Vec<String>,
+    },
     /// add dependencies for traits that have multiple layers
     AddDependencies {
         /// new dependencies to add
@@ -97,11 +152,46 @@ pub enum ExecuteMsg {
         /// dependencies to modify
         dependencies: Vec<Dependencies>,
     },
+    /// add compatibility rules for trait variants
+    AddRules {
+        /// new rules to add
+        rules: Vec<Rule>,
+    },
+    /// remove the compatibility rules of trait variants
+    RemoveRules {
+        /// rules to remove
+        rules: Vec<Rule>,
+    },
+    /// modify the compatibility rules of trait variants
+    ModifyRules {
+        /// rules to modify
+        rules: Vec<Rule>,
+    },
+    /// set the default policy used to resolve rule conflicts when transmuting
+    SetConflictPolicy { policy: ConflictPolicy },
     /// disallow the use of a permit
     RevokePermit {
         /// name of the permit that is no longer valid
         permit_name: String,
     },
+    /// allows a minter to roll a new genesis image.  Every roll advances the server's seed, so
+    /// the same image can not be rolled again or previewed.  A minter contract receives the
+    /// image in the response data of a submessage reply, and should mint whatever it rolls
+    RollImage {
+        /// entropy used to roll the image
+        entropy: String,
+        /// optionally true if the image should be a legacy u8 vector
+        legacy: Option<bool>,
+    },
+    /// allows a minter to roll multiple new genesis images
+    RollImages {
+        /// entropy used to roll the images
+        entropy: String,
+        /// number of images to roll
+        count: u16,
+        /// optionally true if the images should be legacy u8 vectors
+        legacy: Option<bool>,
+    },
 }
 
 /// Responses from handle functions
@@ -120,6 +210,11 @@ pub enum ExecuteAnswer {
         // current viewers
         viewers: Vec<Addr>,
     },
+    /// response from adding/removing viewer contracts or rotating their keys
+    ViewerContracts {
+        /// current viewer contracts
+        viewer_contracts: Vec<ContractInfo>,
+    },
     /// response from adding/removing minters
     MintersList {
         // current operators
@@ -128,7 +223,7 @@ pub enum ExecuteAnswer {
     /// response from adding new trait categories
     AddCategories {
         /// number of categories
-        count: u8,
+        count: u16,
     },
     /// response from adding new trait variants
     AddVariants { status: String },
@@ -136,6 +231,10 @@ pub enum ExecuteAnswer {
     ModifyCategory { status: String },
     /// response from modifying existing trait variants
     ModifyVariants { status: String },
+    /// response from setting variant statuses
+    SetVariantStatus { status: String },
+    /// response from setting genesis weights
+    SetGenesisWeights { status: String },
     /// response from setting common metadata
     SetMetadata { metadata: CommonMetadata },
     /// response from adding dependencies
@@ -144,8 +243,32 @@ pub enum ExecuteAnswer {
     RemoveDependencies { status: String },
     /// response from modifying dependencies
     ModifyDependencies { status: String },
+    /// response from setting the render config
+    SetRenderConfig { status: String },
+    /// response from setting svg fragments
+    SetSvgFragments { status: String },
+    /// response from setting palettes
+    SetPalettes { status: String },
+    /// response from removing svg library items
+    RemoveSvgLibraryItems { status: String },
+    /// response from setting effects
+    SetEffects { status: String },
+    /// response from removing effects
+    RemoveEffects { status: String },
+    /// response from adding rules
+    AddRules { status: String },
+    /// response from removing rules
+    RemoveRules { status: String },
+    /// response from modifying rules
+    ModifyRules { status: String },
+    /// response from setting the conflict policy
+    SetConflictPolicy { status: String },
     /// response from revoking a permit
     RevokePermit { status: String },
+    /// the rolled genesis image
+    RollImage { image: ImageVector },
+    /// the rolled genesis images
+    RollImages { images: Vec<ImageVector> },
 }
 
 /// Queries
@@ -178,11 +301,11 @@ pub enum QueryMsg {
         /// optional category name to display
         name: Option<String>,
         /// optional category index to display
-        index: Option<u8>,
+        index: Option<u16>,
         /// optional trait variant index to start at
-        start_at: Option<u8>,
+        start_at: Option<u16>,
         /// max number of variants to display
-        limit: Option<u8>,
+        limit: Option<u16>,
         /// optionally true if svgs should be displayed.  Defaults to false
         display_svg: Option<bool>,
     },
@@ -220,6 +343,26 @@ pub enum QueryMsg {
         /// max number of dependencies to display
         limit: Option<u16>,
     },
+    /// lists every inconsistency found in the trait catalog
+    ValidateCatalog {
+        /// optional address and viewing key of an admin
+        viewer: Option<ViewerInfo>,
+        /// optional permit used to verify admin identity.  If both viewer and permit
+        /// are provided, the viewer will be ignored
+        permit: Option<Permit>,
+    },
+    /// displays the trait compatibility rules and the default conflict policy
+    Rules {
+        /// optional address and viewing key of an admin, minter, or viewer
+        viewer: Option<ViewerInfo>,
+        /// optional permit used to verify identity.  If both viewer and permit
+        /// are provided, the viewer will be ignored
+        permit: Option<Permit>,
+        /// optional rule index to start at
+        start_at: Option<u16>,
+        /// max number of rules to display
+        limit: Option<u16>,
+    },
     /// generates metadata from the input image vector
     TokenMetadata {
         /// optional address and viewing key of an admin, minter or viewer
@@ -228,7 +371,46 @@ pub enum QueryMsg {
         /// are provided, the viewer will be ignored
         permit: Option<Permit>,
         /// image indices
-        image: Vec<u8>,
+        image: ImageVector,
+        /// optional token id used to fill in `{token_id}` metadata templates
+        token_id: Option<String>,
+    },
+    /// displays the effect layers
+    Effects {
+        /// optional address and viewing key of an admin
+        viewer: Option<ViewerInfo>,
+        /// optional permit used to verify admin identity.  If both viewer and permit
+        /// are provided, the viewer will be ignored
+        permit: Option<Permit>,
+    },
+    /// displays the shared svg fragments and color palettes
+    SvgLibrary {
+        /// optional address and viewing key of an admin
+        viewer: Option<ViewerInfo>,
+        /// optional permit used to verify admin identity.  If both viewer and permit
+        /// are provided, the viewer will be ignored
+        permit: Option<Permit>,
+    },
+    /// displays how token images are rendered in metadata
+    RenderConfig {
+        /// optional address and viewing key of an admin, minter or viewer
+        viewer: Option<ViewerInfo>,
+        /// optional permit used to verify identity.  If both viewer and permit
+        /// are provided, the viewer will be ignored
+        permit: Option<Permit>,
+    },
+    /// generates metadata for multiple image vectors
+    BatchTokenMetadata {
+        /// optional address and viewing key of an admin, minter or viewer
+        viewer: Option<ViewerInfo>,
+        /// optional permit used to verify admin identity.  If both viewer and permit
+        /// are provided, the viewer will be ignored
+        permit: Option<Permit>,
+        /// image vectors
+        images: Vec<ImageVector>,
+        /// optional token ids in the same order as the images, used to fill in `{token_id}`
+        /// metadata templates
+        token_ids: Option<Vec<String>>,
     },
     /// display info that achemy/reveal contracts will need
     ServeAlchemy {
@@ -240,22 +422,42 @@ pub enum QueryMsg {
         /// address and viewing key of the alchemy contract
         viewer: ViewerInfo,
         /// image indices
-        image: Vec<u8>,
+        image: ImageVector,
     },
     /// return the new image vec resulting from altering the specified layers
     Transmute {
         /// address and viewing key of the alchemy contract
         viewer: ViewerInfo,
         /// current image indices
-        current: Vec<u8>,
+        current: ImageVector,
         /// transmuted layers
         new_layers: Vec<LayerId>,
+        /// optional policy used to resolve rule conflicts.  Defaults to the policy set by
+        /// the admins
+        policy: Option<ConflictPolicy>,
     },
     /// display the StoredLayerId for jawless and cyclops, and the info about skull materials
     SkullTypePlus {
         /// address and viewing key of the alchemy contract
         viewer: ViewerInfo,
     },
+    /// display the StoredLayerIds of the specified layers
+    LayerIndices {
+        /// address and viewing key of the alchemy contract
+        viewer: ViewerInfo,
+        /// layers to look up
+        layers: Vec<LayerId>,
+    },
+    /// displays the genesis weights of a category
+    GenesisWeights {
+        /// optional address and viewing key of an admin
+        viewer: Option<ViewerInfo>,
+        /// optional permit used to verify admin identity.  If both viewer and permit
+        /// are provided, the viewer will be ignored
+        permit: Option<Permit>,
+        /// name of the category
+        category: String,
+    },
 }
 
 /// responses to queries
@@ -267,26 +469,27 @@ pub enum QueryAnswer {
         admins: Vec<Addr>,
         minters: Vec<Addr>,
         viewers: Vec<Addr>,
+        viewer_contracts: Vec<ContractInfo>,
     },
     /// display a trait category
     Category {
         /// number of categories
-        category_count: u8,
+        category_count: u16,
         /// this category's index
-        index: u8,
+        index: u16,
         /// trait category name
         name: String,
         /// true if this category is skipped during rolls
         skip: bool,
         /// number of variants in this category
-        variant_count: u8,
+        variant_count: u16,
         /// paginated variants for this category
         variants: Vec<VariantInfoPlus>,
     },
     /// display a layer variant
     Variant {
         /// the index of the category this variant belongs to
-        category_index: u8,
+        category_index: u16,
         /// all the variant info
         info: VariantInfoPlus,
     },
@@ -295,16 +498,43 @@ pub enum QueryAnswer {
         public_metadata: Option<Metadata>,
         private_metadata: Option<Metadata>,
     },
+    /// displays how token images are rendered in metadata
+    RenderConfig { config: RenderConfig },
+    /// displays the effect layers
+    Effects { effects: Vec<Effect> },
+    /// displays the shared svg fragments and color palettes
+    SvgLibrary {
+        fragments: Vec<SvgFragment>,
+        palettes: Vec<Palette>,
+    },
+    /// metadata of multiple image vectors
+    BatchTokenMetadata {
+        /// metadata in the same order as the requested images
+        metadata: Vec<BatchMetadata>,
+    },
     /// displays the trait variants with dependencies (multiple layers)
     Dependencies {
         /// number of dependencies
         count: u16,
         dependencies: Vec<Dependencies>,
     },
+    /// lists every inconsistency found in the trait catalog
+    ValidateCatalog {
+        /// descriptions of the problems found.  Empty if the catalog is consistent
+        problems: Vec<String>,
+    },
+    /// displays the trait compatibility rules
+    Rules {
+        /// number of rules
+        count: u16,
+        rules: Vec<Rule>,
+        /// default policy used to resolve conflicts when transmuting
+        policy: ConflictPolicy,
+    },
     /// info needed by alchemy/reveal contracts
     ServeAlchemy {
         /// categories that are skipped when rolling/revealing
-        skip: Vec<u8>,
+        skip: Vec<u16>,
         /// variant display dependencies
         dependencies: Vec<StoredDependencies>,
         /// category names
@@ -313,7 +543,7 @@ pub enum QueryAnswer {
     /// state info
     State {
         /// number of categories
-        category_count: u8,
+        category_count: u16,
         /// categories that are skipped when rolling
         skip: Vec<String>,
     },
@@ -326,8 +556,8 @@ pub enum QueryAnswer {
     },
     /// display the new image vec after transmuting the requested layers
     Transmute {
-        /// new image
-        image: Vec<u8>,
+        /// new image in the same format as the current image
+        image: ImageVector,
     },
     /// display the StoredLayerId for jawless and cyclops, and the info about skull materials
     SkullTypePlus {
@@ -336,10 +566,17 @@ pub enum QueryAnswer {
         /// jawless layer
         jawless: StoredLayerId,
         /// skull category index
-        skull_idx: u8,
+        skull_idx: u16,
         /// list of all skull materials
         skull_variants: Vec<VariantIdxName>,
     },
+    /// display the StoredLayerIds of the requested layers
+    LayerIndices {
+        /// StoredLayerIds in the same order as requested
+        layers: Vec<StoredLayerId>,
+    },
+    /// display the genesis weights of a category
+    GenesisWeights { weights: CategoryWeights },
 }
 
 /// trait variant information
@@ -357,11 +594,67 @@ pub struct VariantInfo {
 #[derive(Serialize, Deserialize, JsonSchema, Clone, PartialEq, Eq, Debug)]
 pub struct VariantInfoPlus {
     /// index of variant
-    pub index: u8,
+    pub index: u16,
     /// variant info
     pub variant_info: VariantInfo,
     /// layer variants it includes
     pub includes: Vec<LayerId>,
+    /// whether the variant is active, hidden, or retired
+    pub status: VariantStatus,
+}
+
+/// status of a trait variant
+#[derive(Serialize, Deserialize, JsonSchema, Clone, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum VariantStatus {
+    /// the variant can be rolled
+    Active,
+    /// the variant renders normally but should not be rolled
+    Hidden,
+    /// the variant should not be rolled and renders as its replacement
+    Retired {
+        /// name of the replacement variant in the same category
+        replacement: String,
+    },
+}
+
+/// genesis roll weights of a category's variants.  Variants without a weight are never rolled
+#[derive(Serialize, Deserialize, JsonSchema, Clone, PartialEq, Eq, Debug)]
+pub struct CategoryWeights {
+    /// name of the category
+    pub category: String,
+    /// weights of the variants
+    pub variants: Vec<VariantWeight>,
+}
+
+/// a variant's genesis roll weight
+#[derive(Serialize, Deserialize, JsonSchema, Clone, PartialEq, Eq, Debug)]
+pub struct VariantWeight {
+    /// name of the variant
+    pub name: String,
+    /// weight used when no condition applies
+    pub weight: u16,
+    /// weights that apply if the image already has a layer from an earlier category.  The
+    /// first matching condition is used
+    pub conditional: Vec<ConditionalWeight>,
+}
+
+/// a weight that applies if the image already has a layer
+#[derive(Serialize, Deserialize, JsonSchema, Clone, PartialEq, Eq, Debug)]
+pub struct ConditionalWeight {
+    /// the layer the image must have
+    pub layer: LayerId,
+    /// the weight to use
+    pub weight: u16,
+}
+
+/// info needed to set a variant's status
+#[derive(Serialize, Deserialize, JsonSchema, Clone, PartialEq, Eq, Debug)]
+pub struct VariantStatusInfo {
+    /// the variant
+    pub id: LayerId,
+    /// the new status
+    pub status: VariantStatus,
 }
 
 /// trait category information
@@ -438,6 +731,153 @@ impl Dependencies {
     }
 }
 
+/// describes which layers a trait variant can not be combined with, and which layers it needs
+#[derive(Serialize, Deserialize, JsonSchema, Clone, PartialEq, Eq, Debug)]
+pub struct Rule {
+    /// id of the layer variant the rule applies to
+    pub id: LayerId,
+    /// layers that can not be in the same image as this variant
+    pub excludes: Vec<LayerId>,
+    /// if not empty, the image must also have at least one of these layers
+    pub requires: Vec<LayerId>,
+}
+
+impl Rule {
+    /// Returns StdResult<StoredRule> from creating a StoredRule from a Rule
+    ///
+    /// # Arguments
+    ///
+    /// * `storage` - a reference to the contract storage
+    pub fn to_stored(&self, storage: &dyn Storage) -> StdResult<StoredRule> {
+        let id = self.id.to_stored(storage)?;
+        let excludes = self
+            .excludes
+            .iter()
+            .map(|l| l.to_stored(storage))
+            .collect::<StdResult<Vec<StoredLayerId>>>()?;
+        // only one variant of a category can be in an image
+        if excludes.iter().any(|l| l.category == id.category) {
+            return Err(StdError::generic_err(format!(
+                "Variant: {} in Category: {} can not exclude variants of its own category",
+                self.id.variant, self.id.category
+            )));
+        }
+        let requires = self
+            .requires
+            .iter()
+            .map(|l| l.to_stored(storage))
+            .collect::<StdResult<Vec<StoredLayerId>>>()?;
+        if requires.contains(&id) || requires.iter().any(|r| excludes.contains(r)) {
+            return Err(StdError::generic_err(format!(
+                "Variant: {} in Category: {} can not require itself or a layer it excludes",
+                self.id.variant, self.id.category
+            )));
+        }
+        Ok(StoredRule {
+            id,
+            excludes,
+            requires,
+        })
+    }
+}
+
+/// how to resolve a transmutation that breaks a compatibility rule
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum ConflictPolicy {
+    /// fail the transmutation
+    Reject,
+    /// set the conflicting layer that was not transmuted to its None variant
+    FallBackToNone,
+    /// re-roll the conflicting layer that was not transmuted using the genesis weights
+    Reroll,
+}
+
+/// variant index that marks an unrevealed category in a versioned image vector
+pub const UNREVEALED: u16 = u16::MAX;
+/// variant index that marks an unrevealed category in a legacy image vector
+pub const LEGACY_UNREVEALED: u8 = u8::MAX;
+/// current image vector format version
+pub const IMAGE_VERSION: u8 = 2;
+
+/// an image vector of variant indices, one per trait category.  Legacy vectors are plain
+/// arrays of u8 indices, versioned vectors are objects with u16 indices
+#[derive(Serialize, Deserialize, JsonSchema, Clone, PartialEq, Eq, Debug)]
+#[serde(untagged)]
+pub enum ImageVector {
+    /// u8 indices where 255 marks an unrevealed category
+    Legacy(Vec<u8>),
+    /// u16 indices where 65535 marks an unrevealed category
+    Versioned {
+        /// format version
+        version: u8,
+        /// variant indices
+        indices: Vec<u16>,
+    },
+}
+
+impl ImageVector {
+    /// Returns StdResult<Vec<u16>> from converting the image vector to u16 indices that use
+    /// UNREVEALED as the unrevealed marker
+    pub fn to_indices(&self) -> StdResult<Vec<u16>> {
+        match self {
+            ImageVector::Legacy(image) => Ok(image
+                .iter()
+                .map(|u| {
+                    if *u == LEGACY_UNREVEALED {
+                        UNREVEALED
+                    } else {
+                        *u as u16
+                    }
+                })
+                .collect()),
+            ImageVector::Versioned { version, indices } => {
+                if *version != IMAGE_VERSION {
+                    return Err(StdError::generic_err(format!(
+                        "Unsupported image vector version {}",
+                        version
+                    )));
+                }
+                Ok(indices.clone())
+            }
+        }
+    }
+
+    /// Returns StdResult<ImageVector> from converting u16 indices to an image vector with the
+    /// same format as this one
+    ///
+    /// # Arguments
+    ///
+    /// * `indices` - the variant indices
+    pub fn with_indices(&self, indices: Vec<u16>) -> StdResult<ImageVector> {
+        match self {
+            ImageVector::Legacy(_) => indices
+                .into_iter()
+                .map(|u| {
+                    if u == UNREVEALED {
+                        Ok(LEGACY_UNREVEALED)
+                    } else {
+                        u8::try_from(u)
+                            .ok()
+                            .filter(|b| *b != LEGACY_UNREVEALED)
+                            .ok_or_else(|| {
+                                StdError::generic_err(format!(
+                                    "Variant index {} does not fit in a legacy image vector",
+                                    u
+                                ))
+                            })
+                    }
+                })
+                .collect::<StdResult<Vec<u8>>>()
+                .map(ImageVector::Legacy),
+            ImageVector::Versioned { .. } => Ok(ImageVector::Versioned {
+                version: IMAGE_VERSION,
+                indices,
+            }),
+        }
+    }
+}
+
 /// identifies a layer
 #[derive(Serialize, Deserialize, JsonSchema, Clone, PartialEq, Eq, Debug)]
 pub struct LayerId {
@@ -455,14 +895,12 @@ impl LayerId {
     /// * `storage` - a reference to the contract storage
     pub fn to_stored(&self, storage: &dyn Storage) -> StdResult<StoredLayerId> {
         let cat_map = ReadonlyPrefixedStorage::new(storage, PREFIX_CATEGORY_MAP);
-        let cat_idx: u8 = may_load(&cat_map, self.category.as_bytes())?.ok_or_else(|| {
+        let cat_idx: u16 = may_load(&cat_map, self.category.as_bytes())?.ok_or_else(|| {
             StdError::generic_err(format!("Category name:  {} does not exist", &self.category))
         })?;
-        let var_map = ReadonlyPrefixedStorage::multilevel(
-            storage,
-            &[PREFIX_VARIANT_MAP, &cat_idx.to_le_bytes()],
-        );
-        let var_idx: u8 = may_load(&var_map, self.variant.as_bytes())?.ok_or_else(|| {
+        let var_map =
+            ReadonlyPrefixedStorage::multilevel(storage, &[PREFIX_VARIANT_MAP, &idx_key(cat_idx)]);
+        let var_idx: u16 = may_load(&var_map, self.variant.as_bytes())?.ok_or_else(|| {
             StdError::generic_err(format!(
                 "Category {} does not have a variant named {}",
                 &self.category, &self.variant
@@ -476,12 +914,46 @@ impl LayerId {
     }
 }
 
+/// describes which layers a trait variant can not be combined with, and which layers it needs
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct StoredRule {
+    /// id of the layer variant the rule applies to
+    pub id: StoredLayerId,
+    /// layers that can not be in the same image as this variant
+    pub excludes: Vec<StoredLayerId>,
+    /// if not empty, the image must also have at least one of these layers
+    pub requires: Vec<StoredLayerId>,
+}
+
+impl StoredRule {
+    /// Returns StdResult<Rule> from creating a Rule from a StoredRule
+    ///
+    /// # Arguments
+    ///
+    /// * `storage` - a reference to the contract storage
+    pub fn to_display(&self, storage: &dyn Storage) -> StdResult<Rule> {
+        Ok(Rule {
+            id: self.id.to_display(storage)?,
+            excludes: self
+                .excludes
+                .iter()
+                .map(|l| l.to_display(storage))
+                .collect::<StdResult<Vec<LayerId>>>()?,
+            requires: self
+                .requires
+                .iter()
+                .map(|l| l.to_display(storage))
+                .collect::<StdResult<Vec<LayerId>>>()?,
+        })
+    }
+}
+
 /// identifies a layer
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
 pub struct StoredLayerId {
     /// the layer category
-    pub category: u8,
-    pub variant: u8,
+    pub category: u16,
+    pub variant: u16,
 }
 
 impl StoredLayerId {
@@ -492,11 +964,11 @@ impl StoredLayerId {
     /// * `storage` - a reference to the contract storage
     pub fn to_display(&self, storage: &dyn Storage) -> StdResult<LayerId> {
         let cat_store = ReadonlyPrefixedStorage::new(storage, PREFIX_CATEGORY);
-        let cat_key = self.category.to_le_bytes();
+        let cat_key = idx_key(self.category);
         let cat: Category = may_load(&cat_store, &cat_key)?
             .ok_or_else(|| StdError::generic_err("Category storage is corrupt"))?;
         let var_store = ReadonlyPrefixedStorage::multilevel(storage, &[PREFIX_VARIANT, &cat_key]);
-        let var: VariantInfo = may_load(&var_store, &self.variant.to_le_bytes())?
+        let var: VariantInfo = may_load(&var_store, &idx_key(self.variant))?
             .ok_or_else(|| StdError::generic_err("Variant storage is corrupt"))?;
         Ok(LayerId {
             category: cat.name,
@@ -505,6 +977,191 @@ impl StoredLayerId {
     }
 }
 
+/// where token images are placed in metadata
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum RenderMode {
+    /// the full svg is inlined in `image_data`
+    Inline,
+    /// `image` is a url to an off-chain renderer
+    Url,
+    /// both `image_data` and `image` are set
+    Both,
+}
+
+/// how token images are rendered in metadata
+#[derive(Serialize, Deserialize, JsonSchema, Clone, PartialEq, Eq, Debug)]
+pub struct RenderConfig {
+    /// where the image is placed
+    pub mode: RenderMode,
+    /// base of the renderer url.  The image url is this base followed by the url-safe base64
+    /// encoding of the image format version and the rendered u16 variant indices (little
+    /// endian), with 65535 marking layers that are not drawn.  Required unless the mode is Inline
+    pub base_url: Option<String>,
+    /// true if the image url should also be added as a `media` entry whose url fragment is
+    /// `#sha256=` followed by the hex sha256 hash of the content the url encodes: the svg of the
+    /// rendered trait layers in order, with shared fragments inlined.  The svg header and any
+    /// effects are not covered
+    pub include_media: bool,
+    /// true if shared svg fragments should be emitted once in `<defs>` and drawn with `<use>`
+    /// instead of being inlined where they are referenced
+    #[serde(default)]
+    pub fragment_defs: bool,
+    /// optional opening `<svg>` tag, used to change the viewBox or rendering attributes.
+    /// Defaults to a 24x24 crispEdges viewBox
+    #[serde(default)]
+    pub svg_header: Option<String>,
+}
+
+/// svg composited around the trait layers when triggered
+#[derive(Serialize, Deserialize, JsonSchema, Clone, PartialEq, Eq, Debug)]
+pub struct Effect {
+    /// name of the effect
+    pub name: String,
+    /// the effect is applied if any of these triggers match
+    pub triggers: Vec<EffectTrigger>,
+    /// optional definitions added to `<defs>`, such as filters, gradients, or animations
+    pub defs: Option<String>,
+    /// optional svg drawn beneath all trait layers
+    pub underlay: Option<String>,
+    /// optional svg drawn above all trait layers
+    pub overlay: Option<String>,
+    /// optional id of a filter applied to the composed trait layers
+    pub filter: Option<String>,
+}
+
+impl Effect {
+    /// Returns StdResult<StoredEffect> from creating a StoredEffect from an Effect
+    ///
+    /// # Arguments
+    ///
+    /// * `storage` - a reference to the contract storage
+    pub fn to_stored(&self, storage: &dyn Storage) -> StdResult<StoredEffect> {
+        Ok(StoredEffect {
+            name: self.name.clone(),
+            triggers: self
+                .triggers
+                .iter()
+                .map(|t| {
+                    Ok(match t {
+                        EffectTrigger::Variant { layer } => StoredEffectTrigger::Variant {
+                            layer: layer.to_stored(storage)?,
+                        },
+                        EffectTrigger::AlchemicalStatus { status } => {
+                            StoredEffectTrigger::AlchemicalStatus {
+                                status: status.clone(),
+                            }
+                        }
+                    })
+                })
+                .collect::<StdResult<Vec<StoredEffectTrigger>>>()?,
+            defs: self.defs.clone(),
+            underlay: self.underlay.clone(),
+            overlay: self.overlay.clone(),
+            filter: self.filter.clone(),
+        })
+    }
+}
+
+/// what causes an effect to be applied
+#[derive(Serialize, Deserialize, JsonSchema, Clone, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum EffectTrigger {
+    /// the image draws this layer
+    Variant { layer: LayerId },
+    /// the image has this alchemical status ("Raw" or "Transmuted")
+    AlchemicalStatus { status: String },
+}
+
+/// svg composited around the trait layers when triggered
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct StoredEffect {
+    /// name of the effect
+    pub name: String,
+    /// the effect is applied if any of these triggers match
+    pub triggers: Vec<StoredEffectTrigger>,
+    /// optional definitions added to `<defs>`
+    pub defs: Option<String>,
+    /// optional svg drawn beneath all trait layers
+    pub underlay: Option<String>,
+    /// optional svg drawn above all trait layers
+    pub overlay: Option<String>,
+    /// optional id of a filter applied to the composed trait layers
+    pub filter: Option<String>,
+}
+
+impl StoredEffect {
+    /// Returns StdResult<Effect> from creating an Effect from a StoredEffect
+    ///
+    /// # Arguments
+    ///
+    /// * `storage` - a reference to the contract storage
+    pub fn to_display(&self, storage: &dyn Storage) -> StdResult<Effect> {
+        Ok(Effect {
+            name: self.name.clone(),
+            triggers: self
+                .triggers
+                .iter()
+                .map(|t| {
+                    Ok(match t {
+                        StoredEffectTrigger::Variant { layer } => EffectTrigger::Variant {
+                            layer: layer.to_display(storage)?,
+                        },
+                        StoredEffectTrigger::AlchemicalStatus { status } => {
+                            EffectTrigger::AlchemicalStatus {
+                                status: status.clone(),
+                            }
+                        }
+                    })
+                })
+                .collect::<StdResult<Vec<EffectTrigger>>>()?,
+            defs: self.defs.clone(),
+            underlay: self.underlay.clone(),
+            overlay: self.overlay.clone(),
+            filter: self.filter.clone(),
+        })
+    }
+}
+
+/// what causes an effect to be applied
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub enum StoredEffectTrigger {
+    /// the image draws this layer
+    Variant { layer: StoredLayerId },
+    /// the image has this alchemical status
+    AlchemicalStatus { status: String },
+}
+
+/// a reusable svg snippet.  A variant's svg references it as `{frag:NAME}`, or as
+/// `{frag:NAME:PALETTE}` to replace each `{color:N}` in the snippet with the Nth palette color
+#[derive(Serialize, Deserialize, JsonSchema, Clone, PartialEq, Eq, Debug)]
+pub struct SvgFragment {
+    /// name of the fragment
+    pub name: String,
+    /// svg snippet
+    pub svg: String,
+}
+
+/// a list of colors substituted into svg fragments
+#[derive(Serialize, Deserialize, JsonSchema, Clone, PartialEq, Eq, Debug)]
+pub struct Palette {
+    /// name of the palette
+    pub name: String,
+    /// colors in slot order
+    pub colors: Vec<String>,
+}
+
+/// the metadata of one image in a batch
+#[derive(Serialize, Deserialize, JsonSchema, Clone, PartialEq, Eq, Debug)]
+pub struct BatchMetadata {
+    /// public metadata, or None if the image could not be processed
+    pub public_metadata: Option<Metadata>,
+    /// private metadata, or None if the image could not be processed
+    pub private_metadata: Option<Metadata>,
+    /// the reason the image could not be processed
+    pub error: Option<String>,
+}
+
 /// the metadata common to all NFTs
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
 pub struct CommonMetadata {
@@ -545,7 +1202,56 @@ impl StoredDependencies {
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
 pub struct VariantIdxName {
     /// index of the variant
-    pub idx: u8,
+    pub idx: u16,
     /// display name of the variant
     pub name: String,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_image_vector_conversion() {
+        // callers send either a plain array or a versioned object
+        assert_eq!(
+            cosmwasm_std::from_slice::<ImageVector>(b"[1,255]").unwrap(),
+            ImageVector::Legacy(vec![1, 255])
+        );
+        assert_eq!(
+            cosmwasm_std::from_slice::<ImageVector>(br#"{"version":2,"indices":[300]}"#).unwrap(),
+            ImageVector::Versioned {
+                version: 2,
+                indices: vec![300],
+            }
+        );
+        // legacy vectors map 255 to the u16 unrevealed marker and back
+        let legacy = ImageVector::Legacy(vec![0, 254, 255]);
+        assert_eq!(legacy.to_indices().unwrap(), vec![0, 254, UNREVEALED]);
+        assert_eq!(
+            legacy.with_indices(vec![1, 254, UNREVEALED]).unwrap(),
+            ImageVector::Legacy(vec![1, 254, 255])
+        );
+        // indices that do not fit below the legacy marker are rejected
+        assert!(legacy.with_indices(vec![255]).is_err());
+        assert!(legacy.with_indices(vec![256]).is_err());
+        // versioned vectors keep their u16 indices
+        let versioned = ImageVector::Versioned {
+            version: IMAGE_VERSION,
+            indices: vec![300, UNREVEALED],
+        };
+        assert_eq!(versioned.to_indices().unwrap(), vec![300, UNREVEALED]);
+        assert_eq!(
+            versioned.with_indices(vec![255, 65534]).unwrap(),
+            ImageVector::Versioned {
+                version: IMAGE_VERSION,
+                indices: vec![255, 65534],
+            }
+        );
+        let unknown = ImageVector::Versioned {
+            version: 3,
+            indices: vec![0],
+        };
+        assert!(unknown.to_indices().is_err());
+    }
+}
@@ -1,15 +1,39 @@
 use serde::{Deserialize, Serialize};
 
+use crate::msg::StoredLayerId;
+
 /// storage key for the admins list
 pub const ADMINS_KEY: &[u8] = b"admin";
 /// storage key for the viewers list
 pub const VIEWERS_KEY: &[u8] = b"vwers";
+/// storage key for the contracts on the viewer allowlist that are issued keys by this server
+pub const VIEWER_CONTRACTS_KEY: &[u8] = b"vwrctr";
 /// storage key for the minters list
 pub const MINTERS_KEY: &[u8] = b"mntrs";
+/// storage key for the prng seed used when rolling genesis images
+pub const PRNG_SEED_KEY: &[u8] = b"prngseed";
 /// storage key for the State
 pub const STATE_KEY: &[u8] = b"state";
+/// storage key for the version of the storage layout
+pub const LAYOUT_VERSION_KEY: &[u8] = b"layout";
+/// version of the storage layout with u16 category and variant indices
+pub const U16_LAYOUT: u8 = 2;
 /// storage key for the variant dependencies
 pub const DEPENDENCIES_KEY: &[u8] = b"depend";
+/// storage key for the trait compatibility rules
+pub const RULES_KEY: &[u8] = b"rules";
+/// storage key for the default policy used to resolve rule conflicts when transmuting
+pub const CONFLICT_POLICY_KEY: &[u8] = b"cnflct";
+/// storage key for the statuses of variants that are not active
+pub const VARIANT_STATUS_KEY: &[u8] = b"vrntsts";
+/// storage key for the rendering mode configuration
+pub const RENDER_CONFIG_KEY: &[u8] = b"rndrcfg";
+/// storage key for the shared svg fragments
+pub const SVG_FRAGMENTS_KEY: &[u8] = b"svgfrgs";
+/// storage key for the shared color palettes
+pub const PALETTES_KEY: &[u8] = b"palettes";
+/// storage key for the effect layers
+pub const EFFECTS_KEY: &[u8] = b"effects";
 /// storage key for the common metadata
 pub const METADATA_KEY: &[u8] = b"metadata";
 /// storage prefix for mapping a category name to its index
@@ -20,6 +44,8 @@ pub const PREFIX_VARIANT_MAP: &[u8] = b"vrntmap";
 pub const PREFIX_CATEGORY: &[u8] = b"category";
 /// prefix for the storage of category variants
 pub const PREFIX_VARIANT: &[u8] = b"variant";
+/// prefix for the storage of a category's genesis weights
+pub const PREFIX_GENESIS_WEIGHTS: &[u8] = b"gnwgts";
 /// prefix for the storage of revoked permits
 pub const PREFIX_REVOKED_PERMITS: &str = "revoke";
 
@@ -31,14 +57,81 @@ pub struct Category {
     /// true if this category is skipped during rolls
     pub skip: bool,
     /// count of variants in this category
-    pub cnt: u8,
+    pub cnt: u16,
 }
 
 /// config values needed when rolling a new NFT
 #[derive(Serialize, Deserialize)]
 pub struct State {
+    /// number of categories
+    pub cat_cnt: u16,
+    /// layer indices to skip when rolling
+    pub skip: Vec<u16>,
+}
+
+/// trait category as stored before indices were widened to u16
+#[derive(Deserialize)]
+pub struct LegacyCategory {
+    /// name
+    pub name: String,
+    /// true if this category is skipped during rolls
+    pub skip: bool,
+    /// count of variants in this category
+    pub cnt: u8,
+}
+
+/// State as stored before indices were widened to u16
+#[derive(Deserialize)]
+pub struct LegacyState {
     /// number of categories
     pub cat_cnt: u8,
     /// layer indices to skip when rolling
     pub skip: Vec<u8>,
 }
+
+/// layer id as stored before indices were widened to u16
+#[derive(Deserialize)]
+pub struct LegacyLayerId {
+    /// the layer category
+    pub category: u8,
+    /// the layer variant
+    pub variant: u8,
+}
+
+/// variant dependencies as stored before indices were widened to u16
+#[derive(Deserialize)]
+pub struct LegacyDependencies {
+    /// id of the layer variant that has dependencies
+    pub id: LegacyLayerId,
+    /// the other layers that are correlated to this variant
+    pub correlated: Vec<LegacyLayerId>,
+}
+
+/// status of a variant that is hidden from rolls or retired
+#[derive(Serialize, Deserialize)]
+pub struct StoredVariantStatus {
+    /// the variant
+    pub id: StoredLayerId,
+    /// index of the replacement variant if this variant is retired
+    pub replacement: Option<u16>,
+}
+
+/// a variant's genesis roll weight
+#[derive(Serialize, Deserialize)]
+pub struct StoredVariantWeight {
+    /// index of the variant
+    pub variant: u16,
+    /// weight used when no condition applies
+    pub weight: u16,
+    /// weights that apply if the image already has a layer, in priority order
+    pub conditional: Vec<StoredConditionalWeight>,
+}
+
+/// a weight that applies if the image already has a layer
+#[derive(Serialize, Deserialize)]
+pub struct StoredConditionalWeight {
+    /// the layer the image must have
+    pub layer: StoredLayerId,
+    /// the weight to use
+    pub weight: u16,
+}
@@ -2,6 +2,21 @@ use cosmwasm_std::{StdError, StdResult, Storage};
 use serde::{de::DeserializeOwned, Serialize};
 use std::any::type_name;
 
+/// Returns Vec<u8> of the storage key of a category or variant index.  Indices below 255 keep
+/// the single byte keys used before indices were widened to u16, so existing categories and
+/// variants do not need to move.  Larger indices are keyed by 255 followed by the little-endian
+/// bytes of the index
+///
+/// # Arguments
+///
+/// * `idx` - the category or variant index
+pub fn idx_key(idx: u16) -> Vec<u8> {
+    match u8::try_from(idx) {
+        Ok(byte) if byte < 255 => vec![byte],
+        _ => [[255u8].as_slice(), &idx.to_le_bytes()].concat(),
+    }
+}
+
 /// Returns StdResult<()> resulting from saving an item to storage
 ///
 /// # Arguments
@@ -0,0 +1,18 @@
+use crate::contract::BLOCK_SIZE;
+use secret_toolkit::utils::HandleCallback;
+use serde::Serialize;
+
+/// handle msgs sent to contracts on the viewer allowlist
+#[derive(Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ViewerHandleMsg {
+    /// deliver the viewing key the contract should use when querying this server
+    ReceiveQueryKey {
+        /// the viewing key
+        key: String,
+    },
+}
+
+impl HandleCallback for ViewerHandleMsg {
+    const BLOCK_SIZE: usize = BLOCK_SIZE;
+}